@@ -0,0 +1,143 @@
+#ifndef DPMS_H
+#define DPMS_H
+
+/* The C interface of src/ffi.rs; regenerate with cbindgen --config cbindgen.toml --output include/dpms.h */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// The call succeeded
+#define DPMS_OK 0
+
+// A pointer argument was NULL, or a string not UTF-8
+#define DPMS_ERR_INVALID_ARGUMENT -1
+
+// dpms panicked; the displays were left as they were
+#define DPMS_ERR_PANIC -2
+
+#define DPMS_ERR_UNSUPPORTED_ENVIRONMENT 1
+
+#define DPMS_ERR_PROTOCOL_NOT_SUPPORTED 2
+
+#define DPMS_ERR_NO_DISPLAY_FOUND 3
+
+#define DPMS_ERR_DISPLAY_NOT_FOUND 4
+
+#define DPMS_ERR_AMBIGUOUS_DISPLAY 5
+
+#define DPMS_ERR_DAEMON_START_FAILED 6
+
+#define DPMS_ERR_DAEMON_STOP_TIMEOUT 7
+
+#define DPMS_ERR_DAEMON_UNKILLABLE 8
+
+#define DPMS_ERR_DAEMON_CONFLICT 9
+
+#define DPMS_ERR_SIGNAL 10
+
+#define DPMS_ERR_CONTROL 11
+
+#define DPMS_ERR_PID_FILE 12
+
+#define DPMS_ERR_DRM 13
+
+#define DPMS_ERR_DRM_MASTER_HELD 14
+
+#define DPMS_ERR_DRM_PERMISSION_DENIED 15
+
+#define DPMS_ERR_DRM_OPEN_FAILED 16
+
+#define DPMS_ERR_NO_SEAT_MANAGER 18
+
+#define DPMS_ERR_DISPLAY_SERVER_ACTIVE 19
+
+#define DPMS_ERR_DRM_DEVICE_BUSY 20
+
+#define DPMS_ERR_DRM_DEVICE_GONE 21
+
+#define DPMS_ERR_FBCON_PERMISSION_DENIED 22
+
+#define DPMS_ERR_SEAT 23
+
+#define DPMS_ERR_DBUS 24
+
+#define DPMS_ERR_PARTIAL_FAILURE 25
+
+#define DPMS_ERR_DRY_RUN_NOT_SUPPORTED 26
+
+#define DPMS_ERR_COMPOSITOR_TIMEOUT 27
+
+#define DPMS_ERR_COMPOSITOR_IPC 28
+
+#define DPMS_ERR_IDLE_NOTIFY_NOT_SUPPORTED 29
+
+#define DPMS_ERR_OFF_TIMEOUT_NOT_SUPPORTED 30
+
+#define DPMS_ERR_DPMS_TIMEOUTS_NOT_SUPPORTED 31
+
+#define DPMS_ERR_POWER_CONTROL_BUSY 32
+
+#define DPMS_ERR_WAYLAND_SOCKET_INVALID 33
+
+#define DPMS_ERR_AMBIGUOUS_WAYLAND_SOCKET 34
+
+#define DPMS_ERR_RECONNECT_FAILED 35
+
+#define DPMS_ERR_IO 36
+
+#define DPMS_ERR_BACKEND_NOT_BUILT 37
+
+#define DPMS_ERR_REMOTE_NOT_INSTALLED 38
+
+#define DPMS_ERR_SSH_FAILED 39
+
+#define DPMS_ERR_PERMISSION_DENIED 40
+
+#define DPMS_ERR_NO_CONNECTED_DISPLAY 41
+
+#define DPMS_ERR_NO_OUTPUT_ADVERTISED 42
+
+#define DPMS_ERR_PER_DISPLAY_NOT_SUPPORTED 43
+
+// Called by `dpms_list_displays` for each display, with whether it is on
+typedef void (*DpmsDisplayCallback)(const char *name, int on, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif  // __cplusplus
+
+// Turn `display` on if `on` is non-zero, else off
+//
+// # Safety
+// `display` must be NULL, for the default displays, or point to a
+// NUL-terminated display name such as `DP-1`.
+int dpms_set_power(const char *display, int on);
+
+// Store 1 in `*out` if any display `display` names is on, else 0
+//
+// # Safety
+// `display` must be NULL or point to a NUL-terminated string, and `out`
+// must point to a writable `int`.
+int dpms_get_power(const char *display, int *out);
+
+// Call `callback` with each connected display's name and whether it is on
+//
+// The name is only valid during the call.
+//
+// # Safety
+// `callback` must be safe to call with `user_data`, which is passed along
+// untouched.
+int dpms_list_displays(DpmsDisplayCallback callback, void *user_data);
+
+// Describe `code`, as returned by the other calls
+//
+// The string is static and must not be freed.
+const char *dpms_strerror(int code);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* DPMS_H */
@@ -5,11 +5,90 @@
 ///
 /// Implementations:
 /// - Wayland backend: Uses `zwlr_output_power_management_v1` protocol
+///   (or `org_kde_kwin_dpms` on KWin)
 /// - X11 backend: Would use XRandR (not yet implemented)
 /// - TTY backend: Uses libseat + DRM atomic commits with daemon mode
+use std::io;
+use std::thread;
+use std::time::Duration;
+
 use crate::display::{DisplayInfo, DisplayTarget};
 use crate::error::Error;
-use crate::output::PowerState;
+use crate::output::{DaemonReport, PowerState};
+
+/// Interval between `get_power` polls in the default `watch` implementation
+pub const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The X server's DPMS timeouts, in seconds of inactivity; 0 disables a
+/// stage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DpmsTimeouts {
+    pub standby: u16,
+    pub suspend: u16,
+    pub off: u16,
+}
+
+impl DpmsTimeouts {
+    /// Timeouts the X server accepts: each stage that is enabled comes no
+    /// earlier than the enabled stages before it
+    ///
+    /// # Returns
+    /// The timeouts, or why they are out of order
+    pub fn new(standby: u16, suspend: u16, off: u16) -> Result<Self, String> {
+        let stages = [("standby", standby), ("suspend", suspend), ("off", off)];
+        let mut previous: Option<(&str, u16)> = None;
+        for (name, secs) in stages.into_iter().filter(|(_, secs)| *secs != 0) {
+            if let Some((earlier, earlier_secs)) = previous
+                && secs < earlier_secs
+            {
+                return Err(format!(
+                    "the {} timeout ({}s) is shorter than the {} timeout ({}s)",
+                    name, secs, earlier, earlier_secs
+                ));
+            }
+            previous = Some((name, secs));
+        }
+        Ok(DpmsTimeouts {
+            standby,
+            suspend,
+            off,
+        })
+    }
+}
+
+/// What `dpms timeouts` does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeoutsAction {
+    /// Set new timeouts and enable DPMS
+    Set(DpmsTimeouts),
+    /// Only report the current timeouts
+    Show,
+    /// Disable DPMS, so the server never blanks the displays by itself
+    Disable,
+}
+
+/// The displays in `displays` whose power state isn't the one in `last`,
+/// including displays `last` doesn't have
+pub fn power_changes<'a>(
+    last: &[DisplayInfo],
+    displays: &'a [DisplayInfo],
+) -> impl Iterator<Item = &'a DisplayInfo> {
+    displays.iter().filter(|display| {
+        !last
+            .iter()
+            .any(|d| d.name == display.name && d.power == display.power)
+    })
+}
+
+/// Event emitted while watching display power state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    /// A display reported its power state (initial state or a change)
+    Power(DisplayInfo),
+    /// The backend lost its connection and re-established it.
+    /// Previously reported state may be stale.
+    Reconnected,
+}
 
 /// PowerBackend interface for monitor power control
 ///
@@ -28,6 +107,27 @@ pub trait PowerBackend {
     /// - `Err(Error)` if the operation failed
     fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error>;
 
+    /// Check that `set_power` would succeed, without changing anything
+    ///
+    /// Backs `--dry-run`. Nothing is printed; `main` reports the lines.
+    ///
+    /// The default implementation reports that the backend can't check a
+    /// change up front.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<String>)` if the power change would be accepted: what it
+    ///   would do, one line each, e.g. `Would turn off eDP-1 on /dev/dri/card0`
+    /// - `Err(Error)` if it would fail, or can't be checked
+    fn check_power(
+        &self,
+        _target: &DisplayTarget,
+        _state: PowerState,
+    ) -> Result<Vec<String>, Error> {
+        Err(Error::DryRunNotSupported {
+            backend: self.name().to_string(),
+        })
+    }
+
     /// Get the current power state of the specified display(s)
     ///
     /// # Parameters
@@ -44,4 +144,209 @@ pub trait PowerBackend {
     /// - `Ok(Vec<DisplayInfo>)` with all connected displays
     /// - `Err(Error)` if displays could not be enumerated
     fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error>;
+
+    /// Short name of the backend, e.g. `wayland`, for messages that name it
+    fn name(&self) -> &'static str;
+
+    /// Describe the backend and the mechanism it uses, for verbose output
+    ///
+    /// # Returns
+    /// A short label such as `wayland (org_kde_kwin_dpms)`
+    fn describe(&self) -> String;
+
+    /// Watch the power state of the specified display(s), reporting changes
+    ///
+    /// The initial state of every targeted display is reported first, then one
+    /// event per change. This only returns on error, including one from
+    /// `on_event`, which ends the watch as `Error::Io`.
+    ///
+    /// The default implementation polls `get_power`. Backends with a
+    /// persistent connection should override it with an event-driven version.
+    ///
+    /// # Parameters
+    /// - `target`: Which display(s) to watch (Named, All, or Default)
+    /// - `max_reconnects`: How many reconnect attempts to make after the
+    ///   connection breaks before giving up (ignored by connectionless backends)
+    /// - `on_event`: Called for every event; fails when it can't pass the
+    ///   event on, e.g. because stdout was closed
+    fn watch(
+        &mut self,
+        target: &DisplayTarget,
+        _max_reconnects: u32,
+        on_event: &mut dyn FnMut(&WatchEvent) -> io::Result<()>,
+    ) -> Result<(), Error> {
+        let mut last: Vec<DisplayInfo> = Vec::new();
+
+        loop {
+            let displays = self.get_power(target)?;
+            for display in power_changes(&last, &displays) {
+                on_event(&WatchEvent::Power(display.clone()))?;
+            }
+            last = displays;
+
+            thread::sleep(WATCH_POLL_INTERVAL);
+        }
+    }
+
+    /// Turn the specified display(s) off after a period of user inactivity
+    ///
+    /// Displays are turned back on when activity resumes. Runs until
+    /// interrupted by SIGINT/SIGTERM and always leaves the displays On.
+    ///
+    /// The default implementation reports that idle detection is unavailable;
+    /// only backends that can observe user activity override it.
+    ///
+    /// # Parameters
+    /// - `target`: Which display(s) to manage (Named, All, or Default)
+    /// - `timeout`: Inactivity period before the displays are turned off
+    fn idle(&mut self, _target: &DisplayTarget, _timeout: Duration) -> Result<(), Error> {
+        Err(Error::IdleNotifyNotSupported)
+    }
+
+    /// Turn displays off, and back on by themselves after `timeout`
+    ///
+    /// Displays already held off get the new deadline instead. Backs
+    /// `dpms off --timeout`.
+    ///
+    /// The default implementation reports that the backend can't restore
+    /// displays by itself.
+    ///
+    /// # Parameters
+    /// - `target`: Which display(s) to turn off (Named, All, or Default)
+    /// - `timeout`: How long the displays stay off
+    fn power_off_for(&mut self, _target: &DisplayTarget, _timeout: Duration) -> Result<(), Error> {
+        Err(Error::OffTimeoutNotSupported)
+    }
+
+    /// How long the displays this backend turned back on had been off
+    ///
+    /// The default implementation doesn't know.
+    ///
+    /// # Returns
+    /// `(display name, seconds)` for each display whose off time is known
+    fn off_durations(&self) -> Vec<(String, u64)> {
+        Vec::new()
+    }
+
+    /// How the backend reaches the displays, if not directly, e.g. `service`
+    /// for a running `dpms serve`
+    fn via(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Daemons holding displays off on the backend's behalf, for status
+    /// reports
+    ///
+    /// The default implementation has none.
+    fn daemons(&self) -> Vec<DaemonReport> {
+        Vec::new()
+    }
+
+    /// Set, show or disable the display server's DPMS timeouts, as
+    /// `xset dpms` does
+    ///
+    /// Only X servers have these; Wayland compositors and the TTY daemon
+    /// own their idle timeouts, so the default implementation reports that.
+    ///
+    /// # Returns
+    /// - `Ok(DpmsTimeouts)` with the timeouts in effect afterwards
+    /// - `Err(Error::DpmsTimeoutsNotSupported)` if the backend has none
+    fn dpms_timeouts(&mut self, _action: TimeoutsAction) -> Result<DpmsTimeouts, Error> {
+        Err(Error::DpmsTimeoutsNotSupported {
+            backend: self.name().to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Backend that replays a fixed sequence of `get_power` results
+    struct ScriptedBackend {
+        script: RefCell<Vec<Result<Vec<DisplayInfo>, Error>>>,
+    }
+
+    impl PowerBackend for ScriptedBackend {
+        fn set_power(&mut self, _target: &DisplayTarget, _state: PowerState) -> Result<(), Error> {
+            Ok(())
+        }
+
+        fn get_power(&self, _target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+            self.script.borrow_mut().remove(0)
+        }
+
+        fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
+            Ok(Vec::new())
+        }
+
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn describe(&self) -> String {
+            "scripted".to_string()
+        }
+    }
+
+    #[test]
+    fn check_power_is_unsupported_by_default() {
+        let backend = ScriptedBackend {
+            script: RefCell::new(Vec::new()),
+        };
+        let err = backend
+            .check_power(&DisplayTarget::Default, PowerState::Off)
+            .unwrap_err();
+        assert!(matches!(err, Error::DryRunNotSupported { ref backend } if backend == "scripted"));
+    }
+
+    #[test]
+    fn dpms_timeouts_are_x11_only_by_default() {
+        let mut backend = ScriptedBackend {
+            script: RefCell::new(Vec::new()),
+        };
+        let err = backend.dpms_timeouts(TimeoutsAction::Show).unwrap_err();
+        assert!(
+            matches!(err, Error::DpmsTimeoutsNotSupported { ref backend } if backend == "scripted")
+        );
+        assert!(err.to_string().contains("X11"));
+    }
+
+    fn make_display(name: &str, power: PowerState) -> DisplayInfo {
+        DisplayInfo {
+            name: name.to_string(),
+            power,
+            description: None,
+            make: None,
+            model: None,
+            drm: None,
+        }
+    }
+
+    #[test]
+    fn default_watch_reports_initial_state_and_changes() {
+        let mut backend = ScriptedBackend {
+            script: RefCell::new(vec![
+                Ok(vec![make_display("DP-1", PowerState::On)]),
+                Ok(vec![make_display("DP-1", PowerState::Off)]),
+                Err(Error::NoDisplayFound),
+            ]),
+        };
+
+        let mut events = Vec::new();
+        let result = backend.watch(&DisplayTarget::Default, 0, &mut |e| {
+            events.push(e.clone());
+            Ok(())
+        });
+
+        assert!(matches!(result, Err(Error::NoDisplayFound)));
+        assert_eq!(
+            events,
+            vec![
+                WatchEvent::Power(make_display("DP-1", PowerState::On)),
+                WatchEvent::Power(make_display("DP-1", PowerState::Off)),
+            ]
+        );
+    }
 }
@@ -0,0 +1,174 @@
+/// Backlight power for internal panels
+///
+/// Turning off an internal panel through DRM leaves the backlight glowing
+/// on some laptops. The daemon can additionally switch the panel's
+/// backlight off through its sysfs `bl_power` attribute.
+use crate::error::{Context, Error};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Where the kernel lists backlight devices
+const BACKLIGHT_CLASS: &str = "/sys/class/backlight";
+
+/// `FB_BLANK_UNBLANK`: backlight on
+const BL_POWER_ON: u8 = 0;
+
+/// `FB_BLANK_POWERDOWN`: backlight off
+const BL_POWER_OFF: u8 = 4;
+
+/// A backlight device whose power can be switched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Backlight {
+    /// Name under /sys/class/backlight, e.g. `intel_backlight`
+    pub name: String,
+    bl_power: PathBuf,
+}
+
+impl Backlight {
+    /// Find the backlight of the panel on `connector` of DRM device `device`
+    ///
+    /// # Returns
+    /// The backlight linked to the connector, or the only backlight if none
+    /// is linked; `None` if there's no match
+    pub fn for_panel(device: &Path, connector: &str) -> Option<Backlight> {
+        let card = device.file_name()?.to_string_lossy();
+        find_backlight(Path::new(BACKLIGHT_CLASS), &card, connector)
+    }
+
+    /// Switch the backlight on or off
+    ///
+    /// # Returns
+    /// - `Ok(())` - `bl_power` was written
+    /// - `Err(Error::PermissionDenied)` - This user may not write `bl_power`
+    /// - `Err(Error::Io)` - Writing failed for another reason
+    pub fn set_power(&self, on: bool) -> Result<(), Error> {
+        let level = if on { BL_POWER_ON } else { BL_POWER_OFF };
+        fs::write(&self.bl_power, level.to_string()).map_err(|e| write_error(&self.bl_power, e))
+    }
+}
+
+/// Map a failure to write `path` to an error, with a hint for permissions
+fn write_error(path: &Path, err: std::io::Error) -> Error {
+    match err.kind() {
+        std::io::ErrorKind::PermissionDenied => Error::PermissionDenied {
+            resource: path.display().to_string(),
+            hint: "add your user to the 'video' group, or install a udev rule \
+                   making it group-writable"
+                .to_string(),
+        },
+        _ => Error::Io(std::io::Error::new(
+            err.kind(),
+            Context::wrap(format!("Failed to write {}", path.display()), err),
+        )),
+    }
+}
+
+/// Find the backlight for `card`'s `connector` among the devices in `class_dir`
+///
+/// Drivers like i915 link a backlight's `device` to the connector, e.g.
+/// `card0-eDP-1`. Others link it to the GPU or a platform device; with a
+/// single backlight that one must be the panel's.
+fn find_backlight(class_dir: &Path, card: &str, connector: &str) -> Option<Backlight> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(class_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+
+    let connector_dir = format!("{}-{}", card, connector);
+    let linked = entries.iter().find(|entry| {
+        fs::read_link(entry.join("device"))
+            .ok()
+            .and_then(|target| target.file_name().map(|n| n == connector_dir.as_str()))
+            .unwrap_or(false)
+    });
+
+    let entry = match (linked, entries.as_slice()) {
+        (Some(entry), _) => entry,
+        (None, [only]) => only,
+        (None, _) => return None,
+    };
+    Some(Backlight {
+        name: entry.file_name()?.to_string_lossy().into_owned(),
+        bl_power: entry.join("bl_power"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// Create a backlight class directory with devices linked to `targets`
+    fn class_dir(dir: &str, backlights: &[(&str, &str)]) -> PathBuf {
+        let dir = Path::new("/tmp").join(dir);
+        let _ = fs::remove_dir_all(&dir);
+        for (name, device) in backlights {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            fs::write(dir.join(name).join("bl_power"), "0").unwrap();
+            symlink(device, dir.join(name).join("device")).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn finds_backlight_linked_to_connector() {
+        let dir = class_dir(
+            "dpms-test-backlight-linked",
+            &[
+                ("acpi_video0", "../../devices/LNXSYSTM:00/LNXVIDEO:00"),
+                ("intel_backlight", "../../card0-eDP-1"),
+            ],
+        );
+
+        let backlight = find_backlight(&dir, "card0", "eDP-1").unwrap();
+        assert_eq!(backlight.name, "intel_backlight");
+        assert!(find_backlight(&dir, "card1", "eDP-1").is_none());
+    }
+
+    #[test]
+    fn single_backlight_is_the_panels() {
+        let dir = class_dir(
+            "dpms-test-backlight-single",
+            &[("amdgpu_bl0", "../../0000:04:00.0")],
+        );
+
+        let backlight = find_backlight(&dir, "card1", "eDP-1").unwrap();
+        assert_eq!(backlight.name, "amdgpu_bl0");
+
+        backlight.set_power(false).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("amdgpu_bl0/bl_power")).unwrap(),
+            "4"
+        );
+        backlight.set_power(true).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("amdgpu_bl0/bl_power")).unwrap(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn denied_write_says_how_to_fix_it() {
+        let path = Path::new("/sys/class/backlight/intel_backlight/bl_power");
+        let err = write_error(
+            path,
+            std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        );
+        assert!(matches!(
+            err,
+            Error::PermissionDenied { ref resource, ref hint }
+                if resource == "/sys/class/backlight/intel_backlight/bl_power"
+                    && hint.contains("'video' group")
+        ));
+
+        let err = write_error(path, std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert!(matches!(err, Error::Io(_)));
+    }
+
+    #[test]
+    fn missing_class_dir_has_no_backlight() {
+        let dir = Path::new("/tmp/dpms-test-backlight-none");
+        assert!(find_backlight(dir, "card0", "eDP-1").is_none());
+    }
+}
@@ -1,8 +1,22 @@
 use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
 use clap_complete::{Shell as ClapShell, generate};
+use std::ffi::OsString;
 use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
 
+use crate::backend::{DpmsTimeouts, TimeoutsAction};
 use crate::display::DisplayTarget;
+use crate::remote::Destination;
+
+/// Default number of reconnect attempts for `status --watch`
+const DEFAULT_MAX_RECONNECTS: u32 = 5;
+
+/// Default upper bound for a single roundtrip with the compositor
+const DEFAULT_COMPOSITOR_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Environment variable naming the DRM device when `--card` isn't given
+const DRM_DEVICE_ENV: &str = "DPMS_DRM_DEVICE";
 
 /// Shell type for completions
 #[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
@@ -27,40 +41,297 @@ impl From<Shell> for ClapShell {
     }
 }
 
+/// Backend selection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum BackendChoice {
+    /// Detect from the environment
+    #[default]
+    Auto,
+    /// Wayland power management protocols
+    Wayland,
+    /// Mutter's DisplayConfig D-Bus API
+    Gnome,
+    /// Hyprland IPC socket
+    Hyprland,
+    /// DRM via the daemon
+    Tty,
+}
+
+/// When to color text output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is unset
+    #[default]
+    Auto,
+    /// Always color, even when piped
+    Always,
+    /// Never color
+    Never,
+}
+
 /// CLI command
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Command {
     On {
         target: DisplayTarget,
+        json: bool,
+        dry_run: bool,
     },
     Off {
         target: DisplayTarget,
+        json: bool,
+        dry_run: bool,
+        /// Turn the displays back on after this long
+        timeout: Option<Duration>,
     },
     Toggle {
         target: DisplayTarget,
+        json: bool,
     },
     Status {
         target: DisplayTarget,
         json: bool,
+        /// Print the bare display array instead of the status report
+        compat: bool,
+        /// Print one terse line for status bars
+        short: bool,
+        watch: bool,
+        max_reconnects: u32,
     },
     List {
         json: bool,
         verbose: bool,
     },
+    Idle {
+        target: DisplayTarget,
+        timeout: Duration,
+    },
     Completion {
         shell: Shell,
     },
+    /// Turn displays back on after the TTY daemon died without restoring them
+    Recover,
+    /// Set, show or disable the X server's DPMS timeouts
+    Timeouts {
+        action: TimeoutsAction,
+    },
+    /// Offer power control on the session bus until interrupted
+    Serve {
+        /// Install a D-Bus service file instead, so the bus starts the
+        /// service on demand
+        install: bool,
+    },
+    /// Answer JSON requests on stdin until it ends
+    Repl {
+        /// Also report power changes as notifications
+        watch: bool,
+    },
+    /// Show what the running TTY daemons are doing
+    DaemonStatus {
+        json: bool,
+    },
+    /// Replace the running TTY daemons with ones run by this dpms
+    DaemonRestart,
     /// Internal: run as daemon process (not for user use)
-    DaemonInternal,
+    DaemonInternal {
+        /// Only turn off the CRTC driving this connector
+        connector: Option<String>,
+        /// Stay attached to the terminal instead of daemonizing
+        foreground: bool,
+        /// Run under systemd: stay attached and report readiness to it
+        systemd: bool,
+        /// Restore the displays and exit at this time, in seconds since the epoch
+        restore_at: Option<u64>,
+    },
 }
 
+impl Command {
+    /// Whether the command asked for JSON output
+    pub fn json(&self) -> bool {
+        match self {
+            Command::On { json, .. }
+            | Command::Off { json, .. }
+            | Command::Toggle { json, .. }
+            | Command::Status { json, .. }
+            | Command::List { json, .. }
+            | Command::DaemonStatus { json } => *json,
+            _ => false,
+        }
+    }
+}
+
+/// Options that apply to every command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Explicit Wayland socket to connect to; forces the Wayland backend
+    pub wayland_socket: Option<PathBuf>,
+    /// Backend to use instead of auto-detection
+    pub backend: BackendChoice,
+    /// Upper bound for a single roundtrip with the compositor
+    pub compositor_timeout: Duration,
+    /// Open DRM devices directly instead of through libseat (TTY backend)
+    pub no_libseat: bool,
+    /// DRM device to use instead of auto-selection (TTY backend)
+    pub card: Option<PathBuf>,
+    /// Seat to act on; must match the libseat session (TTY backend)
+    pub seat: Option<String>,
+    /// Leave displays plugged in while held off alone (TTY backend)
+    pub allow_new_displays: bool,
+    /// Also switch off internal panel backlights (TTY backend)
+    pub with_backlight: bool,
+    /// Turn held displays back on while the system sleeps (TTY backend)
+    pub restore_on_suspend: bool,
+    /// Turn held displays back on at the first input activity (TTY backend)
+    pub until_input: bool,
+    /// Block sleep and idle actions while displays are held off (TTY backend)
+    pub inhibit_sleep: bool,
+    /// Turn displays off even while a display server runs (TTY backend)
+    pub force_tty: bool,
+    /// Kill a daemon that ignores SIGTERM this long, then restore directly (TTY backend)
+    pub kill_after: Option<Duration>,
+    /// Use the backends directly even while `dpms serve` is running
+    pub no_service: bool,
+    /// Run the command on this host over SSH instead
+    pub host: Option<Destination>,
+    /// When to color text output
+    pub color: ColorChoice,
+    /// Tag error messages with `Error::code`
+    pub error_codes: bool,
+    /// Leave out informational notices such as "Display already off"
+    pub quiet: bool,
+    /// Panic on purpose, to check what a crash looks like
+    pub debug_panic: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            wayland_socket: None,
+            backend: BackendChoice::Auto,
+            compositor_timeout: DEFAULT_COMPOSITOR_TIMEOUT,
+            no_libseat: false,
+            card: None,
+            seat: None,
+            allow_new_displays: false,
+            with_backlight: false,
+            restore_on_suspend: false,
+            until_input: false,
+            inhibit_sleep: false,
+            force_tty: false,
+            kill_after: None,
+            no_service: false,
+            host: None,
+            color: ColorChoice::Auto,
+            error_codes: false,
+            quiet: false,
+            debug_panic: false,
+        }
+    }
+}
+
+/// Exit status contract, shown after `--help`; mirrors `error::ExitCode`
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  Error not covered below, or a crash
+  2  Invalid usage
+  3  No display connected, or none matches the name given
+  4  Permission denied on the displays or the seat, or another process holds them
+  5  Daemon could not be started or stopped
+  6  Not supported by this environment, compositor or build";
+
 /// Monitor power control tool
 #[derive(Parser, Debug)]
 #[command(name = "dpms")]
 #[command(about = "Control monitor power state", long_about = None)]
+#[command(after_help = EXIT_CODES)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Connect to this Wayland socket instead of WAYLAND_DISPLAY (forces Wayland backend)
+    #[arg(long, global = true, value_name = "PATH")]
+    wayland_socket: Option<PathBuf>,
+
+    /// Backend to use
+    #[arg(long, global = true, value_enum, default_value_t = BackendChoice::Auto)]
+    backend: BackendChoice,
+
+    /// Give up when the compositor doesn't answer within this time (e.g. 500ms, 5s)
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_duration, default_value = "5s")]
+    compositor_timeout: Duration,
+
+    /// Open DRM devices directly instead of through libseat (TTY backend)
+    #[arg(long, global = true)]
+    no_libseat: bool,
+
+    /// DRM device to use instead of auto-selection, e.g. /dev/dri/card1; also
+    /// read from DPMS_DRM_DEVICE (TTY backend)
+    #[arg(long, global = true, env = "DPMS_CARD", value_name = "PATH")]
+    card: Option<PathBuf>,
+
+    /// Seat whose displays to control, e.g. seat1 (TTY backend)
+    #[arg(long, global = true, env = "XDG_SEAT", value_name = "NAME")]
+    seat: Option<String>,
+
+    /// Don't turn off displays plugged in while the daemon holds others off (TTY backend)
+    #[arg(long, global = true)]
+    allow_new_displays: bool,
+
+    /// Also switch off the backlight of internal panels via sysfs bl_power (TTY backend)
+    #[arg(long, global = true)]
+    with_backlight: bool,
+
+    /// Turn held displays back on before the system suspends (TTY backend)
+    #[arg(long, global = true)]
+    restore_on_suspend: bool,
+
+    /// Turn displays back on at the first key press or mouse movement; the
+    /// daemon reads every input device on the seat (TTY backend)
+    #[arg(long, global = true)]
+    until_input: bool,
+
+    /// Keep the system from suspending or running idle actions while the
+    /// displays are held off, via a logind inhibitor (TTY backend)
+    #[arg(long, global = true)]
+    inhibit_sleep: bool,
+
+    /// Turn displays off even if a compositor or X server holds them (TTY backend)
+    #[arg(long, global = true)]
+    force_tty: bool,
+
+    /// When turning displays on, SIGKILL a daemon that hasn't exited after this
+    /// long and restore the displays directly (TTY backend)
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_duration)]
+    kill_after: Option<Duration>,
+
+    /// Use the backends directly instead of a running `dpms serve`
+    #[arg(long, global = true)]
+    no_service: bool,
+
+    /// Run the command on another host with the system ssh, e.g. htpc or
+    /// user@htpc:2222; dpms must be installed there
+    #[arg(long, global = true, value_name = "[USER@]HOST[:PORT]")]
+    host: Option<Destination>,
+
+    /// Color display states in text output; auto colors on a terminal unless
+    /// NO_COLOR is set. JSON is never colored
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
+
+    /// Print errors as Error[code]: message, with the stable code scripts
+    /// can match on, e.g. no_display_found
+    #[arg(long, global = true)]
+    error_codes: bool,
+
+    /// Don't print notices such as "Display already off"; warnings and
+    /// errors are still printed
+    #[arg(short, long, global = true)]
+    quiet: bool,
+
+    /// Panic on purpose, to check the crash report
+    #[arg(long, global = true, hide = true)]
+    debug_panic: bool,
 }
 
 #[derive(Subcommand, Debug)]
@@ -73,6 +344,14 @@ enum Commands {
         /// Target all displays
         #[arg(long)]
         all: bool,
+
+        /// Output per-display results as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Say what turning the displays on would do, without doing it (TTY backend)
+        #[arg(long)]
+        dry_run: bool,
     },
     /// Turn display off
     Off {
@@ -82,6 +361,20 @@ enum Commands {
         /// Target all displays
         #[arg(long)]
         all: bool,
+
+        /// Output per-display results as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Have the driver test turning the displays off, without doing it
+        /// (TTY backend). Whether they would come back on isn't checked
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Turn the displays back on after this long, e.g. 30m; run again to
+        /// move the deadline (TTY backend)
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, conflicts_with = "dry_run")]
+        timeout: Option<Duration>,
     },
     /// Toggle display power state
     Toggle {
@@ -91,15 +384,41 @@ enum Commands {
         /// Target all displays
         #[arg(long)]
         all: bool,
+
+        /// Output per-display results as JSON
+        #[arg(long)]
+        json: bool,
     },
     /// Show display power status
     Status {
         /// Target display name (e.g., DP-1, eDP-1)
         display: Option<String>,
 
+        /// Report all displays
+        #[arg(long)]
+        all: bool,
+
         /// Output status as JSON
         #[arg(long)]
         json: bool,
+
+        /// With --json, print the bare display array of earlier versions
+        /// instead of the status report, for scripts written against it
+        #[arg(long, requires = "json", conflicts_with = "watch")]
+        compat: bool,
+
+        /// Print just on or off, or DP-1:on HDMI-A-1:off for several
+        /// displays, on one line for status bars; with --watch, a line per change
+        #[arg(long, conflicts_with = "json")]
+        short: bool,
+
+        /// Keep running and report power state changes
+        #[arg(long)]
+        watch: bool,
+
+        /// Reconnect attempts before giving up when the compositor goes away
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_RECONNECTS, requires = "watch")]
+        max_reconnects: u32,
     },
     /// List all connected displays
     List {
@@ -111,21 +430,125 @@ enum Commands {
         #[arg(short, long)]
         verbose: bool,
     },
+    /// Turn displays off after a period of inactivity (runs until interrupted)
+    Idle {
+        /// Target display name (e.g., DP-1, eDP-1)
+        display: Option<String>,
+
+        /// Target all displays
+        #[arg(long)]
+        all: bool,
+
+        /// Inactivity before displays turn off (e.g. 90s, 5m, 1h; plain numbers are seconds)
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+        timeout: Duration,
+    },
     /// Generate shell completion script
     Completion {
         /// Shell type
         #[arg(value_enum)]
         shell: Shell,
     },
+    /// Turn displays back on after the TTY daemon died without restoring them
+    Recover,
+    /// Offer power control to other applications as org.dpms.PowerControl on
+    /// the session bus (runs until interrupted)
+    Serve {
+        /// Install a service file so the session bus starts `dpms serve` on
+        /// demand, then exit
+        #[arg(long)]
+        install: bool,
+    },
+    /// Answer newline-delimited JSON requests on stdin with one line each on
+    /// stdout, reusing one connection to the displays (exits at end of input)
+    Repl {
+        /// Also send a power_changed notification whenever a display's
+        /// power changes
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Configure the X server's DPMS timeouts like `xset dpms` (X11 only)
+    Timeouts {
+        /// Seconds of inactivity before standby, suspend and off; 0 disables
+        /// a stage
+        #[arg(
+            num_args = 3,
+            value_names = ["STANDBY", "SUSPEND", "OFF"],
+            required_unless_present_any = ["show", "disable"]
+        )]
+        seconds: Vec<u16>,
+
+        /// Print the current timeouts
+        #[arg(long, conflicts_with_all = ["seconds", "disable"])]
+        show: bool,
+
+        /// Disable DPMS, so the X server never turns the displays off by itself
+        #[arg(long, conflicts_with = "seconds")]
+        disable: bool,
+    },
+    /// Inspect the TTY daemon
+    Daemon {
+        #[command(subcommand)]
+        command: DaemonCommands,
+    },
     /// Internal daemon mode (not for user use)
     #[command(hide = true)]
-    DaemonInternal,
+    DaemonInternal {
+        /// Only turn off the CRTC driving this connector
+        #[arg(long)]
+        connector: Option<String>,
+        /// Stay attached to the terminal, printing errors to it
+        #[arg(long)]
+        foreground: bool,
+        /// Run as a systemd `Type=notify` service, logging to the journal
+        #[arg(long)]
+        systemd: bool,
+        /// Restore the displays and exit at this time, in seconds since the epoch
+        #[arg(long, value_name = "SECONDS")]
+        restore_at: Option<u64>,
+    },
+}
+
+/// `dpms daemon` subcommands
+#[derive(Subcommand, Debug)]
+enum DaemonCommands {
+    /// Show what the running daemons are doing, asking them directly where
+    /// they have a control socket
+    Status {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Stop the running daemons and start new ones for the same displays,
+    /// e.g. after upgrading dpms; takes the same options as `off`
+    Restart,
 }
 
-/// Parse command-line arguments and return the Command
-pub fn parse() -> Command {
+/// Parse command-line arguments and return the Command and global Options
+pub fn parse() -> (Command, Options) {
     let cli = Cli::parse();
-    command_from_commands(cli.command)
+    let options = Options {
+        wayland_socket: cli.wayland_socket,
+        backend: cli.backend,
+        compositor_timeout: cli.compositor_timeout,
+        no_libseat: cli.no_libseat,
+        card: card_override(cli.card, std::env::var_os(DRM_DEVICE_ENV)),
+        seat: cli.seat,
+        allow_new_displays: cli.allow_new_displays,
+        with_backlight: cli.with_backlight,
+        restore_on_suspend: cli.restore_on_suspend,
+        until_input: cli.until_input,
+        inhibit_sleep: cli.inhibit_sleep,
+        force_tty: cli.force_tty,
+        kill_after: cli.kill_after,
+        no_service: cli.no_service,
+        host: cli.host,
+        color: cli.color,
+        error_codes: cli.error_codes,
+        quiet: cli.quiet,
+        debug_panic: cli.debug_panic,
+    };
+    (command_from_commands(cli.command), options)
 }
 
 /// Generate shell completion script to stdout
@@ -135,35 +558,155 @@ pub fn generate_completions(shell: Shell) {
     generate(clap_shell, &mut cmd, "dpms", &mut io::stdout());
 }
 
+/// Parse a duration such as `500ms`, `90s`, `5m` or `1h`; plain numbers are seconds
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => value.split_at(index),
+        None => (value, "s"),
+    };
+
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", value))?;
+    let millis = match unit {
+        "ms" => number,
+        "s" => number.saturating_mul(1000),
+        "m" => number.saturating_mul(60_000),
+        "h" => number.saturating_mul(3_600_000),
+        _ => {
+            return Err(format!(
+                "invalid duration unit '{}' (use ms, s, m or h)",
+                unit
+            ));
+        }
+    };
+
+    if millis == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(Duration::from_millis(millis))
+}
+
+/// The DRM device chosen with `--card`/`DPMS_CARD`, else `DPMS_DRM_DEVICE`
+///
+/// An empty `DPMS_DRM_DEVICE` counts as unset.
+fn card_override(card: Option<PathBuf>, drm_device: Option<OsString>) -> Option<PathBuf> {
+    card.or_else(|| {
+        drm_device
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
+/// What `dpms timeouts` was asked to do
+///
+/// # Returns
+/// The action, or why the timeouts are out of order
+fn timeouts_action(seconds: &[u16], show: bool, disable: bool) -> Result<TimeoutsAction, String> {
+    match seconds {
+        _ if show => Ok(TimeoutsAction::Show),
+        _ if disable => Ok(TimeoutsAction::Disable),
+        [standby, suspend, off] => {
+            DpmsTimeouts::new(*standby, *suspend, *off).map(TimeoutsAction::Set)
+        }
+        _ => Err("expected standby, suspend and off timeouts".to_string()),
+    }
+}
+
 /// Convert internal Commands enum to public Command enum
 fn command_from_commands(cmd: Commands) -> Command {
     match cmd {
-        Commands::On { display, all } => Command::On {
+        Commands::On {
+            display,
+            all,
+            json,
+            dry_run,
+        } => Command::On {
             target: DisplayTarget::from_args(display, all),
+            json,
+            dry_run,
         },
-        Commands::Off { display, all } => Command::Off {
+        Commands::Off {
+            display,
+            all,
+            json,
+            dry_run,
+            timeout,
+        } => Command::Off {
             target: DisplayTarget::from_args(display, all),
+            json,
+            dry_run,
+            timeout,
         },
-        Commands::Toggle { display, all } => Command::Toggle {
+        Commands::Toggle { display, all, json } => Command::Toggle {
             target: DisplayTarget::from_args(display, all),
+            json,
         },
-        Commands::Status { display, json } => Command::Status {
-            target: if let Some(name) = display {
-                DisplayTarget::Named(name)
-            } else {
-                DisplayTarget::Default
-            },
+        Commands::Status {
+            display,
+            all,
+            json,
+            compat,
+            short,
+            watch,
+            max_reconnects,
+        } => Command::Status {
+            target: DisplayTarget::from_args(display, all),
             json,
+            compat,
+            short,
+            watch,
+            max_reconnects,
         },
         Commands::List { json, verbose } => Command::List { json, verbose },
+        Commands::Idle {
+            display,
+            all,
+            timeout,
+        } => Command::Idle {
+            target: DisplayTarget::from_args(display, all),
+            timeout,
+        },
         Commands::Completion { shell } => Command::Completion { shell },
-        Commands::DaemonInternal => Command::DaemonInternal,
+        Commands::Recover => Command::Recover,
+        Commands::Serve { install } => Command::Serve { install },
+        Commands::Repl { watch } => Command::Repl { watch },
+        Commands::Timeouts {
+            seconds,
+            show,
+            disable,
+        } => Command::Timeouts {
+            action: timeouts_action(&seconds, show, disable).unwrap_or_else(|e| {
+                Cli::command()
+                    .error(clap::error::ErrorKind::ValueValidation, e)
+                    .exit()
+            }),
+        },
+        Commands::Daemon {
+            command: DaemonCommands::Status { json },
+        } => Command::DaemonStatus { json },
+        Commands::Daemon {
+            command: DaemonCommands::Restart,
+        } => Command::DaemonRestart,
+        Commands::DaemonInternal {
+            connector,
+            foreground,
+            systemd,
+            restore_at,
+        } => Command::DaemonInternal {
+            connector,
+            foreground,
+            systemd,
+            restore_at,
+        },
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::ExitCode;
 
     // Basic command parsing tests (backwards compatibility)
 
@@ -174,7 +717,9 @@ mod tests {
         assert!(matches!(
             command,
             Command::On {
-                target: DisplayTarget::Default
+                target: DisplayTarget::Default,
+                json: false,
+                dry_run: false,
             }
         ));
     }
@@ -186,7 +731,10 @@ mod tests {
         assert!(matches!(
             command,
             Command::Off {
-                target: DisplayTarget::Default
+                target: DisplayTarget::Default,
+                json: false,
+                dry_run: false,
+                timeout: None,
             }
         ));
     }
@@ -195,7 +743,7 @@ mod tests {
     fn parse_command_status() {
         let cli = Cli::try_parse_from(["dpms", "status"]).unwrap();
         let command = command_from_commands(cli.command);
-        if let Command::Status { target, json } = command {
+        if let Command::Status { target, json, .. } = command {
             assert_eq!(target, DisplayTarget::Default);
             assert!(!json);
         } else {
@@ -207,7 +755,7 @@ mod tests {
     fn parse_command_status_json() {
         let cli = Cli::try_parse_from(["dpms", "status", "--json"]).unwrap();
         let command = command_from_commands(cli.command);
-        if let Command::Status { target, json } = command {
+        if let Command::Status { target, json, .. } = command {
             assert_eq!(target, DisplayTarget::Default);
             assert!(json);
         } else {
@@ -224,7 +772,9 @@ mod tests {
         assert_eq!(
             command,
             Command::On {
-                target: DisplayTarget::Named("DP-1".to_string())
+                target: DisplayTarget::Named("DP-1".to_string()),
+                json: false,
+                dry_run: false,
             }
         );
     }
@@ -236,11 +786,48 @@ mod tests {
         assert_eq!(
             command,
             Command::Off {
-                target: DisplayTarget::Named("eDP-1".to_string())
+                target: DisplayTarget::Named("eDP-1".to_string()),
+                json: false,
+                dry_run: false,
+
+                timeout: None,
             }
         );
     }
 
+    #[test]
+    fn parse_off_dry_run() {
+        let cli = Cli::try_parse_from(["dpms", "off", "HDMI-A-1", "--dry-run"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::Off {
+                target: DisplayTarget::Named("HDMI-A-1".to_string()),
+                json: false,
+                dry_run: true,
+
+                timeout: None,
+            }
+        );
+        assert!(Cli::try_parse_from(["dpms", "toggle", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn parse_off_timeout() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--timeout", "30m"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::Off {
+                target: DisplayTarget::Default,
+                json: false,
+                dry_run: false,
+                timeout: Some(Duration::from_secs(1800)),
+            }
+        );
+        assert!(Cli::try_parse_from(["dpms", "off", "--timeout", "30m", "--dry-run"]).is_err());
+    }
+
     #[test]
     fn parse_off_all() {
         let cli = Cli::try_parse_from(["dpms", "off", "--all"]).unwrap();
@@ -248,7 +835,11 @@ mod tests {
         assert_eq!(
             command,
             Command::Off {
-                target: DisplayTarget::All
+                target: DisplayTarget::All,
+                json: false,
+                dry_run: false,
+
+                timeout: None,
             }
         );
     }
@@ -260,7 +851,8 @@ mod tests {
         assert_eq!(
             command,
             Command::Toggle {
-                target: DisplayTarget::Default
+                target: DisplayTarget::Default,
+                json: false,
             }
         );
     }
@@ -272,7 +864,8 @@ mod tests {
         assert_eq!(
             command,
             Command::Toggle {
-                target: DisplayTarget::Named("DP-1".to_string())
+                target: DisplayTarget::Named("DP-1".to_string()),
+                json: false,
             }
         );
     }
@@ -316,6 +909,174 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_daemon_internal_connector() {
+        let cli =
+            Cli::try_parse_from(["dpms", "daemon-internal", "--connector", "HDMI-A-1"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::DaemonInternal {
+                connector: Some("HDMI-A-1".to_string()),
+                foreground: false,
+                systemd: false,
+                restore_at: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_daemon_internal_foreground() {
+        let cli = Cli::try_parse_from(["dpms", "daemon-internal", "--foreground"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::DaemonInternal {
+                connector: None,
+                foreground: true,
+                systemd: false,
+                restore_at: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_daemon_internal_systemd() {
+        let cli = Cli::try_parse_from(["dpms", "daemon-internal", "--systemd"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::DaemonInternal {
+                connector: None,
+                foreground: false,
+                systemd: true,
+                restore_at: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_daemon_internal_restore_at() {
+        let cli =
+            Cli::try_parse_from(["dpms", "daemon-internal", "--restore-at", "1714557600"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert!(matches!(
+            command,
+            Command::DaemonInternal {
+                restore_at: Some(1_714_557_600),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_recover() {
+        let cli = Cli::try_parse_from(["dpms", "recover"]).unwrap();
+        assert_eq!(command_from_commands(cli.command), Command::Recover);
+    }
+
+    #[test]
+    fn parse_daemon_status() {
+        let cli = Cli::try_parse_from(["dpms", "daemon", "status"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::DaemonStatus { json: false }
+        );
+        let cli = Cli::try_parse_from(["dpms", "daemon", "status", "--json"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::DaemonStatus { json: true }
+        );
+        assert!(Cli::try_parse_from(["dpms", "daemon"]).is_err());
+    }
+
+    #[test]
+    fn parse_timeouts() {
+        let cli = Cli::try_parse_from(["dpms", "timeouts", "600", "900", "1200"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::Timeouts {
+                action: TimeoutsAction::Set(DpmsTimeouts {
+                    standby: 600,
+                    suspend: 900,
+                    off: 1200
+                })
+            }
+        );
+        let cli = Cli::try_parse_from(["dpms", "timeouts", "--show"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::Timeouts {
+                action: TimeoutsAction::Show
+            }
+        );
+        let cli = Cli::try_parse_from(["dpms", "timeouts", "--disable"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::Timeouts {
+                action: TimeoutsAction::Disable
+            }
+        );
+
+        assert!(Cli::try_parse_from(["dpms", "timeouts"]).is_err());
+        assert!(Cli::try_parse_from(["dpms", "timeouts", "600", "900"]).is_err());
+        assert!(Cli::try_parse_from(["dpms", "timeouts", "600", "900", "soon"]).is_err());
+        assert!(Cli::try_parse_from(["dpms", "timeouts", "70000", "0", "0"]).is_err());
+        assert!(Cli::try_parse_from(["dpms", "timeouts", "--show", "--disable"]).is_err());
+        assert!(Cli::try_parse_from(["dpms", "timeouts", "1", "2", "3", "--show"]).is_err());
+    }
+
+    #[test]
+    fn timeouts_must_not_decrease() {
+        assert!(timeouts_action(&[600, 600, 600], false, false).is_ok());
+        assert!(timeouts_action(&[0, 0, 0], false, false).is_ok());
+        // Disabled stages don't count
+        assert!(timeouts_action(&[600, 0, 300], false, false).is_err());
+        assert!(timeouts_action(&[0, 900, 600], false, false).is_err());
+        assert!(timeouts_action(&[600, 0, 900], false, false).is_ok());
+        assert_eq!(
+            timeouts_action(&[900, 600, 1200], false, false),
+            Err(
+                "the suspend timeout (600s) is shorter than the standby timeout (900s)".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn parse_serve() {
+        let cli = Cli::try_parse_from(["dpms", "serve"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::Serve { install: false }
+        );
+        let cli = Cli::try_parse_from(["dpms", "serve", "--install"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::Serve { install: true }
+        );
+    }
+
+    #[test]
+    fn parse_repl() {
+        let cli = Cli::try_parse_from(["dpms", "repl"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::Repl { watch: false }
+        );
+        let cli = Cli::try_parse_from(["dpms", "repl", "--watch"]).unwrap();
+        assert_eq!(
+            command_from_commands(cli.command),
+            Command::Repl { watch: true }
+        );
+    }
+
+    #[test]
+    fn parse_daemon_restart() {
+        let cli = Cli::try_parse_from(["dpms", "daemon", "restart", "--with-backlight"]).unwrap();
+        assert!(cli.with_backlight);
+        assert_eq!(command_from_commands(cli.command), Command::DaemonRestart);
+    }
+
     #[test]
     fn parse_list_verbose_long() {
         let cli = Cli::try_parse_from(["dpms", "list", "--verbose"]).unwrap();
@@ -337,7 +1098,11 @@ mod tests {
             command,
             Command::Status {
                 target: DisplayTarget::Named("DP-1".to_string()),
-                json: false
+                json: false,
+                compat: false,
+                short: false,
+                watch: false,
+                max_reconnects: DEFAULT_MAX_RECONNECTS,
             }
         );
     }
@@ -350,11 +1115,258 @@ mod tests {
             command,
             Command::Status {
                 target: DisplayTarget::Named("DP-1".to_string()),
-                json: true
+                json: true,
+                compat: false,
+                short: false,
+                watch: false,
+                max_reconnects: DEFAULT_MAX_RECONNECTS,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_status_watch() {
+        let cli = Cli::try_parse_from(["dpms", "status", "--watch"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::Status {
+                target: DisplayTarget::Default,
+                json: false,
+                compat: false,
+                short: false,
+                watch: true,
+                max_reconnects: DEFAULT_MAX_RECONNECTS,
+            }
+        );
+    }
+
+    #[test]
+    fn parse_status_watch_max_reconnects() {
+        let cli =
+            Cli::try_parse_from(["dpms", "status", "--watch", "--max-reconnects", "10"]).unwrap();
+        let command = command_from_commands(cli.command);
+        if let Command::Status {
+            watch,
+            max_reconnects,
+            ..
+        } = command
+        {
+            assert!(watch);
+            assert_eq!(max_reconnects, 10);
+        } else {
+            panic!("Expected Command::Status, got {:?}", command);
+        }
+    }
+
+    #[test]
+    fn parse_status_all() {
+        let cli = Cli::try_parse_from(["dpms", "status", "--all", "--json"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert!(matches!(
+            command,
+            Command::Status {
+                target: DisplayTarget::All,
+                json: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_status_compat() {
+        let cli = Cli::try_parse_from(["dpms", "status", "--json", "--compat"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert!(matches!(
+            command,
+            Command::Status {
+                json: true,
+                compat: true,
+                ..
             }
+        ));
+        assert!(Cli::try_parse_from(["dpms", "status", "--compat"]).is_err());
+        assert!(Cli::try_parse_from(["dpms", "status", "--json", "--compat", "--watch"]).is_err());
+    }
+
+    #[test]
+    fn parse_status_short() {
+        let cli = Cli::try_parse_from(["dpms", "status", "--short", "--watch"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert!(matches!(
+            command,
+            Command::Status {
+                short: true,
+                watch: true,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["dpms", "status", "--short", "--json"]).is_err());
+    }
+
+    #[test]
+    fn parse_max_reconnects_requires_watch() {
+        let result = Cli::try_parse_from(["dpms", "status", "--max-reconnects", "10"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_wayland_socket_option() {
+        let cli = Cli::try_parse_from([
+            "dpms",
+            "--wayland-socket",
+            "/run/user/1001/wayland-1",
+            "off",
+        ])
+        .unwrap();
+        assert_eq!(
+            cli.wayland_socket,
+            Some(PathBuf::from("/run/user/1001/wayland-1"))
         );
     }
 
+    #[test]
+    fn parse_wayland_socket_after_subcommand() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--wayland-socket", "wayland-1"]).unwrap();
+        assert_eq!(cli.wayland_socket, Some(PathBuf::from("wayland-1")));
+    }
+
+    #[test]
+    fn parse_without_wayland_socket() {
+        let cli = Cli::try_parse_from(["dpms", "off"]).unwrap();
+        assert_eq!(cli.wayland_socket, None);
+    }
+
+    #[test]
+    fn parse_card_option() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--card", "/dev/dri/card1"]).unwrap();
+        assert_eq!(cli.card, Some(PathBuf::from("/dev/dri/card1")));
+        let cli =
+            Cli::try_parse_from(["dpms", "daemon-internal", "--card", "/dev/dri/card1"]).unwrap();
+        assert_eq!(cli.card, Some(PathBuf::from("/dev/dri/card1")));
+    }
+
+    #[test]
+    fn drm_device_env_applies_without_card() {
+        let card9 = || Some(OsString::from("/dev/dri/card9"));
+        assert_eq!(
+            card_override(None, card9()),
+            Some(PathBuf::from("/dev/dri/card9"))
+        );
+        assert_eq!(
+            card_override(Some(PathBuf::from("/dev/dri/card1")), card9()),
+            Some(PathBuf::from("/dev/dri/card1"))
+        );
+        assert_eq!(card_override(None, Some(OsString::new())), None);
+        assert_eq!(card_override(None, None), None);
+    }
+
+    #[test]
+    fn parse_seat_option() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--seat", "seat1"]).unwrap();
+        assert_eq!(cli.seat.as_deref(), Some("seat1"));
+        let cli = Cli::try_parse_from(["dpms", "--seat", "seat1", "daemon-internal"]).unwrap();
+        assert_eq!(cli.seat.as_deref(), Some("seat1"));
+    }
+
+    #[test]
+    fn parse_allow_new_displays_flag() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--allow-new-displays"]).unwrap();
+        assert!(cli.allow_new_displays);
+        let cli = Cli::try_parse_from(["dpms", "daemon-internal", "--allow-new-displays"]).unwrap();
+        assert!(cli.allow_new_displays);
+        let cli = Cli::try_parse_from(["dpms", "off"]).unwrap();
+        assert!(!cli.allow_new_displays);
+    }
+
+    #[test]
+    fn parse_with_backlight_flag() {
+        let cli = Cli::try_parse_from(["dpms", "off", "eDP-1", "--with-backlight"]).unwrap();
+        assert!(cli.with_backlight);
+        let cli = Cli::try_parse_from(["dpms", "daemon-internal", "--with-backlight"]).unwrap();
+        assert!(cli.with_backlight);
+    }
+
+    #[test]
+    fn parse_restore_on_suspend_flag() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--restore-on-suspend"]).unwrap();
+        assert!(cli.restore_on_suspend);
+        let cli = Cli::try_parse_from(["dpms", "daemon-internal", "--restore-on-suspend"]).unwrap();
+        assert!(cli.restore_on_suspend);
+    }
+
+    #[test]
+    fn parse_inhibit_sleep_flag() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--inhibit-sleep"]).unwrap();
+        assert!(cli.inhibit_sleep);
+        let cli = Cli::try_parse_from(["dpms", "daemon-internal", "--inhibit-sleep"]).unwrap();
+        assert!(cli.inhibit_sleep);
+        let cli = Cli::try_parse_from(["dpms", "off"]).unwrap();
+        assert!(!cli.inhibit_sleep);
+    }
+
+    #[test]
+    fn parse_until_input_flag() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--until-input"]).unwrap();
+        assert!(cli.until_input);
+        let cli = Cli::try_parse_from(["dpms", "off"]).unwrap();
+        assert!(!cli.until_input);
+    }
+
+    #[test]
+    fn parse_force_tty_flag() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--force-tty"]).unwrap();
+        assert!(cli.force_tty);
+        let cli = Cli::try_parse_from(["dpms", "off"]).unwrap();
+        assert!(!cli.force_tty);
+    }
+
+    #[test]
+    fn parse_kill_after() {
+        let cli = Cli::try_parse_from(["dpms", "on", "--kill-after", "3"]).unwrap();
+        assert_eq!(cli.kill_after, Some(Duration::from_secs(3)));
+        let cli = Cli::try_parse_from(["dpms", "--kill-after", "1m", "recover"]).unwrap();
+        assert_eq!(cli.kill_after, Some(Duration::from_secs(60)));
+        let cli = Cli::try_parse_from(["dpms", "on"]).unwrap();
+        assert_eq!(cli.kill_after, None);
+        assert!(Cli::try_parse_from(["dpms", "on", "--kill-after", "soon"]).is_err());
+    }
+
+    #[test]
+    fn parse_no_service() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--no-service"]).unwrap();
+        assert!(cli.no_service);
+        let cli = Cli::try_parse_from(["dpms", "--no-service", "status"]).unwrap();
+        assert!(cli.no_service);
+        let cli = Cli::try_parse_from(["dpms", "off"]).unwrap();
+        assert!(!cli.no_service);
+    }
+
+    #[test]
+    fn parse_host() {
+        let cli = Cli::try_parse_from(["dpms", "--host", "me@htpc:2222", "off"]).unwrap();
+        assert_eq!(
+            cli.host,
+            Some(Destination {
+                target: "me@htpc".to_string(),
+                port: Some(2222)
+            })
+        );
+        let cli = Cli::try_parse_from(["dpms", "status", "--json", "--host", "htpc"]).unwrap();
+        assert_eq!(cli.host.map(|host| host.target), Some("htpc".to_string()));
+        assert!(Cli::try_parse_from(["dpms", "off", "--host", "htpc:ssh"]).is_err());
+    }
+
+    #[test]
+    fn parse_no_libseat_flag() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--no-libseat"]).unwrap();
+        assert!(cli.no_libseat);
+        let cli = Cli::try_parse_from(["dpms", "--no-libseat", "daemon-internal"]).unwrap();
+        assert!(cli.no_libseat);
+        let cli = Cli::try_parse_from(["dpms", "off"]).unwrap();
+        assert!(!cli.no_libseat);
+    }
+
     #[test]
     fn parse_completion_bash() {
         let cli = Cli::try_parse_from(["dpms", "completion", "bash"]).unwrap();
@@ -419,4 +1431,118 @@ mod tests {
         let err = result.unwrap_err();
         assert_eq!(err.exit_code(), 2, "Usage errors should exit with code 2");
     }
+
+    #[test]
+    fn help_lists_exit_codes() {
+        let help = Cli::command().render_help().to_string();
+        assert!(help.contains("Exit codes:"));
+        for code in [
+            ExitCode::Success,
+            ExitCode::Error,
+            ExitCode::Usage,
+            ExitCode::NoDisplay,
+            ExitCode::Permission,
+            ExitCode::Daemon,
+            ExitCode::Unsupported,
+        ] {
+            let line = format!("\n  {}  ", code as u8);
+            assert!(help.contains(&line), "--help misses exit code {:?}", code);
+        }
+    }
+
+    #[test]
+    fn parse_backend_defaults_to_auto() {
+        let cli = Cli::try_parse_from(["dpms", "status"]).unwrap();
+        assert_eq!(cli.backend, BackendChoice::Auto);
+    }
+
+    #[test]
+    fn parse_backend_hyprland() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--backend", "hyprland"]).unwrap();
+        assert_eq!(cli.backend, BackendChoice::Hyprland);
+    }
+
+    #[test]
+    fn parse_backend_invalid() {
+        let result = Cli::try_parse_from(["dpms", "--backend", "x11", "off"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_idle() {
+        let cli = Cli::try_parse_from(["dpms", "idle", "--timeout", "5m"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::Idle {
+                target: DisplayTarget::Default,
+                timeout: Duration::from_secs(300),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_idle_with_display() {
+        let cli = Cli::try_parse_from(["dpms", "idle", "DP-1", "--timeout", "90"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::Idle {
+                target: DisplayTarget::Named("DP-1".to_string()),
+                timeout: Duration::from_secs(90),
+            }
+        );
+    }
+
+    #[test]
+    fn parse_idle_requires_timeout() {
+        let result = Cli::try_parse_from(["dpms", "idle"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("-5s").is_err());
+    }
+
+    #[test]
+    fn parse_compositor_timeout_default() {
+        let cli = Cli::try_parse_from(["dpms", "status"]).unwrap();
+        assert_eq!(cli.compositor_timeout, DEFAULT_COMPOSITOR_TIMEOUT);
+    }
+
+    #[test]
+    fn parse_compositor_timeout() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--compositor-timeout", "2s"]).unwrap();
+        assert_eq!(cli.compositor_timeout, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn parse_off_all_json() {
+        let cli = Cli::try_parse_from(["dpms", "off", "--all", "--json"]).unwrap();
+        let command = command_from_commands(cli.command);
+        assert_eq!(
+            command,
+            Command::Off {
+                target: DisplayTarget::All,
+                json: true,
+                dry_run: false,
+
+                timeout: None,
+            }
+        );
+    }
 }
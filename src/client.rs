@@ -0,0 +1,226 @@
+/// Client for a running `dpms serve`
+///
+/// While `dpms serve` owns its bus name, `dpms on`, `off`, `toggle`,
+/// `status` and `list` ask it instead of opening their own Wayland or DRM
+/// connection, so the displays have a single owner. The probe only looks
+/// for an owner of the name, so an installed service file doesn't get the
+/// service started, and gives up after `PROBE_TIMEOUT`; without a running
+/// service the command falls back to the backends as usual.
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::{Connection, Proxy};
+use zbus::names::BusName;
+
+use crate::backend::PowerBackend;
+use crate::cli::Command;
+use crate::display::{DisplayInfo, DisplayTarget};
+use crate::error::{Context, Error};
+use crate::output::PowerState;
+use crate::service::{SERVICE_IFACE, SERVICE_NAME, SERVICE_PATH};
+
+/// How long to look for a running service before using the backends directly
+const PROBE_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Convert a D-Bus error from calling `method` into our error type, keeping
+/// only the service's message for failed calls
+fn dbus_error(method: &str, e: zbus::Error) -> Error {
+    match e {
+        zbus::Error::MethodError(_, Some(message), _) => Error::DbusError(message.into()),
+        e => Error::DbusError(Context::wrap(format!("{} failed", method), e)),
+    }
+}
+
+/// Whether `command` can be carried out by the service
+///
+/// Dry runs and `--timeout` need the TTY backend's own support, and
+/// watching is left to the backends, which report changes as they happen.
+pub fn routes(command: &Command) -> bool {
+    match command {
+        Command::On { dry_run, .. } => !dry_run,
+        Command::Off {
+            dry_run, timeout, ..
+        } => !dry_run && timeout.is_none(),
+        Command::Status { watch, .. } => !watch,
+        Command::Toggle { .. } | Command::List { .. } => true,
+        _ => false,
+    }
+}
+
+/// Backend that hands every request to a running `dpms serve`
+pub struct ServiceBackend {
+    proxy: Proxy<'static>,
+}
+
+impl ServiceBackend {
+    /// Connect to `dpms serve`, if it is running
+    ///
+    /// # Returns
+    /// `None` if there is no session bus, nobody owns the service's name, or
+    /// the bus didn't answer within `PROBE_TIMEOUT`
+    pub fn connect() -> Option<Self> {
+        let (found, probe) = mpsc::channel();
+        // A stuck bus must not hold up the command, so probe on a thread
+        // that is abandoned after the timeout
+        thread::spawn(move || {
+            let _ = found.send(probe_service());
+        });
+        let connection = probe.recv_timeout(PROBE_TIMEOUT).ok()??;
+        let proxy = Proxy::new_owned(
+            connection,
+            SERVICE_NAME.to_string(),
+            SERVICE_PATH.to_string(),
+            SERVICE_IFACE.to_string(),
+        )
+        .ok()?;
+        Some(ServiceBackend { proxy })
+    }
+}
+
+/// Connect to the session bus and check that the service's name is owned
+fn probe_service() -> Option<Connection> {
+    let connection = Connection::session().ok()?;
+    let name = BusName::try_from(SERVICE_NAME).ok()?;
+    DBusProxy::new(&connection)
+        .ok()?
+        .name_has_owner(name)
+        .ok()?
+        .then_some(connection)
+}
+
+/// The displays `target` names among `displays`
+fn select(displays: Vec<DisplayInfo>, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+    let DisplayTarget::Named(name) = target else {
+        return Ok(displays);
+    };
+    match displays.iter().position(|d| d.name == *name) {
+        Some(index) => Ok(vec![displays[index].clone()]),
+        None => Err(Error::DisplayNotFound {
+            name: name.clone(),
+            available: displays.into_iter().map(|d| d.name).collect(),
+        }),
+    }
+}
+
+/// Build the DisplayInfo for a display reported by `ListDisplays`
+fn display_info((name, description, on): (String, String, bool)) -> DisplayInfo {
+    DisplayInfo {
+        name,
+        power: if on { PowerState::On } else { PowerState::Off },
+        description: (!description.is_empty()).then_some(description),
+        make: None,
+        model: None,
+        drm: None,
+    }
+}
+
+impl PowerBackend for ServiceBackend {
+    fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
+        // The service treats an empty name as its default displays
+        let display = match target {
+            DisplayTarget::Named(name) => name.as_str(),
+            DisplayTarget::All | DisplayTarget::Default => "",
+        };
+        self.proxy
+            .call::<_, _, ()>("SetPower", &(display, state.name()))
+            .map_err(|e| dbus_error("SetPower", e))
+    }
+
+    fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+        select(self.list_displays()?, target)
+    }
+
+    fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
+        let displays: Vec<(String, String, bool)> = self
+            .proxy
+            .call("ListDisplays", &())
+            .map_err(|e| dbus_error("ListDisplays", e))?;
+        Ok(displays.into_iter().map(display_info).collect())
+    }
+
+    fn name(&self) -> &'static str {
+        "service"
+    }
+
+    fn describe(&self) -> String {
+        format!("service ({})", SERVICE_NAME)
+    }
+
+    fn via(&self) -> Option<&'static str> {
+        Some("service")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listed(name: &str, on: bool) -> DisplayInfo {
+        display_info((name.to_string(), String::new(), on))
+    }
+
+    #[test]
+    fn only_plain_power_and_status_commands_are_routed() {
+        let off = |dry_run, timeout| Command::Off {
+            target: DisplayTarget::Default,
+            json: false,
+            dry_run,
+            timeout,
+        };
+        assert!(routes(&off(false, None)));
+        assert!(!routes(&off(true, None)));
+        assert!(!routes(&off(false, Some(Duration::from_secs(60)))));
+        assert!(routes(&Command::List {
+            json: true,
+            verbose: false
+        }));
+        assert!(!routes(&Command::Status {
+            target: DisplayTarget::All,
+            json: false,
+            compat: false,
+            short: false,
+            watch: true,
+            max_reconnects: 0,
+        }));
+        assert!(!routes(&Command::Serve { install: false }));
+        assert!(!routes(&Command::Recover));
+    }
+
+    #[test]
+    fn listed_displays_become_display_info() {
+        let display = display_info(("DP-1".to_string(), "Dell U2720Q".to_string(), false));
+        assert_eq!(display.name, "DP-1");
+        assert_eq!(display.power, PowerState::Off);
+        assert_eq!(display.description.as_deref(), Some("Dell U2720Q"));
+        assert_eq!(listed("eDP-1", true).description, None);
+    }
+
+    #[test]
+    fn targets_select_among_listed_displays() {
+        let displays = vec![listed("DP-1", true), listed("eDP-1", false)];
+
+        assert_eq!(
+            select(displays.clone(), &DisplayTarget::All).unwrap().len(),
+            2
+        );
+        assert_eq!(
+            select(displays.clone(), &DisplayTarget::Default)
+                .unwrap()
+                .len(),
+            2
+        );
+        let named = select(displays.clone(), &DisplayTarget::Named("eDP-1".to_string())).unwrap();
+        assert_eq!(named.len(), 1);
+        assert_eq!(named[0].power, PowerState::Off);
+
+        match select(displays, &DisplayTarget::Named("HDMI-A-1".to_string())) {
+            Err(Error::DisplayNotFound { name, available }) => {
+                assert_eq!(name, "HDMI-A-1");
+                assert_eq!(available, ["DP-1", "eDP-1"]);
+            }
+            other => panic!("expected DisplayNotFound, got {:?}", other.map(|d| d.len())),
+        }
+    }
+}
@@ -0,0 +1,274 @@
+/// Daemon control socket
+///
+/// Each daemon listens on a Unix stream socket next to its PID file, e.g.
+/// `dpms.sock`, for requests from other dpms processes. A request is a
+/// single line such as `restore-at 1714557600`; the daemon answers with
+/// `ok` or `error: <reason>` and closes the connection. `status` is
+/// answered with `ok ` and the daemon's state JSON on one line.
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long either side waits for the other
+const IO_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Longest request line the daemon reads
+const MAX_REQUEST_LEN: u64 = 256;
+
+/// What another dpms process can ask the daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    /// Restore the displays and exit at this time, in seconds since the epoch
+    RestoreAt(u64),
+    /// Report what the daemon is doing
+    Status,
+}
+
+impl Request {
+    /// Parse a request line
+    ///
+    /// # Returns
+    /// The request, or why it isn't one
+    fn parse(line: &str) -> Result<Request, String> {
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next(), words.next()) {
+            (Some("restore-at"), Some(secs), None) => secs
+                .parse()
+                .map(Request::RestoreAt)
+                .map_err(|_| format!("invalid time '{}'", secs)),
+            (Some("status"), None, _) => Ok(Request::Status),
+            (Some(verb), ..) => Err(format!("unknown request '{}'", verb)),
+            (None, ..) => Err("empty request".to_string()),
+        }
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Request::RestoreAt(secs) => write!(f, "restore-at {}", secs),
+            Request::Status => write!(f, "status"),
+        }
+    }
+}
+
+/// The daemon's end of the control socket
+///
+/// The socket file is removed again when this is dropped.
+pub struct ControlSocket {
+    listener: UnixListener,
+    path: PathBuf,
+    /// The answer to `status`
+    status: String,
+}
+
+impl ControlSocket {
+    /// Listen at `path`, replacing a socket left behind by a killed daemon
+    ///
+    /// Only the daemon's user may connect. Only call this while holding the instance's lock, so no live daemon
+    /// is listening there.
+    pub fn bind(path: &Path) -> io::Result<Self> {
+        let _ = fs::remove_file(path);
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        listener.set_nonblocking(true)?;
+        Ok(ControlSocket {
+            listener,
+            path: path.to_path_buf(),
+            status: String::new(),
+        })
+    }
+
+    /// Set what `status` requests are answered with, on a single line
+    pub fn set_status(&mut self, status: String) {
+        self.status = status;
+    }
+
+    /// Take the next waiting request, answering `ok` once it is understood
+    ///
+    /// Malformed requests are answered with an error and skipped, and
+    /// `status` requests are answered here.
+    ///
+    /// # Returns
+    /// `None` once no more connections are waiting
+    pub fn accept(&self) -> Option<Request> {
+        loop {
+            let (stream, _) = self.listener.accept().ok()?;
+            if let Some(request) = serve(stream, &self.status) {
+                return Some(request);
+            }
+        }
+    }
+}
+
+impl AsFd for ControlSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.listener.as_fd()
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Read one request from `stream` and answer it
+///
+/// # Returns
+/// The request, unless it was malformed or answered with `status`
+fn serve(stream: UnixStream, status: &str) -> Option<Request> {
+    stream.set_nonblocking(false).ok()?;
+    stream.set_read_timeout(Some(IO_TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(IO_TIMEOUT)).ok()?;
+
+    let mut line = String::new();
+    BufReader::new((&stream).take(MAX_REQUEST_LEN))
+        .read_line(&mut line)
+        .ok()?;
+    let (request, reply) = match Request::parse(&line) {
+        Ok(Request::Status) => (None, format!("ok {}", status)),
+        Ok(request) => (Some(request), "ok".to_string()),
+        Err(reason) => (None, format!("error: {}", reason)),
+    };
+    let _ = writeln!(&stream, "{}", reply);
+    request
+}
+
+/// Send `request` to the daemon listening at `path`
+///
+/// # Returns
+/// - `Ok(())` - The daemon accepted the request
+/// - `Err(_)` - No daemon answered, or it refused the request
+pub fn send(path: &Path, request: Request) -> io::Result<()> {
+    query(path, request).map(|_| ())
+}
+
+/// Send `request` to the daemon listening at `path` and read its answer
+///
+/// # Returns
+/// - `Ok(String)` - What the daemon answered after `ok`; empty for most
+///   requests
+/// - `Err(_)` - No daemon answered, or it refused the request
+pub fn query(path: &Path, request: Request) -> io::Result<String> {
+    let stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    writeln!(&stream, "{}", request)?;
+
+    let mut reply = String::new();
+    BufReader::new(&stream).read_line(&mut reply)?;
+    let reply = reply.trim_end();
+    if let Some(answer) = reply.strip_prefix("ok ") {
+        return Ok(answer.to_string());
+    }
+    match reply {
+        "ok" => Ok(String::new()),
+        "" => Err(io::Error::other("the daemon did not answer")),
+        other => Err(io::Error::other(
+            other.strip_prefix("error: ").unwrap_or(other).to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn parses_requests() {
+        assert_eq!(
+            Request::parse("restore-at 1714557600\n"),
+            Ok(Request::RestoreAt(1_714_557_600))
+        );
+        assert_eq!(
+            Request::parse(&Request::RestoreAt(42).to_string()),
+            Ok(Request::RestoreAt(42))
+        );
+        assert!(Request::parse("restore-at soon").is_err());
+        assert!(Request::parse("restore-at 1 2").is_err());
+        assert_eq!(Request::parse("status\n"), Ok(Request::Status));
+        assert!(Request::parse("status now").is_err());
+        assert!(Request::parse("toggle").is_err());
+        assert!(Request::parse("").is_err());
+    }
+
+    #[test]
+    fn request_round_trip() {
+        let path = Path::new("/tmp/dpms-test-control.sock");
+        let socket = ControlSocket::bind(path).unwrap();
+        assert_eq!(socket.accept(), None);
+        assert_eq!(
+            fs::metadata(path).unwrap().permissions().mode() & 0o777,
+            0o600
+        );
+
+        let client = thread::spawn(|| {
+            let path = Path::new("/tmp/dpms-test-control.sock");
+            let accepted = send(path, Request::RestoreAt(1_000));
+            let refused = UnixStream::connect(path).and_then(|stream| {
+                writeln!(&stream, "toggle")?;
+                let mut reply = String::new();
+                BufReader::new(&stream).read_line(&mut reply)?;
+                Ok(reply)
+            });
+            (accepted, refused)
+        });
+
+        // The malformed request is answered and skipped
+        let mut request = None;
+        for _ in 0..100 {
+            request = socket.accept();
+            if request.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut refused = None;
+        for _ in 0..100 {
+            if client.is_finished() {
+                refused = Some(client.join().unwrap());
+                break;
+            }
+            assert_eq!(socket.accept(), None);
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(request, Some(Request::RestoreAt(1_000)));
+        let (accepted, refused) = refused.unwrap();
+        assert!(accepted.is_ok());
+        assert_eq!(refused.unwrap(), "error: unknown request 'toggle'\n");
+
+        drop(socket);
+        assert!(!path.exists());
+        assert!(send(path, Request::RestoreAt(1_000)).is_err());
+    }
+
+    #[test]
+    fn status_is_answered_by_the_socket() {
+        let path = Path::new("/tmp/dpms-test-control-status.sock");
+        let mut socket = ControlSocket::bind(path).unwrap();
+        socket.set_status(r#"{"pid":42}"#.to_string());
+
+        let client = thread::spawn(|| {
+            let path = Path::new("/tmp/dpms-test-control-status.sock");
+            query(path, Request::Status)
+        });
+        for _ in 0..100 {
+            // Status is answered without being handed on
+            assert_eq!(socket.accept(), None);
+            if client.is_finished() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert_eq!(client.join().unwrap().unwrap(), r#"{"pid":42}"#);
+    }
+}
@@ -0,0 +1,135 @@
+/// What the user sees when dpms panics
+///
+/// A panic is a bug in dpms, not something the user can fix, so instead of
+/// Rust's message they get one paragraph: the command, the backend, the
+/// version and where to report it. `DPMS_BACKTRACE=1` adds the backtrace.
+/// The hook only prints; unwinding goes on, so the daemon's panic guard still
+/// turns the displays back on, and `main` exits with 1 rather than 101.
+use std::backtrace::Backtrace;
+use std::panic::{self, PanicHookInfo};
+use std::sync::{Mutex, PoisonError};
+
+/// Set to `1` to have the backtrace printed after the report
+pub const BACKTRACE_ENV: &str = "DPMS_BACKTRACE";
+
+/// Where crashes get reported
+const ISSUES_URL: &str = concat!(env!("CARGO_PKG_REPOSITORY"), "/issues");
+
+/// The backend running the command, once one is chosen
+static BACKEND: Mutex<Option<&'static str>> = Mutex::new(None);
+
+/// Record that `backend`, e.g. `wayland`, runs the command from now on
+pub fn set_backend(backend: &'static str) {
+    *BACKEND.lock().unwrap_or_else(PoisonError::into_inner) = Some(backend);
+}
+
+/// Report panics with `report`, adding the backtrace if `DPMS_BACKTRACE=1`
+pub fn install() {
+    panic::set_hook(Box::new(|info| {
+        let backtrace = std::env::var_os(BACKTRACE_ENV).is_some_and(|v| v == "1");
+        eprint!("{}", report(info, backtrace));
+        if backtrace {
+            eprintln!("{}", Backtrace::force_capture());
+        }
+    }));
+}
+
+/// The report for the panic `info`
+///
+/// # Parameters
+/// - `backtrace`: Whether the backtrace is printed after it; if not, the
+///   report says how to get one
+pub fn report(info: &PanicHookInfo<'_>, backtrace: bool) -> String {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic");
+    let command: Vec<String> = std::env::args_os()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let backend = *BACKEND.lock().unwrap_or_else(PoisonError::into_inner);
+
+    format_report(
+        &command.join(" "),
+        backend,
+        message,
+        info.location().map(ToString::to_string).as_deref(),
+        backtrace,
+    )
+}
+
+/// Put a panic in one paragraph, ending in a newline
+fn format_report(
+    command: &str,
+    backend: Option<&str>,
+    message: &str,
+    location: Option<&str>,
+    backtrace: bool,
+) -> String {
+    let backend = match backend {
+        Some(backend) => format!("{} backend", backend),
+        None => "no backend chosen yet".to_string(),
+    };
+    let location = location.map(|l| format!(" at {}", l)).unwrap_or_default();
+    let next = if backtrace {
+        format!(
+            "Please report it at {} with the backtrace below.",
+            ISSUES_URL
+        )
+    } else {
+        format!(
+            "Run the command again with {}=1 for the full backtrace, and report it at {}.",
+            BACKTRACE_ENV, ISSUES_URL
+        )
+    };
+    format!(
+        "dpms {} crashed running `{}` ({}): {}{}. This is a bug in dpms, not \
+         something you did. {}\n",
+        env!("CARGO_PKG_VERSION"),
+        command,
+        backend,
+        message,
+        location,
+        next
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_says_what_ran_and_how_to_report_it() {
+        let report = format_report(
+            "dpms off HDMI-A-1",
+            Some("wayland"),
+            "index out of bounds",
+            Some("src/wayland.rs:10:5"),
+            false,
+        );
+        assert_eq!(
+            report,
+            format!(
+                "dpms {} crashed running `dpms off HDMI-A-1` (wayland backend): index out \
+                 of bounds at src/wayland.rs:10:5. This is a bug in dpms, not something \
+                 you did. Run the command again with DPMS_BACKTRACE=1 for the full \
+                 backtrace, and report it at {}.\n",
+                env!("CARGO_PKG_VERSION"),
+                ISSUES_URL
+            )
+        );
+        assert_eq!(report.lines().count(), 1);
+    }
+
+    #[test]
+    fn report_before_a_backend_points_at_the_backtrace() {
+        let report = format_report("dpms status", None, "oops", None, true);
+        assert!(report.contains("`dpms status` (no backend chosen yet): oops. "));
+        assert!(report.ends_with(&format!(
+            "Please report it at {} with the backtrace below.\n",
+            ISSUES_URL
+        )));
+    }
+}
@@ -3,436 +3,3069 @@
 /// This module implements the daemon process for TTY display power control.
 /// The daemon holds DRM master to keep the display off and responds to signals:
 /// - SIGTERM/SIGINT: Restore display and exit cleanly
+/// - Seat re-enabled after a VT switch: Turn the displays off again
+/// - DRM hotplug: Turn newly plugged displays off too and forget unplugged ones
 ///
-/// The daemon uses a PID file at `/run/user/$UID/dpms.pid` for single-instance
-/// enforcement and IPC coordination.
-use crate::drm_ops::{SeatHolder, open_drm};
-use crate::error::Error;
+/// Only one daemon runs at a time: it holds an exclusive `flock` on
+/// `/run/user/$UID/dpms.lock` for its lifetime, which the kernel drops when
+/// it dies. Under the lock it writes a PID file at `/run/user/$UID/dpms.pid`
+/// for IPC coordination. The first line holds the PID; following lines
+/// record the daemon's start time, the seat, the displays the daemon turned
+/// off and the devices it failed to turn off. Those runtime files are
+/// handled in `instance`, spawning the daemon in `startup`, and turning
+/// displays back on after a daemon died in `recover`.
+use crate::backlight::Backlight;
+use crate::control::{self, ControlSocket, Request};
+use crate::crash;
+use crate::display;
+use crate::drm_ops::{ConnectorInfo, DrmControl, OpenOptions, SeatHolder, open_drm};
+use crate::error::{Context, Error, ResultExt};
+use crate::fbcon::ConsoleHold;
+use crate::hotplug::HotplugMonitor;
+use crate::input::InputMonitor;
+use crate::logfile;
+use crate::output::{PowerState, format_timestamp, unix_now};
+use crate::sleep::{SleepEvent, SleepInhibitor, SleepMonitor};
+use crate::systemd;
+use crate::ui;
+use drm::control::crtc;
+use nix::errno::Errno;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+#[cfg(target_os = "linux")]
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
-use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use signal_hook::consts::signal::{SIGHUP, SIGINT, SIGTERM, SIGUSR1, SIGUSR2};
 use signal_hook::flag;
+use signal_hook::low_level::pipe;
+use std::collections::VecDeque;
 use std::fs;
-use std::io::{Read, Write};
-use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::io::Read;
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::net::UnixStream;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::Duration;
 
-/// Get the PID file path for the daemon
+mod instance;
+mod recover;
+mod startup;
+
+pub use instance::{
+    DaemonInfo, DaemonStatus, HeldDisplay, Instance, OwnedCrtc, SavedCrtc, daemon_infos,
+    daemon_seat, daemon_statuses, daemons_holding_off, is_daemon_running, owned_crtcs,
+    resolve_instance, running_daemons, runtime_dir, warn_outdated_daemons,
+};
+use instance::{
+    INFO_FORMAT_VERSION, PROCESSES, asserted_name, holds, info_path, is_recorded_daemon,
+    lock_daemon, outdated_daemon, read_asserted, read_daemon_info, read_owned_crtcs, read_pid_file,
+    read_saved_state, read_start_time, remove_pid_file, write_daemon_info, write_pid_file,
+    write_saved_state,
+};
+pub use recover::{has_saved_state, recover, reenable_inactive};
+use recover::{recover_devices, recover_instances};
+use startup::{StartupStatus, daemonize};
+pub use startup::{check_daemon_conflict, set_daemon_executable, start_daemon};
+
+/// How long `stop_daemon` waits for the daemon to exit after a signal
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Longest the CLI sleeps between checks on a daemon it waits for
 ///
-/// # Returns
-/// Path to `/run/user/$UID/dpms.pid`
-///
-/// # Errors
-/// Returns `Error::PidFileError` if XDG_RUNTIME_DIR is not set
-pub fn get_pid_file_path() -> Result<PathBuf, Error> {
-    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| {
-        // Fallback to /run/user/$UID if XDG_RUNTIME_DIR not set
-        let uid = nix::unistd::Uid::effective();
-        format!("/run/user/{}", uid)
-    });
+/// Runtime file changes wake it sooner; this catches what inotify doesn't
+/// report, such as a process exiting.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often the CLI checks while waiting without inotify
+const WAIT_FALLBACK_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How soon the CLI checks again after a runtime file changed, as a daemon
+/// that just closed its files needs a moment more to be gone
+const WAIT_SETTLE_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Exit status of a daemon that panicked, EX_SOFTWARE from sysexits.h
+const PANIC_EXIT_CODE: i32 = 70;
+
+/// How the daemon treats displays while it holds them off
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DaemonOptions {
+    /// Leave displays plugged in while the daemon runs on instead of
+    /// turning them off too
+    pub allow_new_displays: bool,
+    /// Also switch off the backlight of held internal panels
+    pub with_backlight: bool,
+    /// Turn the held displays back on while the system sleeps
+    pub restore_on_suspend: bool,
+    /// Restore the displays and exit at the first input activity
+    pub until_input: bool,
+    /// Block sleep and idle actions while the displays are held off
+    pub inhibit_sleep: bool,
+    /// Restore the displays and exit at this time, in seconds since the epoch
+    pub restore_at: Option<u64>,
+}
+
+impl DaemonOptions {
+    /// Command-line arguments that pass these options on to the daemon
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.allow_new_displays {
+            args.push("--allow-new-displays".to_string());
+        }
+        if self.with_backlight {
+            args.push("--with-backlight".to_string());
+        }
+        if self.restore_on_suspend {
+            args.push("--restore-on-suspend".to_string());
+        }
+        if self.until_input {
+            args.push("--until-input".to_string());
+        }
+        if self.inhibit_sleep {
+            args.push("--inhibit-sleep".to_string());
+        }
+        if let Some(secs) = self.restore_at {
+            args.push("--restore-at".to_string());
+            args.push(secs.to_string());
+        }
+        args
+    }
+}
+
+/// A CRTC the daemon turned off, with the device it belongs to
+struct DisabledCrtc<'a, D> {
+    drm: &'a D,
+    connector: String,
+    crtc: crtc::Handle,
+}
 
-    Ok(PathBuf::from(runtime_dir).join("dpms.pid"))
+impl<D: DrmControl> DisabledCrtc<'_, D> {
+    /// The PID file record for this CRTC
+    fn owned(&self) -> OwnedCrtc {
+        OwnedCrtc {
+            device: self.drm.path().display().to_string(),
+            connector: self.connector.clone(),
+            crtc: self.crtc.into(),
+            mechanism: Some(self.drm.mechanism()),
+        }
+    }
 }
 
-/// Check if a process with the given PID is running
+/// Find the connectors and CRTCs to turn off on one device
 ///
-/// # Parameters
-/// - `pid`: Process ID to check
+/// With a target connector, only that connector is considered. Otherwise
+/// every CRTC driving a connected connector is used.
 ///
 /// # Returns
-/// `true` if the process exists and is running
-fn is_process_running(pid: Pid) -> bool {
-    // Sending signal 0 doesn't actually send a signal, but checks if we can send to the process
-    signal::kill(pid, None).is_ok()
+/// The targeted connectors, each with its CRTC set
+fn find_target_crtcs(
+    drm: &impl DrmControl,
+    connector: Option<&str>,
+) -> Result<Vec<ConnectorInfo>, Error> {
+    match connector {
+        Some(name) => {
+            let crtc_handle = drm.find_crtc_for_connector(name)?;
+            Ok(drm
+                .list_connectors()?
+                .into_iter()
+                .filter(|c| c.name == name && c.crtc == Some(crtc_handle))
+                .collect())
+        }
+        None => drm.find_all_active_crtcs(),
+    }
 }
 
-/// Read PID from PID file
-///
-/// # Parameters
-/// - `path`: Path to PID file
+/// Describe the displays held off for the state JSON
 ///
-/// # Returns
-/// - `Ok(Some(Pid))` - PID was read successfully
-/// - `Ok(None)` - PID file doesn't exist
-/// - `Err(Error)` - Failed to read or parse PID file
-fn read_pid_file<P: AsRef<Path>>(path: P) -> Result<Option<Pid>, Error> {
-    let path = path.as_ref();
+/// `saved` supplies whether each CRTC was active before it was turned off.
+/// The seat and what happened to it are left for the daemon to fill in.
+fn daemon_info<D: DrmControl>(
+    disabled: &[DisabledCrtc<'_, D>],
+    saved: &[SavedCrtc],
+    started: u64,
+    restore_at: Option<u64>,
+    asserted: PowerState,
+) -> DaemonInfo {
+    let displays: Vec<HeldDisplay> = disabled
+        .iter()
+        .map(|entry| {
+            let device = entry.drm.path().display().to_string();
+            let crtc = u32::from(entry.crtc);
+            let was_active = saved
+                .iter()
+                .find(|s| s.device == device && s.crtc == crtc)
+                .is_none_or(|s| s.active);
+            HeldDisplay {
+                device,
+                connector: entry.connector.clone(),
+                crtc,
+                was_active,
+                asserted: asserted_name(asserted).to_string(),
+            }
+        })
+        .collect();
+    let mut devices: Vec<String> = displays.iter().map(|d| d.device.clone()).collect();
+    devices.dedup();
 
-    if !path.exists() {
-        return Ok(None);
+    DaemonInfo {
+        version: INFO_FORMAT_VERSION,
+        pid: Pid::this().as_raw(),
+        started,
+        devices,
+        displays,
+        restore_deadline: restore_at,
+        seat: None,
+        seat_cycles: 0,
+        last_error: None,
+        sleep_inhibited: false,
     }
+}
 
-    let mut file = fs::File::open(path)
-        .map_err(|e| Error::PidFileError(format!("Failed to open PID file: {}", e)))?;
+/// Record the pre-off state of the targeted connectors of a device
+fn save_targets(drm: &impl DrmControl, targets: &[ConnectorInfo]) -> Vec<SavedCrtc> {
+    targets
+        .iter()
+        .filter_map(|c| {
+            let crtc_handle = c.crtc?;
+            Some(SavedCrtc {
+                device: drm.path().display().to_string(),
+                crtc: crtc_handle.into(),
+                connector_id: c.handle.into(),
+                connector: c.name.clone(),
+                // Assume it was lit if the state can't be read
+                active: drm.get_crtc_active(crtc_handle).unwrap_or(true),
+            })
+        })
+        .collect()
+}
+
+/// Turn off the targeted CRTCs of every device
+///
+/// Each device's CRTCs are turned off in a single atomic commit. With a
+/// target connector, only the device that has it is touched. A failure on
+/// one device doesn't stop the others.
+///
+/// `on_save` is called with all CRTC states saved so far right before each
+/// device's CRTCs are turned off, so they can be persisted first.
+///
+/// # Returns
+/// The CRTCs that were turned off, and `(device path, reason)` for each
+/// device that failed
+fn disable_all<'a, D: DrmControl>(
+    devices: &'a [D],
+    connector: Option<&str>,
+    on_save: &mut dyn FnMut(&[SavedCrtc]),
+) -> (Vec<DisabledCrtc<'a, D>>, Vec<(String, String)>) {
+    let mut disabled = Vec::new();
+    let mut failed = Vec::new();
+    let mut saved = Vec::new();
 
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .map_err(|e| Error::PidFileError(format!("Failed to read PID file: {}", e)))?;
+    for drm in devices {
+        let result = find_target_crtcs(drm, connector).and_then(|targets| {
+            let crtcs: Vec<crtc::Handle> = targets.iter().filter_map(|c| c.crtc).collect();
+            saved.extend(save_targets(drm, &targets));
+            on_save(&saved);
+            drm.set_crtcs_active(&crtcs, false).map(|_| targets)
+        });
+        match result {
+            Ok(targets) => {
+                disabled.extend(targets.into_iter().filter_map(|c| {
+                    Some(DisabledCrtc {
+                        drm,
+                        crtc: c.crtc?,
+                        connector: c.name,
+                    })
+                }));
+                // A connector name only exists on one device
+                if connector.is_some() {
+                    return (disabled, Vec::new());
+                }
+            }
+            // The named connector is on another device
+            Err(Error::DisplayNotFound { .. }) => {}
+            Err(e) => failed.push((drm.path().display().to_string(), e.to_string())),
+        }
+    }
 
-    let pid_num: i32 = contents
-        .trim()
-        .parse()
-        .map_err(|e| Error::PidFileError(format!("Invalid PID in file: {}", e)))?;
+    if let (Some(name), true) = (connector, disabled.is_empty() && failed.is_empty()) {
+        failed.push((name.to_string(), "connector not found".to_string()));
+    }
 
-    Ok(Some(Pid::from_raw(pid_num)))
+    (disabled, failed)
 }
 
-/// Write PID to PID file
+/// Check that the targeted CRTCs of every device could be turned off
 ///
-/// # Parameters
-/// - `path`: Path to PID file
-/// - `pid`: PID to write
+/// Like `disable_all`, but the commit turning them off is only tested, so
+/// nothing changes.
 ///
 /// # Returns
-/// - `Ok(())` - PID was written successfully
-/// - `Err(Error)` - Failed to write PID file
-fn write_pid_file<P: AsRef<Path>>(path: P, pid: Pid) -> Result<(), Error> {
-    let path = path.as_ref();
+/// Each display that would be turned off, e.g. `eDP-1 on /dev/dri/card0`,
+/// and `(device path, reason)` for each device that failed
+fn check_all<D: DrmControl>(
+    devices: &[D],
+    connector: Option<&str>,
+) -> (Vec<String>, Vec<(String, String)>) {
+    let mut passed = Vec::new();
+    let mut failed = Vec::new();
 
-    let mut file = fs::File::create(path)
-        .map_err(|e| Error::PidFileError(format!("Failed to create PID file: {}", e)))?;
+    for drm in devices {
+        let device = drm.path().display().to_string();
+        let result = find_target_crtcs(drm, connector).and_then(|targets| {
+            let crtcs: Vec<crtc::Handle> = targets.iter().filter_map(|c| c.crtc).collect();
+            drm.test_crtcs_active(&crtcs, false)?;
+            Ok(targets)
+        });
+        match result {
+            Ok(targets) => {
+                passed.extend(
+                    targets
+                        .into_iter()
+                        .map(|c| format!("{} on {}", c.name, device)),
+                );
+                if connector.is_some() {
+                    return (passed, Vec::new());
+                }
+            }
+            Err(Error::DisplayNotFound { .. }) => {}
+            Err(e) => failed.push((device, e.to_string())),
+        }
+    }
 
-    write!(file, "{}", pid)
-        .map_err(|e| Error::PidFileError(format!("Failed to write PID: {}", e)))?;
+    if let (Some(name), true) = (connector, passed.is_empty() && failed.is_empty()) {
+        failed.push((name.to_string(), "connector not found".to_string()));
+    }
 
-    Ok(())
+    (passed, failed)
 }
 
-/// Remove PID file
+/// Report what turning the displays off would do, without doing it
+///
+/// Opens the devices like the daemon would and tests the commit that
+/// turns the targeted displays off. Turning them back on isn't tested:
+/// while they are lit, that commit changes nothing, so a driver would
+/// accept it even if it later refuses to light a dark CRTC. Restore
+/// failures are handled when they happen, by keeping the state for
+/// `dpms recover`.
 ///
 /// # Parameters
-/// - `path`: Path to PID file
+/// - `connector`: Only check the display on this connector, e.g. `HDMI-A-1`
+/// - `options`: How DRM devices are opened
 ///
 /// # Returns
-/// - `Ok(())` - PID file was removed or didn't exist
-/// - `Err(Error)` - Failed to remove PID file
-fn remove_pid_file<P: AsRef<Path>>(path: P) -> Result<(), Error> {
-    let path = path.as_ref();
+/// - `Ok(Vec<String>)` - Every targeted display could be turned off; a line
+///   saying so for each
+/// - `Err(Error::NoDisplayFound)` - Nothing would be turned off
+/// - `Err(Error::PartialFailure)` - Some devices would fail
+pub fn check_off(connector: Option<&str>, options: &OpenOptions) -> Result<Vec<String>, Error> {
+    let (_seat, devices) = open_drm(options)?;
+    let (passed, failed) = check_all(&devices, connector);
 
-    if path.exists() {
-        fs::remove_file(path)
-            .map_err(|e| Error::PidFileError(format!("Failed to remove PID file: {}", e)))?;
+    match (passed.is_empty(), failed.is_empty()) {
+        (_, false) => Err(Error::PartialFailure { failed }),
+        (true, true) => Err(Error::NoDisplayFound),
+        (false, true) => Ok(passed
+            .iter()
+            .map(|display| format!("Would turn off {}", display))
+            .collect()),
     }
-
-    Ok(())
 }
 
-/// Check if the dpms daemon is currently running
+/// Turn the given CRTCs back on, reporting failures
 ///
-/// Returns the PID of the running daemon, or None if no daemon is running.
-/// Also cleans up stale PID files if the process is no longer alive.
+/// Each CRTC is restored on its own, so one failing doesn't keep the others
+/// dark. CRTCs whose monitor was unplugged in the meantime are skipped.
 ///
 /// # Returns
-/// - `Some(Pid)` - Daemon is running with this PID
-/// - `None` - No daemon is running (or stale PID was cleaned up)
-pub fn is_daemon_running() -> Option<Pid> {
-    let pid_path = match get_pid_file_path() {
-        Ok(p) => p,
-        Err(_) => return None,
-    };
+/// The CRTCs that failed to come back on, with the reason
+fn restore_all<'a, 'd, D: DrmControl>(
+    disabled: &'a [DisabledCrtc<'d, D>],
+) -> Vec<(&'a DisabledCrtc<'d, D>, String)> {
+    let mut failed = Vec::new();
+    for entry in disabled {
+        match entry.drm.crtc_drives_connected(entry.crtc) {
+            Ok(true) => {}
+            Ok(false) => {
+                ui::notice(format_args!(
+                    "Skipping {} on {}: no longer connected",
+                    entry.connector,
+                    entry.drm.path().display()
+                ));
+                continue;
+            }
+            // Still try to restore if the check itself failed
+            Err(e) => ui::warning(format_args!(
+                "Failed to check {} on {}: {}",
+                entry.connector,
+                entry.drm.path().display(),
+                e
+            )),
+        }
 
-    let pid = match read_pid_file(&pid_path) {
-        Ok(Some(pid)) => pid,
-        Ok(None) => return None,
-        Err(_) => return None,
-    };
+        if let Err(e) = entry.drm.set_crtc_active(entry.crtc, true) {
+            ui::warning(format_args!(
+                "Failed to restore CRTC for {} on {}: {}",
+                entry.connector,
+                entry.drm.path().display(),
+                e
+            ));
+            failed.push((entry, e.to_string()));
+        }
+    }
+    failed
+}
 
-    // Check if process is still alive
-    if is_process_running(pid) {
-        Some(pid)
-    } else {
-        // Process is dead, clean up stale PID file
-        let _ = remove_pid_file(&pid_path);
-        None
+/// Keep the saved state of the CRTCs that couldn't be restored, so `dpms
+/// recover` can retry them, and forget the rest
+///
+/// # Returns
+/// - `Ok(())` - Everything was restored; the state file is gone
+/// - `Err(Error::PartialFailure)` - The displays in `unrestored` are still off
+fn keep_unrestored<D: DrmControl>(
+    state_path: &Path,
+    unrestored: &[(&DisabledCrtc<'_, D>, String)],
+) -> Result<(), Error> {
+    if unrestored.is_empty() {
+        let _ = fs::remove_file(state_path);
+        return Ok(());
     }
+
+    let kept: Vec<SavedCrtc> = read_saved_state(state_path)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|saved| {
+            unrestored.iter().any(|(entry, _)| {
+                entry.drm.path().display().to_string() == saved.device
+                    && u32::from(entry.crtc) == saved.crtc
+            })
+        })
+        .collect();
+    if let Err(e) = write_saved_state(state_path, &kept) {
+        ui::warning(e);
+    }
+    ui::warning(format_args!(
+        "Left {} display(s) off for `dpms recover` to retry",
+        unrestored.len()
+    ));
+    Err(Error::PartialFailure {
+        failed: unrestored
+            .iter()
+            .map(|(entry, reason)| (entry.connector.clone(), reason.clone()))
+            .collect(),
+    })
+}
+
+/// Ask the daemons holding `connector` that were toggled on to turn their
+/// displays off again, with SIGUSR2
+///
+/// With `None`, asks the daemon holding every display.
+///
+/// # Returns
+/// - `Ok(true)` - A daemon was asked
+/// - `Ok(false)` - No such daemon was toggled on
+/// - `Err(Error::SignalError)` - Sending the signal failed
+pub fn reassert_daemons(connector: Option<&str>) -> Result<bool, Error> {
+    let mut asked = false;
+    for (instance, pid) in running_daemons() {
+        if holds(&instance, connector) && read_asserted(instance.pid_path()) == PowerState::On {
+            signal::kill(pid, Signal::SIGUSR2)
+                .context(Error::SignalError, "Failed to send SIGUSR2")?;
+            asked = true;
+        }
+    }
+    Ok(asked)
+}
+
+/// Move the time at which the daemons holding `connector` restore their
+/// displays, over their control sockets
+///
+/// With `None`, reschedules the daemon holding every display.
+///
+/// # Parameters
+/// - `restore_at`: The new time, in seconds since the epoch
+///
+/// # Returns
+/// - `Ok(())` - Every such daemon accepted the new time
+/// - `Err(Error::ControlError)` - A daemon couldn't be reached or refused,
+///   or was started by another dpms and can only be signalled
+pub fn reschedule_daemons(connector: Option<&str>, restore_at: u64) -> Result<(), Error> {
+    for (instance, pid) in running_daemons() {
+        if holds(&instance, connector) {
+            if let Some(reason) = outdated_daemon(&instance, pid) {
+                return Err(Error::ControlError(
+                    format!(
+                        "{}: the daemon was started by {} and can't be rescheduled; \
+                     run `dpms daemon restart` first",
+                        instance, reason
+                    )
+                    .into(),
+                ));
+            }
+            control::send(&instance.socket_path(), Request::RestoreAt(restore_at))
+                .with_context(Error::ControlError, || instance.to_string())?;
+        }
+    }
+    Ok(())
 }
 
 /// Daemon main loop
 ///
 /// This function runs in the spawned daemon process. It:
-/// 1. Opens libseat session and DRM device
-/// 2. Disables CRTC (turns off display)
-/// 3. Writes PID file
-/// 4. Registers signal handlers for SIGTERM and SIGINT
-/// 5. Waits for shutdown signal
-/// 6. Restores CRTC (turns on display)
-/// 7. Cleans up and exits
+/// 1. Detaches from the terminal, unless running in the foreground or under
+///    systemd, and logs to `logfile::log_path()` from then on
+/// 2. Opens libseat session and every DRM device driving a display, or
+///    only the card with `connector` on it
+/// 3. Disables the CRTC driving `connector`, or every active CRTC on each
+///    device if no connector is given (turns off displays)
+/// 4. Writes the instance's PID file, recording the owned CRTCs and devices that failed
+/// 5. Registers signal handlers for SIGTERM and SIGINT
+/// 6. Waits for shutdown signal, following VT switches and hotplug
+/// 7. Restores the CRTCs (turns on displays)
+/// 8. Cleans up and exits
+///
+/// # Parameters
+/// - `connector`: Only turn off the display on this connector, e.g. `HDMI-A-1`
+/// - `foreground`: Keep the terminal, working directory and standard streams
+/// - `systemd`: Like `foreground`, and notify systemd once the displays are
+///   off and again when restoring them
+/// - `options`: How DRM devices are opened
+/// - `daemon`: How displays are treated while held off
 ///
 /// # Returns
 /// This function does not return - it exits the process
-pub fn daemon_main() -> ! {
-    // Use signal-hook for safe signal handling
-    let shutdown_requested = Arc::new(AtomicBool::new(false));
+pub fn daemon_main(
+    connector: Option<&str>,
+    foreground: bool,
+    systemd: bool,
+    options: &OpenOptions,
+    daemon: &DaemonOptions,
+) -> ! {
+    let mut status = StartupStatus::from_env();
+    // systemd tracks the process itself and collects its output
+    let detach = !foreground && !systemd;
 
-    // Register signal handlers - these safely set the flag when signals arrive
-    if let Err(e) = flag::register(SIGTERM, Arc::clone(&shutdown_requested)) {
-        eprintln!("Failed to register SIGTERM handler: {}", e);
-        std::process::exit(1);
+    if detach && let Err(e) = daemonize(&mut status) {
+        status.fail(&format!("Failed to detach from terminal: {}", e));
     }
-    if let Err(e) = flag::register(SIGINT, Arc::clone(&shutdown_requested)) {
-        eprintln!("Failed to register SIGINT handler: {}", e);
-        std::process::exit(1);
+    // Without a log, diagnostics go to /dev/null as before
+    if detach {
+        let _ = logfile::start(&logfile::log_path());
     }
 
-    // Open seat and DRM device
-    let (mut seat_holder, drm) = match open_drm() {
-        Ok(result) => result,
-        Err(e) => {
-            eprintln!("Failed to open DRM device: {}", e);
-            std::process::exit(1);
+    // The runtime files are named after the card holding the connector
+    let (instance, options) = match resolve_instance(connector, options) {
+        Ok(resolved) => resolved,
+        Err(e) => status.fail(&e.to_string()),
+    };
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    eprintln!("Starting for {}: {}", instance, args.join(" "));
+
+    // A racing `dpms off` may have started a daemon first; leave it be
+    let _lock = match lock_daemon(&instance.lock_path()) {
+        Ok(Some(lock)) => lock,
+        Ok(None) => {
+            status.already_running();
+            logfile::finish();
+            std::process::exit(0);
         }
+        Err(e) => status.fail(&e.to_string()),
+    };
+
+    // Use signal-hook for safe signal handling
+    let signals = SignalFlags::default();
+    let wake = match register_signals(&signals) {
+        Ok(wake) => wake,
+        Err(e) => status.fail(&e.to_string()),
+    };
+
+    // Open seat and DRM devices
+    let (mut seat_holder, devices) = match open_drm(&options) {
+        Ok(result) => result,
+        Err(e) => status.fail(&e.to_string()),
     };
 
-    // Find active CRTC
-    let crtc_handle = match drm.find_active_crtc() {
-        Ok(handle) => handle,
+    for drm in &devices {
+        let connected: Vec<String> = drm
+            .list_connectors()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|c| c.state == drm::control::connector::State::Connected)
+            .map(|c| c.name)
+            .collect();
+        eprintln!(
+            "Using {} ({}, {}): {}",
+            drm.path().display(),
+            drm.driver(),
+            drm.mechanism(),
+            connected.join(", ")
+        );
+    }
+
+    // Without uevents, displays plugged in later are simply left alone
+    let hotplug = match HotplugMonitor::new() {
+        Ok(monitor) => Some(monitor),
         Err(e) => {
-            eprintln!("Failed to find active CRTC: {}", e);
-            std::process::exit(1);
+            eprintln!("Not following hotplug: {}", e);
+            None
         }
     };
 
-    // Disable CRTC (turn off display)
-    if let Err(e) = drm.set_crtc_active(crtc_handle, false) {
-        eprintln!("Failed to disable CRTC: {}", e);
-        std::process::exit(1);
+    let mut sleep = SleepMonitor::new(daemon.restore_on_suspend);
+
+    // Without it, a later `dpms off --timeout` can't move the deadline
+    let mut control = Control {
+        socket: ControlSocket::bind(&instance.socket_path())
+            .map_err(|e| eprintln!("No control socket: {}", e))
+            .ok(),
+        restore_at: daemon.restore_at,
+        input: daemon
+            .until_input
+            .then(|| InputMonitor::new(&mut seat_holder))
+            .and_then(|monitor| {
+                monitor
+                    .map_err(|e| eprintln!("Not watching input: {}", e))
+                    .ok()
+            }),
+    };
+
+    // The cursor, kernel messages or the console's own blank timer could
+    // otherwise redraw it on the panels
+    let console = ConsoleHold::new();
+
+    // Hold the displays off until asked to stop
+    let seat = seat_holder.name();
+    let mut pending = VecDeque::new();
+    // A bug must not leave the displays dark
+    panic::set_hook(Box::new(log_panic));
+    // The first wait comes once the displays are off and the PID file written
+    let mut ready = false;
+    let (state_path, pid_path) = (instance.state_path(), instance.pid_path());
+    let result = guard_panics(&devices, &state_path, &pid_path, || {
+        hold_displays_off(
+            &devices,
+            connector,
+            seat.as_deref(),
+            daemon,
+            &state_path,
+            &pid_path,
+            &mut |info| {
+                // Answered by the socket, so it needs to be current before waiting
+                if let Some(socket) = &mut control.socket {
+                    socket.set_status(serde_json::to_string(info).unwrap_or_default());
+                }
+                if systemd && !ready {
+                    systemd::notify(systemd::READY);
+                    ready = true;
+                }
+                let event = next_event(
+                    &mut seat_holder,
+                    hotplug.as_ref(),
+                    &mut sleep,
+                    &signals,
+                    &wake,
+                    &mut control,
+                    &mut pending,
+                );
+                if systemd
+                    && matches!(
+                        event,
+                        DaemonEvent::Shutdown | DaemonEvent::Expired | DaemonEvent::InputActivity
+                    )
+                {
+                    systemd::notify(systemd::STOPPING);
+                }
+                event
+            },
+        )
+    });
+
+    // Release DRM master before exiting; process::exit skips destructors
+    drop(devices);
+    drop(control);
+    drop(console);
+
+    match result {
+        None => {
+            eprintln!("Exiting after a panic");
+            logfile::finish();
+            std::process::exit(PANIC_EXIT_CODE);
+        }
+        Some(Ok(())) => {
+            eprintln!("Exiting after restoring the displays");
+            logfile::finish();
+            std::process::exit(0);
+        }
+        Some(Err(e)) => status.fail(&e.to_string()),
     }
+}
 
-    // Write PID file
-    let pid_path = match get_pid_file_path() {
-        Ok(p) => p,
-        Err(e) => {
-            eprintln!("Failed to get PID file path: {}", e);
-            // Try to restore display before exiting
-            let _ = drm.set_crtc_active(crtc_handle, true);
-            std::process::exit(1);
+/// Log a panic with its backtrace, to the daemon log once there is one
+///
+/// Nobody is there to re-run a daemon, so the backtrace is always written.
+fn log_panic(info: &panic::PanicHookInfo<'_>) {
+    eprint!("{}", crash::report(info, true));
+    eprintln!("{}", std::backtrace::Backtrace::force_capture());
+}
+
+/// Run `hold`, turning the displays back on if it panics
+///
+/// The CRTCs recorded in `state_path` are restored on `devices`, whose DRM
+/// master the daemon still holds, and the runtime files are removed so no
+/// PID file points at a daemon that is gone. CRTCs that can't be restored
+/// stay in the state file for `dpms recover`.
+///
+/// # Returns
+/// What `hold` returned, or `None` if it panicked
+fn guard_panics<D: DrmControl>(
+    devices: &[D],
+    state_path: &Path,
+    pid_path: &Path,
+    hold: impl FnOnce() -> Result<(), Error>,
+) -> Option<Result<(), Error>> {
+    if let Ok(result) = panic::catch_unwind(AssertUnwindSafe(hold)) {
+        return Some(result);
+    }
+
+    eprintln!("Restoring the displays after a panic");
+    let failed = match read_saved_state(state_path) {
+        Ok(saved) => recover_devices(devices, &saved),
+        Err(e) => vec![(state_path.display().to_string(), e.to_string())],
+    };
+    if failed.is_empty() {
+        let _ = fs::remove_file(state_path);
+    } else {
+        for (name, reason) in &failed {
+            eprintln!("Failed to restore {}: {}", name, reason);
+        }
+        eprintln!("Left the state file for `dpms recover` to retry");
+    }
+    let _ = fs::remove_file(info_path(pid_path));
+    let _ = fs::remove_file(pid_path);
+    None
+}
+
+/// What the daemon reacts to while it holds the displays off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DaemonEvent {
+    /// SIGTERM/SIGINT: restore the displays and exit
+    Shutdown,
+    /// The seat was taken away, e.g. by switching to another VT
+    #[cfg_attr(not(feature = "seat"), allow(dead_code))]
+    SeatDisabled,
+    /// The seat is ours again
+    #[cfg_attr(not(feature = "seat"), allow(dead_code))]
+    SeatEnabled,
+    /// A connector may have been plugged in or unplugged
+    Hotplug,
+    /// The system is about to suspend
+    Suspending,
+    /// The system has resumed from suspend
+    Resumed,
+    /// SIGUSR1: turn the held displays on if they are off, else off again
+    Toggle,
+    /// SIGUSR2: turn the held displays off again, whatever lit them
+    Reassert,
+    /// SIGHUP: re-scan the connectors, as after a hotplug
+    Rescan,
+    /// The `off --timeout` deadline passed: restore the displays and exit
+    Expired,
+    /// Asked over the control socket to restore the displays at this time
+    RestoreAt(u64),
+    /// Someone used a keyboard or mouse, with `--until-input`: restore the
+    /// displays and exit
+    InputActivity,
+}
+
+impl DaemonEvent {
+    /// What happened, for the daemon's log
+    fn describe(self) -> &'static str {
+        match self {
+            DaemonEvent::Shutdown => "Shutting down; restoring the displays",
+            DaemonEvent::SeatDisabled => "Seat disabled",
+            DaemonEvent::SeatEnabled => "Seat enabled",
+            DaemonEvent::Hotplug => "Connectors may have changed",
+            DaemonEvent::Suspending => "System suspending",
+            DaemonEvent::Resumed => "System resumed",
+            DaemonEvent::Toggle => "SIGUSR1: toggling the displays",
+            DaemonEvent::Reassert => "SIGUSR2: turning the displays off again",
+            DaemonEvent::Rescan => "SIGHUP: re-scanning connectors",
+            DaemonEvent::Expired => "Timeout reached; restoring the displays",
+            DaemonEvent::RestoreAt(_) => "Asked to restore the displays at a new time",
+            DaemonEvent::InputActivity => "Input activity; restoring the displays",
         }
+    }
+}
+
+/// What the daemon's signal handlers have asked for
+///
+/// Each flag only records that its signal arrived, so signals that arrive
+/// before the daemon gets to them count once.
+#[derive(Default)]
+struct SignalFlags {
+    /// SIGTERM or SIGINT
+    shutdown: Arc<AtomicBool>,
+    /// SIGUSR1
+    toggle: Arc<AtomicBool>,
+    /// SIGUSR2
+    reassert: Arc<AtomicBool>,
+    /// SIGHUP
+    rescan: Arc<AtomicBool>,
+}
+
+/// Requests from other dpms processes, and what else makes the daemon give
+/// the displays back
+#[derive(Default)]
+struct Control {
+    /// Where requests arrive; `None` if it couldn't be set up
+    socket: Option<ControlSocket>,
+    /// When to restore the displays and exit, in seconds since the epoch
+    restore_at: Option<u64>,
+    /// Input devices whose activity restores the displays, with
+    /// `--until-input`
+    input: Option<InputMonitor>,
+}
+
+/// Register handlers for SIGTERM, SIGINT, SIGUSR1, SIGUSR2 and SIGHUP
+///
+/// Signals set their flag in `signals` and make the returned socket
+/// readable, which wakes the daemon from waiting for events.
+///
+/// # Returns
+/// - `Ok(UnixStream)` - The non-blocking socket to wait on
+/// - `Err(Error::SignalError)` - A handler couldn't be registered
+fn register_signals(signals: &SignalFlags) -> Result<UnixStream, Error> {
+    let signal_error = |e: std::io::Error| {
+        Error::SignalError(Context::wrap("Failed to set up signal handling", e))
     };
+    let (wake, waker) = UnixStream::pair().map_err(signal_error)?;
+    wake.set_nonblocking(true).map_err(signal_error)?;
+    for (signal, requested) in [
+        (SIGTERM, &signals.shutdown),
+        (SIGINT, &signals.shutdown),
+        (SIGUSR1, &signals.toggle),
+        (SIGUSR2, &signals.reassert),
+        (SIGHUP, &signals.rescan),
+    ] {
+        // The flag is set before the wakeup is sent
+        flag::register(signal, Arc::clone(requested)).map_err(signal_error)?;
+        pipe::register(signal, waker.try_clone().map_err(signal_error)?).map_err(signal_error)?;
+    }
+    Ok(wake)
+}
 
-    if let Err(e) = write_pid_file(&pid_path, Pid::this()) {
-        eprintln!("Failed to write PID file: {}", e);
-        // Try to restore display before exiting
-        let _ = drm.set_crtc_active(crtc_handle, true);
-        std::process::exit(1);
+/// Block until a signal, seat, hotplug, sleep or control event may have
+/// arrived, or the displays are due to be restored
+///
+/// Without logind, the sleep monitor needs polling and bounds the wait.
+/// Otherwise this waits indefinitely, so an idle daemon never wakes up.
+fn wait_for_activity(
+    seat: &mut SeatHolder,
+    hotplug: Option<&HotplugMonitor>,
+    sleep: &SleepMonitor,
+    wake: &UnixStream,
+    control: &Control,
+) -> Result<(), Error> {
+    let until_restore = control
+        .restore_at
+        .map(|at| Duration::from_secs(at.saturating_sub(unix_now())));
+    let timeout = [sleep.poll_interval(), until_restore]
+        .into_iter()
+        .flatten()
+        .min()
+        .map_or(PollTimeout::NONE, |wait| {
+            PollTimeout::try_from(wait).unwrap_or(PollTimeout::MAX)
+        });
+    let mut fds = vec![PollFd::new(wake.as_fd(), PollFlags::POLLIN)];
+    fds.extend(
+        [
+            seat.fd(),
+            hotplug.map(AsFd::as_fd),
+            sleep.fd(),
+            control.socket.as_ref().map(AsFd::as_fd),
+            control.input.as_ref().and_then(InputMonitor::watch_fd),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|fd| PollFd::new(fd, PollFlags::POLLIN)),
+    );
+    fds.extend(
+        control
+            .input
+            .iter()
+            .flat_map(InputMonitor::fds)
+            .map(|fd| PollFd::new(fd, PollFlags::POLLIN)),
+    );
+
+    match poll(&mut fds, timeout) {
+        Ok(_) | Err(Errno::EINTR) => {}
+        Err(e) => return Err(Error::Io(e.into())),
     }
 
-    // Main daemon loop - wait for shutdown signal
-    while !shutdown_requested.load(Ordering::SeqCst) {
+    // Drain the signal wakeups; the signal flags say what happened
+    let mut buf = [0u8; 64];
+    while matches!((&*wake).read(&mut buf), Ok(len) if len > 0) {}
+    Ok(())
+}
+
+/// Wait for the next thing the daemon has to react to
+///
+/// Seat events are dispatched even when nothing is pending, which keeps the
+/// libseat session alive. Events that arrive together are queued in
+/// `pending` and handed out one at a time. A new restore time received on
+/// the control socket takes effect right away.
+fn next_event(
+    seat: &mut SeatHolder,
+    hotplug: Option<&HotplugMonitor>,
+    sleep: &mut SleepMonitor,
+    signals: &SignalFlags,
+    wake: &UnixStream,
+    control: &mut Control,
+    pending: &mut VecDeque<DaemonEvent>,
+) -> DaemonEvent {
+    loop {
+        if signals.shutdown.load(Ordering::SeqCst) {
+            return DaemonEvent::Shutdown;
+        }
+        if control.restore_at.is_some_and(|at| unix_now() >= at) {
+            return DaemonEvent::Expired;
+        }
+        if let Some(event) = pending.pop_front() {
+            return event;
+        }
+
         // Dispatch seat events if using libseat (required to keep session alive)
-        if let SeatHolder::Seat(ref mut seat) = seat_holder
-            && let Err(e) = seat.dispatch(100)
+        #[cfg(feature = "seat")]
+        match seat.dispatch(0) {
+            Ok(events) => {
+                // The input devices were revoked while the seat was away
+                if let Some(input) = &mut control.input
+                    && events
+                        .iter()
+                        .any(|event| matches!(event, libseat::SeatEvent::Enable))
+                {
+                    input.rescan(seat);
+                }
+                pending.extend(events.into_iter().map(|event| match event {
+                    libseat::SeatEvent::Disable => DaemonEvent::SeatDisabled,
+                    libseat::SeatEvent::Enable => DaemonEvent::SeatEnabled,
+                }));
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                return DaemonEvent::Shutdown;
+            }
+        }
+        if let Some(input) = &mut control.input
+            && input.drain(seat)
         {
-            eprintln!("Failed to dispatch seat events: {:?}", e);
-            break;
+            pending.push_back(DaemonEvent::InputActivity);
+        }
+        if hotplug.is_some_and(HotplugMonitor::drain) {
+            pending.push_back(DaemonEvent::Hotplug);
+        }
+        pending.extend(sleep.poll().into_iter().map(|event| match event {
+            SleepEvent::Suspending => DaemonEvent::Suspending,
+            SleepEvent::Resumed => DaemonEvent::Resumed,
+        }));
+        if signals.toggle.swap(false, Ordering::SeqCst) {
+            pending.push_back(DaemonEvent::Toggle);
+        }
+        if signals.reassert.swap(false, Ordering::SeqCst) {
+            pending.push_back(DaemonEvent::Reassert);
+        }
+        if signals.rescan.swap(false, Ordering::SeqCst) {
+            pending.push_back(DaemonEvent::Rescan);
+        }
+        while let Some(request) = control.socket.as_ref().and_then(ControlSocket::accept) {
+            match request {
+                Request::RestoreAt(at) => {
+                    control.restore_at = Some(at);
+                    pending.push_back(DaemonEvent::RestoreAt(at));
+                }
+                // Answered by the socket itself
+                Request::Status => {}
+            }
         }
 
-        // Sleep briefly to avoid busy-waiting
-        thread::sleep(Duration::from_millis(100));
+        if pending.is_empty()
+            && !signals.shutdown.load(Ordering::SeqCst)
+            && let Err(e) = wait_for_activity(seat, hotplug, sleep, wake, control)
+        {
+            eprintln!("{}", e);
+            return DaemonEvent::Shutdown;
+        }
     }
+}
 
-    // Shutdown sequence: restore display
-    if let Err(e) = drm.set_crtc_active(crtc_handle, true) {
-        eprintln!("Failed to restore CRTC: {}", e);
+/// Turn the given CRTCs off again, one commit per device
+///
+/// Used when the seat comes back after a VT switch, since whoever had it in
+/// the meantime may have lit the displays.
+///
+/// # Returns
+/// The last failure, if any device couldn't be turned off
+fn reassert_off<D: DrmControl>(disabled: &[DisabledCrtc<'_, D>]) -> Option<String> {
+    let mut devices: Vec<&D> = Vec::new();
+    for entry in disabled {
+        if !devices.iter().any(|d| d.path() == entry.drm.path()) {
+            devices.push(entry.drm);
+        }
     }
 
-    // Remove PID file
-    if let Err(e) = remove_pid_file(&pid_path) {
-        eprintln!("Failed to remove PID file: {}", e);
+    let mut failure = None;
+    for drm in devices {
+        let crtcs: Vec<crtc::Handle> = disabled
+            .iter()
+            .filter(|e| e.drm.path() == drm.path())
+            .map(|e| e.crtc)
+            .collect();
+        if let Err(e) = drm.set_crtcs_active(&crtcs, false) {
+            let message = format!(
+                "Failed to turn displays off again on {}: {}",
+                drm.path().display(),
+                e
+            );
+            eprintln!("{}", message);
+            failure = Some(message);
+        }
     }
+    failure
+}
 
-    // Exit cleanly
-    std::process::exit(0);
+/// Describe the last display `restore_all` couldn't turn back on
+fn last_restore_failure<D: DrmControl>(
+    failed: &[(&DisabledCrtc<'_, D>, String)],
+) -> Option<String> {
+    failed.last().map(|(entry, reason)| {
+        format!(
+            "Failed to restore {} on {}: {}",
+            entry.connector,
+            entry.drm.path().display(),
+            reason
+        )
+    })
 }
 
-/// Start the dpms daemon
-///
-/// Spawns a new daemon process that:
-/// 1. Opens a libseat session
-/// 2. Opens DRM device
-/// 3. Disables CRTC (turns off display)
-/// 4. Writes PID file
-/// 5. Waits for SIGTERM/SIGINT to restore and exit
+/// Switch off the backlights of the held internal panels
 ///
-/// The parent process returns immediately after verifying the daemon started.
+/// Lacking permission only warns, since the panels are off either way.
 ///
 /// # Returns
-/// - `Ok(())` - Daemon started successfully
-/// - `Err(Error::DaemonStartFailed)` - Daemon failed to start
-pub fn start_daemon() -> Result<(), Error> {
-    // Check if daemon is already running (defense in depth)
-    if is_daemon_running().is_some() {
-        return Ok(()); // Already running, idempotent
-    }
-
-    // Get current executable path
-    let exe_path = std::env::current_exe()
-        .map_err(|e| Error::DaemonStartFailed(format!("Failed to get executable path: {}", e)))?;
-
-    // Spawn daemon as a separate process with daemon-internal subcommand
-    let child = Command::new(&exe_path)
-        .arg("daemon-internal")
-        .stdin(Stdio::null())
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .map_err(|e| Error::DaemonStartFailed(format!("Failed to spawn daemon: {}", e)))?;
-
-    let child_pid = Pid::from_raw(child.id() as i32);
-
-    // Wait for daemon to start and write PID file
-    // Retry up to 20 times (2 seconds total) to handle slow DRM init
-    let pid_path = get_pid_file_path()?;
-    for _ in 0..20 {
-        thread::sleep(Duration::from_millis(100));
+/// The backlights that were switched off, to switch back on at shutdown
+fn backlights_off<D: DrmControl>(disabled: &[DisabledCrtc<'_, D>]) -> Vec<Backlight> {
+    let mut switched = Vec::new();
+    for entry in disabled {
+        if !display::is_internal_panel(&entry.connector) {
+            continue;
+        }
+        let Some(backlight) = Backlight::for_panel(entry.drm.path(), &entry.connector) else {
+            eprintln!("Warning: no backlight found for {}", entry.connector);
+            continue;
+        };
+        if switched.contains(&backlight) {
+            continue;
+        }
+        match backlight.set_power(false) {
+            Ok(()) => switched.push(backlight),
+            Err(e) => eprintln!("Warning: leaving backlight {} on: {}", backlight.name, e),
+        }
+    }
+    switched
+}
 
-        if pid_path.exists() {
-            // Verify the PID in the file is actually the child we spawned
-            if let Ok(Some(pid)) = read_pid_file(&pid_path)
-                && pid == child_pid
-            {
-                return Ok(());
+/// Turn the held displays and their backlights on or off, keeping hold of
+/// the CRTCs
+///
+/// # Returns
+/// The backlights that are now switched off, and the last display that
+/// couldn't be switched
+fn assert_power<D: DrmControl>(
+    disabled: &[DisabledCrtc<'_, D>],
+    state: PowerState,
+    options: &DaemonOptions,
+    backlights: &[Backlight],
+) -> (Vec<Backlight>, Option<String>) {
+    match state {
+        PowerState::On => {
+            let failure = last_restore_failure(&restore_all(disabled));
+            backlights_on(backlights);
+            (Vec::new(), failure)
+        }
+        PowerState::Off => {
+            let failure = reassert_off(disabled);
+            if options.with_backlight {
+                (backlights_off(disabled), failure)
+            } else {
+                (Vec::new(), failure)
             }
         }
     }
+}
 
-    Err(Error::DaemonStartFailed(
-        "Daemon did not write PID file within timeout".to_string(),
-    ))
+/// Switch backlights back on, reporting failures
+fn backlights_on(backlights: &[Backlight]) {
+    for backlight in backlights {
+        if let Err(e) = backlight.set_power(true) {
+            eprintln!("Failed to restore backlight {}: {}", backlight.name, e);
+        }
+    }
 }
 
-/// Stop the daemon by sending SIGTERM
+/// Bring the held displays in line with the connectors after a hotplug
 ///
-/// Sends SIGTERM to the daemon process, which triggers it to:
-/// 1. Restore CRTC ACTIVE property to 1 (turn display back on)
-/// 2. Remove PID file
-/// 3. Exit cleanly
+/// Displays that were unplugged are dropped so shutdown doesn't try to
+/// restore them. Unless `allow_new_displays` is set, targeted displays that
+/// lit up since are turned off too, their state saved to `state_path` first.
+/// A summary of the held displays is logged when they changed.
 ///
 /// # Returns
-/// - `Ok(())` - Daemon stopped successfully
-/// - `Err(Error::DaemonStopTimeout)` - Daemon didn't stop within timeout
-/// - `Err(Error)` - Failed to send signal or read PID file
-pub fn stop_daemon() -> Result<(), Error> {
-    let pid_path = get_pid_file_path()?;
-
-    let pid = match read_pid_file(&pid_path)? {
-        Some(pid) => pid,
-        None => {
-            // No PID file, daemon not running
-            return Ok(());
+/// Whether the set of held displays changed
+fn follow_hotplug<'a, D: DrmControl>(
+    devices: &'a [D],
+    connector: Option<&str>,
+    allow_new_displays: bool,
+    state_path: &Path,
+    disabled: &mut Vec<DisabledCrtc<'a, D>>,
+) -> bool {
+    let before = disabled.len();
+    disabled.retain(|entry| match entry.drm.crtc_drives_connected(entry.crtc) {
+        Ok(false) => {
+            eprintln!(
+                "{} on {} was unplugged; no longer holding it off",
+                entry.connector,
+                entry.drm.path().display()
+            );
+            false
         }
-    };
+        _ => true,
+    });
+    let mut changed = disabled.len() != before;
 
-    // Check if process is actually running
-    if !is_process_running(pid) {
-        // Process already dead, clean up stale PID file
-        remove_pid_file(&pid_path)?;
-        return Ok(());
+    if allow_new_displays {
+        return changed;
     }
 
-    // Send SIGTERM to daemon
-    signal::kill(pid, Signal::SIGTERM)
-        .map_err(|e| Error::SignalError(format!("Failed to send SIGTERM: {}", e)))?;
+    for drm in devices {
+        let targets: Vec<ConnectorInfo> = match find_target_crtcs(drm, connector) {
+            Ok(targets) => targets
+                .into_iter()
+                .filter(|c| {
+                    !disabled
+                        .iter()
+                        .any(|d| d.drm.path() == drm.path() && Some(d.crtc) == c.crtc)
+                })
+                .collect(),
+            Err(_) => continue,
+        };
+        if targets.is_empty() {
+            continue;
+        }
 
-    // Wait for daemon to exit (up to 5 seconds)
-    for _ in 0..50 {
-        thread::sleep(Duration::from_millis(100));
+        let mut saved = read_saved_state(state_path).unwrap_or_default();
+        saved.extend(save_targets(drm, &targets));
+        if let Err(e) = write_saved_state(state_path, &saved) {
+            eprintln!("{}", e);
+        }
 
-        if !is_process_running(pid) {
-            // Daemon stopped, clean up PID file if still present
-            let _ = remove_pid_file(&pid_path);
-            return Ok(());
+        let crtcs: Vec<crtc::Handle> = targets.iter().filter_map(|c| c.crtc).collect();
+        if let Err(e) = drm.set_crtcs_active(&crtcs, false) {
+            eprintln!(
+                "Failed to turn off new displays on {}: {}",
+                drm.path().display(),
+                e
+            );
+            continue;
+        }
+        for c in targets {
+            if let Some(crtc) = c.crtc {
+                eprintln!("Holding off newly lit {} too", c.name);
+                disabled.push(DisabledCrtc {
+                    drm,
+                    crtc,
+                    connector: c.name,
+                });
+                changed = true;
+            }
         }
     }
 
-    // Timeout - daemon didn't stop
-    Err(Error::DaemonStopTimeout)
+    if changed {
+        let held: Vec<&str> = disabled.iter().map(|d| d.connector.as_str()).collect();
+        eprintln!("Now holding off: {}", held.join(", "));
+    }
+    changed
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// How far the daemon has got with the displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    /// Nothing has been turned off yet
+    Init,
+    /// Turning the targeted displays off
+    Disabling,
+    /// Holding the displays off
+    Running,
+    /// Turning the displays back on
+    Restoring,
+    /// The displays are back on, or none were turned off
+    Done,
+}
 
-    #[test]
-    fn test_get_pid_file_path() {
-        // Test with XDG_RUNTIME_DIR set
-        unsafe {
-            std::env::set_var("XDG_RUNTIME_DIR", "/run/user/1000");
+impl Phase {
+    /// What the daemon was doing, for the daemon's log
+    fn describe(self) -> &'static str {
+        match self {
+            Phase::Init => "starting",
+            Phase::Disabling => "turning the displays off",
+            Phase::Running => "holding the displays off",
+            Phase::Restoring => "restoring the displays",
+            Phase::Done => "done",
         }
-        let path = get_pid_file_path().unwrap();
-        assert_eq!(path, PathBuf::from("/run/user/1000/dpms.pid"));
     }
+}
 
-    #[test]
-    fn is_process_running_self() {
-        // Test with our own PID (which is definitely running)
-        let pid = Pid::this();
-        assert!(is_process_running(pid));
+/// The CRTCs the daemon turned off, turned back on when dropped
+///
+/// Every way out of `hold_displays_off` after the displays went off, an
+/// error, a panic or a signal that arrives before the first wait, goes
+/// through here; a normal shutdown calls `restore` to learn the outcome.
+struct HeldCrtcs<'a, 'd, D: DrmControl> {
+    disabled: Vec<DisabledCrtc<'d, D>>,
+    /// Backlights switched off along with the CRTCs
+    backlights: Vec<Backlight>,
+    /// Where the pre-off state is saved, for `dpms recover`
+    state_path: &'a Path,
+    phase: Phase,
+}
+
+impl<'a, 'd, D: DrmControl> HeldCrtcs<'a, 'd, D> {
+    /// Nothing held yet, saving state to `state_path`
+    fn new(state_path: &'a Path) -> Self {
+        HeldCrtcs {
+            disabled: Vec::new(),
+            backlights: Vec::new(),
+            state_path,
+            phase: Phase::Init,
+        }
     }
 
-    #[test]
-    fn is_process_running_nonexistent() {
-        // Test with a PID that definitely doesn't exist (PID_MAX is typically 32768)
-        let pid = Pid::from_raw(99999);
-        assert!(!is_process_running(pid));
+    /// Turn the displays back on, then their backlights whether or not the
+    /// CRTCs came back
+    ///
+    /// # Returns
+    /// - `Ok(())` - Everything was restored and the state file removed
+    /// - `Err(Error::PartialFailure)` - Some displays stay off, kept in the
+    ///   state file for `dpms recover`
+    fn restore(&mut self) -> Result<(), Error> {
+        self.phase = Phase::Restoring;
+        let unrestored = restore_all(&self.disabled);
+        backlights_on(&self.backlights);
+        let result = keep_unrestored(self.state_path, &unrestored);
+        self.phase = Phase::Done;
+        result
     }
+}
 
-    #[test]
-    fn read_pid_file_nonexistent() {
-        let result = read_pid_file("/tmp/dpms-test-nonexistent.pid").unwrap();
-        assert!(result.is_none());
+impl<D: DrmControl> Drop for HeldCrtcs<'_, '_, D> {
+    fn drop(&mut self) {
+        if matches!(self.phase, Phase::Disabling | Phase::Running) && !self.disabled.is_empty() {
+            eprintln!(
+                "Stopped while {}; restoring the displays",
+                self.phase.describe()
+            );
+            if let Err(e) = self.restore() {
+                eprintln!("{}", e);
+            }
+        }
     }
+}
 
-    #[test]
-    fn write_and_read_pid_file() {
-        let test_path = "/tmp/dpms-test-write-read.pid";
-        let test_pid = Pid::from_raw(12345);
+/// Turn off the targeted displays and hold them off until `next_event`
+/// returns `Shutdown`, then restore them
+///
+/// While the seat is disabled the devices can't be used, so nothing is
+/// touched; once it is enabled again the displays are turned off again.
+/// Hotplug events and `Rescan` update the held displays as `follow_hotplug`
+/// describes, and the PID file is rewritten to match.
+///
+/// With `options.with_backlight`, the backlights of held internal panels are
+/// switched off too, and always switched back on at shutdown.
+///
+/// With `options.inhibit_sleep`, a logind lock blocks sleep and idle actions
+/// while the displays are asserted off; it is released while they are
+/// toggled on and once they are restored. Without logind, the displays are
+/// held off anyway.
+///
+/// Drivers often light the panels again on resume, so after a resume the
+/// displays are turned off again and re-scanned. With
+/// `options.restore_on_suspend`, they are turned back on while the system
+/// sleeps.
+///
+/// `Toggle` turns the held displays back on without letting go of them, and
+/// the next `Toggle` or a `Reassert` turns them off again. While toggled on,
+/// the displays are left alone on resume and newly lit ones aren't turned
+/// off. The PID file records which state the daemon is asserting.
+///
+/// The pre-off state is saved to `state_path` before anything is turned
+/// off, so a killed daemon can still be recovered from. The PID file is
+/// written once the displays are off, recording `seat` so status can show
+/// which seat's displays are held; if that fails, they are restored
+/// straight away. Both files are removed once the displays are restored.
+///
+/// # Returns
+/// - `Ok(())` - The displays were held off and restored
+/// - `Err(Error::PartialFailure)` - Nothing could be turned off
+/// - `Err(Error::NoDisplayFound)` - There was nothing to turn off
+/// - `Err(Error::PidFileError)` - The PID file couldn't be written
+fn hold_displays_off<D: DrmControl>(
+    devices: &[D],
+    connector: Option<&str>,
+    seat: Option<&str>,
+    options: &DaemonOptions,
+    state_path: &Path,
+    pid_path: &Path,
+    next_event: &mut dyn FnMut(&DaemonInfo) -> DaemonEvent,
+) -> Result<(), Error> {
+    // Whatever happens from here on, the displays come back on
+    let mut held = HeldCrtcs::new(state_path);
 
-        // Clean up any existing file
-        let _ = fs::remove_file(test_path);
+    // Disable the targeted CRTCs (turn off displays), saving their state
+    // first so a killed daemon can still be recovered from
+    held.phase = Phase::Disabling;
+    let failed;
+    (held.disabled, failed) = disable_all(devices, connector, &mut |saved| {
+        if let Err(e) = write_saved_state(state_path, saved) {
+            eprintln!("{}", e);
+        }
+    });
+    for (device, reason) in &failed {
+        eprintln!("Failed to disable CRTC on {}: {}", device, reason);
+    }
+    if held.disabled.is_empty() {
+        held.phase = Phase::Done;
+        let _ = fs::remove_file(state_path);
+        return Err(if failed.is_empty() {
+            Error::NoDisplayFound
+        } else {
+            Error::PartialFailure { failed }
+        });
+    }
 
-        // Write PID
-        write_pid_file(test_path, test_pid).unwrap();
+    let owned: Vec<OwnedCrtc> = held.disabled.iter().map(DisabledCrtc::owned).collect();
+    let mut asserted = PowerState::Off;
+    // Without it there's no way to find the daemon, so `held` restores the
+    // displays straight away
+    write_pid_file(pid_path, Pid::this(), seat, asserted, &owned, &failed)?;
+    for entry in &owned {
+        eprintln!(
+            "Turned off {} (CRTC {}) on {}",
+            entry.connector, entry.crtc, entry.device
+        );
+    }
 
-        // Read it back
-        let read_pid = read_pid_file(test_path).unwrap();
-        assert_eq!(read_pid, Some(test_pid));
+    // Only for observing the daemon, so failing to write it is no reason
+    // to give up
+    let started = unix_now();
+    let info_path = info_path(pid_path);
+    let mut saved = read_saved_state(state_path).unwrap_or_default();
+    let mut restore_at = options.restore_at;
+    let mut seat_cycles = 0;
+    let mut last_error = failed
+        .last()
+        .map(|(device, reason)| format!("Failed to disable CRTC on {}: {}", device, reason));
+    let mut inhibitor = None;
+    if options.inhibit_sleep {
+        inhibitor = take_sleep_inhibitor();
+    }
+    let mut info = DaemonInfo {
+        seat: seat.map(str::to_string),
+        last_error: last_error.clone(),
+        sleep_inhibited: inhibitor.is_some(),
+        ..daemon_info(&held.disabled, &saved, started, restore_at, asserted)
+    };
+    if let Err(e) = write_daemon_info(&info_path, &info) {
+        eprintln!("{}", e);
+    }
+    if let Some(at) = restore_at {
+        eprintln!("Restoring the displays at {}", format_timestamp(at));
+    }
 
-        // Clean up
-        let _ = fs::remove_file(test_path);
+    if options.with_backlight {
+        held.backlights = backlights_off(&held.disabled);
     }
 
-    #[test]
-    fn test_remove_pid_file() {
-        let test_path = "/tmp/dpms-test-remove.pid";
+    held.phase = Phase::Running;
 
-        // Create a file
-        let _ = fs::File::create(test_path);
+    let mut seat_enabled = true;
+    loop {
+        let event = next_event(&info);
+        eprintln!("{}", event.describe());
+        let mut record = false;
+        let rescan = match event {
+            DaemonEvent::Shutdown | DaemonEvent::Expired | DaemonEvent::InputActivity => break,
+            DaemonEvent::SeatDisabled => {
+                seat_enabled = false;
+                false
+            }
+            DaemonEvent::SeatEnabled => {
+                // Only a seat that was taken away needs the displays turned
+                // off again, and monitors may have changed in the meantime
+                let was_disabled = !seat_enabled;
+                if was_disabled {
+                    seat_cycles += 1;
+                    let failure = match asserted {
+                        PowerState::Off => reassert_off(&held.disabled),
+                        PowerState::On => last_restore_failure(&restore_all(&held.disabled)),
+                    };
+                    last_error = failure.or(last_error);
+                }
+                seat_enabled = true;
+                was_disabled
+            }
+            // The devices can't be used until the seat comes back
+            DaemonEvent::Hotplug | DaemonEvent::Rescan => seat_enabled,
+            DaemonEvent::Suspending => {
+                if options.restore_on_suspend && seat_enabled && asserted == PowerState::Off {
+                    last_error = last_restore_failure(&restore_all(&held.disabled)).or(last_error);
+                    backlights_on(&held.backlights);
+                }
+                false
+            }
+            DaemonEvent::Resumed => {
+                if seat_enabled && asserted == PowerState::Off {
+                    last_error = reassert_off(&held.disabled).or(last_error);
+                    if options.with_backlight {
+                        held.backlights = backlights_off(&held.disabled);
+                    }
+                }
+                // Monitors may have been swapped while asleep
+                seat_enabled
+            }
+            DaemonEvent::Toggle | DaemonEvent::Reassert => {
+                let wanted = match (event, asserted) {
+                    (DaemonEvent::Toggle, PowerState::Off) => PowerState::On,
+                    _ => PowerState::Off,
+                };
+                // With the seat gone, the state is applied when it returns
+                if seat_enabled {
+                    let failure;
+                    (held.backlights, failure) =
+                        assert_power(&held.disabled, wanted, options, &held.backlights);
+                    last_error = failure.or(last_error);
+                }
+                if wanted != asserted {
+                    asserted = wanted;
+                    record = true;
+                    if options.inhibit_sleep {
+                        inhibitor = match asserted {
+                            PowerState::Off => take_sleep_inhibitor(),
+                            PowerState::On => None,
+                        };
+                    }
+                }
+                false
+            }
+            DaemonEvent::RestoreAt(at) => {
+                eprintln!("Restoring the displays at {}", format_timestamp(at));
+                restore_at = Some(at);
+                false
+            }
+        };
 
-        // Remove it
-        remove_pid_file(test_path).unwrap();
+        // Displays lit while toggled on are the user's doing
+        let changed = rescan
+            && follow_hotplug(
+                devices,
+                connector,
+                options.allow_new_displays || asserted == PowerState::On,
+                state_path,
+                &mut held.disabled,
+            );
+        if changed || record {
+            let owned: Vec<OwnedCrtc> = held.disabled.iter().map(DisabledCrtc::owned).collect();
+            if let Err(e) = write_pid_file(pid_path, Pid::this(), seat, asserted, &owned, &failed) {
+                eprintln!("{}", e);
+                last_error = Some(e.to_string());
+            }
+        }
+        if changed {
+            saved = read_saved_state(state_path).unwrap_or_default();
+        }
+        let current = DaemonInfo {
+            seat: info.seat.clone(),
+            seat_cycles,
+            last_error: last_error.clone(),
+            sleep_inhibited: inhibitor.is_some(),
+            ..daemon_info(&held.disabled, &saved, started, restore_at, asserted)
+        };
+        if current != info {
+            if let Err(e) = write_daemon_info(&info_path, &current) {
+                eprintln!("{}", e);
+            }
+            info = current;
+        }
+    }
 
-        // Verify it's gone
-        assert!(!Path::new(test_path).exists());
+    let result = held.restore();
+    drop(inhibitor);
+    let _ = fs::remove_file(&info_path);
 
-        // Removing non-existent file should succeed (idempotent)
-        remove_pid_file(test_path).unwrap();
+    // Remove PID file
+    if let Err(e) = remove_pid_file(pid_path) {
+        eprintln!("Failed to remove PID file: {}", e);
+    }
+
+    result
+}
+
+/// Block sleep and idle actions, warning instead if logind won't
+fn take_sleep_inhibitor() -> Option<SleepInhibitor> {
+    SleepInhibitor::take()
+        .map_err(|e| eprintln!("Not inhibiting sleep: {}", e))
+        .ok()
+}
+
+/// A daemon that was stopped, turning its displays back on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoppedDaemon {
+    pub pid: i32,
+    /// When it turned the displays off, in seconds since the epoch; `None`
+    /// if its state JSON was missing or unreadable
+    pub started_at: Option<u64>,
+    /// How long the displays were off, in seconds; `None` if unknown, or if
+    /// they had been toggled on
+    pub duration: Option<u64>,
+    /// The connectors it held, e.g. `HDMI-A-1`
+    pub connectors: Vec<String>,
+}
+
+/// Describe a daemon that is about to be stopped
+///
+/// # Parameters
+/// - `info`: Its state JSON, if it could be read
+/// - `asserted`: The state it kept its displays in
+/// - `owned`: The displays recorded in its PID file
+/// - `now`: The current time, in seconds since the epoch
+fn stopped_daemon(
+    pid: Pid,
+    info: Option<&DaemonInfo>,
+    asserted: PowerState,
+    owned: &[OwnedCrtc],
+    now: u64,
+) -> StoppedDaemon {
+    let started_at = info.map(|info| info.started);
+    StoppedDaemon {
+        pid: pid.as_raw(),
+        started_at,
+        // A start in the future can only come from a corrupt file
+        duration: started_at
+            .filter(|_| asserted == PowerState::Off)
+            .and_then(|started| now.checked_sub(started)),
+        connectors: owned.iter().map(|o| o.connector.clone()).collect(),
+    }
+}
+
+/// Stop the daemons holding `connector`, or every daemon if `None`
+///
+/// Each instance is stopped on its own, so one that can't be stopped
+/// doesn't keep the others' displays off.
+///
+/// # Returns
+/// - `Ok(Vec<StoppedDaemon>)` - Every daemon stopped; empty if none was running
+/// - `Err(Error)` - The first daemon that failed to stop, as from `stop_instance`
+pub fn stop_daemons(
+    connector: Option<&str>,
+    kill_after: Option<Duration>,
+    options: &OpenOptions,
+) -> Result<Vec<StoppedDaemon>, Error> {
+    let mut stopped = Vec::new();
+    let mut result = Ok(());
+    for (instance, _) in running_daemons() {
+        if let Some(name) = connector
+            && !read_owned_crtcs(instance.pid_path())
+                .iter()
+                .any(|o| o.connector == name)
+        {
+            continue;
+        }
+        match stop_instance(&instance, kill_after, options) {
+            Ok(daemon) => stopped.extend(daemon),
+            Err(e) if result.is_ok() => result = Err(e),
+            Err(_) => {}
+        }
+    }
+    result.map(|()| stopped)
+}
+
+/// Stop a daemon instance by sending SIGTERM
+///
+/// Sends SIGTERM to the daemon process, which triggers it to:
+/// 1. Restore CRTC ACTIVE property to 1 (turn display back on)
+/// 2. Remove PID file
+/// 3. Exit cleanly
+///
+/// A daemon stuck in a DRM ioctl may never get that far. With `kill_after`,
+/// it is killed once that has passed, and the displays are restored from
+/// the saved state file instead.
+///
+/// # Parameters
+/// - `kill_after`: How long to wait before SIGKILL; `None` to give up after
+///   five seconds instead
+/// - `options`: How DRM devices are opened for restoring directly
+///
+/// # Returns
+/// - `Ok(Some(StoppedDaemon))` - Daemon stopped successfully
+/// - `Ok(None)` - No daemon was running
+/// - `Err(Error::DaemonStopTimeout)` - Daemon didn't stop within timeout
+/// - `Err(Error::DaemonUnkillable)` - Daemon survived SIGKILL too
+/// - `Err(Error)` - Failed to send signal, read PID file or restore directly
+fn stop_instance(
+    instance: &Instance,
+    kill_after: Option<Duration>,
+    options: &OpenOptions,
+) -> Result<Option<StoppedDaemon>, Error> {
+    let pid_path = instance.pid_path();
+
+    let pid = match read_pid_file(&pid_path)? {
+        Some(pid) => pid,
+        None => {
+            // No PID file, daemon not running
+            return Ok(None);
+        }
+    };
+
+    // Check if the daemon is actually running; never signal a process that
+    // merely reuses its PID
+    let started = read_start_time(&pid_path);
+    if !is_recorded_daemon(&PROCESSES, pid, started) {
+        // Process already dead, clean up stale PID file
+        remove_pid_file(&pid_path)?;
+        ui::notice("No daemon running");
+        return Ok(None);
+    }
+
+    let owned = read_owned_crtcs(&pid_path);
+    // The daemon removes its state JSON as it exits
+    let stopped = stopped_daemon(
+        pid,
+        read_daemon_info(&info_path(&pid_path)).as_ref(),
+        read_asserted(&pid_path),
+        &owned,
+        unix_now(),
+    );
+
+    // Send SIGTERM to daemon
+    signal::kill(pid, Signal::SIGTERM).context(Error::SignalError, "Failed to send SIGTERM")?;
+
+    // Wait for daemon to exit
+    if wait_for_exit(&pid_path, pid, started, kill_after.unwrap_or(STOP_TIMEOUT)) {
+        // Daemon stopped, clean up PID file if still present
+        let _ = remove_pid_file(&pid_path);
+        // It leaves the displays it couldn't restore to recovery
+        if instance.state_path().exists() {
+            ui::notice("The daemon couldn't restore every display; retrying");
+            return recover_instances(std::slice::from_ref(instance), options)
+                .map(|()| Some(stopped));
+        }
+        for entry in &owned {
+            ui::notice(format_args!(
+                "Restored {} (CRTC {}) on {}",
+                entry.connector, entry.crtc, entry.device
+            ));
+        }
+        return Ok(Some(stopped));
+    }
+
+    // Timeout - daemon didn't stop
+    let Some(kill_after) = kill_after else {
+        return Err(Error::DaemonStopTimeout);
+    };
+    ui::warning(format_args!(
+        "Daemon (PID {}) ignored SIGTERM for {}s; killing it",
+        pid,
+        kill_after.as_secs_f32()
+    ));
+    signal::kill(pid, Signal::SIGKILL).context(Error::SignalError, "Failed to send SIGKILL")?;
+    if !wait_for_exit(&pid_path, pid, started, STOP_TIMEOUT) {
+        return Err(Error::DaemonUnkillable { pid: pid.as_raw() });
+    }
+
+    // The killed daemon couldn't restore; its DRM master died with it
+    remove_pid_file(&pid_path)?;
+    recover_instances(std::slice::from_ref(instance), options)?;
+    ui::notice("Restored the displays the killed daemon held off");
+    Ok(Some(stopped))
+}
+
+/// Replace every running daemon with one run by this dpms, e.g. after an
+/// upgrade
+///
+/// Each daemon is stopped, restoring its displays, and a new one is started
+/// for the same displays straight away, so they are only on for as long as
+/// that takes. The new daemon keeps the old one's `off --timeout` deadline
+/// and otherwise uses `daemon`. A daemon that was toggled on, or whose
+/// deadline has passed, is only stopped.
+///
+/// # Returns
+/// - `Ok(())` - Every daemon was replaced, or none was running
+/// - `Err(Error)` - The first daemon that failed to stop or start again
+pub fn restart_daemons(
+    options: &OpenOptions,
+    daemon: &DaemonOptions,
+    kill_after: Option<Duration>,
+) -> Result<(), Error> {
+    let running = running_daemons();
+    if running.is_empty() {
+        ui::notice("No daemon running");
+        return Ok(());
+    }
+    let mut result = Ok(());
+    for (instance, _) in running {
+        if let Err(e) = restart_instance(&instance, options, daemon, kill_after)
+            && result.is_ok()
+        {
+            result = Err(e);
+        }
+    }
+    result
+}
+
+/// Stop the daemon of `instance` and start a new one in its place
+///
+/// Everything the new daemon needs is worked out before the old one is
+/// stopped.
+fn restart_instance(
+    instance: &Instance,
+    options: &OpenOptions,
+    daemon: &DaemonOptions,
+    kill_after: Option<Duration>,
+) -> Result<(), Error> {
+    let pid_path = instance.pid_path();
+    let restore_at = read_daemon_info(&info_path(&pid_path)).and_then(|info| info.restore_deadline);
+    let holding =
+        read_asserted(&pid_path) == PowerState::Off && restore_at.is_none_or(|at| at > unix_now());
+    let connector = match instance {
+        Instance::All => None,
+        Instance::Display { connector, .. } => Some(connector.as_str()),
+    };
+    let options = instance.open_options(options);
+    let daemon = DaemonOptions {
+        restore_at,
+        ..daemon.clone()
+    };
+
+    stop_instance(instance, kill_after, &options)?;
+    if !holding {
+        ui::notice(format_args!(
+            "Stopped the daemon for {}; its displays stay on",
+            instance
+        ));
+        return Ok(());
+    }
+    start_daemon(connector, &options, &daemon)?;
+    ui::notice(format_args!("Restarted the daemon for {}", instance));
+    Ok(())
+}
+
+/// Wait until the daemon with `pid` and start time `started` has exited
+///
+/// The daemon removing its PID file, or closing its lock file as it exits,
+/// ends the wait early.
+///
+/// # Parameters
+/// - `pid_path`: The daemon's PID file, whose directory is watched
+///
+/// # Returns
+/// `false` if it is still running after `timeout`
+fn wait_for_exit(pid_path: &Path, pid: Pid, started: Option<u64>, timeout: Duration) -> bool {
+    let deadline = std::time::Instant::now() + timeout;
+    let watch = RuntimeWatch::new(pid_path);
+    let mut interval = watch.interval();
+    loop {
+        if !is_recorded_daemon(&PROCESSES, pid, started) {
+            return true;
+        }
+        let now = std::time::Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        if watch.wait(None, interval.min(deadline - now)) {
+            interval = WAIT_SETTLE_INTERVAL;
+        }
+    }
+}
+
+/// Wakes the CLI when the runtime files next to a daemon's PID file change
+///
+/// A PID file being written or removed, or a daemon closing its lock file
+/// as it exits, ends a wait straight away instead of at the next check.
+/// Without inotify, or before the runtime directory exists, waits sleep for
+/// a short interval instead.
+#[cfg(target_os = "linux")]
+struct RuntimeWatch(Option<Inotify>);
+
+/// Without inotify, waits sleep for a short interval
+#[cfg(not(target_os = "linux"))]
+struct RuntimeWatch;
+
+#[cfg(target_os = "linux")]
+impl RuntimeWatch {
+    /// Watch the directory holding `pid_path`
+    fn new(pid_path: &Path) -> Self {
+        let dir = pid_path.parent().unwrap_or(Path::new("."));
+        let events = AddWatchFlags::IN_CREATE
+            | AddWatchFlags::IN_CLOSE
+            | AddWatchFlags::IN_MOVED_TO
+            | AddWatchFlags::IN_DELETE;
+        let watch = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+            .ok()
+            .filter(|watch| watch.add_watch(dir, events).is_ok());
+        RuntimeWatch(watch)
+    }
+
+    /// The inotify descriptor, readable when a runtime file changed
+    fn fd(&self) -> Option<BorrowedFd<'_>> {
+        self.0.as_ref().map(AsFd::as_fd)
+    }
+
+    /// Read the pending events, so the next wait blocks again
+    fn drain(&self) {
+        if let Some(watch) = &self.0 {
+            while watch.read_events().is_ok_and(|events| !events.is_empty()) {}
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl RuntimeWatch {
+    fn new(_pid_path: &Path) -> Self {
+        RuntimeWatch
+    }
+
+    fn fd(&self) -> Option<BorrowedFd<'_>> {
+        None
+    }
+
+    fn drain(&self) {}
+}
+
+impl RuntimeWatch {
+    /// The longest to wait before checking again without being woken
+    fn interval(&self) -> Duration {
+        match self.fd() {
+            Some(_) => WAIT_POLL_INTERVAL,
+            None => WAIT_FALLBACK_INTERVAL,
+        }
+    }
+
+    /// Wait until a runtime file changes, `fd` becomes readable, or
+    /// `timeout` has passed
+    ///
+    /// # Returns
+    /// `true` if woken before the timeout
+    fn wait(&self, fd: Option<BorrowedFd<'_>>, timeout: Duration) -> bool {
+        let mut fds: Vec<PollFd> = self
+            .fd()
+            .into_iter()
+            .chain(fd)
+            .map(|fd| PollFd::new(fd, PollFlags::POLLIN))
+            .collect();
+        if fds.is_empty() {
+            thread::sleep(timeout);
+            return false;
+        }
+        let woken = poll(
+            &mut fds,
+            PollTimeout::try_from(timeout).unwrap_or(PollTimeout::MAX),
+        )
+        .is_ok_and(|ready| ready > 0);
+        self.drain();
+        woken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::instance::{ProcessTable, read_daemon_seat};
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn wait_for_exit_returns_when_the_daemon_lets_go() {
+        let dir = Path::new("/tmp/dpms-test-exit");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let pid_path = dir.join("dpms.pid");
+        // Holds its lock file open, as a daemon does, until it exits
+        let mut child = Command::new("sh")
+            .args(["-c", "exec 3>/tmp/dpms-test-exit/dpms.lock; sleep 0.25"])
+            .spawn()
+            .unwrap();
+        let pid = Pid::from_raw(child.id() as i32);
+        let started = PROCESSES.start_time(pid);
+        let reaper = thread::spawn(move || {
+            child.wait().unwrap();
+            std::time::Instant::now()
+        });
+
+        assert!(wait_for_exit(
+            &pid_path,
+            pid,
+            started,
+            Duration::from_secs(2)
+        ));
+        let returned = std::time::Instant::now();
+
+        let exited = reaper.join().unwrap();
+        let _ = fs::remove_dir_all(dir);
+        assert!(returned - exited < WAIT_POLL_INTERVAL / 2);
     }
 
     #[test]
-    fn is_daemon_running_no_pid_file() {
-        // When no PID file exists, should return None
-        // This assumes no actual daemon is running
-        unsafe {
-            std::env::set_var("XDG_RUNTIME_DIR", "/tmp/dpms-test-nofile");
+    fn daemon_options_to_args() {
+        assert!(DaemonOptions::default().to_args().is_empty());
+        let options = DaemonOptions {
+            allow_new_displays: true,
+            with_backlight: true,
+            restore_on_suspend: true,
+            until_input: true,
+            inhibit_sleep: true,
+            restore_at: Some(1_714_557_600),
+        };
+        assert_eq!(
+            options.to_args(),
+            [
+                "--allow-new-displays",
+                "--with-backlight",
+                "--restore-on-suspend",
+                "--until-input",
+                "--inhibit-sleep",
+                "--restore-at",
+                "1714557600"
+            ]
+        );
+    }
+
+    #[test]
+    fn stopped_daemon_reports_how_long_displays_were_off() {
+        let owned = [OwnedCrtc {
+            device: "/dev/dri/card0".to_string(),
+            connector: "HDMI-A-1".to_string(),
+            crtc: 51,
+            mechanism: None,
+        }];
+        let info = daemon_info::<FakeDrm>(&[], &[], 1_700_000_000, None, PowerState::Off);
+        let now = info.started + 754;
+
+        let stopped = stopped_daemon(
+            Pid::from_raw(4242),
+            Some(&info),
+            PowerState::Off,
+            &owned,
+            now,
+        );
+        assert_eq!(
+            stopped,
+            StoppedDaemon {
+                pid: 4242,
+                started_at: Some(info.started),
+                duration: Some(754),
+                connectors: vec!["HDMI-A-1".to_string()],
+            }
+        );
+
+        // Missing or corrupt state JSON, or displays toggled on
+        let missing = stopped_daemon(Pid::from_raw(4242), None, PowerState::Off, &owned, now);
+        assert_eq!((missing.started_at, missing.duration), (None, None));
+        let future = stopped_daemon(Pid::from_raw(4242), Some(&info), PowerState::Off, &owned, 0);
+        assert_eq!(future.duration, None);
+        let toggled = stopped_daemon(
+            Pid::from_raw(4242),
+            Some(&info),
+            PowerState::On,
+            &owned,
+            now,
+        );
+        assert_eq!(toggled.duration, None);
+    }
+
+    use crate::drm_ops::fake::{Call, FakeDrm};
+
+    fn two_displays() -> FakeDrm {
+        FakeDrm::new("/dev/dri/card0")
+            .with_connector("eDP-1", 70, Some(40))
+            .with_connector("HDMI-A-1", 77, Some(51))
+    }
+
+    fn set_active(crtcs: &[u32], active: bool) -> Call {
+        Call::SetActive {
+            crtcs: crtcs.to_vec(),
+            active,
         }
-        let result = is_daemon_running();
+    }
+
+    #[test]
+    fn disable_all_turns_each_device_off_in_one_commit() {
+        let devices = [
+            two_displays().with_connector("DP-1", 78, Some(62)),
+            FakeDrm::new("/dev/dri/card1").with_connector("DP-2", 90, Some(60)),
+        ];
+
+        let mut saves = Vec::new();
+        let (disabled, failed) = disable_all(&devices, None, &mut |saved| {
+            saves.push(saved.len());
+        });
+
+        assert!(failed.is_empty());
+        assert_eq!(devices[0].calls(), [set_active(&[40, 51, 62], false)]);
+        assert_eq!(devices[1].calls(), [set_active(&[60], false)]);
+        // Saved before each device's commit, including the ones before it
+        assert_eq!(saves, [3, 4]);
+        let connectors: Vec<&str> = disabled.iter().map(|d| d.connector.as_str()).collect();
+        assert_eq!(connectors, ["eDP-1", "HDMI-A-1", "DP-1", "DP-2"]);
+    }
+
+    #[test]
+    fn restore_all_skips_crtcs_whose_display_was_unplugged() {
+        let devices = [two_displays()];
+        let (disabled, _) = disable_all(&devices, None, &mut |_| {});
+        devices[0].unplug("HDMI-A-1");
+
+        let failed = restore_all(&disabled);
+
+        assert!(failed.is_empty());
+        assert_eq!(
+            devices[0].calls(),
+            [set_active(&[40, 51], false), set_active(&[40], true)]
+        );
+        assert!(devices[0].is_active(40));
+        assert!(!devices[0].is_active(51));
+    }
+
+    #[test]
+    fn hold_displays_off_disables_waits_and_restores() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-hold.state");
+        let pid_path = Path::new("/tmp/dpms-test-hold.pid");
+
+        let mut waited = false;
+        hold_displays_off(
+            &devices,
+            None,
+            Some("seat0"),
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| {
+                // While held, both CRTCs are off and recorded on disk
+                assert!(!devices[0].is_active(40));
+                assert!(!devices[0].is_active(51));
+                assert_eq!(read_owned_crtcs(pid_path).len(), 2);
+                assert_eq!(read_daemon_seat(pid_path).as_deref(), Some("seat0"));
+                assert_eq!(read_saved_state(state_path).unwrap().len(), 2);
+                waited = true;
+                DaemonEvent::Shutdown
+            },
+        )
+        .unwrap();
+
+        assert!(waited);
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+        assert!(!state_path.exists());
+        assert!(!pid_path.exists());
+    }
+
+    #[test]
+    fn hold_displays_off_restores_when_pid_file_write_fails() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-pidfail.state");
+        let pid_path = Path::new("/tmp/dpms-test-no-such-dir/dpms.pid");
+
+        let err = hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| panic!("must not wait without a PID file"),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, Error::PidFileError(_)));
+        assert!(devices[0].is_active(40));
+        assert!(devices[0].is_active(51));
+        assert!(!state_path.exists());
+    }
+
+    #[test]
+    fn hold_displays_off_fails_when_nothing_turns_off() {
+        let devices = [two_displays().failing_disable()];
+        let state_path = Path::new("/tmp/dpms-test-nodisable.state");
+        let pid_path = Path::new("/tmp/dpms-test-nodisable.pid");
+
+        let err = hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| panic!("must not wait when nothing is off"),
+        )
+        .unwrap_err();
+
+        assert!(matches!(err, Error::PartialFailure { ref failed } if failed.len() == 1));
+        assert!(!pid_path.exists());
+        assert!(!state_path.exists());
+    }
+
+    #[test]
+    fn check_all_tests_without_changing_anything() {
+        let devices = [
+            two_displays(),
+            FakeDrm::new("/dev/dri/card1")
+                .with_connector("DP-2", 90, Some(60))
+                .rejecting_disable_test(),
+        ];
+
+        let (passed, failed) = check_all(&devices, None);
+        assert_eq!(
+            passed,
+            ["eDP-1 on /dev/dri/card0", "HDMI-A-1 on /dev/dri/card0"]
+        );
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, "/dev/dri/card1");
+        assert!(devices.iter().all(|d| d.calls().is_empty()));
+        assert!(devices[0].is_active(40) && devices[1].is_active(60));
+
+        let (passed, failed) = check_all(&devices, Some("HDMI-A-1"));
+        assert_eq!(passed, ["HDMI-A-1 on /dev/dri/card0"]);
+        assert!(failed.is_empty());
+
+        let (passed, failed) = check_all(&devices, Some("VGA-1"));
+        assert!(passed.is_empty());
+        assert_eq!(failed[0].1, "connector not found");
+    }
+
+    #[test]
+    fn nothing_to_turn_off_lists_the_connectors_seen() {
+        let devices = [FakeDrm::new("/dev/dri/card0")
+            .with_connector("eDP-1", 70, None)
+            .with_connector("HDMI-A-1", 77, Some(51))];
+        devices[0].unplug("HDMI-A-1");
+
+        let (passed, failed) = check_all(&devices, None);
+        assert!(passed.is_empty());
+        assert_eq!(
+            failed,
+            [(
+                "/dev/dri/card0".to_string(),
+                "No connected display found \
+                 (card0: eDP-1=connected (no CRTC), HDMI-A-1=disconnected)"
+                    .to_string()
+            )]
+        );
+
+        let (_, failed) = check_all(&devices, Some("HDMI-A-1"));
+        assert_eq!(
+            failed[0].1,
+            "No connected display found (card0: HDMI-A-1=disconnected)"
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_targets_named_connector_on_its_device() {
+        let devices = [
+            FakeDrm::new("/dev/dri/card0").with_connector("eDP-1", 70, Some(40)),
+            FakeDrm::new("/dev/dri/card1").with_connector("DP-2", 90, Some(60)),
+        ];
+        let state_path = Path::new("/tmp/dpms-test-named.state");
+        let pid_path = Path::new("/tmp/dpms-test-named.pid");
+
+        hold_displays_off(
+            &devices,
+            Some("DP-2"),
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| {
+                assert!(devices[0].is_active(40));
+                assert!(!devices[1].is_active(60));
+                DaemonEvent::Shutdown
+            },
+        )
+        .unwrap();
+
+        assert!(devices[0].calls().is_empty());
+        assert_eq!(
+            devices[1].calls(),
+            [set_active(&[60], false), set_active(&[60], true)]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_skips_unplugged_display_on_restore() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-unplug.state");
+        let pid_path = Path::new("/tmp/dpms-test-unplug.pid");
+
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| {
+                devices[0].unplug("HDMI-A-1");
+                DaemonEvent::Shutdown
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            devices[0].calls(),
+            [set_active(&[40, 51], false), set_active(&[40], true)]
+        );
+    }
+
+    /// Hand out `events` in order, then `Shutdown`
+    fn scripted(events: &[DaemonEvent]) -> impl FnMut(&DaemonInfo) -> DaemonEvent {
+        let mut events: VecDeque<DaemonEvent> = events.iter().copied().collect();
+        move |_: &DaemonInfo| events.pop_front().unwrap_or(DaemonEvent::Shutdown)
+    }
+
+    #[test]
+    fn hold_displays_off_writes_info_until_shutdown() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-info.state");
+        let pid_path = Path::new("/tmp/dpms-test-info.pid");
+
+        let mut info = None;
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| {
+                info = read_daemon_info(&info_path(pid_path));
+                DaemonEvent::Shutdown
+            },
+        )
+        .unwrap();
+
+        let info = info.unwrap();
+        assert_eq!(info.pid, Pid::this().as_raw());
+        assert_eq!(info.devices, ["/dev/dri/card0"]);
+        let held: Vec<(&str, u32, bool)> = info
+            .displays
+            .iter()
+            .map(|d| (d.connector.as_str(), d.crtc, d.was_active))
+            .collect();
+        assert_eq!(held, [("eDP-1", 40, true), ("HDMI-A-1", 51, true)]);
+        assert!(!info_path(pid_path).exists());
+    }
+
+    #[test]
+    fn hold_displays_off_records_restore_deadline_until_it_expires() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-timeout.state");
+        let pid_path = Path::new("/tmp/dpms-test-timeout.pid");
+        let options = DaemonOptions {
+            restore_at: Some(2_000),
+            ..DaemonOptions::default()
+        };
+
+        let mut deadlines = Vec::new();
+        let mut events = scripted(&[DaemonEvent::RestoreAt(3_000), DaemonEvent::Expired]);
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &options,
+            state_path,
+            pid_path,
+            &mut |info| {
+                deadlines.push(
+                    read_daemon_info(&info_path(pid_path)).and_then(|info| info.restore_deadline),
+                );
+                events(info)
+            },
+        )
+        .unwrap();
+
+        // Expiring restores the displays like a shutdown, before `scripted`
+        // gets to hand one out
+        assert_eq!(deadlines, [Some(2_000), Some(3_000)]);
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+        assert!(!info_path(pid_path).exists());
+        assert!(!pid_path.exists());
+    }
+
+    #[test]
+    fn hold_displays_off_keeps_state_of_displays_it_cannot_restore() {
+        let devices = [two_displays()
+            .with_connector("DP-1", 80, Some(60))
+            .failing_enable_of(51)];
+        let state_path = Path::new("/tmp/dpms-test-restorefail.state");
+        let pid_path = Path::new("/tmp/dpms-test-restorefail.pid");
+
+        let err = hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut scripted(&[]),
+        )
+        .unwrap_err();
+
+        // The failure doesn't stop the displays after it from coming back
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51, 60], false),
+                set_active(&[40], true),
+                set_active(&[51], true),
+                set_active(&[60], true)
+            ]
+        );
+        assert!(devices[0].is_active(40));
+        assert!(!devices[0].is_active(51));
+        assert!(devices[0].is_active(60));
+        assert!(
+            matches!(err, Error::PartialFailure { ref failed } if failed.len() == 1 && failed[0].0 == "HDMI-A-1")
+        );
+
+        // Only the display still off is left for `dpms recover`
+        let kept = read_saved_state(state_path).unwrap();
+        let _ = fs::remove_file(state_path);
+        assert_eq!(kept.len(), 1);
+        assert_eq!((kept[0].crtc, kept[0].active), (51, true));
+        assert!(!pid_path.exists());
+        assert!(!info_path(pid_path).exists());
+    }
+
+    #[test]
+    fn panic_while_holding_restores_the_displays() {
+        let devices = [two_displays().panicking_after_disable()];
+        let state_path = Path::new("/tmp/dpms-test-panic.state");
+        let pid_path = Path::new("/tmp/dpms-test-panic.pid");
+
+        let result = guard_panics(&devices, state_path, pid_path, || {
+            hold_displays_off(
+                &devices,
+                None,
+                None,
+                &DaemonOptions::default(),
+                state_path,
+                pid_path,
+                &mut scripted(&[DaemonEvent::Hotplug]),
+            )
+        });
+
         assert!(result.is_none());
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+        assert!(devices[0].is_active(40));
+        assert!(devices[0].is_active(51));
+        assert!(!state_path.exists());
+        assert!(!pid_path.exists());
+        assert!(!info_path(pid_path).exists());
+    }
+
+    #[test]
+    fn guard_panics_passes_results_through() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-nopanic.state");
+        let pid_path = Path::new("/tmp/dpms-test-nopanic.pid");
+
+        let result = guard_panics(&devices, state_path, pid_path, || {
+            Err(Error::NoDisplayFound)
+        });
+
+        assert!(matches!(result, Some(Err(Error::NoDisplayFound))));
+        assert!(devices[0].calls().is_empty());
+    }
+
+    #[test]
+    fn held_crtcs_are_restored_when_left_early() {
+        let state_path = Path::new("/tmp/dpms-test-held.state");
+        for phase in [
+            Phase::Init,
+            Phase::Disabling,
+            Phase::Running,
+            Phase::Restoring,
+            Phase::Done,
+        ] {
+            let devices = [two_displays()];
+            let mut held = HeldCrtcs::new(state_path);
+            (held.disabled, _) = disable_all(&devices, None, &mut |saved| {
+                write_saved_state(state_path, saved).unwrap();
+            });
+            held.phase = phase;
+            drop(held);
+
+            // Restoring and Done have turned the displays back on already
+            let restored = matches!(phase, Phase::Disabling | Phase::Running);
+            assert_eq!(devices[0].is_active(40), restored, "{:?}", phase);
+            assert_eq!(devices[0].is_active(51), restored, "{:?}", phase);
+            assert_eq!(state_path.exists(), !restored, "{:?}", phase);
+            let _ = fs::remove_file(state_path);
+        }
+    }
+
+    #[test]
+    fn held_crtcs_restore_reports_once() {
+        let state_path = Path::new("/tmp/dpms-test-held-once.state");
+        let devices = [two_displays().failing_enable_of(51)];
+        let mut held = HeldCrtcs::new(state_path);
+        (held.disabled, _) = disable_all(&devices, None, &mut |saved| {
+            write_saved_state(state_path, saved).unwrap();
+        });
+        held.phase = Phase::Running;
+
+        let result = held.restore();
+        drop(held);
+        let kept = read_saved_state(state_path).unwrap();
+        let _ = fs::remove_file(state_path);
+
+        assert!(matches!(result, Err(Error::PartialFailure { .. })));
+        // Dropping after `restore` doesn't try again
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+        assert_eq!(kept.len(), 1);
+    }
+
+    #[test]
+    fn hold_displays_off_reports_its_status_before_each_wait() {
+        let devices = [two_displays().failing_enable_of(51)];
+        let state_path = Path::new("/tmp/dpms-test-status.state");
+        let pid_path = Path::new("/tmp/dpms-test-status.pid");
+
+        let mut events = scripted(&[
+            DaemonEvent::SeatDisabled,
+            DaemonEvent::SeatEnabled,
+            DaemonEvent::Toggle,
+        ]);
+        let mut reported = Vec::new();
+        let _ = hold_displays_off(
+            &devices,
+            None,
+            Some("seat0"),
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |info| {
+                reported.push(info.clone());
+                events(info)
+            },
+        );
+        let _ = fs::remove_file(state_path);
+
+        assert_eq!(reported.len(), 4);
+        assert_eq!(reported[0].seat.as_deref(), Some("seat0"));
+        assert_eq!(reported[0].seat_cycles, 0);
+        assert_eq!(reported[0].last_error, None);
+        assert!(reported[0].displays.iter().all(|d| d.asserted == "off"));
+        assert_eq!(reported[2].seat_cycles, 1);
+        // Toggled on, but HDMI-A-1 couldn't be turned back on
+        assert!(reported[3].displays.iter().all(|d| d.asserted == "on"));
+        assert!(
+            reported[3]
+                .last_error
+                .as_deref()
+                .is_some_and(|e| e.starts_with("Failed to restore HDMI-A-1 on /dev/dri/card0"))
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_restores_on_input_activity() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-input.state");
+        let pid_path = Path::new("/tmp/dpms-test-input.pid");
+
+        let mut events = scripted(&[DaemonEvent::InputActivity, DaemonEvent::Toggle]);
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut events,
+        )
+        .unwrap();
+
+        // The toggle after it is never seen
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+        assert!(!pid_path.exists());
+    }
+
+    #[test]
+    fn hold_displays_off_reasserts_off_after_vt_switch() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-vt.state");
+        let pid_path = Path::new("/tmp/dpms-test-vt.pid");
+
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut scripted(&[DaemonEvent::SeatDisabled, DaemonEvent::SeatEnabled]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_reasserts_off_after_resume() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-resume.state");
+        let pid_path = Path::new("/tmp/dpms-test-resume.pid");
+
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut scripted(&[DaemonEvent::Suspending, DaemonEvent::Resumed]),
+        )
+        .unwrap();
+
+        // Nothing changes before sleep without --restore-on-suspend
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_can_restore_for_suspend() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-suspend.state");
+        let pid_path = Path::new("/tmp/dpms-test-suspend.pid");
+        let options = DaemonOptions {
+            restore_on_suspend: true,
+            ..DaemonOptions::default()
+        };
+
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &options,
+            state_path,
+            pid_path,
+            &mut scripted(&[DaemonEvent::Suspending, DaemonEvent::Resumed]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true),
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_toggles_without_letting_go() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-toggle.state");
+        let pid_path = Path::new("/tmp/dpms-test-toggle.pid");
+
+        let mut events = scripted(&[
+            DaemonEvent::Toggle,
+            DaemonEvent::Toggle,
+            DaemonEvent::Toggle,
+            DaemonEvent::Reassert,
+        ]);
+        let mut asserted = Vec::new();
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |info| {
+                asserted.push(read_asserted(pid_path));
+                events(info)
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true),
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true),
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+        assert_eq!(
+            asserted,
+            [
+                PowerState::Off,
+                PowerState::On,
+                PowerState::Off,
+                PowerState::On,
+                PowerState::Off
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_ignores_enable_without_disable() {
+        // libseat reports the initial enable too; the displays are already off
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-enable.state");
+        let pid_path = Path::new("/tmp/dpms-test-enable.pid");
+
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut scripted(&[DaemonEvent::SeatEnabled, DaemonEvent::SeatDisabled]),
+        )
+        .unwrap();
+
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_turns_off_displays_plugged_in_later() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-plug.state");
+        let pid_path = Path::new("/tmp/dpms-test-plug.pid");
+
+        let mut step = 0;
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| {
+                step += 1;
+                match step {
+                    1 => {
+                        devices[0].plug("DP-1", 80, 60);
+                        DaemonEvent::Hotplug
+                    }
+                    _ => {
+                        // The new display is held and recorded like the others
+                        assert!(!devices[0].is_active(60));
+                        assert_eq!(read_owned_crtcs(pid_path).len(), 3);
+                        assert_eq!(read_saved_state(state_path).unwrap().len(), 3);
+                        DaemonEvent::Shutdown
+                    }
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[60], false),
+                set_active(&[40], true),
+                set_active(&[51], true),
+                set_active(&[60], true)
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_can_leave_new_displays_alone() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-allow.state");
+        let pid_path = Path::new("/tmp/dpms-test-allow.pid");
+        let options = DaemonOptions {
+            allow_new_displays: true,
+            ..Default::default()
+        };
+
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &options,
+            state_path,
+            pid_path,
+            &mut |_| {
+                if devices[0].is_active(60) {
+                    return DaemonEvent::Shutdown;
+                }
+                devices[0].plug("DP-1", 80, 60);
+                DaemonEvent::Hotplug
+            },
+        )
+        .unwrap();
+
+        assert!(devices[0].is_active(60));
+        assert_eq!(
+            devices[0].calls(),
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40], true),
+                set_active(&[51], true)
+            ]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_forgets_unplugged_displays() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-prune.state");
+        let pid_path = Path::new("/tmp/dpms-test-prune.pid");
+
+        let mut step = 0;
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| {
+                step += 1;
+                match step {
+                    1 => {
+                        devices[0].unplug("HDMI-A-1");
+                        DaemonEvent::Hotplug
+                    }
+                    _ => {
+                        let owned = read_owned_crtcs(pid_path);
+                        assert_eq!(owned.len(), 1);
+                        assert_eq!(owned[0].connector, "eDP-1");
+                        DaemonEvent::Shutdown
+                    }
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            devices[0].calls(),
+            [set_active(&[40, 51], false), set_active(&[40], true)]
+        );
+    }
+
+    #[test]
+    fn hold_displays_off_defers_hotplug_while_seat_is_away() {
+        let devices = [two_displays()];
+        let state_path = Path::new("/tmp/dpms-test-plug-vt.state");
+        let pid_path = Path::new("/tmp/dpms-test-plug-vt.pid");
+
+        let mut step = 0;
+        hold_displays_off(
+            &devices,
+            None,
+            None,
+            &DaemonOptions::default(),
+            state_path,
+            pid_path,
+            &mut |_| {
+                step += 1;
+                match step {
+                    1 => DaemonEvent::SeatDisabled,
+                    2 => {
+                        devices[0].plug("DP-1", 80, 60);
+                        DaemonEvent::Hotplug
+                    }
+                    3 => {
+                        // Nothing may touch the devices while the seat is away
+                        assert_eq!(devices[0].calls().len(), 1);
+                        DaemonEvent::SeatEnabled
+                    }
+                    _ => DaemonEvent::Shutdown,
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            devices[0].calls()[..3],
+            [
+                set_active(&[40, 51], false),
+                set_active(&[40, 51], false),
+                set_active(&[60], false)
+            ]
+        );
+    }
+
+    #[test]
+    fn wait_for_exit_outlasts_ignored_sigterm() {
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' TERM; sleep 5"])
+            .spawn()
+            .unwrap();
+        let pid = Pid::from_raw(child.id() as i32);
+        // Give the shell time to ignore SIGTERM
+        thread::sleep(Duration::from_millis(100));
+        let started = PROCESSES.start_time(pid);
+
+        signal::kill(pid, Signal::SIGTERM).unwrap();
+        let pid_path = Path::new("/tmp/dpms-test-ignored-sigterm.pid");
+        assert!(!wait_for_exit(
+            pid_path,
+            pid,
+            started,
+            Duration::from_millis(200)
+        ));
+
+        signal::kill(pid, Signal::SIGKILL).unwrap();
+        let reaper = thread::spawn(move || child.wait());
+        assert!(wait_for_exit(
+            pid_path,
+            pid,
+            started,
+            Duration::from_secs(2)
+        ));
+        reaper.join().unwrap().unwrap();
+    }
+
+    /// Held by tests that signal the test process; every registered
+    /// `SignalFlags` sees those signals
+    static SELF_SIGNAL: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    #[test]
+    fn sigterm_wakes_waiting_daemon_promptly() {
+        let _guard = SELF_SIGNAL.lock().unwrap();
+        let signals = SignalFlags::default();
+        let wake = register_signals(&signals).unwrap();
+        let mut seat = SeatHolder::None;
+        let mut sleep = SleepMonitor::clocks_only();
+
+        let signaller = thread::spawn(|| {
+            thread::sleep(Duration::from_millis(50));
+            signal::kill(Pid::this(), Signal::SIGTERM).unwrap();
+            std::time::Instant::now()
+        });
+        let event = next_event(
+            &mut seat,
+            None,
+            &mut sleep,
+            &signals,
+            &wake,
+            &mut Control::default(),
+            &mut VecDeque::new(),
+        );
+        let latency = signaller.join().unwrap().elapsed();
+
+        assert_eq!(event, DaemonEvent::Shutdown);
+        assert!(latency < Duration::from_millis(50), "took {:?}", latency);
+    }
+
+    #[test]
+    fn user_signals_become_events() {
+        let _guard = SELF_SIGNAL.lock().unwrap();
+        let signals = SignalFlags::default();
+        let wake = register_signals(&signals).unwrap();
+        let mut seat = SeatHolder::None;
+        let mut sleep = SleepMonitor::clocks_only();
+        let mut pending = VecDeque::new();
+
+        signal::kill(Pid::this(), Signal::SIGUSR1).unwrap();
+        signal::kill(Pid::this(), Signal::SIGUSR2).unwrap();
+        signal::kill(Pid::this(), Signal::SIGHUP).unwrap();
+        let mut control = Control::default();
+        let mut next = || {
+            next_event(
+                &mut seat,
+                None,
+                &mut sleep,
+                &signals,
+                &wake,
+                &mut control,
+                &mut pending,
+            )
+        };
+        let mut events = [next(), next(), next()];
+        events.sort_by_key(|event| event.describe());
+        assert_eq!(
+            events,
+            [
+                DaemonEvent::Rescan,
+                DaemonEvent::Toggle,
+                DaemonEvent::Reassert
+            ]
+        );
+    }
+
+    #[test]
+    fn restore_deadline_ends_the_wait() {
+        let _guard = SELF_SIGNAL.lock().unwrap();
+        let signals = SignalFlags::default();
+        let wake = register_signals(&signals).unwrap();
+        let mut control = Control {
+            restore_at: Some(unix_now() + 1),
+            ..Control::default()
+        };
+
+        let event = next_event(
+            &mut SeatHolder::None,
+            None,
+            &mut SleepMonitor::clocks_only(),
+            &signals,
+            &wake,
+            &mut control,
+            &mut VecDeque::new(),
+        );
+        assert_eq!(event, DaemonEvent::Expired);
+    }
+
+    #[test]
+    fn control_requests_move_the_deadline() {
+        let _guard = SELF_SIGNAL.lock().unwrap();
+        let signals = SignalFlags::default();
+        let wake = register_signals(&signals).unwrap();
+        let path = Path::new("/tmp/dpms-test-deadline.sock");
+        let mut control = Control {
+            socket: Some(ControlSocket::bind(path).unwrap()),
+            restore_at: Some(unix_now() + 3600),
+            ..Control::default()
+        };
+
+        let at = unix_now() + 60;
+        let client = thread::spawn(move || {
+            control::send(
+                Path::new("/tmp/dpms-test-deadline.sock"),
+                Request::RestoreAt(at),
+            )
+        });
+        let event = next_event(
+            &mut SeatHolder::None,
+            None,
+            &mut SleepMonitor::clocks_only(),
+            &signals,
+            &wake,
+            &mut control,
+            &mut VecDeque::new(),
+        );
+
+        assert_eq!(event, DaemonEvent::RestoreAt(at));
+        assert_eq!(control.restore_at, Some(at));
+        assert!(client.join().unwrap().is_ok());
+    }
+
+    #[test]
+    fn reassert_off_commits_once_per_device() {
+        let devices = [
+            FakeDrm::new("/dev/dri/card0").with_connector("eDP-1", 70, Some(40)),
+            FakeDrm::new("/dev/dri/card1")
+                .with_connector("DP-1", 80, Some(60))
+                .with_connector("DP-2", 90, Some(61)),
+        ];
+        let disabled = [
+            DisabledCrtc {
+                drm: &devices[0],
+                connector: "eDP-1".to_string(),
+                crtc: drm::control::from_u32(40).unwrap(),
+            },
+            DisabledCrtc {
+                drm: &devices[1],
+                connector: "DP-1".to_string(),
+                crtc: drm::control::from_u32(60).unwrap(),
+            },
+            DisabledCrtc {
+                drm: &devices[1],
+                connector: "DP-2".to_string(),
+                crtc: drm::control::from_u32(61).unwrap(),
+            },
+        ];
+
+        reassert_off(&disabled);
+
+        assert_eq!(devices[0].calls(), [set_active(&[40], false)]);
+        assert_eq!(devices[1].calls(), [set_active(&[60, 61], false)]);
+        assert!(!devices[1].is_active(61));
     }
 
-    // Note: Full integration tests for spawn/daemon require real DRM hardware
-    // and are part of manual testing
+    // Note: Spawning the daemon itself requires real DRM hardware and is
+    // part of manual testing
 }
@@ -0,0 +1,1664 @@
+/// Runtime files of daemon instances
+///
+/// Every daemon instance has a lock, a PID file, a pre-off state file and a
+/// state JSON in the runtime directory, named after the displays it holds.
+/// This reads and writes them, refusing files another user could have
+/// planted, and tells whether the process a PID file names is still the
+/// daemon that wrote it.
+use crate::control::{self, Request};
+use crate::display;
+use crate::drm_ops::{DrmControl, OpenOptions, PowerMechanism, open_drm_readonly};
+use crate::error::{Context, Error, ResultExt};
+use crate::output::{PowerState, unix_now};
+use crate::ui;
+use nix::errno::Errno;
+use nix::fcntl::{Flock, FlockArg};
+use nix::sys::signal;
+use nix::unistd::Pid;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{Read, Write};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Mode of the runtime files, which only their owner may read or write
+const PRIVATE_FILE_MODE: u32 = 0o600;
+
+/// Mode of a runtime directory the daemon has to create
+const PRIVATE_DIR_MODE: u32 = 0o700;
+
+/// The dpms version daemons record in their PID file
+const DPMS_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Prefix of the runtime files of a daemon holding a single display
+const INSTANCE_PREFIX: &str = "dpms-";
+
+/// Where DRM device nodes live; instances only record the node's name
+const DRI_DIR: &str = "/dev/dri";
+
+/// Which displays a daemon holds off, naming its runtime files
+///
+/// A daemon holding every display uses `dpms.pid`, `dpms.lock` and
+/// `dpms.state`, the names from before daemons were per display, so one
+/// left running by an older dpms is still found. A daemon holding a single
+/// display is bound to that display's card and uses e.g.
+/// `dpms-card0-HDMI-A-1.pid`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Instance {
+    /// Every display on every card
+    All,
+    /// One connector, on the card named like `card0`
+    Display { card: String, connector: String },
+}
+
+impl Instance {
+    /// File name of the instance's runtime file with `extension`
+    fn file_name(&self, extension: &str) -> String {
+        match self {
+            Instance::All => format!("dpms.{}", extension),
+            Instance::Display { card, connector } => {
+                format!("{}{}-{}.{}", INSTANCE_PREFIX, card, connector, extension)
+            }
+        }
+    }
+
+    /// The instance owning a runtime file, from the file's name
+    ///
+    /// Card nodes are named `cardN`, so the card ends at the first `-`.
+    ///
+    /// # Returns
+    /// `None` if `name` isn't a runtime file with `extension`
+    fn from_file_name(name: &str, extension: &str) -> Option<Instance> {
+        let stem = name.strip_suffix(extension)?.strip_suffix('.')?;
+        if stem == "dpms" {
+            return Some(Instance::All);
+        }
+        let (card, connector) = stem.strip_prefix(INSTANCE_PREFIX)?.split_once('-')?;
+        if card.is_empty() || connector.is_empty() {
+            return None;
+        }
+        Some(Instance::Display {
+            card: card.to_string(),
+            connector: connector.to_string(),
+        })
+    }
+
+    /// Get the PID file path for the daemon
+    ///
+    /// # Returns
+    /// Path to e.g. `/run/user/$UID/dpms.pid`
+    pub fn pid_path(&self) -> PathBuf {
+        runtime_dir().join(self.file_name("pid"))
+    }
+
+    /// Get the path of the file the daemon holds locked while it runs
+    ///
+    /// # Returns
+    /// Path to e.g. `/run/user/$UID/dpms.lock`, next to the PID file
+    pub fn lock_path(&self) -> PathBuf {
+        runtime_dir().join(self.file_name("lock"))
+    }
+
+    /// Get the path of the file recording the pre-off CRTC state
+    ///
+    /// # Returns
+    /// Path to e.g. `/run/user/$UID/dpms.state`, next to the PID file
+    pub fn state_path(&self) -> PathBuf {
+        runtime_dir().join(self.file_name("state"))
+    }
+
+    /// Get the path of the daemon's control socket
+    ///
+    /// # Returns
+    /// Path to e.g. `/run/user/$UID/dpms.sock`, next to the PID file
+    pub fn socket_path(&self) -> PathBuf {
+        runtime_dir().join(self.file_name("sock"))
+    }
+
+    /// How to open only the devices this instance held
+    pub(super) fn open_options(&self, options: &OpenOptions) -> OpenOptions {
+        match self {
+            Instance::All => options.clone(),
+            Instance::Display { card, .. } => OpenOptions {
+                card: Some(Path::new(DRI_DIR).join(card)),
+                ..options.clone()
+            },
+        }
+    }
+
+    /// Whether this instance and `other` can't both hold DRM master
+    ///
+    /// Only one process can be master of a card, so instances conflict
+    /// unless they are bound to different cards.
+    pub(super) fn conflicts_with(&self, other: &Instance) -> bool {
+        match (self, other) {
+            (Instance::Display { card, .. }, Instance::Display { card: other, .. }) => {
+                card == other
+            }
+            _ => true,
+        }
+    }
+}
+
+impl std::fmt::Display for Instance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instance::All => write!(f, "all displays"),
+            Instance::Display { card, connector } => write!(f, "{} on {}", connector, card),
+        }
+    }
+}
+
+/// Find the instance that holds `connector`, or every display if `None`
+///
+/// `connector` may be the start of a single connector's name, as with the
+/// other backends. The card is the first one that has the connector, only
+/// looking at the one given with `--card`; if that card can't be read, the
+/// name is taken as given. The returned options open only that card.
+///
+/// # Returns
+/// - `Ok((Instance, OpenOptions))` - The instance and how its daemon opens devices
+/// - `Err(Error::DisplayNotFound)` - No card has the connector
+/// - `Err(Error::AmbiguousDisplay)` - Several connectors start with `connector`
+pub fn resolve_instance(
+    connector: Option<&str>,
+    options: &OpenOptions,
+) -> Result<(Instance, OpenOptions), Error> {
+    let Some(name) = connector else {
+        return Ok((Instance::All, options.clone()));
+    };
+
+    let connectors: Vec<(PathBuf, String)> = open_drm_readonly(options)
+        .iter()
+        .flat_map(|drm| {
+            let connectors = drm.list_connectors().unwrap_or_default();
+            connectors
+                .into_iter()
+                .map(|c| (drm.path().to_path_buf(), c.name))
+        })
+        .collect();
+    let (path, name) = match &options.card {
+        Some(card) if connectors.is_empty() => (card.clone(), name.to_string()),
+        _ => display::find_display_by_name(&connectors, name, |(_, name)| Some(name.as_str()))
+            .cloned()?,
+    };
+
+    // By-path links would put a `-` into the card's name
+    let path = fs::canonicalize(&path).unwrap_or(path);
+    let card = path
+        .file_name()
+        .map(|card| card.to_string_lossy().into_owned())
+        .ok_or_else(|| Error::DrmError(format!("Not a device node: {}", path.display()).into()))?;
+    let instance = Instance::Display {
+        card,
+        connector: name,
+    };
+    Ok((
+        instance,
+        OpenOptions {
+            card: Some(path),
+            ..options.clone()
+        },
+    ))
+}
+
+/// The instances with a runtime file of `extension` in `dir`, sorted
+pub(super) fn instances_in(dir: &Path, extension: &str) -> Vec<Instance> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut instances: Vec<Instance> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name();
+            Instance::from_file_name(&name.to_string_lossy(), extension)
+        })
+        .collect();
+    instances.sort();
+    instances
+}
+
+/// Take the single-instance lock without waiting
+///
+/// The lock file is never removed, so every daemon locks the same inode.
+///
+/// # Returns
+/// - `Ok(Some(Flock))` - The lock, held until it's dropped or the process dies
+/// - `Ok(None)` - Another daemon holds the lock
+/// - `Err(Error::PermissionDenied)` - The runtime directory isn't writable
+/// - `Err(Error::PidFileError)` - The lock file couldn't be opened or locked
+pub(super) fn lock_daemon(path: &Path) -> Result<Option<Flock<fs::File>>, Error> {
+    if let Some(dir) = path.parent() {
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(PRIVATE_DIR_MODE)
+            .create(dir)
+            .map_err(|e| runtime_dir_error(dir, e))?;
+    }
+    let file = fs::File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .mode(PRIVATE_FILE_MODE)
+        .open(path)
+        .context(Error::PidFileError, "Failed to open lock file")?;
+    match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+        Ok(lock) => Ok(Some(lock)),
+        Err((_, Errno::EWOULDBLOCK)) => Ok(None),
+        Err((_, e)) => Err(Error::PidFileError(Context::wrap(
+            format!("Failed to lock {}", path.display()),
+            e,
+        ))),
+    }
+}
+
+/// Map a failure to create the runtime directory `dir` to an error
+fn runtime_dir_error(dir: &Path, err: std::io::Error) -> Error {
+    match err.kind() {
+        std::io::ErrorKind::PermissionDenied => Error::PermissionDenied {
+            resource: dir.display().to_string(),
+            hint: "XDG_RUNTIME_DIR is not writable; run within a logind session, or \
+                   set XDG_RUNTIME_DIR to a directory you own"
+                .to_string(),
+        },
+        _ => Error::PidFileError(Context::wrap(
+            format!("Failed to create {}", dir.display()),
+            err,
+        )),
+    }
+}
+
+/// The directory holding the daemon's PID and state files
+pub fn runtime_dir() -> PathBuf {
+    crate::env::runtime_dir()
+}
+
+/// Create or truncate a runtime file only its owner can read or write
+///
+/// A file left behind with wider permissions is narrowed too, as the mode
+/// only applies to new files.
+fn create_private(path: &Path) -> std::io::Result<fs::File> {
+    let file = fs::File::options()
+        .create(true)
+        .truncate(true)
+        .write(true)
+        .mode(PRIVATE_FILE_MODE)
+        .open(path)?;
+    file.set_permissions(fs::Permissions::from_mode(PRIVATE_FILE_MODE))?;
+    Ok(file)
+}
+
+/// Check that a runtime file can be trusted before acting on it
+///
+/// With a shared or mis-set `XDG_RUNTIME_DIR`, another user could plant a
+/// PID file that makes `dpms` signal their process or restore displays
+/// they chose.
+///
+/// # Parameters
+/// - `what`: What the file is, for the error, e.g. `PID file`
+///
+/// # Returns
+/// - `Ok(())` - The file belongs to the effective user, or doesn't exist
+/// - `Err(Error::PidFileError)` - Another user owns or can change the file
+fn check_runtime_file(path: &Path, what: &str) -> Result<(), Error> {
+    match fs::metadata(path) {
+        Ok(metadata) => check_file_owner(
+            path,
+            what,
+            metadata.uid(),
+            metadata.mode(),
+            nix::unistd::Uid::effective().as_raw(),
+        ),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Check the owner and mode of a runtime file against the effective UID
+fn check_file_owner(
+    path: &Path,
+    what: &str,
+    owner: u32,
+    mode: u32,
+    euid: u32,
+) -> Result<(), Error> {
+    if owner != euid {
+        return Err(Error::PidFileError(
+            format!(
+                "Refusing to use {} {} owned by uid {}",
+                what,
+                path.display(),
+                owner
+            )
+            .into(),
+        ));
+    }
+    if mode & 0o022 != 0 {
+        return Err(Error::PidFileError(
+            format!(
+                "Refusing to use {} {} writable by other users",
+                what,
+                path.display()
+            )
+            .into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Check if a process with the given PID is running
+///
+/// # Parameters
+/// - `pid`: Process ID to check
+///
+/// # Returns
+/// `true` if the process exists and is running
+pub(super) fn is_process_running(pid: Pid) -> bool {
+    // Sending signal 0 doesn't actually send a signal, but checks if we can send to the process
+    signal::kill(pid, None).is_ok()
+}
+
+/// Where process start times come from
+pub(super) trait ProcessTable {
+    /// When `pid` started, in clock ticks since boot
+    ///
+    /// # Returns
+    /// `None` if there is no such process
+    fn start_time(&self, pid: Pid) -> Option<u64>;
+}
+
+/// The live process table in `/proc`
+#[cfg(target_os = "linux")]
+pub(super) struct ProcFs;
+
+/// This system's process table
+#[cfg(target_os = "linux")]
+pub(super) const PROCESSES: ProcFs = ProcFs;
+
+#[cfg(target_os = "linux")]
+impl ProcessTable for ProcFs {
+    fn start_time(&self, pid: Pid) -> Option<u64> {
+        parse_start_time(&fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?)
+    }
+}
+
+/// The live process table, read through the `kern.proc.pid` sysctl as
+/// FreeBSD doesn't mount `/proc` by default
+///
+/// Start times are in microseconds since the epoch rather than clock ticks,
+/// which only matters for telling processes apart.
+#[cfg(target_os = "freebsd")]
+pub(super) struct KernProc;
+
+/// This system's process table
+#[cfg(target_os = "freebsd")]
+pub(super) const PROCESSES: KernProc = KernProc;
+
+#[cfg(target_os = "freebsd")]
+impl ProcessTable for KernProc {
+    fn start_time(&self, pid: Pid) -> Option<u64> {
+        use nix::libc;
+
+        let mib = [
+            libc::CTL_KERN,
+            libc::KERN_PROC,
+            libc::KERN_PROC_PID,
+            pid.as_raw(),
+        ];
+        let mut info = std::mem::MaybeUninit::<libc::kinfo_proc>::zeroed();
+        let mut len = size_of::<libc::kinfo_proc>();
+        // SAFETY: `info` has room for the `len` bytes the kernel may write
+        let result = unsafe {
+            libc::sysctl(
+                mib.as_ptr(),
+                mib.len() as libc::c_uint,
+                info.as_mut_ptr().cast(),
+                &mut len,
+                std::ptr::null(),
+                0,
+            )
+        };
+        // No such process leaves nothing written
+        if result != 0 || len != size_of::<libc::kinfo_proc>() {
+            return None;
+        }
+        // SAFETY: zeroed, then filled in by the kernel
+        let started = unsafe { info.assume_init() }.ki_start;
+        Some(started.tv_sec as u64 * 1_000_000 + started.tv_usec as u64)
+    }
+}
+
+/// Read the start time field out of a `/proc/<pid>/stat` line
+///
+/// The command name in the second field may contain spaces and
+/// parentheses, so fields are counted from the last `)`.
+#[cfg(target_os = "linux")]
+fn parse_start_time(stat: &str) -> Option<u64> {
+    let (_, fields) = stat.rsplit_once(')')?;
+    // Field 22 overall; the state, field 3, comes first here
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// Whether `pid` is still the daemon that wrote the PID file
+///
+/// PIDs get reused, so a process with the recorded PID must also have the
+/// recorded start time. PID files without one, from older versions, only
+/// need the process to exist.
+///
+/// # Parameters
+/// - `procs`: The process table to look `pid` up in
+/// - `pid`: PID from the PID file
+/// - `started`: Start time from the PID file
+pub(super) fn is_recorded_daemon(
+    procs: &impl ProcessTable,
+    pid: Pid,
+    started: Option<u64>,
+) -> bool {
+    match started {
+        Some(started) => procs.start_time(pid) == Some(started),
+        None => is_process_running(pid),
+    }
+}
+
+/// Read PID from PID file
+///
+/// # Parameters
+/// - `path`: Path to PID file
+///
+/// # Returns
+/// - `Ok(Some(Pid))` - PID was read successfully
+/// - `Ok(None)` - PID file doesn't exist
+/// - `Err(Error)` - Failed to read or parse PID file
+pub(super) fn read_pid_file<P: AsRef<Path>>(path: P) -> Result<Option<Pid>, Error> {
+    let path = path.as_ref();
+
+    if !path.exists() {
+        return Ok(None);
+    }
+    check_runtime_file(path, "PID file")?;
+
+    let mut file = fs::File::open(path).context(Error::PidFileError, "Failed to open PID file")?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .context(Error::PidFileError, "Failed to read PID file")?;
+
+    let pid_num: i32 = contents
+        .lines()
+        .next()
+        .unwrap_or("")
+        .trim()
+        .parse()
+        .context(Error::PidFileError, "Invalid PID in file")?;
+
+    Ok(Some(Pid::from_raw(pid_num)))
+}
+
+/// A display the daemon turned off, as recorded in the PID file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OwnedCrtc {
+    /// DRM device node, e.g. `/dev/dri/card0`
+    pub device: String,
+    /// Connector name, e.g. `HDMI-A-1`
+    pub connector: String,
+    /// DRM object id of the CRTC
+    pub crtc: u32,
+    /// How it was turned off, and so how it will be turned back on; `None`
+    /// in PID files from before this was recorded
+    pub mechanism: Option<PowerMechanism>,
+}
+
+/// Write the PID file followed by what the daemon owns and what failed
+///
+/// The dpms version is written as a `version<TAB>x.y.z` line, the seat as
+/// a `seat<TAB>name` line, whether the daemon holds
+/// its displays off or was toggled on as an `asserted<TAB>off|on` line, each
+/// owned display as an
+/// `owns<TAB>device<TAB>connector<TAB>crtc<TAB>mechanism` line and each failure as a
+/// `failed<TAB>device<TAB>reason` line.
+///
+/// # Parameters
+/// - `path`: Path to PID file
+/// - `pid`: PID to write
+/// - `seat`: Seat the daemon's session is on, if it went through libseat
+/// - `asserted`: The state the daemon keeps its displays in
+/// - `owned`: Displays the daemon turned off
+/// - `failed`: `(device path, reason)` for each device that failed
+///
+/// # Returns
+/// - `Ok(())` - File was written successfully
+/// - `Err(Error)` - Failed to write PID file
+pub(super) fn write_pid_file<P: AsRef<Path>>(
+    path: P,
+    pid: Pid,
+    seat: Option<&str>,
+    asserted: PowerState,
+    owned: &[OwnedCrtc],
+    failed: &[(String, String)],
+) -> Result<(), Error> {
+    let mut contents = format!("{}\n", pid);
+    if let Some(started) = PROCESSES.start_time(pid) {
+        contents.push_str(&format!("started\t{}\n", started));
+    }
+    contents.push_str(&format!("version\t{}\n", DPMS_VERSION));
+    if let Some(seat) = seat {
+        contents.push_str(&format!("seat\t{}\n", seat));
+    }
+    contents.push_str(&format!("asserted\t{}\n", asserted_name(asserted)));
+    for entry in owned {
+        contents.push_str(&format!(
+            "owns\t{}\t{}\t{}\t{}\n",
+            entry.device,
+            entry.connector,
+            entry.crtc,
+            entry.mechanism.map_or("", PowerMechanism::as_str)
+        ));
+    }
+    for (device, reason) in failed {
+        // Keep each failure on a single line
+        let reason = reason.replace(['\n', '\t'], " ");
+        contents.push_str(&format!("failed\t{}\t{}\n", device, reason));
+    }
+
+    let mut file =
+        create_private(path.as_ref()).context(Error::PidFileError, "Failed to create PID file")?;
+
+    file.write_all(contents.as_bytes())
+        .context(Error::PidFileError, "Failed to write PID")?;
+
+    Ok(())
+}
+
+/// Split the tab-separated lines after the PID that start with `key`
+///
+/// # Returns
+/// The remaining fields of each matching line; empty if the file can't be read
+fn read_state_lines<P: AsRef<Path>>(path: P, key: &str) -> Vec<Vec<String>> {
+    let Ok(contents) = fs::read_to_string(path.as_ref()) else {
+        return Vec::new();
+    };
+
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            (fields.next() == Some(key)).then(|| fields.map(str::to_string).collect())
+        })
+        .collect()
+}
+
+/// Read the state the daemon keeps its displays in from the PID file
+///
+/// # Returns
+/// `On` if the daemon was toggled on, else `Off`, also for PID files from
+/// before this was recorded
+pub(super) fn read_asserted<P: AsRef<Path>>(path: P) -> PowerState {
+    match read_state_lines(path, "asserted").first() {
+        Some(fields) if fields.first().is_some_and(|state| state == "on") => PowerState::On,
+        _ => PowerState::Off,
+    }
+}
+
+/// Read the daemon's start time recorded in the PID file
+///
+/// # Returns
+/// Clock ticks since boot, or `None` if not recorded
+pub(super) fn read_start_time<P: AsRef<Path>>(path: P) -> Option<u64> {
+    read_state_lines(path, "started")
+        .into_iter()
+        .find_map(|fields| fields.first()?.parse().ok())
+}
+
+/// Read the version of the dpms that wrote the PID file
+///
+/// # Returns
+/// The version, or `None` in PID files from before it was recorded
+fn read_daemon_version<P: AsRef<Path>>(path: P) -> Option<String> {
+    read_state_lines(path, "version")
+        .into_iter()
+        .find_map(|fields| fields.into_iter().next())
+}
+
+/// Whether the executable `pid` runs was replaced since it started, as by
+/// an upgrade
+#[cfg(target_os = "linux")]
+fn executable_replaced(pid: Pid) -> bool {
+    fs::read_link(format!("/proc/{}/exe", pid))
+        .is_ok_and(|exe| exe.to_string_lossy().ends_with(" (deleted)"))
+}
+
+/// Without `/proc/<pid>/exe` a replaced executable can't be told apart, so
+/// only the version in the PID file is compared
+#[cfg(not(target_os = "linux"))]
+fn executable_replaced(_pid: Pid) -> bool {
+    false
+}
+
+/// Why a daemon may not understand this dpms, if it may not
+///
+/// # Parameters
+/// - `recorded`: The version in its PID file
+/// - `replaced`: Whether its executable was replaced since it started
+///
+/// # Returns
+/// What started the daemon, e.g. `dpms 0.3.0`, or `None` if it was this
+/// build
+fn mismatch_reason(recorded: Option<&str>, replaced: bool) -> Option<String> {
+    match recorded {
+        None => Some("an older dpms".to_string()),
+        Some(version) if version != DPMS_VERSION => Some(format!("dpms {}", version)),
+        Some(_) if replaced => Some("a dpms binary that has since been replaced".to_string()),
+        Some(_) => None,
+    }
+}
+
+/// What started the daemon of `instance` with `pid`, if not this build
+///
+/// Such a daemon may write an older state format or lack the control
+/// socket, so it is only controlled with signals.
+pub(super) fn outdated_daemon(instance: &Instance, pid: Pid) -> Option<String> {
+    mismatch_reason(
+        read_daemon_version(instance.pid_path()).as_deref(),
+        executable_replaced(pid),
+    )
+}
+
+/// Tell the user a daemon should be replaced
+pub(super) fn warn_outdated(instance: &Instance, pid: Pid, reason: &str) {
+    ui::warning(format_args!(
+        "The daemon for {} (PID {}) was started by {}, not dpms {}; \
+         run `dpms daemon restart` to replace it",
+        instance, pid, reason, DPMS_VERSION
+    ));
+}
+
+/// Warn about the running daemons holding `connector` that were started
+/// by another dpms
+///
+/// With `None`, checks the daemon holding every display.
+pub fn warn_outdated_daemons(connector: Option<&str>) {
+    for (instance, pid) in running_daemons() {
+        if holds(&instance, connector)
+            && let Some(reason) = outdated_daemon(&instance, pid)
+        {
+            warn_outdated(&instance, pid, &reason);
+        }
+    }
+}
+
+/// Read the seat recorded in the PID file
+///
+/// # Returns
+/// The seat name, or `None` if the daemon opened devices directly or the
+/// file can't be read
+pub(super) fn read_daemon_seat<P: AsRef<Path>>(path: P) -> Option<String> {
+    read_state_lines(path, "seat")
+        .into_iter()
+        .find_map(|fields| fields.into_iter().next())
+}
+
+/// Read the failed devices recorded in the PID file
+///
+/// # Returns
+/// `(device path, reason)` for each recorded failure; empty if there are
+/// none or the file can't be read
+pub(super) fn read_failed_devices<P: AsRef<Path>>(path: P) -> Vec<(String, String)> {
+    read_state_lines(path, "failed")
+        .into_iter()
+        .filter_map(|fields| match fields.as_slice() {
+            [device, reason] => Some((device.clone(), reason.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Read the displays recorded as owned in the PID file
+///
+/// # Returns
+/// The displays the daemon turned off; empty if there are none or the file
+/// can't be read
+pub(super) fn read_owned_crtcs<P: AsRef<Path>>(path: P) -> Vec<OwnedCrtc> {
+    read_state_lines(path, "owns")
+        .into_iter()
+        .filter_map(|fields| match fields.as_slice() {
+            [device, connector, crtc] => Some(OwnedCrtc {
+                device: device.clone(),
+                connector: connector.clone(),
+                crtc: crtc.parse().ok()?,
+                mechanism: None,
+            }),
+            [device, connector, crtc, mechanism] => Some(OwnedCrtc {
+                device: device.clone(),
+                connector: connector.clone(),
+                crtc: crtc.parse().ok()?,
+                mechanism: PowerMechanism::from_name(mechanism),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The displays the running daemons hold off
+///
+/// # Returns
+/// The owned displays of every instance that isn't toggled on, or an empty
+/// vector if no daemon is running
+pub fn owned_crtcs() -> Vec<OwnedCrtc> {
+    daemons_holding_off()
+        .into_iter()
+        .flat_map(|(instance, _)| read_owned_crtcs(instance.pid_path()))
+        .collect()
+}
+
+/// The seat of the running daemons
+///
+/// # Returns
+/// The seat name, or `None` if no daemon is running or none has a seat
+pub fn daemon_seat() -> Option<String> {
+    running_daemons()
+        .into_iter()
+        .find_map(|(instance, _)| read_daemon_seat(instance.pid_path()))
+}
+
+/// The state of a CRTC before the daemon turned it off
+///
+/// Saved to the state file before disabling, so the display can be
+/// recovered even if the daemon is killed without restoring it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedCrtc {
+    /// DRM device node, e.g. `/dev/dri/card0`
+    pub device: String,
+    /// DRM object id of the CRTC
+    pub crtc: u32,
+    /// DRM object id of the connector
+    pub connector_id: u32,
+    /// Connector name, e.g. `HDMI-A-1`
+    pub connector: String,
+    /// Whether the CRTC was active before it was turned off
+    pub active: bool,
+}
+
+/// Write the pre-off CRTC state file
+///
+/// Each CRTC is written as a `crtc<TAB>device<TAB>crtc<TAB>connector id<TAB>connector<TAB>active` line.
+///
+/// # Returns
+/// - `Ok(())` - File was written successfully
+/// - `Err(Error::PidFileError)` - Failed to write the file
+pub(super) fn write_saved_state<P: AsRef<Path>>(path: P, saved: &[SavedCrtc]) -> Result<(), Error> {
+    let mut contents = String::new();
+    for entry in saved {
+        contents.push_str(&format!(
+            "crtc\t{}\t{}\t{}\t{}\t{}\n",
+            entry.device,
+            entry.crtc,
+            entry.connector_id,
+            entry.connector,
+            u8::from(entry.active)
+        ));
+    }
+
+    create_private(path.as_ref())
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .context(Error::PidFileError, "Failed to write state file")
+}
+
+/// Read the pre-off CRTC state file
+///
+/// # Returns
+/// - `Ok(Vec<SavedCrtc>)` - The saved CRTCs; empty if the file doesn't exist
+/// - `Err(Error::PidFileError)` - The file couldn't be read
+pub(super) fn read_saved_state<P: AsRef<Path>>(path: P) -> Result<Vec<SavedCrtc>, Error> {
+    check_runtime_file(path.as_ref(), "state file")?;
+    let contents = match fs::read_to_string(path.as_ref()) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(Error::PidFileError(Context::wrap(
+                "Failed to read state file",
+                e,
+            )));
+        }
+    };
+
+    Ok(contents
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["crtc", device, crtc, connector_id, connector, active] => Some(SavedCrtc {
+                    device: device.to_string(),
+                    crtc: crtc.parse().ok()?,
+                    connector_id: connector_id.parse().ok()?,
+                    connector: connector.to_string(),
+                    active: *active == "1",
+                }),
+                _ => None,
+            }
+        })
+        .collect())
+}
+
+/// Version of the daemon's state JSON format, bumped on incompatible changes
+pub(super) const INFO_FORMAT_VERSION: u32 = 1;
+
+/// A display the daemon holds off, as recorded in its state JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeldDisplay {
+    /// DRM device node, e.g. `/dev/dri/card0`
+    pub device: String,
+    /// Connector name, e.g. `HDMI-A-1`
+    pub connector: String,
+    /// DRM object id of the CRTC
+    pub crtc: u32,
+    /// Whether the CRTC was active before the daemon turned it off
+    pub was_active: bool,
+    /// The state the daemon keeps it in: `off`, or `on` after a toggle
+    #[serde(default = "asserted_off")]
+    pub asserted: String,
+}
+
+/// What `HeldDisplay::asserted` is in state JSON from before it was recorded
+fn asserted_off() -> String {
+    asserted_name(PowerState::Off).to_string()
+}
+
+/// How the state a daemon asserts is written in its PID file and state JSON
+pub(super) fn asserted_name(asserted: PowerState) -> &'static str {
+    match asserted {
+        PowerState::On => "on",
+        PowerState::Off => "off",
+    }
+}
+
+/// What a daemon is doing, for status and recovery to report
+///
+/// Written as e.g. `dpms.state.json` next to the PID file. Readers ignore
+/// fields they don't know, so fields can be added without bumping
+/// `version`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DaemonInfo {
+    /// Format version, `INFO_FORMAT_VERSION` when written
+    pub version: u32,
+    pub pid: i32,
+    /// When the daemon turned the displays off, in seconds since the epoch
+    pub started: u64,
+    /// The DRM devices the daemon holds displays on
+    pub devices: Vec<String>,
+    pub displays: Vec<HeldDisplay>,
+    /// When the displays get restored on their own, in seconds since the
+    /// epoch, after `dpms off --timeout`
+    pub restore_deadline: Option<u64>,
+    /// Seat the daemon's session is on, if it went through libseat
+    pub seat: Option<String>,
+    /// How often the seat was taken away and given back, e.g. by VT switches
+    #[serde(default)]
+    pub seat_cycles: u32,
+    /// The last thing that went wrong while holding the displays
+    pub last_error: Option<String>,
+    /// Whether the daemon holds a logind lock blocking sleep, after
+    /// `--inhibit-sleep`
+    #[serde(default)]
+    pub sleep_inhibited: bool,
+}
+
+/// A running daemon's status, as `dpms daemon status` reports it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DaemonStatus {
+    /// `live` if the daemon answered over its control socket, else
+    /// `statefile` for its last state JSON
+    pub source: &'static str,
+    /// Which displays the daemon holds, e.g. `HDMI-A-1 on card0`
+    pub instance: String,
+    /// Seconds since the daemon turned the displays off
+    pub uptime: u64,
+    #[serde(flatten)]
+    pub info: DaemonInfo,
+}
+
+/// The state JSON written next to `pid_path`, e.g. `dpms.state.json`
+pub(super) fn info_path(pid_path: &Path) -> PathBuf {
+    pid_path.with_extension("state.json")
+}
+
+/// Write the daemon's state JSON atomically
+///
+/// It is written to a temporary file first and renamed over `path`, so
+/// readers never see half of it.
+///
+/// # Returns
+/// - `Ok(())` - File was written successfully
+/// - `Err(Error::PidFileError)` - Failed to write the file
+pub(super) fn write_daemon_info(path: &Path, info: &DaemonInfo) -> Result<(), Error> {
+    let message = || format!("Failed to write {}", path.display());
+    let contents = serde_json::to_string_pretty(info).with_context(Error::PidFileError, message)?;
+    let temp = path.with_extension("json.tmp");
+    create_private(&temp)
+        .and_then(|mut file| file.write_all((contents + "\n").as_bytes()))
+        .with_context(Error::PidFileError, message)?;
+    fs::rename(&temp, path).with_context(Error::PidFileError, message)
+}
+
+/// Read a daemon's state JSON
+///
+/// # Returns
+/// `None` if the file is missing or can't be parsed
+pub(super) fn read_daemon_info(path: &Path) -> Option<DaemonInfo> {
+    check_runtime_file(path, "state JSON").ok()?;
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// What the running daemons that hold displays off are doing
+pub fn daemon_infos() -> Vec<DaemonInfo> {
+    daemons_holding_off()
+        .into_iter()
+        .filter_map(|(instance, _)| read_daemon_info(&info_path(&instance.pid_path())))
+        .collect()
+}
+
+/// The status of every running daemon, asked over its control socket
+///
+/// A daemon without a control socket, or one that doesn't answer, is
+/// described by its state JSON instead, as is one started by another dpms.
+pub fn daemon_statuses() -> Vec<DaemonStatus> {
+    let now = unix_now();
+    running_daemons()
+        .into_iter()
+        .filter_map(|(instance, pid)| {
+            let outdated = outdated_daemon(&instance, pid);
+            if let Some(reason) = &outdated {
+                warn_outdated(&instance, pid, reason);
+            }
+            let live = outdated.is_none().then(|| query_status(&instance));
+            let (source, info) = match live.flatten() {
+                Some(info) => ("live", info),
+                None => (
+                    "statefile",
+                    read_daemon_info(&info_path(&instance.pid_path()))?,
+                ),
+            };
+            Some(DaemonStatus {
+                source,
+                instance: instance.to_string(),
+                uptime: now.saturating_sub(info.started),
+                info,
+            })
+        })
+        .collect()
+}
+
+/// Ask the daemon of `instance` what it is doing
+///
+/// # Returns
+/// `None` if it has no control socket or gave no usable answer
+fn query_status(instance: &Instance) -> Option<DaemonInfo> {
+    let path = instance.socket_path();
+    if !path.exists() {
+        return None;
+    }
+    let answer = check_runtime_file(&path, "control socket")
+        .map_err(|e| e.to_string())
+        .and_then(|()| control::query(&path, Request::Status).map_err(|e| e.to_string()))
+        .and_then(|answer| serde_json::from_str(&answer).map_err(|e| e.to_string()));
+    match answer {
+        Ok(info) => Some(info),
+        Err(e) => {
+            ui::notice(format_args!(
+                "No live status from the daemon for {}: {}; using its state file",
+                instance, e
+            ));
+            None
+        }
+    }
+}
+
+/// Remove PID file
+///
+/// # Parameters
+/// - `path`: Path to PID file
+///
+/// # Returns
+/// - `Ok(())` - PID file was removed or didn't exist
+/// - `Err(Error)` - Failed to remove PID file
+pub(super) fn remove_pid_file<P: AsRef<Path>>(path: P) -> Result<(), Error> {
+    let path = path.as_ref();
+
+    if path.exists() {
+        fs::remove_file(path).context(Error::PidFileError, "Failed to remove PID file")?;
+    }
+
+    Ok(())
+}
+
+/// Check if a dpms daemon holding `connector` is currently running
+///
+/// With `None`, checks for the daemon holding every display. Also cleans
+/// up stale PID files of instances that are no longer alive.
+///
+/// # Returns
+/// - `Some(Pid)` - Daemon is running with this PID
+/// - `None` - No such daemon is running (or stale PID was cleaned up)
+pub fn is_daemon_running(connector: Option<&str>) -> Option<Pid> {
+    running_daemons()
+        .into_iter()
+        .find(|(instance, _)| holds(instance, connector))
+        .map(|(_, pid)| pid)
+}
+
+/// Whether `instance` holds `connector`, or is the one holding every
+/// display if `None`
+pub(super) fn holds(instance: &Instance, connector: Option<&str>) -> bool {
+    match connector {
+        Some(name) => read_owned_crtcs(instance.pid_path())
+            .iter()
+            .any(|o| o.connector == name),
+        None => *instance == Instance::All,
+    }
+}
+
+/// The running daemon instances that hold their displays off, rather than
+/// having been toggled on with SIGUSR1
+pub fn daemons_holding_off() -> Vec<(Instance, Pid)> {
+    running_daemons()
+        .into_iter()
+        .filter(|(instance, _)| read_asserted(instance.pid_path()) == PowerState::Off)
+        .collect()
+}
+
+/// Every daemon instance that is currently running, with its PID
+///
+/// Stale PID files found on the way are removed.
+pub fn running_daemons() -> Vec<(Instance, Pid)> {
+    instances_in(&runtime_dir(), "pid")
+        .into_iter()
+        .filter_map(|instance| {
+            let pid = running_pid(&instance.pid_path())?;
+            Some((instance, pid))
+        })
+        .collect()
+}
+
+/// The PID of the daemon that wrote `pid_path`, if it is still alive
+///
+/// A stale PID file is removed.
+fn running_pid(pid_path: &Path) -> Option<Pid> {
+    let pid = match read_pid_file(pid_path) {
+        Ok(Some(pid)) => pid,
+        Ok(None) => return None,
+        Err(e) => {
+            ui::warning(e);
+            return None;
+        }
+    };
+
+    // Check if the daemon is still alive, rather than a process reusing its PID
+    if is_recorded_daemon(&PROCESSES, pid, read_start_time(pid_path)) {
+        Some(pid)
+    } else {
+        // Process is dead, clean up stale PID file
+        let _ = remove_pid_file(pid_path);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_get_pid_file_path() {
+        // Test with XDG_RUNTIME_DIR set
+        unsafe {
+            std::env::set_var("XDG_RUNTIME_DIR", "/run/user/1000");
+        }
+        let path = Instance::All.pid_path();
+        assert_eq!(path, PathBuf::from("/run/user/1000/dpms.pid"));
+    }
+
+    #[test]
+    fn instance_file_names() {
+        let display = Instance::Display {
+            card: "card0".to_string(),
+            connector: "HDMI-A-1".to_string(),
+        };
+        assert_eq!(display.file_name("pid"), "dpms-card0-HDMI-A-1.pid");
+        assert_eq!(Instance::All.file_name("state"), "dpms.state");
+
+        assert_eq!(
+            Instance::from_file_name("dpms-card0-HDMI-A-1.pid", "pid"),
+            Some(display)
+        );
+        assert_eq!(
+            Instance::from_file_name("dpms.lock", "lock"),
+            Some(Instance::All)
+        );
+        assert_eq!(Instance::from_file_name("dpms.state", "pid"), None);
+        assert_eq!(Instance::from_file_name("dpms-card0.pid", "pid"), None);
+        assert_eq!(Instance::from_file_name("dpms.fbcon", "pid"), None);
+    }
+
+    #[test]
+    fn instances_found_by_their_files() {
+        let dir = Path::new("/tmp/dpms-test-instances");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        for name in ["dpms-card1-DP-2.pid", "dpms.pid", "dpms.lock", "dpms.fbcon"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+
+        let found = instances_in(dir, "pid");
+        assert_eq!(
+            found,
+            vec![
+                Instance::All,
+                Instance::Display {
+                    card: "card1".to_string(),
+                    connector: "DP-2".to_string(),
+                },
+            ]
+        );
+        assert!(instances_in(dir, "state").is_empty());
+        assert!(instances_in(Path::new("/tmp/dpms-test-instances-none"), "pid").is_empty());
+    }
+
+    #[test]
+    fn instances_conflict_on_the_same_card() {
+        let display = |card: &str, connector: &str| Instance::Display {
+            card: card.to_string(),
+            connector: connector.to_string(),
+        };
+        assert!(!display("card0", "HDMI-A-1").conflicts_with(&display("card1", "eDP-1")));
+        assert!(display("card0", "HDMI-A-1").conflicts_with(&display("card0", "eDP-1")));
+        assert!(display("card0", "HDMI-A-1").conflicts_with(&Instance::All));
+        assert!(Instance::All.conflicts_with(&display("card1", "eDP-1")));
+
+        let options = display("card1", "eDP-1").open_options(&OpenOptions::default());
+        assert_eq!(options.card, Some(PathBuf::from("/dev/dri/card1")));
+        assert_eq!(
+            Instance::All.open_options(&OpenOptions::default()).card,
+            None
+        );
+    }
+
+    #[test]
+    fn is_process_running_self() {
+        // Test with our own PID (which is definitely running)
+        let pid = Pid::this();
+        assert!(is_process_running(pid));
+    }
+
+    #[test]
+    fn is_process_running_nonexistent() {
+        // Test with a PID that definitely doesn't exist (PID_MAX is typically 32768)
+        let pid = Pid::from_raw(99999);
+        assert!(!is_process_running(pid));
+    }
+
+    #[test]
+    fn read_pid_file_nonexistent() {
+        let result = read_pid_file("/tmp/dpms-test-nonexistent.pid").unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn runtime_files_are_private() {
+        let dir = Path::new("/tmp/dpms-test-private");
+        let _ = fs::remove_dir_all(dir);
+        let pid_path = dir.join("dpms.pid");
+        let state_path = dir.join("dpms.state");
+        let mode = |path: &Path| fs::metadata(path).unwrap().mode() & 0o777;
+
+        // The directory is created for the lock if it's missing
+        let lock = lock_daemon(&dir.join("dpms.lock")).unwrap();
+        assert!(lock.is_some());
+        assert_eq!(mode(dir), 0o700);
+        assert_eq!(mode(&dir.join("dpms.lock")), 0o600);
+
+        // Files left behind with wider permissions are narrowed
+        fs::write(&pid_path, "1\n").unwrap();
+        fs::set_permissions(&pid_path, fs::Permissions::from_mode(0o666)).unwrap();
+        write_pid_file(&pid_path, Pid::this(), None, PowerState::Off, &[], &[]).unwrap();
+        write_saved_state(&state_path, &[]).unwrap();
+        write_daemon_info(&info_path(&pid_path), &sample_info()).unwrap();
+
+        assert_eq!(mode(&pid_path), 0o600);
+        assert_eq!(mode(&state_path), 0o600);
+        assert_eq!(mode(&info_path(&pid_path)), 0o600);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn runtime_files_of_other_users_are_refused() {
+        let path = Path::new("/run/user/1000/dpms.pid");
+        assert!(check_file_owner(path, "PID file", 1000, 0o100600, 1000).is_ok());
+        assert!(check_file_owner(path, "PID file", 1000, 0o100644, 1000).is_ok());
+
+        let err = check_file_owner(path, "PID file", 1001, 0o100600, 1000).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "PID file operation failed: Refusing to use PID file /run/user/1000/dpms.pid owned by uid 1001"
+        );
+        let err = check_file_owner(path, "state file", 1000, 0o100662, 1000).unwrap_err();
+        assert!(err.to_string().ends_with("writable by other users"));
+    }
+
+    #[test]
+    fn writable_runtime_files_are_not_read() {
+        let pid_path = Path::new("/tmp/dpms-test-writable.pid");
+        let state_path = Path::new("/tmp/dpms-test-writable.state");
+        write_pid_file(pid_path, Pid::this(), None, PowerState::Off, &[], &[]).unwrap();
+        write_saved_state(state_path, &[]).unwrap();
+        for path in [pid_path, state_path] {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o620)).unwrap();
+        }
+
+        let pid = read_pid_file(pid_path);
+        let saved = read_saved_state(state_path);
+        let running = running_pid(pid_path);
+        let _ = fs::remove_file(pid_path);
+        let _ = fs::remove_file(state_path);
+
+        assert!(
+            pid.unwrap_err()
+                .to_string()
+                .contains("writable by other users")
+        );
+        assert!(saved.unwrap_err().to_string().contains("state file"));
+        assert_eq!(running, None);
+    }
+
+    #[test]
+    fn write_and_read_pid_file() {
+        let test_path = "/tmp/dpms-test-write-read.pid";
+        let test_pid = Pid::from_raw(12345);
+
+        // Clean up any existing file
+        let _ = fs::remove_file(test_path);
+
+        // Write PID
+        write_pid_file(test_path, test_pid, None, PowerState::Off, &[], &[]).unwrap();
+
+        // Read it back
+        let read_pid = read_pid_file(test_path).unwrap();
+        assert_eq!(read_pid, Some(test_pid));
+        assert_eq!(read_start_time(test_path), None);
+
+        // Our own start time is recorded, for telling a reused PID apart
+        write_pid_file(test_path, Pid::this(), None, PowerState::Off, &[], &[]).unwrap();
+        assert_eq!(
+            read_start_time(test_path),
+            PROCESSES.start_time(Pid::this())
+        );
+        assert!(read_start_time(test_path).is_some());
+
+        // Clean up
+        let _ = fs::remove_file(test_path);
+    }
+
+    /// Process table with made-up start times
+    struct FakeProcs(Vec<(i32, u64)>);
+
+    impl ProcessTable for FakeProcs {
+        fn start_time(&self, pid: Pid) -> Option<u64> {
+            self.0
+                .iter()
+                .find(|(p, _)| *p == pid.as_raw())
+                .map(|(_, started)| *started)
+        }
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn parse_start_time_from_stat() {
+        let stat = "4242 (dpms) S 1 4242 4242 0 -1 4194560 120 0 0 0 1 2 0 0 20 0 1 0 \
+                    987654 12345678 300 18446744073709551615";
+        assert_eq!(parse_start_time(stat), Some(987654));
+
+        // The command name can hold anything, including ") "
+        let stat = "4242 (a) b (c) S 1 4242 4242 0 -1 4194560 120 0 0 0 1 2 0 0 20 0 1 0 \
+                    55 12345678 300";
+        assert_eq!(parse_start_time(stat), Some(55));
+        assert_eq!(parse_start_time("4242 (dpms) S 1"), None);
+    }
+
+    #[test]
+    fn recorded_daemon_needs_matching_start_time() {
+        let procs = FakeProcs(vec![(4242, 1000), (4343, 2000)]);
+
+        assert!(is_recorded_daemon(&procs, Pid::from_raw(4242), Some(1000)));
+        // The daemon died and an unrelated process got its PID
+        assert!(!is_recorded_daemon(&procs, Pid::from_raw(4343), Some(1000)));
+        // No process with that PID at all
+        assert!(!is_recorded_daemon(&procs, Pid::from_raw(4444), Some(1000)));
+    }
+
+    #[test]
+    fn process_table_knows_own_start_time() {
+        let started = PROCESSES.start_time(Pid::this()).unwrap();
+        assert!(is_recorded_daemon(&PROCESSES, Pid::this(), Some(started)));
+        assert!(!is_recorded_daemon(
+            &PROCESSES,
+            Pid::this(),
+            Some(started + 1)
+        ));
+        // Older PID files without a start time only need a live process
+        assert!(is_recorded_daemon(&PROCESSES, Pid::this(), None));
+    }
+
+    #[test]
+    fn only_one_concurrent_daemon_gets_the_lock() {
+        let path = Path::new("/tmp/dpms-test-daemon.lock");
+        let start = Arc::new(std::sync::Barrier::new(2));
+        let racers: Vec<_> = (0..2)
+            .map(|_| {
+                let start = Arc::clone(&start);
+                thread::spawn(move || {
+                    start.wait();
+                    let lock = lock_daemon(path).unwrap();
+                    // Stay alive until both have tried
+                    thread::sleep(Duration::from_millis(50));
+                    lock.is_some()
+                })
+            })
+            .collect();
+        let winners = racers
+            .into_iter()
+            .map(|racer| racer.join().unwrap())
+            .filter(|won| *won)
+            .count();
+        assert_eq!(winners, 1);
+
+        // A dead daemon's lock is gone with it
+        let lock = lock_daemon(path).unwrap().unwrap();
+        assert!(lock_daemon(path).unwrap().is_none());
+        drop(lock);
+        assert!(lock_daemon(path).unwrap().is_some());
+    }
+
+    #[test]
+    fn unwritable_runtime_dir_says_how_to_fix_it() {
+        let dir = Path::new("/run/user/1000");
+        let err = runtime_dir_error(
+            dir,
+            std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+        );
+        assert!(matches!(
+            err,
+            Error::PermissionDenied { ref resource, ref hint }
+                if resource == "/run/user/1000" && hint.contains("XDG_RUNTIME_DIR")
+        ));
+
+        let err = runtime_dir_error(dir, std::io::Error::from(std::io::ErrorKind::NotADirectory));
+        assert!(matches!(err, Error::PidFileError(_)));
+    }
+
+    #[test]
+    fn pid_file_round_trips_owned_and_failed_devices() {
+        let test_path = "/tmp/dpms-test-state.pid";
+        let test_pid = Pid::from_raw(12345);
+        let failed = vec![(
+            "/dev/dri/card1".to_string(),
+            "Atomic commit failed:\nEBUSY".to_string(),
+        )];
+
+        let owned = vec![
+            OwnedCrtc {
+                device: "/dev/dri/card0".to_string(),
+                connector: "HDMI-A-1".to_string(),
+                crtc: 51,
+                mechanism: Some(PowerMechanism::Atomic),
+            },
+            OwnedCrtc {
+                device: "/dev/dri/card1".to_string(),
+                connector: "VGA-1".to_string(),
+                crtc: 60,
+                mechanism: Some(PowerMechanism::LegacyDpms),
+            },
+        ];
+
+        write_pid_file(
+            test_path,
+            test_pid,
+            Some("seat1"),
+            PowerState::On,
+            &owned,
+            &failed,
+        )
+        .unwrap();
+        let read_pid = read_pid_file(test_path).unwrap();
+        let read_seat = read_daemon_seat(test_path);
+        let asserted = read_asserted(test_path);
+        let read_owned = read_owned_crtcs(test_path);
+        let read_failed = read_failed_devices(test_path);
+        let _ = fs::remove_file(test_path);
+
+        assert_eq!(read_pid, Some(test_pid));
+        assert_eq!(read_seat.as_deref(), Some("seat1"));
+        assert_eq!(asserted, PowerState::On);
+        assert_eq!(read_owned, owned);
+        assert_eq!(
+            read_failed,
+            vec![(
+                "/dev/dri/card1".to_string(),
+                "Atomic commit failed: EBUSY".to_string()
+            )]
+        );
+    }
+
+    fn sample_info() -> DaemonInfo {
+        DaemonInfo {
+            version: INFO_FORMAT_VERSION,
+            pid: 4242,
+            started: 1_700_000_000,
+            devices: vec!["/dev/dri/card0".to_string()],
+            displays: vec![HeldDisplay {
+                device: "/dev/dri/card0".to_string(),
+                connector: "HDMI-A-1".to_string(),
+                crtc: 51,
+                was_active: true,
+                asserted: "off".to_string(),
+            }],
+            restore_deadline: None,
+            seat: Some("seat0".to_string()),
+            seat_cycles: 2,
+            last_error: None,
+            sleep_inhibited: false,
+        }
+    }
+
+    #[test]
+    fn daemon_info_round_trips() {
+        let path = Path::new("/tmp/dpms-test-info.state.json");
+        let info = sample_info();
+
+        write_daemon_info(path, &info).unwrap();
+        let read = read_daemon_info(path);
+        let temp_left = path.with_extension("json.tmp").exists();
+        let _ = fs::remove_file(path);
+
+        assert_eq!(read, Some(info));
+        assert!(!temp_left);
+    }
+
+    #[test]
+    fn daemon_info_ignores_unknown_fields() {
+        let json = r#"{
+            "version": 2,
+            "pid": 4242,
+            "started": 1700000000,
+            "devices": ["/dev/dri/card0"],
+            "displays": [{"device": "/dev/dri/card0", "connector": "HDMI-A-1",
+                          "crtc": 51, "was_active": true, "brightness": 80}],
+            "restore_deadline": null,
+            "hostname": "box"
+        }"#;
+        let info: DaemonInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.version, 2);
+        assert_eq!(info.displays, sample_info().displays);
+    }
+
+    #[test]
+    fn daemon_info_reads_state_json_of_older_daemons() {
+        // As written before the seat, errors and toggles were recorded
+        let json = r#"{
+            "version": 1,
+            "pid": 4242,
+            "started": 1700000000,
+            "devices": ["/dev/dri/card0"],
+            "displays": [{"device": "/dev/dri/card0", "connector": "HDMI-A-1",
+                          "crtc": 51, "was_active": true}],
+            "restore_deadline": 1700003600
+        }"#;
+        let info: DaemonInfo = serde_json::from_str(json).unwrap();
+        assert_eq!(info.displays, sample_info().displays);
+        assert_eq!(info.restore_deadline, Some(1_700_003_600));
+        assert_eq!(info.seat, None);
+        assert_eq!(info.seat_cycles, 0);
+        assert_eq!(info.last_error, None);
+    }
+
+    #[test]
+    fn pid_file_records_the_dpms_version() {
+        let path = "/tmp/dpms-test-version.pid";
+        write_pid_file(path, Pid::this(), None, PowerState::Off, &[], &[]).unwrap();
+        let version = read_daemon_version(path);
+        let _ = fs::remove_file(path);
+
+        assert_eq!(version.as_deref(), Some(DPMS_VERSION));
+        assert_eq!(mismatch_reason(version.as_deref(), false), None);
+        assert!(!executable_replaced(Pid::this()));
+    }
+
+    #[test]
+    fn old_pid_files_are_read_as_outdated() {
+        // Only the PID, then owned displays without a mechanism
+        let path = "/tmp/dpms-test-old-format.pid";
+        fs::write(path, "12345\nowns\t/dev/dri/card0\tHDMI-A-1\t51\n").unwrap();
+        let pid = read_pid_file(path).unwrap();
+        let version = read_daemon_version(path);
+        let asserted = read_asserted(path);
+        let owned = read_owned_crtcs(path);
+        let _ = fs::remove_file(path);
+
+        assert_eq!(pid, Some(Pid::from_raw(12345)));
+        assert_eq!(version, None);
+        assert_eq!(asserted, PowerState::Off);
+        assert_eq!(
+            owned,
+            [OwnedCrtc {
+                device: "/dev/dri/card0".to_string(),
+                connector: "HDMI-A-1".to_string(),
+                crtc: 51,
+                mechanism: None,
+            }]
+        );
+        assert_eq!(
+            mismatch_reason(version.as_deref(), false).as_deref(),
+            Some("an older dpms")
+        );
+    }
+
+    #[test]
+    fn other_versions_and_replaced_binaries_are_outdated() {
+        assert_eq!(
+            mismatch_reason(Some("0.0.1"), false).as_deref(),
+            Some("dpms 0.0.1")
+        );
+        assert_eq!(
+            mismatch_reason(Some(DPMS_VERSION), true).as_deref(),
+            Some("a dpms binary that has since been replaced")
+        );
+    }
+
+    #[test]
+    fn read_failed_devices_plain_pid_file() {
+        let test_path = "/tmp/dpms-test-plain.pid";
+        write_pid_file(
+            test_path,
+            Pid::from_raw(12345),
+            None,
+            PowerState::Off,
+            &[],
+            &[],
+        )
+        .unwrap();
+
+        let read_seat = read_daemon_seat(test_path);
+        let read_owned = read_owned_crtcs(test_path);
+        let read_failed = read_failed_devices(test_path);
+        let _ = fs::remove_file(test_path);
+
+        assert_eq!(read_seat, None);
+        assert!(read_owned.is_empty());
+        assert!(read_failed.is_empty());
+    }
+
+    fn saved_crtc(connector_id: u32, active: bool) -> SavedCrtc {
+        SavedCrtc {
+            device: "/dev/dri/card1".to_string(),
+            crtc: 51,
+            connector_id,
+            connector: "HDMI-A-1".to_string(),
+            active,
+        }
+    }
+
+    #[test]
+    fn saved_state_round_trips() {
+        let test_path = "/tmp/dpms-test-saved.state";
+        let saved = vec![
+            saved_crtc(77, true),
+            SavedCrtc {
+                device: "/dev/dri/card0".to_string(),
+                crtc: 40,
+                connector_id: 90,
+                connector: "eDP-1".to_string(),
+                active: false,
+            },
+        ];
+
+        write_saved_state(test_path, &saved).unwrap();
+        let read = read_saved_state(test_path).unwrap();
+        let _ = fs::remove_file(test_path);
+
+        assert_eq!(read, saved);
+    }
+
+    #[test]
+    fn read_saved_state_missing_file_is_empty() {
+        let read = read_saved_state("/tmp/dpms-test-missing.state").unwrap();
+        assert!(read.is_empty());
+    }
+
+    #[test]
+    fn test_remove_pid_file() {
+        let test_path = "/tmp/dpms-test-remove.pid";
+
+        // Create a file
+        let _ = fs::File::create(test_path);
+
+        // Remove it
+        remove_pid_file(test_path).unwrap();
+
+        // Verify it's gone
+        assert!(!Path::new(test_path).exists());
+
+        // Removing non-existent file should succeed (idempotent)
+        remove_pid_file(test_path).unwrap();
+    }
+
+    #[test]
+    fn is_daemon_running_no_pid_file() {
+        // When no PID file exists, should return None
+        // This assumes no actual daemon is running
+        unsafe {
+            std::env::set_var("XDG_RUNTIME_DIR", "/tmp/dpms-test-nofile");
+        }
+        let result = is_daemon_running(None);
+        assert!(result.is_none());
+        let result = is_daemon_running(Some("HDMI-A-1"));
+        assert!(result.is_none());
+    }
+}
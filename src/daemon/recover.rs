@@ -0,0 +1,386 @@
+/// Turning displays back on without a daemon to ask
+///
+/// A daemon that died while holding the displays off leaves its pre-off
+/// state file behind; `recover` turns the saved CRTCs back on from it.
+/// `reenable_inactive` lights up connected displays that are dark for any
+/// other reason.
+use super::instance::{
+    Instance, SavedCrtc, info_path, instances_in, read_daemon_info, read_saved_state,
+    running_daemons, runtime_dir,
+};
+use super::stop_daemons;
+use crate::drm_ops::{ConnectorInfo, DrmControl, OpenOptions, open_drm};
+use crate::error::Error;
+use crate::output::{format_elapsed, unix_now};
+use crate::ui;
+use std::fs;
+use std::time::Duration;
+
+/// What to do with a saved CRTC during recovery
+#[derive(Debug, PartialEq, Eq)]
+enum Recovery {
+    /// Turn the CRTC back on
+    Restore,
+    /// Leave it alone, with the reason
+    Skip(String),
+}
+
+/// Decide whether a saved CRTC should be turned back on
+///
+/// # Parameters
+/// - `entry`: The saved pre-off state
+/// - `connectors`: The connectors currently present on the entry's device
+fn recovery_action(entry: &SavedCrtc, connectors: &[ConnectorInfo]) -> Recovery {
+    if !entry.active {
+        return Recovery::Skip("was already off".to_string());
+    }
+
+    match connectors
+        .iter()
+        .find(|c| u32::from(c.handle) == entry.connector_id)
+    {
+        None => Recovery::Skip("connector no longer exists".to_string()),
+        Some(c) if c.state != drm::control::connector::State::Connected => {
+            Recovery::Skip("no longer connected".to_string())
+        }
+        Some(_) => Recovery::Restore,
+    }
+}
+
+/// Turn displays back on after a daemon died without restoring them
+///
+/// If any daemon is still running they are stopped normally. Otherwise the
+/// pre-off state files of every instance are read and each saved CRTC is
+/// re-enabled directly, then the files are removed.
+///
+/// # Returns
+/// - `Ok(())` - Every saved display was restored or skipped
+/// - `Err(Error::PartialFailure)` - Some displays couldn't be restored
+/// - `Err(Error)` - The devices couldn't be opened
+pub fn recover(options: &OpenOptions, kill_after: Option<Duration>) -> Result<(), Error> {
+    if !running_daemons().is_empty() {
+        return stop_daemons(None, kill_after, options).map(|_| ());
+    }
+
+    recover_instances(&instances_in(&runtime_dir(), "state"), options)
+}
+
+/// Restore the displays saved in the state files of `instances`
+///
+/// A single instance's card is opened alone, since daemons of other
+/// instances may still be master of theirs.
+pub(super) fn recover_instances(
+    instances: &[Instance],
+    options: &OpenOptions,
+) -> Result<(), Error> {
+    let mut saved = Vec::new();
+    for instance in instances {
+        saved.extend(read_saved_state(instance.state_path())?);
+        if let Some(info) = read_daemon_info(&info_path(&instance.pid_path())) {
+            ui::notice(format_args!(
+                "Recovering {} held off by daemon (PID {}) for {}",
+                instance,
+                info.pid,
+                format_elapsed(unix_now().saturating_sub(info.started))
+            ));
+        }
+    }
+    let remove_state = || {
+        for instance in instances {
+            let _ = fs::remove_file(instance.state_path());
+            let _ = fs::remove_file(info_path(&instance.pid_path()));
+            let _ = fs::remove_file(instance.socket_path());
+        }
+    };
+    if saved.is_empty() {
+        ui::notice("Nothing to recover");
+        remove_state();
+        return Ok(());
+    }
+
+    let options = match instances {
+        [instance] => instance.open_options(options),
+        _ => options.clone(),
+    };
+    let (_seat, devices) = open_drm(&options)?;
+    let failed = recover_devices(&devices, &saved);
+    remove_state();
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::PartialFailure { failed })
+    }
+}
+
+/// Turn the saved CRTCs of `devices` back on
+///
+/// # Returns
+/// `(name, reason)` for each saved display that couldn't be restored
+pub(super) fn recover_devices<D: DrmControl>(
+    devices: &[D],
+    saved: &[SavedCrtc],
+) -> Vec<(String, String)> {
+    let mut failed = Vec::new();
+
+    for entry in saved {
+        let Some(drm) = devices
+            .iter()
+            .find(|d| d.path().display().to_string() == entry.device)
+        else {
+            failed.push((entry.device.clone(), "device not found".to_string()));
+            continue;
+        };
+
+        let action = drm
+            .list_connectors()
+            .map(|connectors| recovery_action(entry, &connectors));
+        let result = match action {
+            Ok(Recovery::Restore) => drm::control::from_u32(entry.crtc)
+                .ok_or_else(|| Error::DrmError(format!("Invalid CRTC id {}", entry.crtc).into()))
+                .and_then(|crtc_handle| drm.set_crtc_active(crtc_handle, true)),
+            Ok(Recovery::Skip(reason)) => {
+                ui::notice(format_args!(
+                    "Skipping {} on {}: {}",
+                    entry.connector, entry.device, reason
+                ));
+                continue;
+            }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => ui::notice(format_args!(
+                "Restored {} (CRTC {}) on {}",
+                entry.connector, entry.crtc, entry.device
+            )),
+            Err(e) => failed.push((entry.connector.clone(), e.to_string())),
+        }
+    }
+
+    failed
+}
+
+/// Turn back on inactive CRTCs that no daemon holds off
+///
+/// Used when a display is dark but no daemon is running, e.g. because
+/// another tool blanked it. CRTCs that still have a mode and framebuffer
+/// are simply re-activated; connectors left without either (e.g. after a
+/// crash that left nothing mapped) get a full modeset.
+///
+/// # Parameters
+/// - `connector`: Only re-enable the CRTC driving this connector
+///
+/// # Returns
+/// - `Ok(())` - Every inactive CRTC was re-enabled
+/// - `Err(Error::PartialFailure)` - Some CRTCs couldn't be re-enabled
+/// - `Err(Error)` - The devices couldn't be opened
+pub fn reenable_inactive(connector: Option<&str>, options: &OpenOptions) -> Result<(), Error> {
+    let (_seat, devices) = open_drm(options)?;
+    let failed = reenable_devices(&devices, connector);
+
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::PartialFailure { failed })
+    }
+}
+
+/// Turn back on the dark connected connectors of `devices`
+///
+/// # Returns
+/// `(name, reason)` for each display or device that failed
+fn reenable_devices<D: DrmControl>(
+    devices: &[D],
+    connector: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut failed = Vec::new();
+
+    for drm in devices {
+        let connectors = match drm.list_connectors() {
+            Ok(connectors) => connectors,
+            Err(e) => {
+                failed.push((drm.path().display().to_string(), e.to_string()));
+                continue;
+            }
+        };
+
+        for c in connectors {
+            if c.state != drm::control::connector::State::Connected
+                || connector.is_some_and(|name| name != c.name)
+                || c.crtc
+                    .is_some_and(|h| drm.get_crtc_active(h).unwrap_or(true))
+            {
+                continue;
+            }
+
+            let resumable = match c.crtc {
+                Some(crtc_handle) => drm.crtc_can_resume(crtc_handle).unwrap_or(false),
+                None => false,
+            };
+            let result = match c.crtc {
+                Some(crtc_handle) if resumable => {
+                    drm.set_crtc_active(crtc_handle, true).map(|_| crtc_handle)
+                }
+                _ => drm.modeset_connector(c.handle),
+            };
+            match result {
+                Ok(crtc_handle) => ui::notice(format_args!(
+                    "Re-enabled {} (CRTC {}) on {}",
+                    c.name,
+                    u32::from(crtc_handle),
+                    drm.path().display()
+                )),
+                Err(e) => failed.push((c.name, e.to_string())),
+            }
+        }
+    }
+
+    failed
+}
+
+/// Whether a pre-off state file of an instance that isn't running exists,
+/// meaning a daemon may have died without restoring its displays
+pub fn has_saved_state() -> bool {
+    let running = running_daemons();
+    instances_in(&runtime_dir(), "state")
+        .iter()
+        .any(|instance| !running.iter().any(|(other, _)| other == instance))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::drm_ops::fake::{Call, FakeDrm};
+
+    fn saved_crtc(connector_id: u32, active: bool) -> SavedCrtc {
+        SavedCrtc {
+            device: "/dev/dri/card1".to_string(),
+            crtc: 51,
+            connector_id,
+            connector: "HDMI-A-1".to_string(),
+            active,
+        }
+    }
+
+    fn connector_info(id: u32, state: drm::control::connector::State) -> ConnectorInfo {
+        ConnectorInfo {
+            handle: drm::control::from_u32(id).unwrap(),
+            name: "HDMI-A-1".to_string(),
+            state,
+            crtc: drm::control::from_u32(51),
+            edid: Default::default(),
+        }
+    }
+
+    #[test]
+    fn recovery_restores_connected_connector() {
+        use drm::control::connector::State;
+        let connectors = [connector_info(77, State::Connected)];
+        assert_eq!(
+            recovery_action(&saved_crtc(77, true), &connectors),
+            Recovery::Restore
+        );
+    }
+
+    #[test]
+    fn recovery_skips_stale_entries() {
+        use drm::control::connector::State;
+
+        // The saved connector is gone (e.g. an unplugged MST display)
+        let connectors = [connector_info(78, State::Connected)];
+        assert_eq!(
+            recovery_action(&saved_crtc(77, true), &connectors),
+            Recovery::Skip("connector no longer exists".to_string())
+        );
+
+        let connectors = [connector_info(77, State::Disconnected)];
+        assert_eq!(
+            recovery_action(&saved_crtc(77, true), &connectors),
+            Recovery::Skip("no longer connected".to_string())
+        );
+
+        let connectors = [connector_info(77, State::Connected)];
+        assert_eq!(
+            recovery_action(&saved_crtc(77, false), &connectors),
+            Recovery::Skip("was already off".to_string())
+        );
+    }
+
+    fn set_active(crtcs: &[u32], active: bool) -> Call {
+        Call::SetActive {
+            crtcs: crtcs.to_vec(),
+            active,
+        }
+    }
+
+    #[test]
+    fn recover_devices_restores_saved_crtcs() {
+        let devices = [FakeDrm::new("/dev/dri/card1")
+            .with_connector("HDMI-A-1", 77, Some(51))
+            .with_inactive_crtc(51)];
+        let saved = [
+            SavedCrtc {
+                crtc: 51,
+                ..saved_crtc(77, true)
+            },
+            // Its connector has since disappeared
+            saved_crtc(78, true),
+            SavedCrtc {
+                device: "/dev/dri/card9".to_string(),
+                ..saved_crtc(77, true)
+            },
+        ];
+
+        let failed = recover_devices(&devices, &saved);
+
+        assert!(devices[0].is_active(51));
+        assert_eq!(devices[0].calls(), [set_active(&[51], true)]);
+        assert_eq!(
+            failed,
+            [("/dev/dri/card9".to_string(), "device not found".to_string())]
+        );
+    }
+
+    #[test]
+    fn recover_devices_reports_restore_failures() {
+        let devices = [FakeDrm::new("/dev/dri/card1")
+            .with_connector("HDMI-A-1", 77, Some(51))
+            .with_inactive_crtc(51)
+            .failing_enable()];
+
+        let failed = recover_devices(&devices, &[saved_crtc(77, true)]);
+
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, "HDMI-A-1");
+    }
+
+    #[test]
+    fn reenable_devices_resumes_or_modesets_dark_connectors() {
+        let devices = [FakeDrm::new("/dev/dri/card0")
+            .with_connector("eDP-1", 70, Some(40))
+            .with_connector("HDMI-A-1", 77, Some(51))
+            .with_inactive_crtc(51)
+            .with_connector("DP-1", 80, None)];
+
+        let failed = reenable_devices(&devices, None);
+
+        assert!(failed.is_empty());
+        assert_eq!(
+            devices[0].calls(),
+            [set_active(&[51], true), Call::Modeset { connector: 80 }]
+        );
+    }
+
+    #[test]
+    fn reenable_devices_limits_to_named_connector() {
+        let devices = [FakeDrm::new("/dev/dri/card0")
+            .with_connector("HDMI-A-1", 77, Some(51))
+            .with_inactive_crtc(51)
+            .with_connector("DP-1", 80, None)];
+
+        reenable_devices(&devices, Some("DP-1"));
+
+        assert_eq!(devices[0].calls(), [Call::Modeset { connector: 80 }]);
+    }
+}
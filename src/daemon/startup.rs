@@ -0,0 +1,863 @@
+/// Starting a daemon and hearing back from it
+///
+/// `start_daemon` spawns the daemon with the write end of a status pipe in
+/// `DPMS_STATUS_FD`. The daemon writes its PID there after daemonizing, and
+/// why it gave up if it fails to turn the displays off, so the command fails
+/// with the daemon's own reason rather than a timeout. Success shows in the
+/// PID file.
+use super::instance::{
+    Instance, is_process_running, outdated_daemon, read_failed_devices, read_pid_file,
+    resolve_instance, running_daemons, warn_outdated,
+};
+use super::{DaemonOptions, RuntimeWatch, STOP_TIMEOUT};
+use crate::drm_ops::OpenOptions;
+use crate::error::Error;
+use crate::logfile;
+use crate::ui;
+use nix::sys::signal::{self, Signal};
+use nix::unistd::Pid;
+use std::fs;
+use std::io::{Read, Write};
+use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+
+/// Check that no other running daemon is master of the card to act on
+///
+/// A daemon holds DRM master on its card, which would otherwise pass for a
+/// display server; this tells the two apart before looking for one.
+///
+/// # Parameters
+/// - `connector`: The display to turn off, or `None` for every display
+///
+/// # Returns
+/// - `Ok(())` - No other daemon holds the card
+/// - `Err(Error::DaemonConflict)` - Another daemon holds the card
+pub fn check_daemon_conflict(connector: Option<&str>, options: &OpenOptions) -> Result<(), Error> {
+    let running = running_daemons();
+    if running.is_empty() {
+        return Ok(());
+    }
+    let (instance, _) = resolve_instance(connector, options)?;
+    conflicting_daemon(&instance, &running)
+}
+
+/// Fail if a daemon in `running`, other than `instance` itself, holds its card
+fn conflicting_daemon(instance: &Instance, running: &[(Instance, Pid)]) -> Result<(), Error> {
+    match running
+        .iter()
+        .find(|(other, _)| other != instance && instance.conflicts_with(other))
+    {
+        Some((other, _)) => Err(Error::DaemonConflict {
+            holder: other.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Environment variable naming the pipe the daemon reports startup failures to
+const STATUS_FD_ENV: &str = "DPMS_STATUS_FD";
+
+/// Marks the status pipe line carrying the daemon's PID after daemonizing
+const STATUS_PID_PREFIX: &str = "pid=";
+
+/// How long `start_daemon` waits for the daemon to turn the displays off
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How much longer `start_daemon` waits for a daemon that is still running
+/// at `STARTUP_TIMEOUT`, e.g. behind a slow modeset, before stopping it
+const STARTUP_GRACE: Duration = Duration::from_secs(10);
+
+/// Status pipe line of a daemon that found another one holding the lock
+const STATUS_ALREADY_RUNNING: &str = "running";
+
+/// Detach the daemon from the terminal that launched it
+///
+/// Forks, starts a new session and forks again, so the daemon is neither
+/// hit by the terminal's hangup nor able to acquire it as controlling
+/// terminal. The intermediate processes exit; the last one reports the
+/// daemon's PID on `status`. The daemon then moves to `/`, so it doesn't
+/// keep a filesystem busy, and points its standard streams at `/dev/null`.
+pub(super) fn daemonize(status: &mut StartupStatus) -> std::io::Result<()> {
+    use nix::unistd::{ForkResult, dup2, fork, setsid};
+
+    // SAFETY: nothing has spawned a thread yet, and the parent exits at once
+    if let ForkResult::Parent { .. } = unsafe { fork() }? {
+        // SAFETY: _exit skips destructors and atexit handlers meant for the daemon
+        unsafe { nix::libc::_exit(0) };
+    }
+    setsid()?;
+    // SAFETY: as above
+    if let ForkResult::Parent { child } = unsafe { fork() }? {
+        status.started(child);
+        // SAFETY: as above
+        unsafe { nix::libc::_exit(0) };
+    }
+
+    std::env::set_current_dir("/")?;
+    let null = fs::File::options()
+        .read(true)
+        .write(true)
+        .open("/dev/null")?;
+    for fd in 0..=2 {
+        dup2(null.as_raw_fd(), fd)?;
+    }
+    Ok(())
+}
+
+/// The daemon's end of the pipe `start_daemon` reads startup failures from
+///
+/// Apart from the daemon's PID after daemonizing, only failures are
+/// written; the parent learns of success from the PID file. Once the parent
+/// has returned, reports go nowhere but stderr.
+pub(super) struct StartupStatus(Option<fs::File>);
+
+impl StartupStatus {
+    /// The pipe passed down in `DPMS_STATUS_FD`, if any
+    pub(super) fn from_env() -> Self {
+        let fd = std::env::var(STATUS_FD_ENV)
+            .ok()
+            .and_then(|fd| fd.parse::<i32>().ok())
+            .filter(|fd| *fd > 2);
+        // SAFETY: start_daemon leaves exactly this descriptor open for us
+        StartupStatus(fd.map(|fd| unsafe { fs::File::from_raw_fd(fd) }))
+    }
+
+    /// Tell the parent which process ended up being the daemon
+    fn started(&mut self, pid: Pid) {
+        if let Some(pipe) = &mut self.0 {
+            let _ = writeln!(pipe, "{}{}", STATUS_PID_PREFIX, pid);
+        }
+    }
+
+    /// Tell the parent that another daemon already holds the displays
+    pub(super) fn already_running(&mut self) {
+        eprintln!("Another dpms daemon is already running");
+        if let Some(pipe) = &mut self.0 {
+            let _ = writeln!(pipe, "{}", STATUS_ALREADY_RUNNING);
+        }
+    }
+
+    /// Report why the daemon is giving up
+    fn report(&mut self, message: &str) {
+        eprintln!("{}", message);
+        if let Some(pipe) = &mut self.0 {
+            let _ = writeln!(pipe, "{}", message);
+        }
+    }
+
+    /// Report why the daemon is giving up, and exit
+    pub(super) fn fail(&mut self, message: &str) -> ! {
+        self.report(message);
+        logfile::finish();
+        std::process::exit(1);
+    }
+}
+
+/// Start the dpms daemon
+///
+/// Spawns a new daemon process that:
+/// 1. Opens a libseat session
+/// 2. Opens DRM device
+/// 3. Disables CRTC (turns off display)
+/// 4. Writes PID file
+/// 5. Waits for SIGTERM/SIGINT to restore and exit
+///
+/// The parent process returns immediately after verifying the daemon started.
+///
+/// # Parameters
+/// - `connector`: Only turn off the display on this connector, e.g. `HDMI-A-1`
+///
+/// # Returns
+/// - `Ok(())` - Daemon started successfully
+/// - `Err(Error::PartialFailure)` - Daemon started, but some devices failed to turn off
+/// - `Err(Error::DaemonStartFailed)` - Daemon failed to start
+/// - `Err(Error::DaemonConflict)` - Another daemon holds the card
+pub fn start_daemon(
+    connector: Option<&str>,
+    options: &OpenOptions,
+    daemon: &DaemonOptions,
+) -> Result<(), Error> {
+    let (instance, options) = resolve_instance(connector, options)?;
+
+    // Check if daemon is already running (defense in depth)
+    let running = running_daemons();
+    if let Some((_, pid)) = running.iter().find(|(other, _)| *other == instance) {
+        if let Some(reason) = outdated_daemon(&instance, *pid) {
+            warn_outdated(&instance, *pid, &reason);
+        }
+        return Ok(()); // Already running, idempotent
+    }
+    // Only one daemon can be DRM master of a card
+    conflicting_daemon(&instance, &running)?;
+
+    let exe_path = match DAEMON_EXECUTABLE.get() {
+        Some(exe) => exe.clone(),
+        None => daemon_executable(std::env::current_exe(), std::env::args_os().next())?,
+    };
+
+    // Spawn daemon as a separate process with daemon-internal subcommand
+    // The daemon reports why it couldn't start through a pipe; only its
+    // write end survives the exec
+    let (status_read, status_write) = status_pipe()?;
+    nix::fcntl::fcntl(
+        status_write.as_raw_fd(),
+        nix::fcntl::FcntlArg::F_SETFD(nix::fcntl::FdFlag::empty()),
+    )
+    .map_err(|e| Error::DaemonStartFailed(format!("Failed to set up status pipe: {}", e)))?;
+
+    let mut command = Command::new(&exe_path);
+    command
+        .arg("daemon-internal")
+        .args(options.to_args())
+        .args(daemon.to_args())
+        .env(STATUS_FD_ENV, status_write.as_raw_fd().to_string());
+    if let Some(name) = connector {
+        command.arg("--connector").arg(name);
+    }
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| Error::DaemonStartFailed(format!("Failed to spawn daemon: {}", e)))?;
+    // Otherwise reading the pipe would never see the daemon's end close
+    drop(status_write);
+
+    await_startup(
+        &mut child,
+        status_read.into(),
+        &instance.pid_path(),
+        STARTUP_TIMEOUT,
+        STARTUP_GRACE,
+    )
+}
+
+/// Create a non-blocking pipe that isn't inherited by child processes
+///
+/// # Returns
+/// The read and write ends
+fn status_pipe() -> Result<(OwnedFd, OwnedFd), Error> {
+    nix::unistd::pipe2(nix::fcntl::OFlag::O_CLOEXEC | nix::fcntl::OFlag::O_NONBLOCK)
+        .map_err(|e| Error::DaemonStartFailed(format!("Failed to create status pipe: {}", e)))
+}
+
+/// Wait for a freshly spawned daemon to write its PID file
+///
+/// # Parameters
+/// - `child`: The daemon process
+/// - `status`: Read end of the pipe the daemon reports failures to
+/// - `pid_path`: Where the daemon writes its PID file
+/// - `timeout`: How long to wait for the PID file
+/// - `grace`: How much longer to wait if the daemon is still running then
+///
+/// A daemon that hasn't started once the grace period is over is stopped,
+/// so no daemon is left holding the displays off after being reported as
+/// failed.
+///
+/// # Returns
+/// - `Ok(())` - The daemon, or one started concurrently, is holding the
+///   displays off
+/// - `Err(Error::PartialFailure)` - Some devices failed to turn off
+/// - `Err(Error::DaemonStartFailed)` - The daemon exited, with the reason it
+///   reported, or didn't start in time
+fn await_startup(
+    child: &mut Child,
+    mut status: fs::File,
+    pid_path: &Path,
+    timeout: Duration,
+    grace: Duration,
+) -> Result<(), Error> {
+    // The spawned process is the daemon unless it reports forking another
+    let mut daemon_pid = Pid::from_raw(child.id() as i32);
+    let mut report = String::new();
+    let mut deadline = std::time::Instant::now() + timeout;
+    let mut extended = false;
+    // Wake as soon as the PID file is written or the daemon reports, so a
+    // fast start isn't held up
+    let watch = RuntimeWatch::new(pid_path);
+
+    loop {
+        // Reap the spawned process once it has forked off the daemon
+        let _ = child.try_wait();
+
+        let closed = read_status(&mut status, &mut report);
+        let mut reasons = Vec::new();
+        for line in report.lines() {
+            if line == STATUS_ALREADY_RUNNING {
+                return Ok(());
+            }
+            match line
+                .strip_prefix(STATUS_PID_PREFIX)
+                .and_then(|pid| pid.parse().ok())
+            {
+                Some(pid) => daemon_pid = Pid::from_raw(pid),
+                None => reasons.push(line),
+            }
+        }
+
+        // Every copy of the write end is gone, so the daemon has exited; one
+        // that couldn't open the displays does so straight away
+        if closed {
+            return Err(Error::DaemonStartFailed(if reasons.is_empty() {
+                silent_exit_reason(&logfile::log_path(), daemon_pid)
+            } else {
+                // A reason may take several lines, e.g. the displays to pick from
+                reasons.join("\n")
+            }));
+        }
+
+        if pid_path.exists() {
+            // Verify the PID in the file is actually the daemon we started
+            if let Ok(Some(pid)) = read_pid_file(pid_path)
+                && pid == daemon_pid
+            {
+                let failed = read_failed_devices(pid_path);
+                if !failed.is_empty() {
+                    return Err(Error::PartialFailure { failed });
+                }
+                return Ok(());
+            }
+        }
+
+        let now = std::time::Instant::now();
+        if now >= deadline {
+            if extended || !is_starting(child, daemon_pid) {
+                break;
+            }
+            // Still alive, so most likely held up by the driver
+            ui::notice(format_args!(
+                "Daemon (PID {}) is slow to turn the displays off; waiting up to {}s more",
+                daemon_pid,
+                grace.as_secs_f32()
+            ));
+            deadline += grace;
+            extended = true;
+        }
+        watch.wait(
+            Some(status.as_fd()),
+            watch
+                .interval()
+                .min(deadline.saturating_duration_since(now)),
+        );
+    }
+
+    let waited = (timeout + grace).as_secs_f32();
+    if !is_starting(child, daemon_pid) {
+        return Err(Error::DaemonStartFailed(format!(
+            "Daemon did not write PID file within {}s",
+            waited
+        )));
+    }
+    let stopped = if abandon_startup(child, daemon_pid) {
+        "stopped it"
+    } else {
+        "killed it; run `dpms recover` if a display stays off"
+    };
+    Err(Error::DaemonStartFailed(format!(
+        "daemon (PID {}) did not turn the displays off within {}s; {}",
+        daemon_pid, waited, stopped
+    )))
+}
+
+/// Whether the daemon being started is still running
+///
+/// The spawned process is reaped once it exits, so it is never mistaken for
+/// a running daemon as a zombie.
+fn is_starting(child: &mut Child, daemon_pid: Pid) -> bool {
+    if daemon_pid.as_raw() as u32 == child.id() {
+        matches!(child.try_wait(), Ok(None))
+    } else {
+        is_process_running(daemon_pid)
+    }
+}
+
+/// Stop a daemon that didn't start in time, and reap it if it is the child
+///
+/// SIGTERM lets it restore whatever it turned off; one that ignores it is
+/// killed.
+///
+/// # Returns
+/// `true` if it exited on SIGTERM
+fn abandon_startup(child: &mut Child, daemon_pid: Pid) -> bool {
+    let _ = signal::kill(daemon_pid, Signal::SIGTERM);
+    let deadline = std::time::Instant::now() + STOP_TIMEOUT;
+    while is_starting(child, daemon_pid) {
+        if std::time::Instant::now() >= deadline {
+            let _ = signal::kill(daemon_pid, Signal::SIGKILL);
+            if daemon_pid.as_raw() as u32 == child.id() {
+                let _ = child.wait();
+            }
+            return false;
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+    true
+}
+
+/// Explain a daemon that exited without reporting why
+///
+/// # Returns
+/// The last lines it logged to `log`, if any, with a hint at how to find out
+/// more
+fn silent_exit_reason(log: &Path, pid: Pid) -> String {
+    let hint = "run `dpms daemon-internal --foreground` to see why";
+    let last = logfile::tail(log, pid.as_raw() as u32, 3);
+    if last.is_empty() {
+        format!("daemon exited during startup; {}", hint)
+    } else {
+        format!(
+            "daemon exited during startup after logging: {}; see {} or {}",
+            last.join("; "),
+            log.display(),
+            hint
+        )
+    }
+}
+
+/// The `dpms` binary to run as the daemon instead of the current executable
+static DAEMON_EXECUTABLE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Run the daemon from `exe` rather than the current executable
+///
+/// Programs using dpms as a library aren't dpms themselves, so they point
+/// this at the installed binary. Only the first call has an effect.
+pub fn set_daemon_executable(exe: PathBuf) {
+    let _ = DAEMON_EXECUTABLE.set(exe);
+}
+
+/// Pick the executable to re-exec as the daemon
+///
+/// `current_exe` needs `/proc`; without it, `argv[0]` is used as long as
+/// it names a file rather than something to look up in `PATH`.
+///
+/// # Parameters
+/// - `current_exe`: Result of `std::env::current_exe()`
+/// - `argv0`: The first command-line argument
+fn daemon_executable(
+    current_exe: std::io::Result<PathBuf>,
+    argv0: Option<std::ffi::OsString>,
+) -> Result<PathBuf, Error> {
+    let err = match current_exe {
+        Ok(path) => return Ok(path),
+        Err(e) => e,
+    };
+    match argv0.map(PathBuf::from) {
+        Some(path) if path.components().count() > 1 && path.exists() => Ok(path),
+        _ => Err(Error::DaemonStartFailed(format!(
+            "Failed to get executable path: {}",
+            err
+        ))),
+    }
+}
+
+/// Append whatever the daemon has written to the non-blocking status pipe
+///
+/// # Returns
+/// `true` once the pipe is closed
+fn read_status(status: &mut fs::File, report: &mut String) -> bool {
+    let mut buf = [0u8; 512];
+    loop {
+        match status.read(&mut buf) {
+            Ok(0) => return true,
+            Ok(len) => report.push_str(&String::from_utf8_lossy(&buf[..len])),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return false,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(_) => return true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::WAIT_POLL_INTERVAL;
+    use crate::daemon::instance::write_pid_file;
+    use crate::output::PowerState;
+
+    /// Run a fake daemon that reports `reason` like `daemon_main` and exits
+    fn failed_start(reason: Option<&str>) -> Error {
+        let pid_path = Path::new("/tmp/dpms-test-failed-start.pid");
+        let (read, write) = status_pipe().unwrap();
+        if let Some(reason) = reason {
+            StartupStatus(Some(write.into())).report(reason);
+        } else {
+            drop(write);
+        }
+        let mut child = Command::new("sh").args(["-c", "exit 1"]).spawn().unwrap();
+        await_startup(
+            &mut child,
+            read.into(),
+            pid_path,
+            STARTUP_TIMEOUT,
+            STARTUP_GRACE,
+        )
+        .unwrap_err()
+    }
+
+    #[test]
+    fn start_daemon_reports_why_the_daemon_exited() {
+        let path = "/dev/dri/card0".to_string();
+        for error in [
+            Error::NoDisplayFound,
+            Error::DrmPermissionDenied { path: path.clone() },
+            Error::NoSeatManager { path: path.clone() },
+            Error::DisplayServerActive { path },
+        ] {
+            let err = failed_start(Some(&error.to_string()));
+            assert!(matches!(err, Error::DaemonStartFailed(_)));
+            assert_eq!(
+                err.to_string(),
+                format!("Daemon failed to start: {}", error)
+            );
+        }
+    }
+
+    #[test]
+    fn start_daemon_hints_when_daemon_exits_silently() {
+        let err = failed_start(None);
+        assert!(
+            err.to_string()
+                .contains("run `dpms daemon-internal --foreground` to see why")
+        );
+    }
+
+    #[test]
+    fn silent_exit_quotes_the_daemon_log() {
+        let log = Path::new("/tmp/dpms-test-silent.log");
+        fs::write(
+            log,
+            "2024-05-01T10:00:00Z [77] Starting for all displays: daemon-internal\n\
+             2024-05-01T10:00:00Z [77] thread 'main' panicked at src/drm_ops.rs\n",
+        )
+        .unwrap();
+
+        let reason = silent_exit_reason(log, Pid::from_raw(77));
+        let other = silent_exit_reason(log, Pid::from_raw(78));
+        let _ = fs::remove_file(log);
+
+        assert!(reason.contains("panicked at src/drm_ops.rs"));
+        assert!(reason.contains("/tmp/dpms-test-silent.log"));
+        assert!(reason.contains("run `dpms daemon-internal --foreground` to see why"));
+        assert_eq!(
+            other,
+            "daemon exited during startup; run `dpms daemon-internal --foreground` to see why"
+        );
+    }
+
+    #[test]
+    fn start_daemon_accepts_concurrently_started_daemon() {
+        let pid_path = Path::new("/tmp/dpms-test-concurrent-start.pid");
+        let (read, write) = status_pipe().unwrap();
+        StartupStatus(Some(write.into())).already_running();
+
+        let mut child = Command::new("sh").args(["-c", "exit 0"]).spawn().unwrap();
+        assert!(
+            await_startup(
+                &mut child,
+                read.into(),
+                pid_path,
+                STARTUP_TIMEOUT,
+                STARTUP_GRACE,
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn start_daemon_follows_forked_daemon() {
+        let pid_path = Path::new("/tmp/dpms-test-forked-start.pid");
+        let (read, write) = status_pipe().unwrap();
+        // Stands in for the daemon the spawned process forks off
+        let mut daemon = Command::new("sleep").arg("5").spawn().unwrap();
+        let daemon_pid = Pid::from_raw(daemon.id() as i32);
+        let mut status = StartupStatus(Some(write.into()));
+        status.started(daemon_pid);
+        write_pid_file(pid_path, daemon_pid, None, PowerState::Off, &[], &[]).unwrap();
+
+        // The spawned process exits once the daemon is forked off
+        let mut child = Command::new("sh").args(["-c", "exit 0"]).spawn().unwrap();
+        let result = await_startup(
+            &mut child,
+            read.into(),
+            pid_path,
+            STARTUP_TIMEOUT,
+            STARTUP_GRACE,
+        );
+
+        let _ = daemon.kill();
+        let _ = daemon.wait();
+        let _ = fs::remove_file(pid_path);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn start_daemon_waits_longer_for_a_slow_daemon() {
+        let pid_path = Path::new("/tmp/dpms-test-slow-start.pid");
+        let _ = fs::remove_file(pid_path);
+        let (read, _write) = status_pipe().unwrap();
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let daemon_pid = Pid::from_raw(child.id() as i32);
+        let writer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            write_pid_file(pid_path, daemon_pid, None, PowerState::Off, &[], &[]).unwrap();
+        });
+
+        let result = await_startup(
+            &mut child,
+            read.into(),
+            pid_path,
+            Duration::from_millis(100),
+            Duration::from_secs(5),
+        );
+
+        writer.join().unwrap();
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = fs::remove_file(pid_path);
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn start_daemon_returns_as_soon_as_the_pid_file_is_written() {
+        let dir = Path::new("/tmp/dpms-test-ready");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let pid_path = dir.join("dpms.pid");
+        let (read, _write) = status_pipe().unwrap();
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let daemon_pid = Pid::from_raw(child.id() as i32);
+        let writer = {
+            let pid_path = pid_path.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(250));
+                write_pid_file(&pid_path, daemon_pid, None, PowerState::Off, &[], &[]).unwrap();
+                std::time::Instant::now()
+            })
+        };
+
+        let result = await_startup(
+            &mut child,
+            read.into(),
+            &pid_path,
+            STARTUP_TIMEOUT,
+            STARTUP_GRACE,
+        );
+        let returned = std::time::Instant::now();
+
+        let written = writer.join().unwrap();
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = fs::remove_dir_all(dir);
+        assert!(result.is_ok());
+        // Well within the interval it would otherwise have slept for
+        assert!(returned - written < WAIT_POLL_INTERVAL / 2);
+    }
+
+    #[test]
+    fn start_daemon_stops_a_daemon_that_never_starts() {
+        let pid_path = Path::new("/tmp/dpms-test-stuck-start.pid");
+        let _ = fs::remove_file(pid_path);
+        let (read, _write) = status_pipe().unwrap();
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+
+        let err = await_startup(
+            &mut child,
+            read.into(),
+            pid_path,
+            Duration::from_millis(100),
+            Duration::from_millis(200),
+        )
+        .unwrap_err();
+
+        assert!(err.to_string().ends_with("within 0.3s; stopped it"));
+        // Reaped, so nothing is left behind
+        assert!(matches!(child.try_wait(), Ok(Some(_))));
+    }
+
+    #[test]
+    fn start_daemon_ignores_pid_file_of_other_process() {
+        let pid_path = Path::new("/tmp/dpms-test-other-pid.pid");
+        write_pid_file(pid_path, Pid::this(), None, PowerState::Off, &[], &[]).unwrap();
+
+        let (read, write) = status_pipe().unwrap();
+        let mut status = StartupStatus(Some(write.into()));
+        status.report("No connected display found");
+        drop(status);
+        let mut child = Command::new("sh").args(["-c", "exit 1"]).spawn().unwrap();
+        let err = await_startup(
+            &mut child,
+            read.into(),
+            pid_path,
+            STARTUP_TIMEOUT,
+            STARTUP_GRACE,
+        )
+        .unwrap_err();
+
+        let _ = fs::remove_file(pid_path);
+        assert_eq!(
+            err.to_string(),
+            "Daemon failed to start: No connected display found"
+        );
+    }
+
+    #[test]
+    fn second_instance_on_a_held_card_conflicts() {
+        let display = |card: &str, connector: &str| Instance::Display {
+            card: card.to_string(),
+            connector: connector.to_string(),
+        };
+        let running = [(display("card0", "HDMI-A-1"), Pid::from_raw(77))];
+
+        for instance in [display("card0", "eDP-1"), Instance::All] {
+            let err = conflicting_daemon(&instance, &running).unwrap_err();
+            assert!(matches!(
+                err,
+                Error::DaemonConflict { ref holder } if holder == "HDMI-A-1 on card0"
+            ));
+        }
+        // The daemon itself, and other cards, are no conflict
+        assert!(conflicting_daemon(&display("card0", "HDMI-A-1"), &running).is_ok());
+        assert!(conflicting_daemon(&display("card1", "eDP-1"), &running).is_ok());
+        assert!(conflicting_daemon(&Instance::All, &[]).is_ok());
+    }
+
+    #[test]
+    fn daemon_executable_falls_back_to_argv0() {
+        let no_proc = || Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+        let exe = PathBuf::from("/usr/bin/dpms");
+
+        assert_eq!(
+            daemon_executable(Ok(exe.clone()), Some("dpms".into())).unwrap(),
+            exe
+        );
+        assert_eq!(
+            daemon_executable(no_proc(), Some("/bin/sh".into())).unwrap(),
+            PathBuf::from("/bin/sh")
+        );
+        // A bare name would be looked up in PATH, which may find another dpms
+        assert!(matches!(
+            daemon_executable(no_proc(), Some("sh".into())),
+            Err(Error::DaemonStartFailed(_))
+        ));
+        assert!(daemon_executable(no_proc(), None).is_err());
+    }
+
+    /// A pseudo-terminal, as the terminal a daemon is started from
+    ///
+    /// # Returns
+    /// The master side, and the path of the slave to open as a terminal
+    fn open_pty() -> (OwnedFd, PathBuf) {
+        use nix::libc;
+
+        // SAFETY: plain libc calls on a descriptor only this function has
+        unsafe {
+            let master = libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC);
+            assert!(master >= 0, "{}", std::io::Error::last_os_error());
+            let master = OwnedFd::from_raw_fd(master);
+            let mut name = [0 as libc::c_char; 64];
+            assert_eq!(libc::grantpt(master.as_raw_fd()), 0);
+            assert_eq!(libc::unlockpt(master.as_raw_fd()), 0);
+            assert_eq!(
+                libc::ptsname_r(master.as_raw_fd(), name.as_mut_ptr(), name.len()),
+                0
+            );
+            let name = std::ffi::CStr::from_ptr(name.as_ptr());
+            (master, PathBuf::from(name.to_string_lossy().into_owned()))
+        }
+    }
+
+    #[test]
+    fn daemonize_survives_its_session_closing() {
+        use nix::sys::wait::waitpid;
+        use nix::unistd::{ForkResult, dup2, fork, setsid};
+
+        let (master, tty) = open_pty();
+        let (read, write) = status_pipe().unwrap();
+
+        // SAFETY: the children only set up the session and daemonize, and
+        // never return into the test harness
+        let leader = match unsafe { fork() }.unwrap() {
+            ForkResult::Parent { child } => child,
+            ForkResult::Child => {
+                // The session leader, like the shell of the terminal, with
+                // the terminal as its controlling one
+                drop(read);
+                let session = setsid().is_ok()
+                    && fs::File::options()
+                        .read(true)
+                        .write(true)
+                        .open(&tty)
+                        .is_ok_and(|tty| (0..=2).all(|fd| dup2(tty.as_raw_fd(), fd).is_ok()));
+                if !session {
+                    // SAFETY: _exit skips the destructors meant for the test process
+                    unsafe { nix::libc::_exit(1) };
+                }
+                // SAFETY: as above
+                if let ForkResult::Child = unsafe { fork() }.unwrap() {
+                    // The command the shell ran
+                    drop(master);
+                    if daemonize(&mut StartupStatus(Some(write.into()))).is_ok() {
+                        loop {
+                            nix::unistd::pause();
+                        }
+                    }
+                    // SAFETY: as above
+                    unsafe { nix::libc::_exit(1) };
+                }
+                loop {
+                    nix::unistd::pause();
+                }
+            }
+        };
+        drop(master);
+        drop(write);
+
+        let mut status: fs::File = read.into();
+        let mut report = String::new();
+        let mut waited = Duration::ZERO;
+        let daemon = loop {
+            read_status(&mut status, &mut report);
+            let pid = report
+                .lines()
+                .find_map(|line| line.strip_prefix(STATUS_PID_PREFIX))
+                .and_then(|pid| pid.parse().ok());
+            if let Some(pid) = pid {
+                break Pid::from_raw(pid);
+            }
+            assert!(waited < STARTUP_TIMEOUT, "No daemon PID: {:?}", report);
+            thread::sleep(WAIT_POLL_INTERVAL);
+            waited += WAIT_POLL_INTERVAL;
+        };
+
+        // Closing the session hangs up the terminal, and signals whatever
+        // still runs on it
+        signal::kill(leader, Signal::SIGKILL).unwrap();
+        waitpid(leader, None).unwrap();
+        thread::sleep(WAIT_POLL_INTERVAL);
+
+        let proc = PathBuf::from(format!("/proc/{}", daemon));
+        let state = fs::read_to_string(proc.join("stat")).map(|stat| {
+            let fields = stat.rsplit_once(')').map(|(_, fields)| fields.to_string());
+            fields.unwrap_or_default().trim_start().chars().next()
+        });
+        let cwd = fs::read_link(proc.join("cwd"));
+        let streams: Vec<_> = (0..=2)
+            .map(|fd| fs::read_link(proc.join("fd").join(fd.to_string())))
+            .collect();
+        let _ = signal::kill(daemon, Signal::SIGKILL);
+
+        assert!(
+            matches!(state, Ok(Some(state)) if state != 'Z'),
+            "The daemon died with its session: {:?}",
+            state
+        );
+        assert_eq!(cwd.unwrap(), Path::new("/"));
+        for stream in streams {
+            assert_eq!(stream.unwrap(), Path::new("/dev/null"));
+        }
+    }
+}
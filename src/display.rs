@@ -2,6 +2,12 @@
 //!
 //! This module provides types and functions for selecting target displays
 //! by name, with support for exact and partial matching.
+use crate::error::Error;
+use serde::Serialize;
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::str::Chars;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum DisplayTarget {
     /// Specific display by name
@@ -30,48 +36,216 @@ impl DisplayTarget {
             DisplayTarget::Default
         }
     }
+
+    /// The display a `dpms serve` call or `dpms repl` request names; empty
+    /// for the default displays
+    pub fn from_name(display: &str) -> Self {
+        match display {
+            "" => DisplayTarget::Default,
+            name => DisplayTarget::Named(name.to_string()),
+        }
+    }
 }
 
 /// Display information for listing and status
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// In JSON, fields the backend doesn't know are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct DisplayInfo {
     pub name: String,
     pub power: crate::output::PowerState,
+    #[serde(skip)]
     pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub make: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    /// DRM objects behind the display; only the TTY backend knows them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drm: Option<DrmIds>,
+}
+
+/// DRM object ids of a display, for use with kernel tooling
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DrmIds {
+    /// Device node, e.g. `/dev/dri/card0`
+    pub card: String,
+    pub connector_id: u32,
+    /// CRTC driving the connector, if any
+    pub crtc_id: Option<u32>,
+}
+
+/// Whether a connector drives a built-in panel rather than an external monitor
+pub fn is_internal_panel(connector: &str) -> bool {
+    ["eDP-", "LVDS-", "DSI-"]
+        .iter()
+        .any(|prefix| connector.starts_with(prefix))
+}
+
+/// Compare display names so that numeric parts sort by value
+///
+/// Runs of ASCII digits are compared as numbers, everything else character by
+/// character, so `DP-2` sorts before `DP-10`. Names that only differ in
+/// leading zeros (`DP-01` vs `DP-1`) are ordered by their digit count to keep
+/// the ordering total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let ordering = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x_digits = take_digits(&mut a_chars);
+                let y_digits = take_digits(&mut b_chars);
+                let x_value = x_digits.trim_start_matches('0');
+                let y_value = y_digits.trim_start_matches('0');
+                x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x_digits.len().cmp(&y_digits.len()))
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.cmp(y);
+                a_chars.next();
+                b_chars.next();
+                ordering
+            }
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+/// Find the display called `name`, or the only one whose name starts with it
+///
+/// An exact match wins over prefix matches, so `DP` picks a display named
+/// `DP` even when `DP-1` exists. Displays without a name never match.
+///
+/// # Parameters
+/// - `displays`: Candidates, in display order
+/// - `name`: Name or name prefix given by the user
+/// - `display_name`: Name of a candidate, if it has one
+///
+/// # Returns
+/// - `Ok(display)` - The matching display
+/// - `Err(Error::DisplayNotFound)` - Nothing matches; lists every named display
+/// - `Err(Error::AmbiguousDisplay)` - Several displays start with `name`
+pub fn find_display_by_name<'a, 'n, T>(
+    displays: &'a [T],
+    name: &str,
+    display_name: impl Fn(&'a T) -> Option<&'n str>,
+) -> Result<&'a T, Error> {
+    if let Some(exact) = displays.iter().find(|d| display_name(d) == Some(name)) {
+        return Ok(exact);
+    }
+
+    let matches: Vec<&T> = displays
+        .iter()
+        .filter(|d| display_name(d).is_some_and(|n| n.starts_with(name)))
+        .collect();
+
+    match matches[..] {
+        [only] => Ok(only),
+        [] => Err(Error::DisplayNotFound {
+            name: name.to_string(),
+            available: displays
+                .iter()
+                .filter_map(|d| display_name(d).map(str::to_string))
+                .collect(),
+        }),
+        _ => Err(Error::AmbiguousDisplay {
+            name: name.to_string(),
+            candidates: matches
+                .into_iter()
+                .filter_map(|d| display_name(d).map(str::to_string))
+                .collect(),
+        }),
+    }
+}
+
+/// Consume a run of ASCII digits
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::error::Error;
     use crate::output::PowerState;
 
-    /// Find a display by name with exact and partial matching (test helper)
-    fn find_display_by_name<'a>(
-        displays: &'a [String],
-        name: &str,
-    ) -> Result<&'a str, crate::error::Error> {
-        // Exact match first
-        if let Some(exact) = displays.iter().find(|d| d.as_str() == name) {
-            return Ok(exact);
-        }
+    /// Match against plain names
+    fn find_name<'a>(displays: &'a [String], name: &str) -> Result<&'a String, Error> {
+        find_display_by_name(displays, name, |d| Some(d.as_str()))
+    }
 
-        // Partial match (prefix)
-        let matches: Vec<&String> = displays.iter().filter(|d| d.starts_with(name)).collect();
-
-        match matches.len() {
-            1 => Ok(matches[0]),
-            0 => Err(Error::DisplayNotFound {
-                name: name.to_string(),
-                available: displays.to_vec(),
-            }),
-            _ => Err(Error::AmbiguousDisplay {
-                name: name.to_string(),
-                candidates: matches.iter().map(|s| s.to_string()).collect(),
-            }),
-        }
+    #[test]
+    fn empty_name_is_the_default_displays() {
+        assert_eq!(DisplayTarget::from_name(""), DisplayTarget::Default);
+        assert_eq!(
+            DisplayTarget::from_name("DP-1"),
+            DisplayTarget::Named("DP-1".to_string())
+        );
+    }
+
+    // natural_cmp tests
+
+    #[test]
+    fn recognizes_internal_panels() {
+        assert!(is_internal_panel("eDP-1"));
+        assert!(is_internal_panel("LVDS-1"));
+        assert!(is_internal_panel("DSI-1"));
+        assert!(!is_internal_panel("HDMI-A-1"));
+        assert!(!is_internal_panel("DP-1"));
+    }
+
+    #[test]
+    fn natural_cmp_numeric_suffix() {
+        assert_eq!(natural_cmp("DP-2", "DP-10"), Ordering::Less);
+        assert_eq!(natural_cmp("DP-10", "DP-2"), Ordering::Greater);
+        assert_eq!(natural_cmp("DP-1", "DP-1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn natural_cmp_text_before_numbers_differ() {
+        assert_eq!(natural_cmp("DP-3", "HDMI-A-1"), Ordering::Less);
+        assert_eq!(natural_cmp("HDMI-A-1", "HDMI-A-2"), Ordering::Less);
+    }
+
+    #[test]
+    fn natural_cmp_prefix_sorts_first() {
+        assert_eq!(natural_cmp("DP", "DP-1"), Ordering::Less);
+        assert_eq!(natural_cmp("DP-1", "DP-1-1"), Ordering::Less);
+    }
+
+    #[test]
+    fn natural_cmp_leading_zeros() {
+        assert_eq!(natural_cmp("DP-01", "DP-2"), Ordering::Less);
+        assert_eq!(natural_cmp("DP-1", "DP-01"), Ordering::Less);
+    }
+
+    #[test]
+    fn natural_cmp_large_numbers() {
+        assert_eq!(
+            natural_cmp("out-99999999999999999999", "out-100000000000000000000"),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn natural_cmp_sorts_list() {
+        let mut names = vec!["DP-10", "eDP-1", "DP-2", "DP-1", "HDMI-A-1"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(names, ["DP-1", "DP-2", "DP-10", "HDMI-A-1", "eDP-1"]);
     }
 
     // DisplayTarget::from_args tests
@@ -106,28 +280,28 @@ mod tests {
     #[test]
     fn find_exact_match() {
         let displays = vec!["DP-1".to_string(), "eDP-1".to_string()];
-        let result = find_display_by_name(&displays, "DP-1");
+        let result = find_name(&displays, "DP-1");
         assert_eq!(result.unwrap(), "DP-1");
     }
 
     #[test]
     fn find_partial_match() {
         let displays = vec!["DP-1".to_string(), "eDP-1".to_string()];
-        let result = find_display_by_name(&displays, "DP");
+        let result = find_name(&displays, "DP");
         assert_eq!(result.unwrap(), "DP-1");
     }
 
     #[test]
     fn find_partial_match_edp() {
         let displays = vec!["DP-1".to_string(), "eDP-1".to_string()];
-        let result = find_display_by_name(&displays, "eDP");
+        let result = find_name(&displays, "eDP");
         assert_eq!(result.unwrap(), "eDP-1");
     }
 
     #[test]
     fn find_ambiguous() {
         let displays = vec!["DP-1".to_string(), "DP-2".to_string()];
-        let result = find_display_by_name(&displays, "DP");
+        let result = find_name(&displays, "DP");
         assert!(matches!(result, Err(Error::AmbiguousDisplay { .. })));
 
         if let Err(Error::AmbiguousDisplay { name, candidates }) = result {
@@ -140,7 +314,7 @@ mod tests {
     #[test]
     fn find_not_found() {
         let displays = vec!["DP-1".to_string(), "eDP-1".to_string()];
-        let result = find_display_by_name(&displays, "HDMI");
+        let result = find_name(&displays, "HDMI");
         assert!(matches!(result, Err(Error::DisplayNotFound { .. })));
 
         if let Err(Error::DisplayNotFound { name, available }) = result {
@@ -154,15 +328,28 @@ mod tests {
     fn find_exact_match_preferred() {
         // If exact match exists, prefer it over partial
         let displays = vec!["DP".to_string(), "DP-1".to_string()];
-        let result = find_display_by_name(&displays, "DP");
+        let result = find_name(&displays, "DP");
         assert_eq!(result.unwrap(), "DP");
     }
 
+    #[test]
+    fn find_skips_unnamed_displays() {
+        let displays = [None, Some("DP-1"), Some("DP-2")];
+        let result = find_display_by_name(&displays, "DP-2", |d| *d);
+        assert_eq!(result.unwrap(), &Some("DP-2"));
+
+        let result = find_display_by_name(&displays, "HDMI", |d| *d);
+        assert!(matches!(
+            result,
+            Err(Error::DisplayNotFound { available, .. }) if available == ["DP-1", "DP-2"]
+        ));
+    }
+
     // filter_displays tests
 
     #[test]
     fn filter_all() {
-        let displays = vec![
+        let displays = [
             make_display("DP-1", PowerState::On),
             make_display("eDP-1", PowerState::Off),
         ];
@@ -173,34 +360,34 @@ mod tests {
 
     #[test]
     fn filter_named_exact() {
-        let displays = vec![
+        let displays = [
             make_display("DP-1", PowerState::On),
             make_display("eDP-1", PowerState::Off),
         ];
         let names: Vec<String> = displays.iter().map(|d| d.name.clone()).collect();
-        let matched = find_display_by_name(&names, "DP-1").unwrap();
+        let matched = find_name(&names, "DP-1").unwrap();
         assert_eq!(matched, "DP-1");
     }
 
     #[test]
     fn filter_named_partial() {
-        let displays = vec![
+        let displays = [
             make_display("DP-1", PowerState::On),
             make_display("eDP-1", PowerState::Off),
         ];
         let names: Vec<String> = displays.iter().map(|d| d.name.clone()).collect();
-        let matched = find_display_by_name(&names, "eDP").unwrap();
+        let matched = find_name(&names, "eDP").unwrap();
         assert_eq!(matched, "eDP-1");
     }
 
     #[test]
     fn filter_named_not_found() {
-        let displays = vec![
+        let displays = [
             make_display("DP-1", PowerState::On),
             make_display("eDP-1", PowerState::Off),
         ];
         let names: Vec<String> = displays.iter().map(|d| d.name.clone()).collect();
-        let result = find_display_by_name(&names, "HDMI");
+        let result = find_name(&names, "HDMI");
         assert!(matches!(result, Err(Error::DisplayNotFound { .. })));
     }
 
@@ -213,6 +400,7 @@ mod tests {
             description: None,
             make: None,
             model: None,
+            drm: None,
         }
     }
 
@@ -224,6 +412,7 @@ mod tests {
             description: Some("Test".to_string()),
             make: Some("Dell".to_string()),
             model: Some("U2720Q".to_string()),
+            drm: None,
         };
         assert_eq!(info.name, "DP-1");
         assert_eq!(info.power, PowerState::On);
@@ -1,46 +1,297 @@
 /// DRM operations module for TTY display power control
 ///
 /// This module provides low-level DRM atomic modesetting operations to control
-/// display power state via CRTC ACTIVE property. Uses libseat for device access
-/// without requiring root privileges, with fallback to direct DRM access.
-use crate::error::Error;
+/// display power state via CRTC ACTIVE property, falling back to the legacy
+/// connector DPMS property on drivers without atomic support. Uses libseat for
+/// device access without requiring root privileges, with fallback to direct
+/// DRM access.
+use crate::display::natural_cmp;
+use crate::edid::{self, EdidInfo};
+#[cfg(feature = "seat")]
+use crate::error::ResultExt;
+use crate::error::{ConnectorSeen, Context, Error};
 use drm::Device;
-use drm::control::{AtomicCommitFlags, Device as ControlDevice, atomic, connector, crtc, property};
+use drm::buffer::DrmFourcc;
+use drm::control::{
+    AtomicCommitFlags, Device as ControlDevice, Mode, ModeTypeFlags, ResourceHandle, atomic,
+    connector, crtc, property,
+};
 use drm::node::{DrmNode, NodeType};
+use nix::errno::Errno;
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs::{self, File};
+use std::hash::Hash;
 use std::os::fd::{AsFd, BorrowedFd};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "seat")]
+use std::sync::mpsc;
+
+/// Directory holding the DRM device nodes
+const DRI_DIR: &str = "/dev/dri";
+
+/// sysfs class directory describing the DRM device nodes
+const SYSFS_DRM_DIR: &str = "/sys/class/drm";
+
+/// Drivers that merely expose the firmware-provided framebuffer
+///
+/// The kernel can create these (`simpledrm` and friends) before or alongside
+/// the real GPU driver. Their CRTCs don't control the actual panel, so they
+/// are only used when no real GPU drives a connected display. Both the DRM
+/// driver names and the platform driver names seen in sysfs are listed.
+const FIRMWARE_FB_DRIVERS: &[&str] = &[
+    "simpledrm",
+    "simple-framebuffer",
+    "ofdrm",
+    "of-display",
+    "efidrm",
+    "efi-framebuffer",
+    "vesadrm",
+    "vesa-framebuffer",
+];
+
+/// Whether `driver` only exposes the firmware framebuffer
+fn is_firmware_framebuffer(driver: &str) -> bool {
+    FIRMWARE_FB_DRIVERS.contains(&driver)
+}
+
+/// Read the kernel driver bound to a card node from sysfs
+///
+/// # Parameters
+/// - `sysfs_drm`: The sysfs DRM class directory, normally `/sys/class/drm`
+/// - `card`: The device node, e.g. `/dev/dri/card0`
+///
+/// # Returns
+/// The driver name (e.g. `amdgpu`), or None if it can't be determined
+fn sysfs_driver_name(sysfs_drm: &Path, card: &Path) -> Option<String> {
+    let link = sysfs_drm
+        .join(card.file_name()?)
+        .join("device")
+        .join("driver");
+    let target = fs::read_link(link).ok()?;
+    target.file_name()?.to_str().map(str::to_string)
+}
+
+/// Move firmware framebuffer devices behind real GPUs, keeping the order otherwise
+fn prefer_real_gpus(devices: Vec<PathBuf>, sysfs_drm: &Path) -> Vec<PathBuf> {
+    let (firmware, real): (Vec<PathBuf>, Vec<PathBuf>) = devices.into_iter().partition(|path| {
+        sysfs_driver_name(sysfs_drm, path).is_some_and(|d| is_firmware_framebuffer(&d))
+    });
+    real.into_iter().chain(firmware).collect()
+}
+
+/// List the `card*` nodes in a DRI directory
+///
+/// Only primary nodes (`card0`, `card1`, ...) are returned; render nodes
+/// (`renderD128`, ...) can't do modesetting and are skipped. Paths are sorted
+/// by card number, so `card2` comes before `card10`.
+///
+/// # Parameters
+/// - `dir`: Directory to scan, normally `/dev/dri`
+///
+/// # Returns
+/// The card node paths, or an empty vector if the directory can't be read
+fn card_nodes_in(dir: &Path) -> Vec<PathBuf> {
+    let mut nodes: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .and_then(|n| n.strip_prefix("card"))
+                    .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+            })
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+
+    nodes.sort_by(|a, b| natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()));
+    nodes
+}
+
+/// Whether `path` is a primary DRM node, as opposed to a render node or
+/// not a DRM device at all
+fn is_primary_node(path: &Path) -> bool {
+    DrmNode::from_path(path)
+        .map(|node| node.ty() == NodeType::Primary)
+        .unwrap_or(false)
+}
 
 /// Discover all available DRM primary (card) devices
 ///
 /// Scans `/dev/dri/` for card devices and validates each using `DrmNode`.
 /// Returns paths sorted by card number for consistent ordering.
 ///
+/// Devices bound to real GPU drivers come before firmware framebuffers
+/// like simpledrm.
+///
 /// # Returns
 /// A vector of valid DRM card device paths
 fn discover_drm_devices() -> Vec<PathBuf> {
-    let mut devices = Vec::new();
-
-    if let Ok(entries) = fs::read_dir("/dev/dri") {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            // Only consider card* devices (primary nodes, not render nodes)
-            if let Some(name) = path.file_name().and_then(|n| n.to_str())
-                && name.starts_with("card")
-            {
-                // Validate it's actually a DRM device with correct node type
-                if let Ok(node) = DrmNode::from_path(&path)
-                    && node.ty() == NodeType::Primary
-                {
-                    devices.push(path);
-                }
-            }
+    discover_drm_devices_in(Path::new(DRI_DIR), is_primary_node)
+}
+
+/// [`discover_drm_devices`] in `dir`, keeping the nodes `is_card` accepts
+fn discover_drm_devices_in(dir: &Path, is_card: fn(&Path) -> bool) -> Vec<PathBuf> {
+    let devices = card_nodes_in(dir)
+        .into_iter()
+        .filter(|path| is_card(path))
+        .collect();
+    prefer_real_gpus(devices, Path::new(SYSFS_DRM_DIR))
+}
+
+/// Check that an explicitly chosen device is a DRM card node
+///
+/// # Parameters
+/// - `path`: The chosen device
+/// - `is_card`: Whether an existing node is a card, normally [`is_primary_node`]
+///
+/// # Returns
+/// - `Ok(())` - `path` is a primary DRM node
+/// - `Err(Error::DrmError)` - It doesn't exist or isn't a DRM card node
+fn validate_card(path: &Path, is_card: fn(&Path) -> bool) -> Result<(), Error> {
+    if !path.exists() {
+        return Err(Error::DrmError(
+            format!("DRM device {} does not exist", path.display()).into(),
+        ));
+    }
+    if !is_card(path) {
+        return Err(Error::DrmError(
+            format!("{} is not a DRM card node", path.display()).into(),
+        ));
+    }
+    Ok(())
+}
+
+/// The device nodes to open: the chosen card, or every discovered one
+///
+/// # Returns
+/// - `Ok(Vec<PathBuf>)` - The device paths (never empty)
+/// - `Err(Error::DrmError)` - The chosen card is invalid, or none were found
+pub fn candidate_devices(options: &OpenOptions) -> Result<Vec<PathBuf>, Error> {
+    candidate_devices_in(options, Path::new(DRI_DIR), is_primary_node)
+}
+
+/// [`candidate_devices`] with the cards found in `dir` and checked by `is_card`
+fn candidate_devices_in(
+    options: &OpenOptions,
+    dir: &Path,
+    is_card: fn(&Path) -> bool,
+) -> Result<Vec<PathBuf>, Error> {
+    if let Some(card) = &options.card {
+        validate_card(card, is_card)?;
+        return Ok(vec![card.clone()]);
+    }
+
+    let devices = discover_drm_devices_in(dir, is_card);
+    if devices.is_empty() {
+        return Err(Error::DrmError(
+            format!("No DRM devices found in {}/", dir.display()).into(),
+        ));
+    }
+    Ok(devices)
+}
+
+/// The DRM device the daemon would try first, with its driver
+///
+/// This only looks at sysfs and does not open the device.
+///
+/// # Returns
+/// `(path, driver)` of the preferred device, or None if there is none
+pub fn preferred_drm_device(options: &OpenOptions) -> Option<(PathBuf, String)> {
+    let path = match &options.card {
+        Some(card) => card.clone(),
+        None => discover_drm_devices().into_iter().next()?,
+    };
+    let driver = sysfs_driver_name(Path::new(SYSFS_DRM_DIR), &path)
+        .unwrap_or_else(|| "unknown driver".to_string());
+    Some((path, driver))
+}
+
+/// Format the per-device failures of an open attempt
+///
+/// # Returns
+/// A string like `/dev/dri/card0 (Permission denied), /dev/dri/card1 (...)`
+fn format_attempts(attempts: &[(PathBuf, String)]) -> String {
+    attempts
+        .iter()
+        .map(|(path, reason)| format!("{} ({})", path.display(), reason))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Canonical name of a connector, e.g. `eDP-1` or `HDMI-A-2`
+///
+/// Matches the names the kernel uses in sysfs and compositors report.
+pub fn connector_name(info: &connector::Info) -> String {
+    format_connector_name(info.interface(), info.interface_id())
+}
+
+/// Build a connector name from its interface type and per-type index
+fn format_connector_name(interface: connector::Interface, interface_id: u32) -> String {
+    format!("{}-{}", interface.as_str(), interface_id)
+}
+
+/// Legacy connector `DPMS` property values (from `DRM_MODE_DPMS_*`)
+const DPMS_ON: u64 = 0;
+const DPMS_OFF: u64 = 3;
+
+/// The legacy `DPMS` property value for a power state
+fn dpms_value(active: bool) -> u64 {
+    if active { DPMS_ON } else { DPMS_OFF }
+}
+
+/// How a device turns its displays on and off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerMechanism {
+    /// The CRTC `ACTIVE` property, in atomic commits
+    Atomic,
+    /// The connector `DPMS` property, for drivers without atomic support
+    LegacyDpms,
+}
+
+impl PowerMechanism {
+    /// Short name, as written to the PID file
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PowerMechanism::Atomic => "atomic",
+            PowerMechanism::LegacyDpms => "dpms",
+        }
+    }
+
+    /// Parse a name written by `as_str`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "atomic" => Some(PowerMechanism::Atomic),
+            "dpms" => Some(PowerMechanism::LegacyDpms),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for PowerMechanism {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PowerMechanism::Atomic => write!(f, "atomic"),
+            PowerMechanism::LegacyDpms => write!(f, "legacy DPMS"),
         }
     }
+}
 
-    // Sort by card number for consistent ordering
-    devices.sort();
-    devices
+/// A connector as reported by `DrmDevice::list_connectors`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectorInfo {
+    /// DRM object handle of the connector
+    pub handle: connector::Handle,
+    /// Canonical name, e.g. `HDMI-A-1`
+    pub name: String,
+    /// Whether a display is attached
+    pub state: connector::State,
+    /// CRTC currently driving the connector, if any
+    pub crtc: Option<crtc::Handle>,
+    /// Make and model from the attached display's EDID
+    pub edid: EdidInfo,
 }
 
 /// Wrapper around DRM device
@@ -50,12 +301,66 @@ fn discover_drm_devices() -> Vec<PathBuf> {
 #[derive(Debug)]
 pub struct DrmDevice {
     inner: DrmDeviceInner,
+    /// Device node this was opened from
+    path: PathBuf,
+    /// Kernel driver name, e.g. `amdgpu` or `simpledrm`
+    driver: String,
+    /// Whether atomic modesetting is enabled; otherwise the legacy
+    /// connector DPMS property is used
+    atomic: bool,
+    /// Whether we acquired DRM master ourselves and must drop it
+    master: bool,
+    /// Handles of each CRTC's `ACTIVE` property
+    active_props: PropertyCache<crtc::Handle>,
+    /// Handles of each connector's legacy `DPMS` property
+    dpms_props: PropertyCache<connector::Handle>,
+}
+
+/// Property handles looked up by name, remembered per DRM object
+///
+/// Finding a property by name takes one ioctl per property of the object,
+/// while the handles don't change as long as the driver stays bound. An
+/// entry is dropped when the property is rejected, so the next use scans
+/// again.
+#[derive(Debug)]
+struct PropertyCache<K> {
+    handles: RefCell<HashMap<K, property::Handle>>,
+}
+
+impl<K: Copy + Eq + Hash> PropertyCache<K> {
+    fn new() -> Self {
+        PropertyCache {
+            handles: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// The cached handle for `object`, running `scan` to find it on a miss
+    ///
+    /// Failed scans aren't cached.
+    fn get(
+        &self,
+        object: K,
+        scan: impl FnOnce() -> Result<property::Handle, Error>,
+    ) -> Result<property::Handle, Error> {
+        if let Some(handle) = self.handles.borrow().get(&object) {
+            return Ok(*handle);
+        }
+        let handle = scan()?;
+        self.handles.borrow_mut().insert(object, handle);
+        Ok(handle)
+    }
+
+    /// Forget the handle for `object`
+    fn invalidate(&self, object: K) {
+        self.handles.borrow_mut().remove(&object);
+    }
 }
 
 /// Inner enum to hold either libseat device or direct file
 #[derive(Debug)]
 enum DrmDeviceInner {
     /// Opened via libseat - has DRM master privileges via seat
+    #[cfg(feature = "seat")]
     Libseat(libseat::Device),
     /// Opened directly - may or may not have DRM master
     Direct(File),
@@ -64,6 +369,7 @@ enum DrmDeviceInner {
 impl AsFd for DrmDevice {
     fn as_fd(&self) -> BorrowedFd<'_> {
         match &self.inner {
+            #[cfg(feature = "seat")]
             DrmDeviceInner::Libseat(dev) => dev.as_fd(),
             DrmDeviceInner::Direct(file) => file.as_fd(),
         }
@@ -73,233 +379,939 @@ impl AsFd for DrmDevice {
 impl Device for DrmDevice {}
 impl ControlDevice for DrmDevice {}
 
+impl Drop for DrmDevice {
+    fn drop(&mut self) {
+        // Hand the console back instead of waiting for the fd to close
+        if self.master {
+            let _ = self.release_master_lock();
+        }
+    }
+}
+
+/// Map a failure to open the DRM node `path` to an error
+fn open_error(path: &Path, err: std::io::Error) -> Error {
+    match err.raw_os_error().map(Errno::from_raw) {
+        Some(Errno::EACCES | Errno::EPERM) => Error::DrmPermissionDenied {
+            path: path.display().to_string(),
+        },
+        _ => drm_error(path, "Failed to open", err),
+    }
+}
+
+/// The error for finding nothing to drive among `connectors` of `path`
+///
+/// Lists each connector with its state, so the user can tell a cable that
+/// isn't detected from a display nothing drives.
+fn no_connected_display(path: &Path, connectors: &[ConnectorInfo]) -> Error {
+    Error::NoConnectedDisplay {
+        device: path.display().to_string(),
+        connectors: connectors
+            .iter()
+            .map(|c| ConnectorSeen {
+                name: c.name.clone(),
+                state: match (c.state, c.crtc) {
+                    (connector::State::Connected, Some(_)) => "connected",
+                    (connector::State::Connected, None) => "connected (no CRTC)",
+                    (connector::State::Disconnected, _) => "disconnected",
+                    (connector::State::Unknown, _) => "unknown",
+                }
+                .to_string(),
+            })
+            .collect(),
+    }
+}
+
+/// Map a failed DRM operation on `path` to an error
+///
+/// Common errno values get their own error with a hint on what to do;
+/// anything else becomes a `DrmError` prefixed with `context`. On an open
+/// device, EACCES means the operation needs DRM master and this process
+/// isn't it.
+fn drm_error(path: &Path, context: &str, err: std::io::Error) -> Error {
+    let path = path.display().to_string();
+    match err.raw_os_error().map(Errno::from_raw) {
+        Some(Errno::EACCES | Errno::EPERM) => Error::PermissionDenied {
+            resource: format!("DRM master of {}", path),
+            hint: "only the process driving the display may change it; run from a \
+                   VT with no compositor, or use the Wayland backend inside the session"
+                .to_string(),
+        },
+        Some(Errno::EBUSY) => Error::DrmDeviceBusy { path },
+        Some(Errno::ENOENT | Errno::ENODEV) => Error::DrmDeviceGone { path },
+        _ => Error::DrmError(Context::wrap(format!("{} on {}", context, path), err)),
+    }
+}
+
+/// Map a failure to acquire DRM master on `path` to an error
+///
+/// The kernel reports EBUSY (EINVAL before Linux 5.10) when another
+/// process is already master.
+fn master_error(path: &Path, err: std::io::Error) -> Error {
+    match err.raw_os_error().map(Errno::from_raw) {
+        Some(Errno::EBUSY | Errno::EINVAL) => Error::DrmMasterHeld {
+            path: path.display().to_string(),
+        },
+        _ => drm_error(path, "Failed to acquire DRM master", err),
+    }
+}
+
+/// Acquire DRM master on a directly opened device
+///
+/// # Returns
+/// - `Ok(())` - This process is now DRM master
+/// - `Err(Error::DrmMasterHeld)` - Another process is DRM master
+/// - `Err(Error::PermissionDenied)` - This process may not become master
+/// - `Err(Error::DrmError)` - Acquiring master failed for another reason
+fn acquire_master<D: Device>(device: &D, path: &Path) -> Result<(), Error> {
+    device
+        .acquire_master_lock()
+        .map_err(|e| master_error(path, e))
+}
+
+/// Check that no display server is DRM master of `device`
+///
+/// Becoming master and dropping it again is harmless, and only fails with
+/// `DrmMasterHeld` while another process has it.
+///
+/// # Returns
+/// - `Ok(())` - Nobody else is master, or that couldn't be told
+/// - `Err(Error::DisplayServerActive)` - Another process is master
+fn check_master_free<D: Device>(device: &D, path: &Path) -> Result<(), Error> {
+    match acquire_master(device, path) {
+        Ok(()) => {
+            let _ = device.release_master_lock();
+            Ok(())
+        }
+        Err(Error::DrmMasterHeld { .. }) => Err(Error::DisplayServerActive {
+            path: path.display().to_string(),
+        }),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Check that no compositor or X server is driving the devices to act on
+///
+/// Taking the displays from a running display server would either fail
+/// or be undone by it right away. Devices that can't be opened here are
+/// skipped; the daemon reports those itself.
+///
+/// # Returns
+/// - `Ok(())` - No device is held by another process
+/// - `Err(Error::DisplayServerActive)` - A device is held
+pub fn check_no_display_server(options: &OpenOptions) -> Result<(), Error> {
+    for path in candidate_devices(options)? {
+        let Ok(file) = File::options().read(true).write(true).open(&path) else {
+            continue;
+        };
+        check_master_free(
+            &DrmDevice::new(DrmDeviceInner::Direct(file), path.clone()),
+            &path,
+        )?;
+    }
+    Ok(())
+}
+
 /// Holder for seat - may be None if using direct access
 pub enum SeatHolder {
-    Seat(libseat::Seat),
+    /// A libseat session, with the enable/disable events it has delivered
+    #[cfg(feature = "seat")]
+    Seat {
+        seat: libseat::Seat,
+        events: mpsc::Receiver<libseat::SeatEvent>,
+    },
     None,
 }
 
+/// A device node opened through the seat, or directly without libseat
+#[derive(Debug)]
+pub enum SeatDevice {
+    #[cfg(feature = "seat")]
+    Libseat(libseat::Device),
+    Direct(File),
+}
+
+impl AsFd for SeatDevice {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        match self {
+            #[cfg(feature = "seat")]
+            SeatDevice::Libseat(dev) => dev.as_fd(),
+            SeatDevice::Direct(file) => file.as_fd(),
+        }
+    }
+}
+
+impl SeatHolder {
+    /// Open a device node for non-blocking reads
+    ///
+    /// libseat hands out input devices without extra permissions; direct
+    /// access needs read access to the node, e.g. membership of `input`.
+    ///
+    /// # Returns
+    /// - `Ok(SeatDevice)` - The opened device; give it back with `close_device`
+    /// - `Err(Error::SeatError)` - libseat refused the device
+    /// - `Err(Error::Io)` - The node couldn't be opened directly
+    pub fn open_device(&mut self, path: &Path) -> Result<SeatDevice, Error> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        match self {
+            #[cfg(feature = "seat")]
+            SeatHolder::Seat { seat, .. } => {
+                use nix::fcntl::{FcntlArg, OFlag, fcntl};
+                use std::os::fd::AsRawFd;
+
+                let device = seat.open_device(&path).with_context(Error::SeatError, || {
+                    format!("Failed to open {}", path.display())
+                })?;
+                let _ = fcntl(
+                    device.as_fd().as_raw_fd(),
+                    FcntlArg::F_SETFL(OFlag::O_NONBLOCK),
+                );
+                Ok(SeatDevice::Libseat(device))
+            }
+            SeatHolder::None => File::options()
+                .read(true)
+                .custom_flags(nix::libc::O_NONBLOCK)
+                .open(path)
+                .map(SeatDevice::Direct)
+                .map_err(Error::Io),
+        }
+    }
+
+    /// Give back a device opened with `open_device`, closing it
+    pub fn close_device(&mut self, device: SeatDevice) {
+        match (self, device) {
+            #[cfg(feature = "seat")]
+            (SeatHolder::Seat { seat, .. }, SeatDevice::Libseat(device)) => {
+                use std::os::fd::AsRawFd;
+
+                // libseat leaves closing the descriptor to us
+                let fd = device.as_fd().as_raw_fd();
+                let _ = seat.close_device(device);
+                let _ = nix::unistd::close(fd);
+            }
+            // Files opened directly are closed when dropped
+            _ => {}
+        }
+    }
+
+    /// Name of the seat, or `None` for direct access
+    pub fn name(&mut self) -> Option<String> {
+        match self {
+            #[cfg(feature = "seat")]
+            SeatHolder::Seat { seat, .. } => Some(seat.name().to_string()),
+            SeatHolder::None => None,
+        }
+    }
+
+    /// The libseat connection, readable when events need dispatching
+    ///
+    /// # Returns
+    /// `None` for direct access, which has no seat
+    pub fn fd(&mut self) -> Option<BorrowedFd<'_>> {
+        match self {
+            #[cfg(feature = "seat")]
+            SeatHolder::Seat { seat, .. } => seat.get_fd().ok(),
+            SeatHolder::None => None,
+        }
+    }
+
+    /// Wait up to `timeout_ms` for seat events and return those received
+    ///
+    /// Disable events have already been acknowledged by the time they are
+    /// returned. Direct access has no seat and never has events.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<SeatEvent>)` - The events in the order they arrived
+    /// - `Err(Error::SeatError)` - Dispatching failed, e.g. seatd went away
+    #[cfg(feature = "seat")]
+    pub fn dispatch(&mut self, timeout_ms: i32) -> Result<Vec<libseat::SeatEvent>, Error> {
+        match self {
+            SeatHolder::Seat { seat, events } => {
+                seat.dispatch(timeout_ms)
+                    .context(Error::SeatError, "Failed to dispatch seat events")?;
+                Ok(events.try_iter().collect())
+            }
+            SeatHolder::None => Ok(Vec::new()),
+        }
+    }
+}
+
 impl std::fmt::Debug for SeatHolder {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            SeatHolder::Seat(_) => write!(f, "SeatHolder::Seat(...)"),
+            #[cfg(feature = "seat")]
+            SeatHolder::Seat { .. } => write!(f, "SeatHolder::Seat(...)"),
             SeatHolder::None => write!(f, "SeatHolder::None"),
         }
     }
 }
 
-/// Open a DRM device using libseat for session management
+/// The first CRTC in `possible` that isn't in `used`
+fn pick_free_crtc(possible: &[crtc::Handle], used: &[crtc::Handle]) -> Option<crtc::Handle> {
+    possible.iter().copied().find(|crtc| !used.contains(crtc))
+}
+
+/// The mode to light a connector with: its preferred mode, or the first one
+fn preferred_mode(modes: &[Mode]) -> Option<Mode> {
+    modes
+        .iter()
+        .find(|mode| mode.mode_type().contains(ModeTypeFlags::PREFERRED))
+        .or_else(|| modes.first())
+        .copied()
+}
+
+/// What probing a device's connectors found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Probe {
+    /// At least one connector has a display attached
+    Connected,
+    /// No connector has a display attached
+    Disconnected,
+    /// The connectors couldn't be read
+    Unknown,
+}
+
+/// Whether a device was chosen to act on, and why
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    /// Drives a connected display
+    Selected,
+    /// Nothing better was found
+    Fallback,
+    /// A firmware framebuffer while a real GPU drives a display
+    FirmwareShadowed,
+    /// No display attached
+    NoDisplay,
+}
+
+impl std::fmt::Display for Choice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Choice::Selected => write!(f, "selected"),
+            Choice::Fallback => write!(f, "selected as fallback"),
+            Choice::FirmwareShadowed => write!(f, "skipped, firmware framebuffer"),
+            Choice::NoDisplay => write!(f, "skipped, no connected display"),
+        }
+    }
+}
+
+/// Decide which devices to act on from their probe results
+///
+/// Devices driving a connected display are chosen. Firmware framebuffers
+/// (simpledrm, ...) usually mirror a panel that a real GPU also drives, so
+/// they are skipped when any real GPU has a connected display. If nothing
+/// is chosen, the first device whose connectors couldn't be read, or else
+/// the first device, is kept so there is still something to act on.
+///
+/// # Parameters
+/// - `candidates`: `(probe result, is firmware framebuffer)` per device
+fn choose_devices(candidates: &[(Probe, bool)]) -> Vec<Choice> {
+    let real_connected = candidates
+        .iter()
+        .any(|&(probe, firmware)| probe == Probe::Connected && !firmware);
+
+    let mut choices: Vec<Choice> = candidates
+        .iter()
+        .map(|&(probe, firmware)| match probe {
+            Probe::Connected if real_connected && firmware => Choice::FirmwareShadowed,
+            Probe::Connected => Choice::Selected,
+            Probe::Disconnected | Probe::Unknown => Choice::NoDisplay,
+        })
+        .collect();
+
+    if !choices.contains(&Choice::Selected) {
+        let fallback = candidates
+            .iter()
+            .position(|&(probe, _)| probe == Probe::Unknown)
+            .or((!candidates.is_empty()).then_some(0));
+        if let Some(index) = fallback {
+            choices[index] = Choice::Fallback;
+        }
+    }
+    choices
+}
+
+/// Keep the opened devices that `choose_devices` picks
+fn select_devices(opened: Vec<DrmDevice>) -> Vec<DrmDevice> {
+    let candidates: Vec<(Probe, bool)> = opened
+        .iter()
+        .map(|device| (device.probe(), is_firmware_framebuffer(device.driver())))
+        .collect();
+
+    opened
+        .into_iter()
+        .zip(choose_devices(&candidates))
+        .filter(|(_, choice)| matches!(choice, Choice::Selected | Choice::Fallback))
+        .map(|(device, _)| device)
+        .collect()
+}
+
+/// Report which devices would be acted on and why
+///
+/// Devices are opened read-only, so this works while the daemon or a
+/// compositor holds them.
+///
+/// # Returns
+/// `(path, driver, choice)` for every device that could be opened
+pub fn device_choices(options: &OpenOptions) -> Vec<(PathBuf, String, Choice)> {
+    let opened = open_drm_readonly(options);
+    let candidates: Vec<(Probe, bool)> = opened
+        .iter()
+        .map(|device| (device.probe(), is_firmware_framebuffer(device.driver())))
+        .collect();
+
+    opened
+        .iter()
+        .zip(choose_devices(&candidates))
+        .map(|(device, choice)| (device.path.clone(), device.driver.clone(), choice))
+        .collect()
+}
+
+/// Open DRM devices using libseat for session management
 ///
-/// This function initializes a libseat session and opens the first available
-/// DRM device. This allows DRM operations without root privileges when running
-/// in a logind session.
+/// This function initializes a libseat session and opens every DRM device
+/// that drives a connected display, so hybrid graphics laptops get both the
+/// internal panel and external monitors. This allows DRM operations without
+/// root privileges when running in a logind session.
+///
+/// # Parameters
+/// - `devices`: Device nodes to try, e.g. from `candidate_devices`
+/// - `expected_seat`: Seat the session must be on, e.g. `seat1`
 ///
 /// # Returns
-/// - `Ok((SeatHolder, DrmDevice))` - The opened seat and DRM device
-/// - `Err(Error::SeatError)` - Failed to open seat or device
+/// - `Ok((SeatHolder, Vec<DrmDevice>))` - The opened seat and DRM devices (never empty)
+/// - `Err(Error::SeatError)` - Failed to open seat or any device, or the
+///   session is on a different seat than `expected_seat`
 ///
 /// # Example
 /// ```no_run
 /// # use dpms::drm_ops::open_drm_with_libseat;
-/// let (seat, drm) = open_drm_with_libseat()?;
+/// # use std::path::PathBuf;
+/// let (seat, devices) = open_drm_with_libseat(&[PathBuf::from("/dev/dri/card0")], None)?;
 /// # Ok::<(), dpms::error::Error>(())
 /// ```
-pub fn open_drm_with_libseat() -> Result<(SeatHolder, DrmDevice), Error> {
-    use std::sync::{Arc, Mutex};
-
-    // Track seat events (we need to keep receiving events but don't need to act on them)
-    let seat_event: Arc<Mutex<Option<libseat::SeatEvent>>> = Arc::new(Mutex::new(None));
-    let seat_event_clone = Arc::clone(&seat_event);
+#[cfg(feature = "seat")]
+pub fn open_drm_with_libseat(
+    devices: &[PathBuf],
+    expected_seat: Option<&str>,
+) -> Result<(SeatHolder, Vec<DrmDevice>), Error> {
+    // Seat events are queued for the daemon, which re-asserts the off state
+    // when the seat comes back after a VT switch
+    let (sender, events) = mpsc::channel();
 
     // Open seat with callback for events
-    let mut seat = libseat::Seat::open(move |_seat, event| {
-        // *seat_event_clone.lock().unwrap() = Some(event);
-        if let Ok(mut guard) = seat_event_clone.lock() {
-            *guard = Some(event);
+    let mut seat = libseat::Seat::open(move |seat, event| {
+        // libseat requires a disable to be acknowledged promptly, or it
+        // revokes our devices
+        if let libseat::SeatEvent::Disable = event
+            && let Err(e) = seat.disable()
+        {
+            eprintln!("Failed to acknowledge seat disable: {:?}", e);
         }
+        let _ = sender.send(event);
     })
-    .map_err(|e| Error::SeatError(format!("Failed to open seat: {:?}", e)))?;
+    .context(Error::SeatError, "Failed to open seat")?;
+
+    // libseat always attaches to the caller's seat; make sure it's the one asked for
+    check_seat_name(expected_seat, seat.name())?;
 
     // Dispatch initial events
     seat.dispatch(0)
-        .map_err(|e| Error::SeatError(format!("Failed to dispatch seat events: {:?}", e)))?;
+        .context(Error::SeatError, "Failed to dispatch seat events")?;
 
-    // Discover DRM devices dynamically instead of using hardcoded paths
-    let devices = discover_drm_devices();
-    if devices.is_empty() {
-        return Err(Error::SeatError(
-            "No DRM devices found in /dev/dri/".to_string(),
-        ));
-    }
+    let mut attempts: Vec<(PathBuf, String)> = Vec::new();
+    let mut opened: Vec<DrmDevice> = Vec::new();
 
-    for path in &devices {
+    for path in devices {
         // libseat opens the device and grants us DRM master privileges
         // We MUST use the fd returned by libseat, not open a new one
         match seat.open_device(path) {
             Ok(libseat_device) => {
                 // Create DRM device from the libseat device
-                let drm_device = DrmDevice {
-                    inner: DrmDeviceInner::Libseat(libseat_device),
-                };
-
-                // Set DRM client capabilities for atomic modesetting
-                if let Err(e) =
-                    drm_device.set_client_capability(drm::ClientCapability::Atomic, true)
-                {
-                    return Err(Error::DrmError(format!(
-                        "Failed to set atomic capability: {:?}",
-                        e
-                    )));
-                }
+                let drm_device =
+                    DrmDevice::new(DrmDeviceInner::Libseat(libseat_device), path.clone());
 
-                return Ok((SeatHolder::Seat(seat), drm_device));
+                opened.push(drm_device);
             }
-            Err(_) => continue,
+            Err(e) => attempts.push((path.clone(), format!("{:?}", e))),
         }
     }
 
-    Err(Error::SeatError(
-        "No DRM device could be opened via libseat".to_string(),
-    ))
+    let selected = select_devices(opened);
+    if selected.is_empty() {
+        return Err(Error::SeatError(
+            format!(
+                "No DRM device could be opened via libseat: {}",
+                format_attempts(&attempts)
+            )
+            .into(),
+        ));
+    }
+
+    Ok((SeatHolder::Seat { seat, events }, selected))
 }
 
-/// Open a DRM device directly without libseat
+/// Open DRM devices directly without libseat
 ///
 /// This is a fallback for when libseat is unavailable (e.g., SSH session).
-/// Requires user to be in the video group and attempts to acquire DRM master.
-/// May fail if another process holds DRM master.
+/// Requires user to be in the video group and attempts to acquire DRM master
+/// on every device that drives a connected display. Devices whose DRM master
+/// is held by another process are skipped.
+///
+/// # Parameters
+/// - `devices`: Device nodes to try, e.g. from `candidate_devices`
 ///
 /// # Returns
-/// - `Ok((SeatHolder::None, DrmDevice))` - The opened DRM device
-/// - `Err(Error::DrmError)` - Failed to open device
-pub fn open_drm_direct() -> Result<(SeatHolder, DrmDevice), Error> {
-    let devices = discover_drm_devices();
-    if devices.is_empty() {
-        return Err(Error::DrmError(
-            "No DRM devices found in /dev/dri/".to_string(),
-        ));
-    }
-
-    let mut last_error: Option<String> = None;
+/// - `Ok((SeatHolder::None, Vec<DrmDevice>))` - The opened DRM devices (never empty)
+/// - `Err(Error::DrmMasterHeld)` - Another process is DRM master
+/// - `Err(Error::DrmPermissionDenied)` - The user may not open the devices
+/// - `Err(Error::DrmError)` - Failed to open any device
+pub fn open_drm_direct(devices: &[PathBuf]) -> Result<(SeatHolder, Vec<DrmDevice>), Error> {
+    let mut attempts: Vec<(PathBuf, String)> = Vec::new();
+    let mut held: Option<Error> = None;
+    let mut denied: Option<Error> = None;
+    let mut opened: Vec<DrmDevice> = Vec::new();
 
-    for path in &devices {
+    for path in devices {
         match File::open(path) {
             Ok(file) => {
-                let drm_device = DrmDevice {
-                    inner: DrmDeviceInner::Direct(file),
-                };
+                let mut drm_device = DrmDevice::new(DrmDeviceInner::Direct(file), path.clone());
 
                 // Try to acquire DRM master (required for atomic commits)
                 // This may fail if another process (e.g., compositor) holds it
-                if let Err(e) = drm_device.acquire_master_lock() {
-                    last_error = Some(format!(
-                        "{:?}: failed to acquire DRM master ({:?})",
-                        path, e
-                    ));
-                    continue;
-                }
-
-                // Set DRM client capabilities for atomic modesetting
-                if let Err(e) =
-                    drm_device.set_client_capability(drm::ClientCapability::Atomic, true)
-                {
-                    // This device doesn't support atomic, try next
-                    last_error = Some(format!("{:?}: atomic not supported ({:?})", path, e));
-                    continue;
+                match acquire_master(&drm_device, path) {
+                    Ok(()) => drm_device.master = true,
+                    Err(e) => {
+                        attempts.push((path.clone(), e.to_string()));
+                        if matches!(e, Error::DrmMasterHeld { .. }) && held.is_none() {
+                            held = Some(e);
+                        }
+                        continue;
+                    }
                 }
 
-                return Ok((SeatHolder::None, drm_device));
+                opened.push(drm_device);
             }
             Err(e) => {
-                last_error = Some(format!("{:?}: {}", path, e));
+                let e = open_error(path, e);
+                attempts.push((path.clone(), e.to_string()));
+                if matches!(e, Error::DrmPermissionDenied { .. }) && denied.is_none() {
+                    denied = Some(e);
+                }
                 continue;
             }
         }
     }
 
-    Err(Error::DrmError(last_error.unwrap_or_else(|| {
-        "No DRM device could be opened".to_string()
-    })))
+    let selected = select_devices(opened);
+    if selected.is_empty() {
+        // Someone else driving the display, then missing permissions, are
+        // the likeliest explanations
+        if let Some(e) = held.or(denied) {
+            return Err(e);
+        }
+        return Err(Error::DrmError(
+            format!(
+                "No DRM device could be opened: {}",
+                format_attempts(&attempts)
+            )
+            .into(),
+        ));
+    }
+
+    Ok((SeatHolder::None, selected))
 }
 
-/// Open a DRM device, trying libseat first then falling back to direct access
+/// Check that the seat libseat attached to is the one that was asked for
 ///
 /// # Returns
-/// - `Ok((SeatHolder, DrmDevice))` - The opened DRM device
-/// - `Err(Error)` - Both libseat and direct access failed
-pub fn open_drm() -> Result<(SeatHolder, DrmDevice), Error> {
-    // Try libseat first (preferred - handles session activation properly)
-    match open_drm_with_libseat() {
-        Ok(result) => Ok(result),
-        Err(_libseat_err) => {
-            // Libseat failed, try direct access
-            open_drm_direct()
-        }
+/// - `Ok(())` - No seat was asked for, or the names match
+/// - `Err(Error::SeatError)` - The session is on a different seat
+#[cfg(feature = "seat")]
+fn check_seat_name(expected: Option<&str>, actual: &str) -> Result<(), Error> {
+    match expected {
+        Some(expected) if expected != actual => Err(Error::SeatError(
+            format!(
+                "requested seat {} but this session is on {}",
+                expected, actual
+            )
+            .into(),
+        )),
+        _ => Ok(()),
     }
 }
 
-impl DrmDevice {
-    /// Find the CRTC handle for the first connected connector
-    ///
-    /// Scans all connectors to find the first one in Connected state,
-    /// then returns its associated CRTC handle.
-    ///
-    /// # Returns
-    /// - `Ok(CrtcHandle)` - The CRTC handle for the connected display
-    /// - `Err(Error::NoConnectedDisplay)` - No connected display found
-    /// - `Err(Error::DrmError)` - DRM operation failed
+/// How DRM devices are opened
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Never go through libseat; open device nodes directly
+    pub no_libseat: bool,
+    /// Open exactly this device node instead of discovering devices
+    pub card: Option<PathBuf>,
+    /// Seat the libseat session must be on, e.g. `seat1`
     ///
-    /// # Example
-    /// ```no_run
-    /// # use dpms::drm_ops::open_drm_with_libseat;
-    /// # let (_seat, drm) = open_drm_with_libseat()?;
-    /// let crtc = drm.find_active_crtc()?;
-    /// # Ok::<(), dpms::error::Error>(())
-    /// ```
-    pub fn find_active_crtc(&self) -> Result<crtc::Handle, Error> {
-        // Get resource handles
-        let res = self
-            .resource_handles()
-            .map_err(|e| Error::DrmError(format!("Failed to get resource handles: {:?}", e)))?;
+    /// Direct access has no session, so this is only checked through libseat.
+    pub seat: Option<String>,
+}
 
-        // Iterate through connectors to find first connected one
-        for conn_handle in res.connectors() {
-            let conn_info = self
-                .get_connector(*conn_handle, false)
-                .map_err(|e| Error::DrmError(format!("Failed to get connector info: {:?}", e)))?;
+impl OpenOptions {
+    /// Command-line arguments that pass these options on to the daemon
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.no_libseat {
+            args.push("--no-libseat".to_string());
+        }
+        if let Some(card) = &self.card {
+            args.push("--card".to_string());
+            args.push(card.display().to_string());
+        }
+        if let Some(seat) = &self.seat {
+            args.push("--seat".to_string());
+            args.push(seat.clone());
+        }
+        args
+    }
+}
 
-            if conn_info.state() == connector::State::Connected {
-                // Get the encoder for this connector
-                if let Some(encoder_handle) = conn_info.current_encoder() {
-                    let encoder_info = self.get_encoder(encoder_handle).map_err(|e| {
-                        Error::DrmError(format!("Failed to get encoder info: {:?}", e))
-                    })?;
+/// Whether opening a seat through libseat can work at all
+///
+/// Root without a login session (initramfs, containers) has no seat to
+/// join, and without seatd or logind there is nothing to ask.
+#[cfg(feature = "seat")]
+fn libseat_viable_with(is_root: bool, has_session: bool, has_seat_manager: bool) -> bool {
+    has_seat_manager && (has_session || !is_root)
+}
 
-                    if let Some(crtc_handle) = encoder_info.crtc() {
-                        return Ok(crtc_handle);
-                    }
-                }
+/// Whether seatd or logind is running
+#[cfg(feature = "seat")]
+fn has_seat_manager() -> bool {
+    std::env::var_os("SEATD_SOCK").is_some()
+        || Path::new("/run/seatd.sock").exists()
+        // FreeBSD's seatd, as it has no /run
+        || Path::new("/var/run/seatd.sock").exists()
+        || Path::new("/run/systemd/seats").exists()
+}
 
-                // If no current encoder, try the first possible encoder
-                for &enc_handle in conn_info.encoders() {
-                    let encoder_info = self.get_encoder(enc_handle).map_err(|e| {
-                        Error::DrmError(format!("Failed to get encoder info: {:?}", e))
-                    })?;
+/// Whether libseat is worth trying on this system
+#[cfg(feature = "seat")]
+fn libseat_viable() -> bool {
+    libseat_viable_with(
+        nix::unistd::Uid::effective().is_root(),
+        std::env::var_os("XDG_SESSION_ID").is_some(),
+        has_seat_manager(),
+    )
+}
 
-                    if let Some(crtc_handle) = encoder_info.crtc() {
-                        return Ok(crtc_handle);
-                    }
+/// Point at the missing seat manager when direct access was denied
+///
+/// Without seatd or logind, group membership is the only way left to the
+/// devices, which users rarely know about.
+#[cfg(feature = "seat")]
+fn explain_denied(err: Error, seat_manager: bool) -> Error {
+    match err {
+        Error::DrmPermissionDenied { path } if !seat_manager => Error::NoSeatManager { path },
+        _ => err,
+    }
+}
+
+/// Check cheaply whether opening DRM devices can work at all
+///
+/// Nothing is opened: this only looks for device nodes and a seat manager,
+/// or node permissions when libseat can't be used. That lets `dpms off`
+/// fail straight away instead of waiting for the daemon to give up.
+///
+/// # Returns
+/// - `Ok(())` - libseat can be tried, or some device node is accessible
+/// - `Err(Error::DrmError)` - There are no device nodes
+/// - `Err(Error::DrmPermissionDenied)` - No seat and no accessible node
+/// - `Err(Error::NoSeatManager)` - Neither seatd nor logind is running and
+///   no node is accessible
+pub fn probe_access(options: &OpenOptions) -> Result<(), Error> {
+    let devices = candidate_devices(options)?;
+    #[cfg(feature = "seat")]
+    if !options.no_libseat {
+        if libseat_viable() {
+            return Ok(());
+        }
+        return probe_nodes(&devices).map_err(|e| explain_denied(e, has_seat_manager()));
+    }
+    probe_nodes(&devices)
+}
+
+/// Whether any of `devices` may be opened for reading and writing
+fn probe_nodes(devices: &[PathBuf]) -> Result<(), Error> {
+    use nix::unistd::{AccessFlags, access};
+
+    let accessible =
+        |path: &PathBuf| access(path.as_path(), AccessFlags::R_OK | AccessFlags::W_OK).is_ok();
+    match (devices.iter().any(accessible), devices.first()) {
+        (false, Some(first)) => Err(Error::DrmPermissionDenied {
+            path: first.display().to_string(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Open DRM devices, trying libseat first then falling back to direct access
+///
+/// libseat is skipped when `options.no_libseat` is set, it can't work on
+/// this system, or the `seat` feature is off. With `options.card` set, only
+/// that device is opened.
+///
+/// # Returns
+/// - `Ok((SeatHolder, Vec<DrmDevice>))` - The opened DRM devices (never empty)
+/// - `Err(Error::DrmMasterHeld)` - Another process holds the display
+/// - `Err(Error::DrmOpenFailed)` - Both libseat and direct access failed
+/// - `Err(Error::NoSeatManager)` - There is no seatd or logind and direct
+///   access was denied
+/// - `Err(Error)` - Direct access failed without trying libseat, or the
+///   chosen card has no connected display
+pub fn open_drm(options: &OpenOptions) -> Result<(SeatHolder, Vec<DrmDevice>), Error> {
+    let devices = candidate_devices(options)?;
+
+    #[cfg(not(feature = "seat"))]
+    let (seat, opened) = open_drm_direct(&devices)?;
+    #[cfg(feature = "seat")]
+    let (seat, opened) = if options.no_libseat {
+        open_drm_direct(&devices)?
+    } else if !libseat_viable() {
+        open_drm_direct(&devices).map_err(|e| explain_denied(e, has_seat_manager()))?
+    } else {
+        // Try libseat first (preferred - handles session activation properly)
+        match open_drm_with_libseat(&devices, options.seat.as_deref()) {
+            Ok(result) => result,
+            // Libseat failed, try direct access
+            Err(libseat_err) => open_drm_direct(&devices)
+                .map_err(|direct_err| combine_open_errors(libseat_err, direct_err))?,
+        }
+    };
+
+    if let Some(card) = &options.card
+        && opened
+            .iter()
+            .all(|device| device.probe() == Probe::Disconnected)
+    {
+        return Err(Error::DrmError(
+            format!("{} has no connected connectors", card.display()).into(),
+        ));
+    }
+
+    Ok((seat, opened))
+}
+
+/// The error to report when both libseat and direct access failed
+///
+/// Another process holding DRM master explains both failures, so it's
+/// reported alone. Otherwise both reasons are kept: the libseat one is
+/// usually the more telling in a broken logind setup.
+#[cfg(feature = "seat")]
+fn combine_open_errors(libseat_err: Error, direct_err: Error) -> Error {
+    match direct_err {
+        Error::DrmMasterHeld { .. } => direct_err,
+        _ => Error::DrmOpenFailed {
+            libseat: libseat_err.to_string(),
+            direct: direct_err.to_string(),
+        },
+    }
+}
+
+/// Open every DRM device for reading its state
+///
+/// Unlike `open_drm`, this neither goes through libseat nor takes DRM
+/// master, so it works while the daemon or a compositor holds the device.
+/// Devices that can't be opened are skipped.
+///
+/// # Returns
+/// The opened DRM devices, possibly empty
+pub fn open_drm_readonly(options: &OpenOptions) -> Vec<DrmDevice> {
+    candidate_devices(options)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|path| {
+            let file = File::open(&path).ok()?;
+            Some(DrmDevice::new(DrmDeviceInner::Direct(file), path))
+        })
+        .collect()
+}
+
+/// DRM operations the daemon and TTY backend need
+///
+/// Implemented by `DrmDevice`. Keeping the daemon and backend logic on this
+/// trait lets tests run it against a fake device instead of hardware.
+pub trait DrmControl {
+    /// Device node this device was opened from
+    fn path(&self) -> &Path;
+
+    /// Kernel driver name, e.g. `amdgpu` or `simpledrm`
+    fn driver(&self) -> &str;
+
+    /// Whether power changes use atomic commits rather than the legacy DPMS property
+    fn uses_atomic(&self) -> bool;
+
+    /// The mechanism power changes on this device go through
+    fn mechanism(&self) -> PowerMechanism {
+        if self.uses_atomic() {
+            PowerMechanism::Atomic
+        } else {
+            PowerMechanism::LegacyDpms
+        }
+    }
+
+    /// List all connectors with their names, connection state and CRTC
+    ///
+    /// # Returns
+    /// - `Ok(Vec<ConnectorInfo>)` - One entry per connector, in resource order
+    /// - `Err(Error::DrmError)` - DRM operation failed
+    fn list_connectors(&self) -> Result<Vec<ConnectorInfo>, Error>;
+
+    /// Read whether a CRTC is currently active (lit)
+    ///
+    /// Devices without atomic support keep the mode set when their displays
+    /// are turned off, so there the CRTC is lit if any connector it drives
+    /// has its `DPMS` property on. Otherwise, and on legacy devices whose
+    /// connectors have no `DPMS` property, see `get_crtc_state`.
+    ///
+    /// # Returns
+    /// - `Ok(bool)` - true if the CRTC is active
+    /// - `Err(Error::DrmError)` - DRM operation failed
+    fn get_crtc_active(&self, crtc_handle: crtc::Handle) -> Result<bool, Error> {
+        if !self.uses_atomic() {
+            let mut lit = None;
+            for c in self.list_connectors()? {
+                if c.crtc != Some(crtc_handle) {
+                    continue;
+                }
+                if let Some(on) = self.get_connector_dpms(c.handle)? {
+                    lit = Some(lit.unwrap_or(false) || on);
                 }
             }
+            if let Some(lit) = lit {
+                return Ok(lit);
+            }
         }
+        self.get_crtc_state(crtc_handle)
+    }
+
+    /// Read whether a CRTC is active from the CRTC itself
+    ///
+    /// Uses the ACTIVE property where the driver exposes it, otherwise
+    /// whether the CRTC has a mode set.
+    ///
+    /// # Returns
+    /// - `Ok(bool)` - true if the CRTC is active
+    /// - `Err(Error::DrmError)` - DRM operation failed
+    fn get_crtc_state(&self, crtc_handle: crtc::Handle) -> Result<bool, Error>;
+
+    /// Read the legacy `DPMS` property of a connector
+    ///
+    /// # Returns
+    /// - `Ok(Some(bool))` - true if the property is on, false for any of the
+    ///   standby, suspend and off levels
+    /// - `Ok(None)` - The connector has no `DPMS` property
+    /// - `Err(Error::DrmError)` - DRM operation failed
+    fn get_connector_dpms(&self, conn_handle: connector::Handle) -> Result<Option<bool>, Error>;
 
-        Err(Error::NoDisplayFound)
+    /// Set the ACTIVE property of several CRTCs in one atomic commit
+    ///
+    /// All displays change in the same commit, so they blank together rather
+    /// than one by one. Either every CRTC changes or none does.
+    ///
+    /// Devices without atomic support instead get the legacy `DPMS` property
+    /// set on each connector driven by the CRTCs, one at a time.
+    ///
+    /// # Parameters
+    /// - `crtcs`: The CRTC handles to modify
+    /// - `active`: true to turn displays on, false to turn them off
+    ///
+    /// # Returns
+    /// - `Ok(())` - Atomic commit succeeded
+    /// - `Err(Error::DrmError)` - Atomic commit or property lookup failed
+    fn set_crtcs_active(&self, crtcs: &[crtc::Handle], active: bool) -> Result<(), Error> {
+        if !self.uses_atomic() {
+            return self.set_crtcs_dpms(crtcs, active);
+        }
+        self.commit_crtcs_active(crtcs, active, false)
     }
 
+    /// Check that `set_crtcs_active` would be accepted, without applying it
+    ///
+    /// The commit is validated by the driver with `TEST_ONLY`. The legacy
+    /// DPMS property can't be tested, so on devices without atomic support
+    /// this always succeeds.
+    ///
+    /// # Returns
+    /// - `Ok(())` - The driver would accept the change
+    /// - `Err(Error::DrmError)` - The driver rejected it, or a property
+    ///   lookup failed
+    fn test_crtcs_active(&self, crtcs: &[crtc::Handle], active: bool) -> Result<(), Error> {
+        if !self.uses_atomic() {
+            return Ok(());
+        }
+        self.commit_crtcs_active(crtcs, active, true)
+    }
+
+    /// Set the legacy `DPMS` property on every connector driven by `crtcs`
+    fn set_crtcs_dpms(&self, crtcs: &[crtc::Handle], active: bool) -> Result<(), Error> {
+        let connectors = self.list_connectors()?;
+
+        for &crtc_handle in crtcs {
+            for c in connectors.iter().filter(|c| c.crtc == Some(crtc_handle)) {
+                self.set_connector_dpms(c.handle, active)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Set the ACTIVE property of `crtcs` in one atomic commit
+    ///
+    /// Only for devices with atomic support; `set_crtcs_active` picks the
+    /// mechanism.
+    ///
+    /// # Parameters
+    /// - `test_only`: Have the driver validate the commit without applying it
+    ///
+    /// # Returns
+    /// - `Ok(())` - The driver accepted the commit
+    /// - `Err(Error::DrmError)` - The driver rejected it, or a property
+    ///   lookup failed
+    fn commit_crtcs_active(
+        &self,
+        crtcs: &[crtc::Handle],
+        active: bool,
+        test_only: bool,
+    ) -> Result<(), Error>;
+
+    /// Set the legacy `DPMS` property of a connector, for devices without
+    /// atomic support
+    fn set_connector_dpms(&self, conn_handle: connector::Handle, active: bool)
+    -> Result<(), Error>;
+
+    /// Whether `crtc_handle` still has a mode and framebuffer to scan out
+    ///
+    /// Setting ACTIVE on a CRTC only resumes its current configuration, so
+    /// a CRTC whose mode or framebuffer was torn down can't be turned back
+    /// on this way.
+    fn crtc_can_resume(&self, crtc_handle: crtc::Handle) -> Result<bool, Error>;
+
+    /// Light up a connected connector with a full modeset
+    ///
+    /// Used when the connector has no CRTC, or its CRTC has no mode or
+    /// framebuffer left to resume. Picks the connector's preferred mode and
+    /// a free CRTC, and scans out a black dumb buffer. The framebuffer
+    /// belongs to this open device, so once it's closed the kernel hands
+    /// the display back to the console, which redraws it.
+    ///
+    /// # Returns
+    /// - `Ok(crtc::Handle)` - The CRTC now driving the connector
+    /// - `Err(Error::DrmError)` - No mode or free CRTC, or the modeset failed
+    fn modeset_connector(&self, conn_handle: connector::Handle) -> Result<crtc::Handle, Error>;
+
     /// Set CRTC ACTIVE property via atomic commit
     ///
     /// Uses DRM atomic modesetting to set the ACTIVE property of the specified CRTC.
-    /// This turns the display on (active=true) or off (active=false).
+    /// This turns the display on (active=true) or off (active=false). Devices
+    /// without atomic support use the legacy connector DPMS property instead.
     ///
     /// # Parameters
     /// - `crtc`: The CRTC handle to modify
@@ -311,43 +1323,457 @@ impl DrmDevice {
     ///
     /// # Example
     /// ```no_run
-    /// # use dpms::drm_ops::open_drm_with_libseat;
-    /// # let (_seat, drm) = open_drm_with_libseat()?;
-    /// # let crtc = drm.find_active_crtc()?;
+    /// # use dpms::drm_ops::{DrmControl, OpenOptions, open_drm};
+    /// # let (_seat, devices) = open_drm(&OpenOptions::default())?;
+    /// # let drm = &devices[0];
+    /// # let crtc = drm.find_all_active_crtcs()?[0].crtc.unwrap();
     /// // Turn display off
     /// drm.set_crtc_active(crtc, false)?;
     /// // Turn display on
     /// drm.set_crtc_active(crtc, true)?;
     /// # Ok::<(), dpms::error::Error>(())
     /// ```
-    pub fn set_crtc_active(&self, crtc_handle: crtc::Handle, active: bool) -> Result<(), Error> {
-        // Get properties as a hashmap for cleaner lookup
+    fn set_crtc_active(&self, crtc_handle: crtc::Handle, active: bool) -> Result<(), Error> {
+        self.set_crtcs_active(&[crtc_handle], active)
+    }
+
+    /// Find the CRTC driving the connector with the given name
+    ///
+    /// The CRTC is found via the connector's current encoder, falling back to
+    /// its possible encoders.
+    ///
+    /// # Parameters
+    /// - `name`: Connector name as built by `connector_name`, e.g. `HDMI-A-1`
+    ///
+    /// # Returns
+    /// - `Ok(CrtcHandle)` - The CRTC driving the connector
+    /// - `Err(Error::DisplayNotFound)` - No connector with that name
+    /// - `Err(Error::NoConnectedDisplay)` - The connector isn't connected or has no CRTC
+    /// - `Err(Error::DrmError)` - DRM operation failed
+    fn find_crtc_for_connector(&self, name: &str) -> Result<crtc::Handle, Error> {
+        let connectors = self.list_connectors()?;
+
+        match connectors.iter().find(|c| c.name == name) {
+            Some(c) => c
+                .crtc
+                .ok_or_else(|| no_connected_display(self.path(), std::slice::from_ref(c))),
+            None => Err(Error::DisplayNotFound {
+                name: name.to_string(),
+                available: connectors
+                    .into_iter()
+                    .filter(|c| c.state == connector::State::Connected)
+                    .map(|c| c.name)
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Find every CRTC driving a connected connector
+    ///
+    /// A CRTC driving several cloned connectors is listed once.
+    ///
+    /// # Returns
+    /// - `Ok(Vec<ConnectorInfo>)` - One connected connector per CRTC, in resource order
+    /// - `Err(Error::NoConnectedDisplay)` - No connected display has a CRTC
+    /// - `Err(Error::DrmError)` - DRM operation failed
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use dpms::drm_ops::{DrmControl, OpenOptions, open_drm};
+    /// # let (_seat, devices) = open_drm(&OpenOptions::default())?;
+    /// # let drm = &devices[0];
+    /// let crtcs: Vec<_> = drm
+    ///     .find_all_active_crtcs()?
+    ///     .into_iter()
+    ///     .filter_map(|c| c.crtc)
+    ///     .collect();
+    /// drm.set_crtcs_active(&crtcs, false)?;
+    /// # Ok::<(), dpms::error::Error>(())
+    /// ```
+    fn find_all_active_crtcs(&self) -> Result<Vec<ConnectorInfo>, Error> {
+        let connectors = self.list_connectors()?;
+        let mut active: Vec<ConnectorInfo> = Vec::new();
+        for c in &connectors {
+            if c.state == connector::State::Connected
+                && c.crtc.is_some()
+                && !active.iter().any(|a| a.crtc == c.crtc)
+            {
+                active.push(c.clone());
+            }
+        }
+
+        if active.is_empty() {
+            return Err(no_connected_display(self.path(), &connectors));
+        }
+        Ok(active)
+    }
+
+    /// Whether `crtc_handle` still drives a connected connector
+    ///
+    /// Used before restoring a CRTC, since its monitor may have been
+    /// unplugged while it was off.
+    fn crtc_drives_connected(&self, crtc_handle: crtc::Handle) -> Result<bool, Error> {
+        Ok(self
+            .list_connectors()?
+            .iter()
+            .any(|c| c.state == connector::State::Connected && c.crtc == Some(crtc_handle)))
+    }
+}
+
+impl DrmDevice {
+    /// Wrap an opened device node, looking up its driver name
+    ///
+    /// Atomic modesetting is enabled if the driver supports it.
+    fn new(inner: DrmDeviceInner, path: PathBuf) -> Self {
+        let mut device = DrmDevice {
+            inner,
+            path,
+            driver: String::new(),
+            atomic: false,
+            master: false,
+            active_props: PropertyCache::new(),
+            dpms_props: PropertyCache::new(),
+        };
+        // Older GPUs and some virtual drivers reject atomic
+        device.atomic = device
+            .set_client_capability(drm::ClientCapability::Atomic, true)
+            .is_ok();
+        device.driver = device
+            .get_driver()
+            .map(|d| d.name().to_string_lossy().into_owned())
+            .ok()
+            .or_else(|| sysfs_driver_name(Path::new(SYSFS_DRM_DIR), &device.path))
+            .unwrap_or_else(|| "unknown driver".to_string());
+        device
+    }
+
+    /// Map a failed DRM operation on this device to an error
+    fn error(&self, context: &str, err: std::io::Error) -> Error {
+        drm_error(&self.path, context, err)
+    }
+
+    /// Look up the handle of the property called `name` on `object`
+    ///
+    /// # Returns
+    /// - `Ok(property::Handle)` - The property exists
+    /// - `Err(Error::DrmError)` - Reading the properties failed or the
+    ///   object has no such property
+    fn find_property<T: ResourceHandle>(
+        &self,
+        object: T,
+        name: &str,
+    ) -> Result<property::Handle, Error> {
         let props = self
-            .get_properties(crtc_handle)
-            .map_err(|e| Error::DrmError(format!("Failed to get CRTC properties: {:?}", e)))?;
+            .get_properties(object)
+            .map_err(|e| self.error("Failed to get properties", e))?;
+        props
+            .iter()
+            .find_map(|(handle, _)| {
+                self.get_property(*handle)
+                    .ok()
+                    .filter(|info| info.name().to_bytes() == name.as_bytes())
+                    .map(|info| info.handle())
+            })
+            .ok_or_else(|| Error::DrmError(format!("{} property not found", name).into()))
+    }
+
+    /// Whether any connector on this device has a display attached
+    ///
+    /// Connectors that can't be read are skipped; if the resources can't be
+    /// read at all (some drivers refuse without DRM master), the result is
+    /// `Probe::Unknown`.
+    fn probe(&self) -> Probe {
+        let Ok(res) = self.resource_handles() else {
+            return Probe::Unknown;
+        };
+        let connected = res.connectors().iter().any(|handle| {
+            self.get_connector(*handle, false)
+                .is_ok_and(|info| info.state() == connector::State::Connected)
+        });
+        if connected {
+            Probe::Connected
+        } else {
+            Probe::Disconnected
+        }
+    }
+
+    /// Read the make and model from a connector's `EDID` blob property
+    ///
+    /// # Returns
+    /// The parsed EDID; empty if there is none or it can't be read
+    fn connector_edid(&self, conn_handle: connector::Handle) -> EdidInfo {
+        let Ok(props) = self.get_properties(conn_handle) else {
+            return EdidInfo::default();
+        };
+        let Ok(prop_map) = props.as_hashmap(self) else {
+            return EdidInfo::default();
+        };
+
+        let blob_id = prop_map.get("EDID").and_then(|info| {
+            props
+                .iter()
+                .find(|(handle, _)| **handle == info.handle())
+                .map(|(_, value)| *value)
+        });
+        match blob_id {
+            Some(id) if id != 0 => self
+                .get_property_blob(id)
+                .map(|blob| edid::parse(&blob))
+                .unwrap_or_default(),
+            _ => EdidInfo::default(),
+        }
+    }
+
+    /// Find a CRTC that could drive the connector but drives nothing else
+    ///
+    /// Each of the connector's encoders is checked against its
+    /// `possible_crtcs` mask.
+    fn free_crtc_for_connector(
+        &self,
+        conn_info: &connector::Info,
+    ) -> Result<Option<crtc::Handle>, Error> {
+        let res = self
+            .resource_handles()
+            .map_err(|e| self.error("Failed to get resource handles", e))?;
+        let used: Vec<crtc::Handle> = self
+            .list_connectors()?
+            .into_iter()
+            .filter_map(|c| c.crtc)
+            .collect();
+
+        for &enc_handle in conn_info.encoders() {
+            let encoder_info = self
+                .get_encoder(enc_handle)
+                .map_err(|e| self.error("Failed to get encoder info", e))?;
+            let possible = res.filter_crtcs(encoder_info.possible_crtcs());
+            if let Some(crtc_handle) = pick_free_crtc(&possible, &used) {
+                return Ok(Some(crtc_handle));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Find the CRTC driving a connector
+    ///
+    /// Uses the connector's current encoder, falling back to the first
+    /// possible encoder that has a CRTC.
+    fn crtc_for_connector(
+        &self,
+        conn_info: &connector::Info,
+    ) -> Result<Option<crtc::Handle>, Error> {
+        // Get the encoder for this connector
+        if let Some(encoder_handle) = conn_info.current_encoder() {
+            let encoder_info = self
+                .get_encoder(encoder_handle)
+                .map_err(|e| self.error("Failed to get encoder info", e))?;
+
+            if let Some(crtc_handle) = encoder_info.crtc() {
+                return Ok(Some(crtc_handle));
+            }
+        }
 
-        let prop_map = props.as_hashmap(self).map_err(|e| {
-            Error::DrmError(format!("Failed to convert properties to hashmap: {:?}", e))
-        })?;
+        // If no current encoder, try the first possible encoder
+        for &enc_handle in conn_info.encoders() {
+            let encoder_info = self
+                .get_encoder(enc_handle)
+                .map_err(|e| self.error("Failed to get encoder info", e))?;
 
-        let active_info = prop_map
-            .get("ACTIVE")
-            .ok_or_else(|| Error::DrmError("ACTIVE property not found for CRTC".to_string()))?;
+            if let Some(crtc_handle) = encoder_info.crtc() {
+                return Ok(Some(crtc_handle));
+            }
+        }
+
+        Ok(None)
+    }
 
-        // Create atomic request
+    /// Set the ACTIVE property of `crtcs` in one atomic commit
+    ///
+    /// `ALLOW_MODESET` is always added, as changing ACTIVE requires it.
+    fn commit_active(
+        &self,
+        crtcs: &[crtc::Handle],
+        active: bool,
+        flags: AtomicCommitFlags,
+    ) -> Result<(), Error> {
         let mut req = atomic::AtomicModeReq::new();
-        req.add_property(
-            crtc_handle,
-            active_info.handle(),
-            property::Value::Boolean(active),
-        );
+        for &crtc_handle in crtcs {
+            let active_prop = self
+                .active_props
+                .get(crtc_handle, || self.find_property(crtc_handle, "ACTIVE"))?;
+            req.add_property(crtc_handle, active_prop, property::Value::Boolean(active));
+        }
 
-        // Commit with ALLOW_MODESET flag (required for ACTIVE property changes)
-        let flags = AtomicCommitFlags::ALLOW_MODESET;
-        self.atomic_commit(flags, req)
-            .map_err(|e| Error::DrmError(format!("Atomic commit failed: {:?}", e)))?;
+        self.atomic_commit(flags | AtomicCommitFlags::ALLOW_MODESET, req)
+            .map_err(|e| {
+                // A stale handle fails the whole commit; look them all up again
+                for &crtc_handle in crtcs {
+                    self.active_props.invalidate(crtc_handle);
+                }
+                if flags.contains(AtomicCommitFlags::TEST_ONLY) {
+                    self.error("Atomic test commit rejected", e)
+                } else {
+                    self.error("Atomic commit failed", e)
+                }
+            })
+    }
+}
 
-        Ok(())
+impl DrmControl for DrmDevice {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn driver(&self) -> &str {
+        &self.driver
+    }
+
+    fn uses_atomic(&self) -> bool {
+        self.atomic
+    }
+
+    fn list_connectors(&self) -> Result<Vec<ConnectorInfo>, Error> {
+        let res = self
+            .resource_handles()
+            .map_err(|e| self.error("Failed to get resource handles", e))?;
+
+        let mut connectors = Vec::new();
+        for conn_handle in res.connectors() {
+            let conn_info = self
+                .get_connector(*conn_handle, false)
+                .map_err(|e| self.error("Failed to get connector info", e))?;
+
+            let (crtc, edid) = if conn_info.state() == connector::State::Connected {
+                (
+                    self.crtc_for_connector(&conn_info)?,
+                    self.connector_edid(*conn_handle),
+                )
+            } else {
+                (None, EdidInfo::default())
+            };
+
+            connectors.push(ConnectorInfo {
+                handle: *conn_handle,
+                name: connector_name(&conn_info),
+                state: conn_info.state(),
+                crtc,
+                edid,
+            });
+        }
+
+        Ok(connectors)
+    }
+
+    fn get_crtc_state(&self, crtc_handle: crtc::Handle) -> Result<bool, Error> {
+        let props = self
+            .get_properties(crtc_handle)
+            .map_err(|e| self.error("Failed to get CRTC properties", e))?;
+
+        // Drivers without atomic support may not expose ACTIVE at all
+        if let Ok(active) = self
+            .active_props
+            .get(crtc_handle, || self.find_property(crtc_handle, "ACTIVE"))
+        {
+            let value = props
+                .iter()
+                .find(|(handle, _)| **handle == active)
+                .map(|(_, value)| *value);
+            match value {
+                Some(value) => return Ok(value != 0),
+                None => self.active_props.invalidate(crtc_handle),
+            }
+        }
+
+        let info = self
+            .get_crtc(crtc_handle)
+            .map_err(|e| self.error("Failed to get CRTC info", e))?;
+        Ok(info.mode().is_some())
+    }
+
+    fn get_connector_dpms(&self, conn_handle: connector::Handle) -> Result<Option<bool>, Error> {
+        let Ok(dpms) = self
+            .dpms_props
+            .get(conn_handle, || self.find_property(conn_handle, "DPMS"))
+        else {
+            return Ok(None);
+        };
+
+        let props = self
+            .get_properties(conn_handle)
+            .map_err(|e| self.error("Failed to get connector properties", e))?;
+        let value = props
+            .iter()
+            .find(|(handle, _)| **handle == dpms)
+            .map(|(_, value)| *value);
+        if value.is_none() {
+            self.dpms_props.invalidate(conn_handle);
+        }
+        Ok(value.map(|value| value == DPMS_ON))
+    }
+
+    fn commit_crtcs_active(
+        &self,
+        crtcs: &[crtc::Handle],
+        active: bool,
+        test_only: bool,
+    ) -> Result<(), Error> {
+        let flags = if test_only {
+            AtomicCommitFlags::TEST_ONLY
+        } else {
+            AtomicCommitFlags::empty()
+        };
+        self.commit_active(crtcs, active, flags)
+    }
+
+    fn set_connector_dpms(
+        &self,
+        conn_handle: connector::Handle,
+        active: bool,
+    ) -> Result<(), Error> {
+        let dpms = self
+            .dpms_props
+            .get(conn_handle, || self.find_property(conn_handle, "DPMS"))?;
+
+        self.set_property(conn_handle, dpms, dpms_value(active))
+            .map_err(|e| {
+                self.dpms_props.invalidate(conn_handle);
+                self.error("Failed to set DPMS property", e)
+            })
+    }
+
+    fn crtc_can_resume(&self, crtc_handle: crtc::Handle) -> Result<bool, Error> {
+        let info = self
+            .get_crtc(crtc_handle)
+            .map_err(|e| self.error("Failed to get CRTC info", e))?;
+        Ok(info.mode().is_some() && info.framebuffer().is_some())
+    }
+
+    fn modeset_connector(&self, conn_handle: connector::Handle) -> Result<crtc::Handle, Error> {
+        let conn_info = self
+            .get_connector(conn_handle, false)
+            .map_err(|e| self.error("Failed to get connector info", e))?;
+        let name = connector_name(&conn_info);
+
+        let mode = preferred_mode(conn_info.modes())
+            .ok_or_else(|| Error::DrmError(format!("{} reports no modes", name).into()))?;
+        let crtc_handle = match self.crtc_for_connector(&conn_info)? {
+            Some(crtc_handle) => crtc_handle,
+            None => self.free_crtc_for_connector(&conn_info)?.ok_or_else(|| {
+                Error::DrmError(format!("No free CRTC can drive {}", name).into())
+            })?,
+        };
+
+        let (width, height) = mode.size();
+        let buffer = self
+            .create_dumb_buffer((width.into(), height.into()), DrmFourcc::Xrgb8888, 32)
+            .map_err(|e| self.error("Failed to create dumb buffer", e))?;
+        let fb = self
+            .add_framebuffer(&buffer, 24, 32)
+            .map_err(|e| self.error("Failed to add framebuffer", e))?;
+        self.set_crtc(crtc_handle, Some(fb), (0, 0), &[conn_handle], Some(mode))
+            .map_err(|e| self.error("Modeset failed", e))?;
+
+        Ok(crtc_handle)
     }
 }
 
@@ -355,6 +1781,381 @@ impl DrmDevice {
 mod tests {
     use super::*;
 
+    /// A control device whose master ioctls fail with a fixed errno
+    struct FakeMasterDevice {
+        file: File,
+        errno: Errno,
+    }
+
+    impl AsFd for FakeMasterDevice {
+        fn as_fd(&self) -> BorrowedFd<'_> {
+            self.file.as_fd()
+        }
+    }
+
+    impl Device for FakeMasterDevice {
+        fn acquire_master_lock(&self) -> std::io::Result<()> {
+            Err(std::io::Error::from(self.errno))
+        }
+    }
+
+    fn fake_master_device(errno: Errno) -> FakeMasterDevice {
+        FakeMasterDevice {
+            file: File::open("/dev/null").unwrap(),
+            errno,
+        }
+    }
+
+    #[test]
+    fn pick_free_crtc_skips_used_crtcs() {
+        let crtc = |id| drm::control::from_u32::<crtc::Handle>(id).unwrap();
+        let possible = [crtc(40), crtc(51), crtc(62)];
+
+        assert_eq!(pick_free_crtc(&possible, &[]), Some(crtc(40)));
+        assert_eq!(pick_free_crtc(&possible, &[crtc(40)]), Some(crtc(51)));
+        assert_eq!(pick_free_crtc(&possible, &possible), None);
+        assert_eq!(pick_free_crtc(&[], &[]), None);
+    }
+
+    #[test]
+    fn property_cache_scans_once_per_object() {
+        let crtc = |id| drm::control::from_u32::<crtc::Handle>(id).unwrap();
+        let prop = |id| drm::control::from_u32::<property::Handle>(id).unwrap();
+        let cache = PropertyCache::new();
+        let scans = std::cell::Cell::new(0);
+        let scan = |id| {
+            scans.set(scans.get() + 1);
+            Ok(prop(id))
+        };
+
+        assert_eq!(cache.get(crtc(40), || scan(20)).unwrap(), prop(20));
+        assert_eq!(cache.get(crtc(40), || scan(21)).unwrap(), prop(20));
+        assert_eq!(scans.get(), 1);
+
+        // Another CRTC has its own entry
+        assert_eq!(cache.get(crtc(51), || scan(22)).unwrap(), prop(22));
+        assert_eq!(scans.get(), 2);
+
+        // A rejected handle is looked up again
+        cache.invalidate(crtc(40));
+        assert_eq!(cache.get(crtc(40), || scan(23)).unwrap(), prop(23));
+        assert_eq!(scans.get(), 3);
+    }
+
+    #[test]
+    fn property_cache_does_not_keep_failed_scans() {
+        let conn = drm::control::from_u32::<connector::Handle>(30).unwrap();
+        let prop = drm::control::from_u32::<property::Handle>(2).unwrap();
+        let cache = PropertyCache::new();
+
+        let missing = cache.get(conn, || {
+            Err(Error::DrmError("DPMS property not found".into()))
+        });
+        assert!(missing.is_err());
+        assert_eq!(cache.get(conn, || Ok(prop)).unwrap(), prop);
+    }
+
+    #[test]
+    fn preferred_mode_of_no_modes_is_none() {
+        assert!(preferred_mode(&[]).is_none());
+    }
+
+    #[test]
+    fn choose_devices_prefers_connected_real_gpu() {
+        // dGPU with nothing attached, iGPU driving the panel, simpledrm
+        let choices = choose_devices(&[
+            (Probe::Disconnected, false),
+            (Probe::Connected, false),
+            (Probe::Connected, true),
+        ]);
+        assert_eq!(
+            choices,
+            [
+                Choice::NoDisplay,
+                Choice::Selected,
+                Choice::FirmwareShadowed
+            ]
+        );
+    }
+
+    #[test]
+    fn choose_devices_keeps_connected_firmware_framebuffer_alone() {
+        let choices = choose_devices(&[(Probe::Disconnected, false), (Probe::Connected, true)]);
+        assert_eq!(choices, [Choice::NoDisplay, Choice::Selected]);
+    }
+
+    #[test]
+    fn choose_devices_falls_back_to_unreadable_device() {
+        let choices = choose_devices(&[(Probe::Disconnected, false), (Probe::Unknown, false)]);
+        assert_eq!(choices, [Choice::NoDisplay, Choice::Fallback]);
+
+        let choices = choose_devices(&[(Probe::Disconnected, false), (Probe::Disconnected, false)]);
+        assert_eq!(choices, [Choice::Fallback, Choice::NoDisplay]);
+
+        assert!(choose_devices(&[]).is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "seat")]
+    fn libseat_viability() {
+        assert!(libseat_viable_with(false, true, true));
+        assert!(libseat_viable_with(false, false, true));
+        assert!(libseat_viable_with(true, true, true));
+        // Root outside a login session
+        assert!(!libseat_viable_with(true, false, true));
+        // Neither seatd nor logind
+        assert!(!libseat_viable_with(false, true, false));
+    }
+
+    #[test]
+    fn open_options_to_args() {
+        assert!(OpenOptions::default().to_args().is_empty());
+        let options = OpenOptions {
+            no_libseat: true,
+            ..Default::default()
+        };
+        assert_eq!(options.to_args(), ["--no-libseat"]);
+        let options = OpenOptions {
+            card: Some(PathBuf::from("/dev/dri/card1")),
+            ..Default::default()
+        };
+        assert_eq!(options.to_args(), ["--card", "/dev/dri/card1"]);
+        let options = OpenOptions {
+            seat: Some("seat1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(options.to_args(), ["--seat", "seat1"]);
+    }
+
+    #[test]
+    #[cfg(feature = "seat")]
+    fn check_seat_name_rejects_other_seat() {
+        assert!(check_seat_name(None, "seat0").is_ok());
+        assert!(check_seat_name(Some("seat1"), "seat1").is_ok());
+        let err = check_seat_name(Some("seat1"), "seat0").unwrap_err();
+        assert!(matches!(err, Error::SeatError(_)));
+        assert!(err.to_string().contains("seat1"));
+    }
+
+    #[test]
+    fn validate_card_rejects_missing_and_non_drm_paths() {
+        let err =
+            validate_card(Path::new("/tmp/dpms-test-no-such-card"), is_primary_node).unwrap_err();
+        assert!(err.to_string().contains("does not exist"));
+
+        let err = validate_card(Path::new("/dev/null"), is_primary_node).unwrap_err();
+        assert!(err.to_string().contains("is not a DRM card node"));
+    }
+
+    #[test]
+    fn candidate_devices_rejects_missing_card() {
+        let options = OpenOptions {
+            card: Some(PathBuf::from("/tmp/dpms-test-no-such-card")),
+            ..Default::default()
+        };
+        let err = candidate_devices(&options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "DRM operation failed: DRM device /tmp/dpms-test-no-such-card does not exist"
+        );
+    }
+
+    #[test]
+    fn candidate_devices_rejects_non_drm_card() {
+        let options = OpenOptions {
+            card: Some(PathBuf::from("/dev/null")),
+            ..Default::default()
+        };
+        let err = candidate_devices(&options).unwrap_err();
+        assert!(err.to_string().contains("/dev/null is not a DRM card node"));
+    }
+
+    #[test]
+    fn candidate_devices_uses_only_chosen_card() {
+        // Card numbers no real sysfs has, so prefer_real_gpus keeps the order
+        let dir = PathBuf::from(format!("/tmp/dpms-test-dri-chosen-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["card90", "card91", "renderD128"] {
+            fs::File::create(dir.join(name)).unwrap();
+        }
+        let is_card = |path: &Path| path.exists();
+
+        let all = candidate_devices_in(&OpenOptions::default(), &dir, is_card);
+        let options = OpenOptions {
+            card: Some(dir.join("card91")),
+            ..Default::default()
+        };
+        let chosen = candidate_devices_in(&options, &dir, is_card);
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(all.unwrap(), vec![dir.join("card90"), dir.join("card91")]);
+        assert_eq!(chosen.unwrap(), vec![dir.join("card91")]);
+    }
+
+    #[test]
+    fn acquire_master_held_by_another_process() {
+        let path = Path::new("/dev/dri/card0");
+        for errno in [Errno::EBUSY, Errno::EINVAL] {
+            let err = acquire_master(&fake_master_device(errno), path).unwrap_err();
+            assert!(matches!(
+                err,
+                Error::DrmMasterHeld { ref path } if path == "/dev/dri/card0"
+            ));
+        }
+    }
+
+    #[test]
+    fn acquire_master_other_failure_is_drm_error() {
+        let err = acquire_master(&fake_master_device(Errno::EIO), Path::new("/dev/dri/card1"))
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::DrmError(ref context) if context.message().contains("/dev/dri/card1"))
+        );
+    }
+
+    #[test]
+    fn master_held_elsewhere_means_display_server() {
+        let path = Path::new("/dev/dri/card0");
+        let err = check_master_free(&fake_master_device(Errno::EBUSY), path).unwrap_err();
+        assert!(matches!(err, Error::DisplayServerActive { ref path } if path == "/dev/dri/card0"));
+        assert!(err.to_string().contains("--force-tty"));
+
+        // Failing to check is not proof of a display server
+        assert!(check_master_free(&fake_master_device(Errno::EACCES), path).is_ok());
+    }
+
+    #[test]
+    fn acquire_master_without_permission() {
+        let err = acquire_master(
+            &fake_master_device(Errno::EACCES),
+            Path::new("/dev/dri/card1"),
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PermissionDenied { ref resource, .. } if resource == "DRM master of /dev/dri/card1"
+        ));
+    }
+
+    fn errno_error(errno: Errno) -> Error {
+        drm_error(
+            Path::new("/dev/dri/card0"),
+            "Atomic commit failed",
+            std::io::Error::from(errno),
+        )
+    }
+
+    /// Opening /dev/dri/card0 failed with EACCES
+    #[cfg(feature = "seat")]
+    fn denied_open() -> Error {
+        open_error(
+            Path::new("/dev/dri/card0"),
+            std::io::Error::from(Errno::EACCES),
+        )
+    }
+
+    #[test]
+    fn open_error_maps_permission_errors() {
+        for errno in [Errno::EACCES, Errno::EPERM] {
+            let err = open_error(Path::new("/dev/dri/card0"), std::io::Error::from(errno));
+            assert!(
+                matches!(err, Error::DrmPermissionDenied { ref path } if path == "/dev/dri/card0")
+            );
+            assert!(err.to_string().contains("'video' group"));
+        }
+        let err = open_error(
+            Path::new("/dev/dri/card0"),
+            std::io::Error::from(Errno::EIO),
+        );
+        assert!(
+            matches!(err, Error::DrmError(ref context) if context.message().starts_with("Failed to open"))
+        );
+    }
+
+    #[test]
+    fn commit_without_master_is_permission_denied() {
+        for errno in [Errno::EACCES, Errno::EPERM] {
+            let err = errno_error(errno);
+            assert!(matches!(
+                err,
+                Error::PermissionDenied { ref resource, ref hint }
+                    if resource == "DRM master of /dev/dri/card0" && hint.contains("no compositor")
+            ));
+            assert_eq!(err.exit_code(), crate::error::ExitCode::Permission);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "seat")]
+    fn failed_open_reports_libseat_and_direct_errors() {
+        let libseat_err = Error::SeatError("Could not activate session: Permission denied".into());
+        let err = combine_open_errors(libseat_err, denied_open());
+
+        assert!(matches!(err, Error::DrmOpenFailed { .. }));
+        let message = err.to_string();
+        assert!(message.contains("libseat operation failed: Could not activate session"));
+        assert!(message.contains("Permission denied opening /dev/dri/card0"));
+    }
+
+    #[test]
+    #[cfg(feature = "seat")]
+    fn failed_open_reports_held_master_alone() {
+        let libseat_err = Error::SeatError("Device or resource busy".into());
+        let direct_err = Error::DrmMasterHeld {
+            path: "/dev/dri/card0".into(),
+        };
+        let err = combine_open_errors(libseat_err, direct_err);
+        assert!(matches!(err, Error::DrmMasterHeld { .. }));
+    }
+
+    #[test]
+    #[cfg(feature = "seat")]
+    fn denied_access_without_seat_manager_says_how_to_fix_it() {
+        let err = explain_denied(denied_open(), false);
+        assert!(matches!(err, Error::NoSeatManager { ref path } if path == "/dev/dri/card0"));
+        let message = err.to_string();
+        assert!(message.contains("start seatd"));
+        assert!(message.contains("logind session"));
+        assert!(message.contains("'video' group"));
+
+        // With a seat manager the plain permission error stands
+        let err = explain_denied(denied_open(), true);
+        assert!(matches!(err, Error::DrmPermissionDenied { .. }));
+        let err = explain_denied(errno_error(Errno::EBUSY), false);
+        assert!(matches!(err, Error::DrmDeviceBusy { .. }));
+    }
+
+    #[test]
+    fn drm_error_maps_busy_device() {
+        let err = errno_error(Errno::EBUSY);
+        assert!(matches!(err, Error::DrmDeviceBusy { ref path } if path == "/dev/dri/card0"));
+    }
+
+    #[test]
+    fn drm_error_maps_missing_device() {
+        for errno in [Errno::ENOENT, Errno::ENODEV] {
+            assert!(matches!(errno_error(errno), Error::DrmDeviceGone { .. }));
+        }
+    }
+
+    #[test]
+    fn drm_error_keeps_context_for_other_errors() {
+        use std::error::Error as _;
+
+        let err = errno_error(Errno::EINVAL);
+        assert_eq!(
+            err.to_string(),
+            "DRM operation failed: Atomic commit failed on /dev/dri/card0: \
+             Invalid argument (os error 22)"
+        );
+
+        // The errno is still there to inspect
+        let cause = err.source().and_then(|context| context.source()).unwrap();
+        let io = cause.downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(io.raw_os_error(), Some(Errno::EINVAL as i32));
+    }
+
     #[test]
     fn drm_device_implements_required_traits() {
         // This is a compile-time test - if it compiles, the traits are implemented
@@ -370,12 +2171,212 @@ mod tests {
         // This test verifies the discovery function runs without panic
         // On systems without DRM devices, it returns empty vec
         let devices = discover_drm_devices();
-        // Verify sorting - each path should be <= the next
+        // Verify sorting - each path should sort by card number before the next
         for window in devices.windows(2) {
-            assert!(window[0] <= window[1]);
+            assert_ne!(
+                natural_cmp(&window[0].to_string_lossy(), &window[1].to_string_lossy()),
+                std::cmp::Ordering::Greater
+            );
         }
     }
 
-    // Note: Integration tests that require actual DRM hardware cannot be run in CI
-    // These would be part of manual testing on real hardware
+    #[test]
+    fn card_nodes_in_filters_and_sorts() {
+        let dir = Path::new("/tmp/dpms-test-dri");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        for name in [
+            "card10",
+            "renderD128",
+            "card2",
+            "by-path",
+            "card0",
+            "controlD64",
+            "card",
+        ] {
+            fs::File::create(dir.join(name)).unwrap();
+        }
+
+        let nodes = card_nodes_in(dir);
+        let _ = fs::remove_dir_all(dir);
+
+        assert_eq!(
+            nodes,
+            vec![dir.join("card0"), dir.join("card2"), dir.join("card10")]
+        );
+    }
+
+    #[test]
+    fn card_nodes_in_missing_dir_is_empty() {
+        assert!(card_nodes_in(Path::new("/tmp/dpms-test-no-such-dri")).is_empty());
+    }
+
+    #[test]
+    fn prefer_real_gpus_moves_simpledrm_last() {
+        let sysfs = Path::new("/tmp/dpms-test-sysfs-drm");
+        let _ = fs::remove_dir_all(sysfs);
+        for (card, driver) in [("card0", "simple-framebuffer"), ("card1", "amdgpu")] {
+            let device = sysfs.join(card).join("device");
+            fs::create_dir_all(&device).unwrap();
+            std::os::unix::fs::symlink(
+                format!("../../../bus/platform/drivers/{}", driver),
+                device.join("driver"),
+            )
+            .unwrap();
+        }
+
+        let card0 = PathBuf::from("/dev/dri/card0");
+        let card1 = PathBuf::from("/dev/dri/card1");
+        let card2 = PathBuf::from("/dev/dri/card2");
+        let driver = sysfs_driver_name(sysfs, &card1);
+        let ordered = prefer_real_gpus(vec![card0.clone(), card1.clone(), card2.clone()], sysfs);
+        let _ = fs::remove_dir_all(sysfs);
+
+        assert_eq!(driver.as_deref(), Some("amdgpu"));
+        // Unknown drivers count as real GPUs
+        assert_eq!(ordered, vec![card1, card2, card0]);
+    }
+
+    #[test]
+    fn firmware_framebuffer_drivers() {
+        assert!(is_firmware_framebuffer("simpledrm"));
+        assert!(is_firmware_framebuffer("ofdrm"));
+        assert!(!is_firmware_framebuffer("amdgpu"));
+        assert!(!is_firmware_framebuffer("i915"));
+    }
+
+    #[test]
+    fn format_connector_name_covers_all_interfaces() {
+        use connector::Interface;
+
+        let cases = [
+            (Interface::Unknown, 1, "Unknown-1"),
+            (Interface::VGA, 1, "VGA-1"),
+            (Interface::DVII, 1, "DVI-I-1"),
+            (Interface::DVID, 1, "DVI-D-1"),
+            (Interface::DVIA, 1, "DVI-A-1"),
+            (Interface::Composite, 1, "Composite-1"),
+            (Interface::SVideo, 1, "SVIDEO-1"),
+            (Interface::LVDS, 1, "LVDS-1"),
+            (Interface::Component, 1, "Component-1"),
+            (Interface::NinePinDIN, 1, "DIN-1"),
+            (Interface::DisplayPort, 3, "DP-3"),
+            (Interface::HDMIA, 2, "HDMI-A-2"),
+            (Interface::HDMIB, 1, "HDMI-B-1"),
+            (Interface::TV, 1, "TV-1"),
+            (Interface::EmbeddedDisplayPort, 1, "eDP-1"),
+            (Interface::Virtual, 1, "Virtual-1"),
+            (Interface::DSI, 1, "DSI-1"),
+            (Interface::DPI, 1, "DPI-1"),
+            (Interface::Writeback, 1, "Writeback-1"),
+            (Interface::SPI, 1, "SPI-1"),
+            (Interface::USB, 1, "USB-1"),
+        ];
+
+        for (interface, id, expected) in cases {
+            assert_eq!(format_connector_name(interface, id), expected);
+        }
+    }
+
+    #[test]
+    fn probe_nodes_needs_one_accessible_node() {
+        let missing = PathBuf::from("/tmp/dpms-test-no-such-card");
+        assert!(probe_nodes(&[missing.clone(), PathBuf::from("/dev/null")]).is_ok());
+
+        let err = probe_nodes(std::slice::from_ref(&missing)).unwrap_err();
+        assert!(
+            matches!(err, Error::DrmPermissionDenied { ref path } if path == "/tmp/dpms-test-no-such-card")
+        );
+    }
+
+    #[test]
+    fn power_mechanism_names_round_trip() {
+        for mechanism in [PowerMechanism::Atomic, PowerMechanism::LegacyDpms] {
+            assert_eq!(
+                PowerMechanism::from_name(mechanism.as_str()),
+                Some(mechanism)
+            );
+        }
+        assert_eq!(PowerMechanism::from_name("ACTIVE"), None);
+        assert_eq!(PowerMechanism::LegacyDpms.to_string(), "legacy DPMS");
+    }
+
+    #[test]
+    fn legacy_devices_set_the_dpms_property_of_each_connector() {
+        use fake::{Call, FakeDrm};
+
+        let drm = FakeDrm::new("/dev/dri/card0")
+            .legacy()
+            .with_connector("VGA-1", 70, Some(40))
+            .with_connector("DVI-I-1", 71, Some(40))
+            .with_connector("HDMI-A-1", 77, Some(51));
+        let crtc = drm::control::from_u32(40).unwrap();
+
+        // Nothing to test without atomic support, so nothing is asked for
+        drm.test_crtcs_active(&[crtc], false).unwrap();
+        drm.set_crtcs_active(&[crtc], false).unwrap();
+        assert_eq!(
+            drm.calls(),
+            [
+                Call::SetDpms {
+                    connector: 70,
+                    active: false
+                },
+                Call::SetDpms {
+                    connector: 71,
+                    active: false
+                },
+            ]
+        );
+        assert_eq!(drm.mechanism(), PowerMechanism::LegacyDpms);
+    }
+
+    #[test]
+    fn legacy_crtcs_read_as_off_once_their_connectors_are() {
+        use fake::FakeDrm;
+
+        let drm = FakeDrm::new("/dev/dri/card0")
+            .legacy()
+            .with_connector("VGA-1", 70, Some(40))
+            .with_connector("DVI-I-1", 71, Some(40));
+        let crtc = drm::control::from_u32(40).unwrap();
+
+        assert!(drm.get_crtc_active(crtc).unwrap());
+        drm.set_connector_dpms(drm::control::from_u32(70).unwrap(), false)
+            .unwrap();
+        // Still lit through the other connector
+        assert!(drm.get_crtc_active(crtc).unwrap());
+
+        drm.set_crtcs_active(&[crtc], false).unwrap();
+        // The mode stays set, but DPMS says the displays are dark
+        assert!(drm.is_active(40));
+        assert!(!drm.get_crtc_active(crtc).unwrap());
+
+        drm.set_crtcs_active(&[crtc], true).unwrap();
+        assert!(drm.get_crtc_active(crtc).unwrap());
+    }
+
+    #[test]
+    fn format_attempts_lists_every_device() {
+        let attempts = vec![
+            (
+                PathBuf::from("/dev/dri/card0"),
+                "Permission denied".to_string(),
+            ),
+            (PathBuf::from("/dev/dri/card1"), "busy".to_string()),
+        ];
+        assert_eq!(
+            format_attempts(&attempts),
+            "/dev/dri/card0 (Permission denied), /dev/dri/card1 (busy)"
+        );
+    }
+
+    // Note: Tests that need a real DRM device live in `vkms`, which runs
+    // against the VKMS virtual driver when it's available
 }
+
+#[cfg(test)]
+pub mod fake;
+
+#[cfg(test)]
+mod vkms;
@@ -0,0 +1,267 @@
+//! Fake DRM device for testing the daemon and TTY backend without hardware
+//!
+//! `FakeDrm` implements `DrmControl` over an in-memory list of connectors
+//! and CRTC states. Every power change is recorded so tests can check what
+//! was asked for, and failures can be injected per operation.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+
+use super::*;
+
+/// A power change requested from a `FakeDrm`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Call {
+    /// `set_crtcs_active` with these CRTC ids
+    SetActive { crtcs: Vec<u32>, active: bool },
+    /// The legacy `DPMS` property of this connector id, on a device without
+    /// atomic support
+    SetDpms { connector: u32, active: bool },
+    /// `modeset_connector` on this connector id
+    Modeset { connector: u32 },
+}
+
+/// In-memory stand-in for a `DrmDevice`
+pub struct FakeDrm {
+    path: PathBuf,
+    connectors: RefCell<Vec<ConnectorInfo>>,
+    /// ACTIVE per CRTC id; on a legacy device, whether it has a mode set
+    active: RefCell<BTreeMap<u32, bool>>,
+    /// Whether power changes go through the legacy `DPMS` property
+    legacy: bool,
+    /// The legacy `DPMS` property per connector id, on when missing
+    dpms: RefCell<BTreeMap<u32, bool>>,
+    calls: RefCell<Vec<Call>>,
+    fail_disable: bool,
+    fail_enable: bool,
+    /// CRTC ids that can't be turned on, whatever `fail_enable` says
+    fail_enable_crtcs: Vec<u32>,
+    reject_disable_test: bool,
+    /// Panic at the next connector listing after CRTCs were turned off
+    panic_after_disable: Cell<bool>,
+}
+
+impl FakeDrm {
+    /// A device at `path` with no connectors
+    pub fn new(path: &str) -> Self {
+        FakeDrm {
+            path: PathBuf::from(path),
+            connectors: RefCell::new(Vec::new()),
+            active: RefCell::new(BTreeMap::new()),
+            legacy: false,
+            dpms: RefCell::new(BTreeMap::new()),
+            calls: RefCell::new(Vec::new()),
+            fail_disable: false,
+            fail_enable: false,
+            fail_enable_crtcs: Vec::new(),
+            reject_disable_test: false,
+            panic_after_disable: Cell::new(false),
+        }
+    }
+
+    /// Add a connected connector, lit by `crtc` if it has one
+    pub fn with_connector(self, name: &str, id: u32, crtc: Option<u32>) -> Self {
+        if let Some(crtc) = crtc {
+            self.active.borrow_mut().insert(crtc, true);
+        }
+        self.connectors.borrow_mut().push(ConnectorInfo {
+            handle: drm::control::from_u32(id).unwrap(),
+            name: name.to_string(),
+            state: connector::State::Connected,
+            crtc: crtc.and_then(drm::control::from_u32),
+            edid: EdidInfo::default(),
+        });
+        self
+    }
+
+    /// Turn displays on and off with the legacy `DPMS` property, as drivers
+    /// without atomic support do
+    pub fn legacy(mut self) -> Self {
+        self.legacy = true;
+        self
+    }
+
+    /// Mark a CRTC as already turned off
+    pub fn with_inactive_crtc(self, crtc: u32) -> Self {
+        self.active.borrow_mut().insert(crtc, false);
+        self
+    }
+
+    /// Make every attempt to turn CRTCs off fail
+    pub fn failing_disable(mut self) -> Self {
+        self.fail_disable = true;
+        self
+    }
+
+    /// Make every attempt to turn CRTCs on fail
+    pub fn failing_enable(mut self) -> Self {
+        self.fail_enable = true;
+        self
+    }
+
+    /// Make every attempt to turn on the CRTC with this id fail
+    pub fn failing_enable_of(mut self, crtc: u32) -> Self {
+        self.fail_enable_crtcs.push(crtc);
+        self
+    }
+
+    /// Make the driver reject test commits that turn CRTCs off
+    pub fn rejecting_disable_test(mut self) -> Self {
+        self.reject_disable_test = true;
+        self
+    }
+
+    /// Panic once, at the first connector listing after turning CRTCs off
+    ///
+    /// Stands in for a bug in the daemon while it holds the displays off.
+    pub fn panicking_after_disable(self) -> Self {
+        self.panic_after_disable.set(true);
+        self
+    }
+
+    /// Simulate unplugging the display from a connector
+    pub fn unplug(&self, name: &str) {
+        for c in self.connectors.borrow_mut().iter_mut() {
+            if c.name == name {
+                c.state = connector::State::Disconnected;
+                c.crtc = None;
+            }
+        }
+    }
+
+    /// Simulate plugging in a display that lights up on `crtc`
+    pub fn plug(&self, name: &str, id: u32, crtc: u32) {
+        self.active.borrow_mut().insert(crtc, true);
+        self.connectors.borrow_mut().push(ConnectorInfo {
+            handle: drm::control::from_u32(id).unwrap(),
+            name: name.to_string(),
+            state: connector::State::Connected,
+            crtc: drm::control::from_u32(crtc),
+            edid: EdidInfo::default(),
+        });
+    }
+
+    /// The power changes requested so far
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.borrow().clone()
+    }
+
+    /// Whether the CRTC with this id is active
+    pub fn is_active(&self, crtc: u32) -> bool {
+        self.active.borrow().get(&crtc).copied().unwrap_or(false)
+    }
+}
+
+impl DrmControl for FakeDrm {
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn driver(&self) -> &str {
+        "fake"
+    }
+
+    fn uses_atomic(&self) -> bool {
+        !self.legacy
+    }
+
+    fn list_connectors(&self) -> Result<Vec<ConnectorInfo>, Error> {
+        let disabled = self.calls.borrow().iter().any(|call| {
+            matches!(
+                call,
+                Call::SetActive { active: false, .. } | Call::SetDpms { active: false, .. }
+            )
+        });
+        if disabled && self.panic_after_disable.replace(false) {
+            panic!("injected panic on {}", self.path.display());
+        }
+        Ok(self.connectors.borrow().clone())
+    }
+
+    fn get_crtc_state(&self, crtc_handle: crtc::Handle) -> Result<bool, Error> {
+        Ok(self.is_active(crtc_handle.into()))
+    }
+
+    fn get_connector_dpms(&self, conn_handle: connector::Handle) -> Result<Option<bool>, Error> {
+        if !self.legacy {
+            return Ok(None);
+        }
+        let id = conn_handle.into();
+        Ok(Some(self.dpms.borrow().get(&id).copied().unwrap_or(true)))
+    }
+
+    fn commit_crtcs_active(
+        &self,
+        crtcs: &[crtc::Handle],
+        active: bool,
+        test_only: bool,
+    ) -> Result<(), Error> {
+        assert!(!self.legacy, "atomic commit on a legacy device");
+        if test_only {
+            if !active && self.reject_disable_test {
+                return Err(Error::DrmError(
+                    format!("Atomic test commit rejected on {}", self.path.display()).into(),
+                ));
+            }
+            return Ok(());
+        }
+
+        let ids: Vec<u32> = crtcs.iter().map(|&h| h.into()).collect();
+        self.calls.borrow_mut().push(Call::SetActive {
+            crtcs: ids.clone(),
+            active,
+        });
+
+        let enable_fails =
+            self.fail_enable || ids.iter().any(|id| self.fail_enable_crtcs.contains(id));
+        if (active && enable_fails) || (!active && self.fail_disable) {
+            return Err(Error::DrmDeviceBusy {
+                path: self.path.display().to_string(),
+            });
+        }
+        for id in ids {
+            self.active.borrow_mut().insert(id, active);
+        }
+        Ok(())
+    }
+
+    fn set_connector_dpms(
+        &self,
+        conn_handle: connector::Handle,
+        active: bool,
+    ) -> Result<(), Error> {
+        assert!(self.legacy, "DPMS property set on an atomic device");
+        let id = conn_handle.into();
+        self.calls.borrow_mut().push(Call::SetDpms {
+            connector: id,
+            active,
+        });
+        if (active && self.fail_enable) || (!active && self.fail_disable) {
+            return Err(Error::DrmDeviceBusy {
+                path: self.path.display().to_string(),
+            });
+        }
+        self.dpms.borrow_mut().insert(id, active);
+        Ok(())
+    }
+
+    fn crtc_can_resume(&self, crtc_handle: crtc::Handle) -> Result<bool, Error> {
+        Ok(self.active.borrow().contains_key(&crtc_handle.into()))
+    }
+
+    fn modeset_connector(&self, conn_handle: connector::Handle) -> Result<crtc::Handle, Error> {
+        self.calls.borrow_mut().push(Call::Modeset {
+            connector: conn_handle.into(),
+        });
+
+        // Light the connector on a CRTC of its own
+        let crtc = 100 + u32::from(conn_handle);
+        self.active.borrow_mut().insert(crtc, true);
+        for c in self.connectors.borrow_mut().iter_mut() {
+            if c.handle == conn_handle {
+                c.crtc = drm::control::from_u32(crtc);
+            }
+        }
+        Ok(drm::control::from_u32(crtc).unwrap())
+    }
+}
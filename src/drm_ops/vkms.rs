@@ -0,0 +1,122 @@
+//! Hardware tests for `drm_ops` against the VKMS virtual display driver
+//!
+//! These need a kernel with the `vkms` module loaded and access to its card
+//! node, so they are ignored by default. To run them locally:
+//!
+//! ```text
+//! sudo modprobe vkms
+//! DPMS_TEST_VKMS=1 cargo test vkms -- --ignored
+//! ```
+//!
+//! The tests take DRM master on the VKMS device, so run them from a VT or
+//! anywhere no compositor is driving VKMS. CI runners without the module
+//! (or without `DPMS_TEST_VKMS=1`) skip them with a message instead of
+//! failing.
+
+use super::*;
+
+/// Driver name the kernel reports for VKMS devices
+const VKMS_DRIVER: &str = "vkms";
+
+/// Open the VKMS device with DRM master, or explain why the test is skipped
+fn open_vkms() -> Option<DrmDevice> {
+    if std::env::var_os("DPMS_TEST_VKMS").is_none() {
+        eprintln!("skipping: set DPMS_TEST_VKMS=1 to run VKMS tests");
+        return None;
+    }
+
+    let Some(path) = card_nodes_in(Path::new(DRI_DIR)).into_iter().find(|card| {
+        sysfs_driver_name(Path::new(SYSFS_DRM_DIR), card).as_deref() == Some(VKMS_DRIVER)
+    }) else {
+        eprintln!("skipping: no vkms device found; load it with `modprobe vkms`");
+        return None;
+    };
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("skipping: cannot open {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    let mut device = DrmDevice::new(DrmDeviceInner::Direct(file), path);
+    if let Err(e) = acquire_master(&device, &device.path.clone()) {
+        eprintln!("skipping: {}", e);
+        return None;
+    }
+    device.master = true;
+    Some(device)
+}
+
+/// The CRTC driving VKMS's connector, lighting it first if nothing is mapped
+fn vkms_crtc(device: &DrmDevice) -> crtc::Handle {
+    match device.find_all_active_crtcs() {
+        Ok(targets) => targets[0].crtc.unwrap(),
+        Err(_) => {
+            let connector = device
+                .list_connectors()
+                .unwrap()
+                .into_iter()
+                .find(|c| c.state == connector::State::Connected)
+                .expect("vkms should expose a connected connector");
+            device.modeset_connector(connector.handle).unwrap()
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs the vkms kernel module and DPMS_TEST_VKMS=1"]
+fn vkms_lists_virtual_connector() {
+    let Some(device) = open_vkms() else {
+        return;
+    };
+
+    assert_eq!(device.driver(), VKMS_DRIVER);
+    let connectors = device.list_connectors().unwrap();
+    assert!(
+        connectors
+            .iter()
+            .any(|c| c.name.starts_with("Virtual-") && c.state == connector::State::Connected)
+    );
+}
+
+#[test]
+#[ignore = "needs the vkms kernel module and DPMS_TEST_VKMS=1"]
+fn vkms_toggles_crtc_active() {
+    let Some(device) = open_vkms() else {
+        return;
+    };
+    let crtc_handle = vkms_crtc(&device);
+
+    device.set_crtc_active(crtc_handle, false).unwrap();
+    assert!(!device.get_crtc_active(crtc_handle).unwrap());
+
+    device.set_crtc_active(crtc_handle, true).unwrap();
+    assert!(device.get_crtc_active(crtc_handle).unwrap());
+}
+
+#[test]
+#[ignore = "needs the vkms kernel module and DPMS_TEST_VKMS=1"]
+fn vkms_turns_off_all_active_crtcs_at_once() {
+    let Some(device) = open_vkms() else {
+        return;
+    };
+    vkms_crtc(&device);
+    let crtcs: Vec<crtc::Handle> = device
+        .find_all_active_crtcs()
+        .unwrap()
+        .into_iter()
+        .filter_map(|c| c.crtc)
+        .collect();
+
+    device.set_crtcs_active(&crtcs, false).unwrap();
+    for &crtc_handle in &crtcs {
+        assert!(!device.get_crtc_active(crtc_handle).unwrap());
+        assert!(device.crtc_drives_connected(crtc_handle).unwrap());
+    }
+
+    device.set_crtcs_active(&crtcs, true).unwrap();
+    for &crtc_handle in &crtcs {
+        assert!(device.get_crtc_active(crtc_handle).unwrap());
+    }
+}
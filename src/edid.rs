@@ -0,0 +1,155 @@
+//! Minimal EDID parsing for display make and model
+//!
+//! Only the base block is read: the manufacturer PNP id and the monitor
+//! name. Anything missing, truncated or failing its checksum is ignored.
+
+/// Length of the EDID base block
+const BLOCK_LEN: usize = 128;
+
+/// Fixed header every EDID base block starts with
+const HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+
+/// Offsets of the four 18-byte descriptors in the base block
+const DESCRIPTOR_OFFSETS: [usize; 4] = [54, 72, 90, 108];
+
+/// Display descriptor tag for the monitor name
+const TAG_MONITOR_NAME: u8 = 0xfc;
+
+/// Display descriptor tag for unspecified text, used by laptop panels
+const TAG_TEXT: u8 = 0xfe;
+
+/// Make and model read from an EDID blob
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EdidInfo {
+    /// Three-letter PNP manufacturer id, e.g. `DEL`
+    pub make: Option<String>,
+    /// Monitor name, e.g. `DELL U2415`
+    pub model: Option<String>,
+}
+
+/// Parse the make and model out of an EDID blob
+///
+/// The model is the monitor name descriptor, or the last text descriptor
+/// for panels that have none (laptop panels usually put the part number
+/// there).
+///
+/// # Returns
+/// The parsed fields; both are `None` if the blob isn't a valid EDID
+pub fn parse(edid: &[u8]) -> EdidInfo {
+    let Some(block) = edid.get(..BLOCK_LEN) else {
+        return EdidInfo::default();
+    };
+    if block[..8] != HEADER || block.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) != 0 {
+        return EdidInfo::default();
+    }
+
+    let mut name = None;
+    let mut text = None;
+    for offset in DESCRIPTOR_OFFSETS {
+        let descriptor = &block[offset..offset + 18];
+        // Detailed timings have a non-zero pixel clock here
+        if descriptor[0] != 0 || descriptor[1] != 0 {
+            continue;
+        }
+        match descriptor[3] {
+            TAG_MONITOR_NAME => name = descriptor_text(descriptor),
+            TAG_TEXT => text = descriptor_text(descriptor).or(text),
+            _ => {}
+        }
+    }
+
+    EdidInfo {
+        make: manufacturer_id(block[8], block[9]),
+        model: name.or(text),
+    }
+}
+
+/// Decode the three 5-bit letters of the PNP manufacturer id
+fn manufacturer_id(high: u8, low: u8) -> Option<String> {
+    let packed = u16::from_be_bytes([high, low]);
+    let id: String = [10, 5, 0]
+        .iter()
+        .map(|shift| ((packed >> shift) & 0x1f) as u8)
+        .map(|letter| (b'A' - 1 + letter) as char)
+        .collect();
+    id.chars().all(|c| c.is_ascii_uppercase()).then_some(id)
+}
+
+/// The text of a display descriptor, without the newline and padding
+fn descriptor_text(descriptor: &[u8]) -> Option<String> {
+    let text = &descriptor[5..18];
+    let end = text.iter().position(|&b| b == b'\n').unwrap_or(text.len());
+    let text = String::from_utf8_lossy(&text[..end]).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELL_U2415: [u8; 128] = [
+        0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x10, 0xac, 0xc4, 0xa0, 0x4c, 0x4a, 0x33,
+        0x30, 0x1a, 0x18, 0x01, 0x04, 0xb5, 0x34, 0x1d, 0x78, 0x3a, 0xe7, 0xc5, 0xa5, 0x55, 0x4e,
+        0xa0, 0x26, 0x0d, 0x50, 0x54, 0xa5, 0x4b, 0x00, 0x71, 0x4f, 0x81, 0x80, 0xa9, 0xc0, 0xd1,
+        0xc0, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x02, 0x3a, 0x80, 0x18, 0x71, 0x38,
+        0x2d, 0x40, 0x58, 0x2c, 0x45, 0x00, 0x56, 0x50, 0x21, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00,
+        0xff, 0x00, 0x37, 0x4d, 0x54, 0x30, 0x31, 0x36, 0x37, 0x42, 0x32, 0x59, 0x4e, 0x4c, 0x0a,
+        0x00, 0x00, 0x00, 0xfc, 0x00, 0x44, 0x45, 0x4c, 0x4c, 0x20, 0x55, 0x32, 0x34, 0x31, 0x35,
+        0x0a, 0x20, 0x20, 0x00, 0x00, 0x00, 0xfd, 0x00, 0x38, 0x4c, 0x1e, 0x51, 0x11, 0x00, 0x0a,
+        0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x00, 0xbf,
+    ];
+
+    const BOE_PANEL: [u8; 128] = [
+        0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x09, 0xe5, 0x47, 0x07, 0x4c, 0x4a, 0x33,
+        0x30, 0x1a, 0x18, 0x01, 0x04, 0xb5, 0x34, 0x1d, 0x78, 0x3a, 0xe7, 0xc5, 0xa5, 0x55, 0x4e,
+        0xa0, 0x26, 0x0d, 0x50, 0x54, 0xa5, 0x4b, 0x00, 0x71, 0x4f, 0x81, 0x80, 0xa9, 0xc0, 0xd1,
+        0xc0, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x02, 0x3a, 0x80, 0x18, 0x71, 0x38,
+        0x2d, 0x40, 0x58, 0x2c, 0x45, 0x00, 0x56, 0x50, 0x21, 0x00, 0x00, 0x1e, 0x00, 0x00, 0x00,
+        0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0xfe, 0x00, 0x42, 0x4f, 0x45, 0x20, 0x43, 0x51, 0x0a, 0x20, 0x20, 0x20,
+        0x20, 0x20, 0x20, 0x00, 0x00, 0x00, 0xfe, 0x00, 0x4e, 0x56, 0x31, 0x35, 0x36, 0x46, 0x48,
+        0x4d, 0x2d, 0x4e, 0x34, 0x38, 0x0a, 0x00, 0xc0,
+    ];
+
+    #[test]
+    fn parses_monitor_name() {
+        let info = parse(&DELL_U2415);
+        assert_eq!(info.make.as_deref(), Some("DEL"));
+        assert_eq!(info.model.as_deref(), Some("DELL U2415"));
+    }
+
+    #[test]
+    fn falls_back_to_text_descriptor() {
+        let info = parse(&BOE_PANEL);
+        assert_eq!(info.make.as_deref(), Some("BOE"));
+        assert_eq!(info.model.as_deref(), Some("NV156FHM-N48"));
+    }
+
+    #[test]
+    fn ignores_extension_blocks() {
+        let mut edid = DELL_U2415.to_vec();
+        edid.extend([0u8; 128]);
+        assert_eq!(parse(&edid).model.as_deref(), Some("DELL U2415"));
+    }
+
+    #[test]
+    fn corrupt_checksum_is_ignored() {
+        let mut edid = DELL_U2415;
+        edid[100] ^= 0x01;
+        assert_eq!(parse(&edid), EdidInfo::default());
+    }
+
+    #[test]
+    fn truncated_or_missing_edid_is_ignored() {
+        assert_eq!(parse(&DELL_U2415[..100]), EdidInfo::default());
+        assert_eq!(parse(&[]), EdidInfo::default());
+    }
+
+    #[test]
+    fn bad_header_is_ignored() {
+        let mut edid = DELL_U2415;
+        edid[0] = 0x01;
+        edid[127] = edid[127].wrapping_sub(1);
+        assert_eq!(parse(&edid), EdidInfo::default());
+    }
+}
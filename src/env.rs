@@ -1,6 +1,8 @@
 use crate::error::Error;
 use std::io::IsTerminal;
 use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
 
 /// Detected backend type
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -10,57 +12,230 @@ pub enum Backend {
     Tty,
 }
 
-/// Try to find an available Wayland socket in XDG_RUNTIME_DIR
+/// Directory where compositors place their sockets, and the daemon its
+/// runtime files
+///
+/// Uses XDG_RUNTIME_DIR, falling back to `/run/user/$UID` since SSH sessions
+/// without a logind session may not have it set.
+pub(crate) fn runtime_dir() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => default_runtime_dir(),
+    }
+}
+
+#[cfg(not(target_os = "freebsd"))]
+fn default_runtime_dir() -> PathBuf {
+    PathBuf::from(format!("/run/user/{}", nix::unistd::Uid::effective()))
+}
+
+/// FreeBSD has no `/run`; session managers create `/var/run/user/$UID`
+#[cfg(target_os = "freebsd")]
+fn default_runtime_dir() -> PathBuf {
+    let user_dir = PathBuf::from(format!("/var/run/user/{}", nix::unistd::Uid::effective()));
+    runtime_dir_or_tmp(
+        user_dir.is_dir().then_some(user_dir),
+        std::env::var_os("TMPDIR"),
+    )
+}
+
+/// `user_dir` if there is one, otherwise `$TMPDIR` or `/tmp`
+///
+/// Runtime files there are still checked for their owner before use.
+#[cfg(any(target_os = "freebsd", test))]
+fn runtime_dir_or_tmp(user_dir: Option<PathBuf>, tmpdir: Option<std::ffi::OsString>) -> PathBuf {
+    user_dir
+        .or_else(|| tmpdir.map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("/tmp"))
+}
+
+/// Find live Wayland sockets in a runtime directory
 ///
 /// This is useful for SSH sessions where WAYLAND_DISPLAY is not set
-/// but a compositor is running on the target machine.
+/// but a compositor is running on the target machine. A socket counts as
+/// live if a connection to it succeeds, which skips sockets left behind by
+/// compositors that have exited.
 ///
 /// # Returns
-/// - `Some(socket_name)` - Found a Wayland socket (e.g., "wayland-1")
-/// - `None` - No Wayland socket found
-fn find_wayland_socket() -> Option<String> {
-    let runtime_dir = std::env::var("XDG_RUNTIME_DIR").ok()?;
-    let dir = std::fs::read_dir(&runtime_dir).ok()?;
-
-    for entry in dir.flatten() {
-        let name = entry.file_name();
-        let name_str = name.to_string_lossy();
-        // Look for wayland-N sockets (not .lock files)
-        if name_str.starts_with("wayland-") && !name_str.ends_with(".lock") {
-            // Verify it's a socket
-            if let Ok(metadata) = entry.metadata()
-                && metadata.file_type().is_socket()
-            {
-                return Some(name_str.into_owned());
-            }
-        }
+/// Full paths of live `wayland-*` sockets, sorted by name
+fn find_wayland_sockets(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut sockets: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name_str = name.to_string_lossy();
+            // Look for wayland-N sockets (not .lock files)
+            name_str.starts_with("wayland-") && !name_str.ends_with(".lock")
+        })
+        .filter(|entry| {
+            entry
+                .metadata()
+                .map(|m| m.file_type().is_socket())
+                .unwrap_or(false)
+        })
+        .map(|entry| entry.path())
+        .filter(|path| UnixStream::connect(path).is_ok())
+        .collect();
+
+    sockets.sort();
+    sockets
+}
+
+/// Hyprland's request socket for the running instance
+///
+/// Derived from HYPRLAND_INSTANCE_SIGNATURE. Hyprland 0.40+ keeps its sockets
+/// under `$XDG_RUNTIME_DIR/hypr`, older versions used `/tmp/hypr`.
+pub fn hyprland_socket() -> Option<PathBuf> {
+    let signature = std::env::var_os("HYPRLAND_INSTANCE_SIGNATURE")?;
+    let socket = runtime_dir()
+        .join("hypr")
+        .join(&signature)
+        .join(".socket.sock");
+    if socket.exists() {
+        return Some(socket);
     }
 
-    None
+    let legacy = Path::new("/tmp/hypr").join(&signature).join(".socket.sock");
+    Some(if legacy.exists() { legacy } else { socket })
+}
+
+/// Tools known to hold output power controls or fight over output state
+const KNOWN_POWER_CLIENTS: &[&str] = &["swayidle", "hypridle", "wlopm", "gammastep"];
+
+/// Find running tools that may be holding an output's power control
+///
+/// Best effort: scans `<proc_root>/*/comm` for known tool names and keeps
+/// those that have at least one socket open, since a Wayland client always
+/// does. Connected client sockets carry no path, so which compositor a tool
+/// talks to can't be told apart. Unreadable entries are skipped.
+///
+/// # Returns
+/// Entries such as `swayidle (pid 1234)`, sorted by pid
+pub fn find_conflicting_clients(proc_root: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(proc_root) else {
+        return Vec::new();
+    };
+    let own_pid = std::process::id();
+
+    let mut clients: Vec<(u32, String)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            if pid == own_pid {
+                return None;
+            }
+
+            let comm = std::fs::read_to_string(entry.path().join("comm")).ok()?;
+            let comm = comm.trim();
+            if !KNOWN_POWER_CLIENTS.contains(&comm) {
+                return None;
+            }
+
+            let has_socket = std::fs::read_dir(entry.path().join("fd"))
+                .ok()?
+                .flatten()
+                .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+                .any(|target| target.to_string_lossy().starts_with("socket:"));
+
+            has_socket.then(|| (pid, comm.to_string()))
+        })
+        .collect();
+
+    clients.sort();
+    clients
+        .into_iter()
+        .map(|(pid, name)| format!("{} (pid {})", name, pid))
+        .collect()
+}
+
+/// Check whether the session is a GNOME desktop
+///
+/// `XDG_CURRENT_DESKTOP` is a colon-separated list (e.g. `ubuntu:GNOME`).
+pub fn is_gnome_desktop() -> bool {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|v| desktop_list_contains(&v, "GNOME"))
+        .unwrap_or(false)
+}
+
+/// Check whether a colon-separated desktop list contains `desktop`
+fn desktop_list_contains(list: &str, desktop: &str) -> bool {
+    list.split(':').any(|d| d.eq_ignore_ascii_case(desktop))
 }
 
 /// Detect which backend to use based on environment
 ///
 /// Detection order:
 /// 1. Check if WAYLAND_DISPLAY is set -> Wayland
-/// 2. Check if a Wayland socket exists (for SSH sessions) -> Wayland (sets WAYLAND_DISPLAY)
+/// 2. Check if exactly one live Wayland socket exists (for SSH sessions) -> Wayland
+///    (sets WAYLAND_DISPLAY); several live sockets -> Error asking to pick one
 /// 3. Check if DISPLAY is set -> X11
 /// 4. Check if stdin is a TTY -> TTY
 /// 5. Otherwise -> Error
+///
+/// A detected backend that this build left out is an error too, rather than
+/// falling through to the next one.
 pub fn detect_backend() -> Result<Backend, Error> {
+    let backend = detect_environment()?;
+    if is_built(backend) {
+        Ok(backend)
+    } else {
+        Err(Error::BackendNotBuilt {
+            backend: backend.feature().to_string(),
+        })
+    }
+}
+
+impl Backend {
+    /// Cargo feature that compiles this backend in
+    fn feature(self) -> &'static str {
+        match self {
+            Backend::Wayland => "wayland",
+            Backend::X11 => "x11",
+            Backend::Tty => "tty",
+        }
+    }
+}
+
+/// Whether this build has `backend` compiled in
+///
+/// X11 has no backend to leave out; it is reported as unsupported later.
+pub fn is_built(backend: Backend) -> bool {
+    match backend {
+        Backend::Wayland => cfg!(feature = "wayland"),
+        Backend::X11 => true,
+        Backend::Tty => cfg!(feature = "tty"),
+    }
+}
+
+/// The backend the environment points at, whether or not it is built
+fn detect_environment() -> Result<Backend, Error> {
     // Check for Wayland first
     if std::env::var("WAYLAND_DISPLAY").is_ok() {
         return Ok(Backend::Wayland);
     }
 
     // Try to auto-detect Wayland socket (useful for SSH sessions)
-    if let Some(socket) = find_wayland_socket() {
-        // Set WAYLAND_DISPLAY so the Wayland backend can connect
-        // SAFETY: We're setting this before any Wayland connection is made
-        unsafe {
-            std::env::set_var("WAYLAND_DISPLAY", &socket);
+    let sockets = find_wayland_sockets(&runtime_dir());
+    match sockets.as_slice() {
+        [] => {}
+        [socket] => {
+            // Set WAYLAND_DISPLAY (absolute path) so the Wayland backend can
+            // connect even when XDG_RUNTIME_DIR is unset
+            // SAFETY: We're setting this before any Wayland connection is made
+            unsafe {
+                std::env::set_var("WAYLAND_DISPLAY", socket);
+            }
+            return Ok(Backend::Wayland);
+        }
+        _ => {
+            return Err(Error::AmbiguousWaylandSocket {
+                candidates: sockets.iter().map(|p| p.display().to_string()).collect(),
+            });
         }
-        return Ok(Backend::Wayland);
     }
 
     // Check for X11 (yet unimplemented)
@@ -88,7 +263,23 @@ pub fn detect_backend() -> Result<Backend, Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::os::unix::net::UnixListener;
+
+    #[test]
+    fn runtime_dir_falls_back_to_tmp() {
+        let user_dir = PathBuf::from("/var/run/user/1001");
+        assert_eq!(
+            runtime_dir_or_tmp(Some(user_dir.clone()), Some("/var/tmp".into())),
+            user_dir
+        );
+        assert_eq!(
+            runtime_dir_or_tmp(None, Some("/var/tmp".into())),
+            PathBuf::from("/var/tmp")
+        );
+        assert_eq!(runtime_dir_or_tmp(None, None), PathBuf::from("/tmp"));
+    }
 
+    #[cfg(feature = "wayland")]
     #[test]
     fn detect_wayland_when_env_var_set() {
         // Set WAYLAND_DISPLAY temporarily
@@ -144,10 +335,17 @@ mod tests {
             Err(Error::UnsupportedEnvironment) => {
                 // We're not on a TTY and no Wayland (e.g., CI), this is also correct
             }
+            Err(Error::AmbiguousWaylandSocket { .. }) => {
+                // Several compositors are running, the user has to pick one
+            }
+            Err(Error::BackendNotBuilt { .. }) => {
+                // Detected a backend this build leaves out
+            }
             other => panic!("Unexpected result: {:?}", other),
         }
     }
 
+    #[cfg(feature = "wayland")]
     #[test]
     fn wayland_takes_precedence_over_tty() {
         // Even if we're on a TTY, Wayland should be detected first if env var is set
@@ -168,6 +366,122 @@ mod tests {
         assert_eq!(result.unwrap(), Backend::Wayland);
     }
 
+    #[cfg(not(feature = "wayland"))]
+    #[test]
+    fn detect_wayland_without_the_backend_built() {
+        // SAFETY: This is a test and we're the only ones modifying this env var
+        unsafe {
+            std::env::set_var("WAYLAND_DISPLAY", "wayland-2");
+        }
+
+        let result = detect_backend();
+
+        // SAFETY: This is a test and we're the only ones modifying this env var
+        unsafe {
+            std::env::remove_var("WAYLAND_DISPLAY");
+        }
+
+        match result {
+            Err(Error::BackendNotBuilt { backend }) => assert_eq!(backend, "wayland"),
+            other => panic!("Unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn built_backends_follow_features() {
+        assert_eq!(is_built(Backend::Wayland), cfg!(feature = "wayland"));
+        assert_eq!(is_built(Backend::Tty), cfg!(feature = "tty"));
+        assert!(is_built(Backend::X11));
+    }
+
+    #[test]
+    fn find_wayland_sockets_skips_stale_and_lock_files() {
+        let dir = PathBuf::from("/tmp/dpms-test-sockets-stale");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Live socket
+        let _live = UnixListener::bind(dir.join("wayland-0")).unwrap();
+        // Stale socket: file remains but nobody is listening
+        drop(UnixListener::bind(dir.join("wayland-1")).unwrap());
+        // Lock file and unrelated socket
+        std::fs::File::create(dir.join("wayland-0.lock")).unwrap();
+        let _other = UnixListener::bind(dir.join("pipewire-0")).unwrap();
+
+        let sockets = find_wayland_sockets(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(sockets, vec![dir.join("wayland-0")]);
+    }
+
+    #[test]
+    fn find_wayland_sockets_returns_all_live_sorted() {
+        let dir = PathBuf::from("/tmp/dpms-test-sockets-multi");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let _second = UnixListener::bind(dir.join("wayland-1")).unwrap();
+        let _first = UnixListener::bind(dir.join("wayland-0")).unwrap();
+
+        let sockets = find_wayland_sockets(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(sockets, vec![dir.join("wayland-0"), dir.join("wayland-1")]);
+    }
+
+    #[test]
+    fn find_wayland_sockets_missing_dir() {
+        let sockets = find_wayland_sockets(Path::new("/tmp/dpms-test-no-such-dir"));
+        assert!(sockets.is_empty());
+    }
+
+    /// Add a fake process to a fake /proc tree
+    fn add_fake_process(proc_root: &Path, pid: u32, comm: &str, fds: &[&str]) {
+        let dir = proc_root.join(pid.to_string());
+        std::fs::create_dir_all(dir.join("fd")).unwrap();
+        std::fs::write(dir.join("comm"), format!("{}\n", comm)).unwrap();
+        for (fd, target) in fds.iter().enumerate() {
+            std::os::unix::fs::symlink(target, dir.join("fd").join(fd.to_string())).unwrap();
+        }
+    }
+
+    #[test]
+    fn find_conflicting_clients_in_fake_proc() {
+        let proc_root = PathBuf::from("/tmp/dpms-test-fake-proc");
+        let _ = std::fs::remove_dir_all(&proc_root);
+        std::fs::create_dir_all(&proc_root).unwrap();
+
+        add_fake_process(&proc_root, 4321, "wlopm", &["/dev/null", "socket:[1001]"]);
+        add_fake_process(&proc_root, 1234, "swayidle", &["socket:[1002]"]);
+        // Known tool without a socket: not a Wayland client (yet)
+        add_fake_process(&proc_root, 2000, "gammastep", &["/dev/null"]);
+        // Unrelated client
+        add_fake_process(&proc_root, 3000, "foot", &["socket:[1003]"]);
+        // Non-pid entries are ignored
+        std::fs::create_dir_all(proc_root.join("self")).unwrap();
+        std::fs::write(proc_root.join("uptime"), "1.0 1.0\n").unwrap();
+
+        let clients = find_conflicting_clients(&proc_root);
+        let _ = std::fs::remove_dir_all(&proc_root);
+
+        assert_eq!(clients, ["swayidle (pid 1234)", "wlopm (pid 4321)"]);
+    }
+
+    #[test]
+    fn find_conflicting_clients_missing_proc() {
+        let clients = find_conflicting_clients(Path::new("/tmp/dpms-test-no-such-proc"));
+        assert!(clients.is_empty());
+    }
+
+    #[test]
+    fn desktop_list_matches_gnome() {
+        assert!(desktop_list_contains("GNOME", "GNOME"));
+        assert!(desktop_list_contains("ubuntu:GNOME", "GNOME"));
+        assert!(desktop_list_contains("gnome", "GNOME"));
+        assert!(!desktop_list_contains("KDE", "GNOME"));
+        assert!(!desktop_list_contains("GNOME-Flashback", "GNOME"));
+    }
+
     #[test]
     fn backend_enum_equality() {
         assert_eq!(Backend::Wayland, Backend::Wayland);
@@ -1,18 +1,31 @@
 #![allow(clippy::enum_variant_names)]
 /// Exit codes for dpms CLI
+///
+/// Scripts may rely on these: a code keeps its meaning once released, and
+/// `dpms --help` lists them.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
 pub enum ExitCode {
     /// Operation completed successfully
     Success = 0,
-    /// Runtime error occurred
+    /// Runtime error not covered by a more specific code
     Error = 1,
     /// Invalid command-line usage (reserved for clap, currently unused by dpms)
     Usage = 2,
+    /// No display is connected, or none matches the name given
+    NoDisplay = 3,
+    /// The displays or the seat may not be used by this user, or another
+    /// process holds them
+    Permission = 4,
+    /// The daemon could not be started or stopped
+    Daemon = 5,
+    /// The environment, compositor or build can't do what was asked
+    Unsupported = 6,
 }
 
 impl From<ExitCode> for i32 {
     fn from(code: ExitCode) -> i32 {
-        code as i32
+        i32::from(code as u8)
     }
 }
 
@@ -22,6 +35,137 @@ impl From<ExitCode> for std::process::ExitCode {
     }
 }
 
+/// What dpms was doing when something failed, and the error that failed it
+///
+/// Displays as the message followed by the error, e.g. `Atomic commit
+/// failed on /dev/dri/card0: Permission denied (os error 13)`, and keeps the
+/// error as its `source()` for callers that want to inspect it. Failures
+/// dpms finds itself have a message only.
+#[derive(Debug)]
+pub struct Context {
+    message: String,
+    source: Option<Box<dyn std::error::Error + Send + Sync>>,
+}
+
+impl Context {
+    /// `message` on its own
+    pub fn new(message: impl Into<String>) -> Self {
+        Context {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// `message` caused by `source`
+    pub fn wrap(
+        message: impl Into<String>,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Context {
+            message: message.into(),
+            source: Some(source.into()),
+        }
+    }
+
+    /// What dpms was doing, without the cause
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl std::fmt::Display for Context {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{}: {}", self.message, source),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for Context {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl From<String> for Context {
+    fn from(message: String) -> Self {
+        Context::new(message)
+    }
+}
+
+impl From<&str> for Context {
+    fn from(message: &str) -> Self {
+        Context::new(message)
+    }
+}
+
+/// Wrap a lower-level error in an `Error` variant along with what failed
+///
+/// ```ignore
+/// file.write_all(contents).context(Error::PidFileError, "Failed to write PID")?;
+/// ```
+pub trait ResultExt<T> {
+    /// Turn the error into `variant`, described by `message`
+    fn context(self, variant: fn(Context) -> Error, message: impl Into<String>)
+    -> Result<T, Error>;
+
+    /// Like `context`, building the message only on failure
+    fn with_context<M: Into<String>>(
+        self,
+        variant: fn(Context) -> Error,
+        message: impl FnOnce() -> M,
+    ) -> Result<T, Error>;
+}
+
+impl<T, E> ResultExt<T> for Result<T, E>
+where
+    E: std::error::Error + Send + Sync + 'static,
+{
+    fn context(
+        self,
+        variant: fn(Context) -> Error,
+        message: impl Into<String>,
+    ) -> Result<T, Error> {
+        self.map_err(|e| variant(Context::wrap(message, e)))
+    }
+
+    fn with_context<M: Into<String>>(
+        self,
+        variant: fn(Context) -> Error,
+        message: impl FnOnce() -> M,
+    ) -> Result<T, Error> {
+        self.map_err(|e| variant(Context::wrap(message(), e)))
+    }
+}
+
+/// A connector as seen while looking for a display, for `NoConnectedDisplay`
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ConnectorSeen {
+    /// Connector name, e.g. `HDMI-A-1`
+    pub name: String,
+    /// `connected`, `disconnected` or `unknown`; `connected (no CRTC)` when
+    /// nothing drives it
+    pub state: String,
+}
+
+/// Summarize the connectors of a device, e.g. `card0: eDP-1=disconnected, DP-1=unknown`
+pub fn connector_summary(device: &str, connectors: &[ConnectorSeen]) -> String {
+    let device = std::path::Path::new(device)
+        .file_name()
+        .map_or(device.into(), |name| name.to_string_lossy());
+    if connectors.is_empty() {
+        return format!("{}: no connectors", device);
+    }
+    let connectors: Vec<String> = connectors
+        .iter()
+        .map(|c| format!("{}={}", c.name, c.state))
+        .collect();
+    format!("{}: {}", device, connectors.join(", "))
+}
+
 /// Error types for dpms
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -34,13 +178,16 @@ pub enum Error {
     #[error("No connected display found")]
     NoDisplayFound,
 
-    #[error("Display '{name}' not found. Available: {}", available.join(", "))]
+    #[error("Display '{name}' not found. Available:{}", name_list(available))]
     DisplayNotFound {
         name: String,
         available: Vec<String>,
     },
 
-    #[error("Display '{name}' is ambiguous. Did you mean: {}?", candidates.join(", "))]
+    #[error(
+        "Display '{name}' is ambiguous. Did you mean:{}",
+        name_list(candidates)
+    )]
     AmbiguousDisplay {
         name: String,
         candidates: Vec<String>,
@@ -49,31 +196,342 @@ pub enum Error {
     #[error("Daemon failed to start: {0}")]
     DaemonStartFailed(String),
 
-    #[error("Daemon did not stop within timeout period")]
+    #[error(
+        "Daemon did not stop within timeout period; pass --kill-after to kill it \
+         and restore the displays directly"
+    )]
     DaemonStopTimeout,
 
+    #[error(
+        "Daemon (PID {pid}) ignored SIGTERM and survived SIGKILL; it is stuck in \
+         the kernel and the displays stay off"
+    )]
+    DaemonUnkillable { pid: i32 },
+
+    #[error(
+        "The daemon holding {holder} is DRM master of the card; turn it on first, \
+         as only one daemon can hold a card"
+    )]
+    DaemonConflict { holder: String },
+
     #[error("Signal operation failed: {0}")]
-    SignalError(String),
+    SignalError(#[source] Context),
+
+    #[error("Daemon control request failed: {0}")]
+    ControlError(#[source] Context),
 
     #[error("PID file operation failed: {0}")]
-    PidFileError(String),
+    PidFileError(#[source] Context),
 
     #[error("DRM operation failed: {0}")]
-    DrmError(String),
+    DrmError(#[source] Context),
+
+    #[error(
+        "DRM master for {path} is held by another process; \
+         another compositor or display server is running"
+    )]
+    DrmMasterHeld { path: String },
+
+    #[error(
+        "Permission denied opening {path}; add your user to the 'video' group \
+         or run inside a logind session"
+    )]
+    DrmPermissionDenied { path: String },
+
+    #[error("Could not open a DRM device; libseat: {libseat}; direct access: {direct}")]
+    DrmOpenFailed { libseat: String, direct: String },
+
+    #[error(
+        "Permission denied opening {path} and no seat manager found; either start \
+         seatd, run within a logind session, or add your user to the 'video' group"
+    )]
+    NoSeatManager { path: String },
+
+    #[error(
+        "A compositor or X server is using {path}; use the Wayland backend from \
+         inside the session, or pass --force-tty to take the display anyway"
+    )]
+    DisplayServerActive { path: String },
+
+    #[error("DRM device {path} is busy; another display server may be active")]
+    DrmDeviceBusy { path: String },
+
+    #[error("DRM device {path} disappeared")]
+    DrmDeviceGone { path: String },
+
+    #[error("Permission denied writing {path}; blanking the console requires root")]
+    FbconPermissionDenied { path: String },
 
     #[error("libseat operation failed: {0}")]
-    SeatError(String),
+    SeatError(#[source] Context),
+
+    #[error("D-Bus operation failed: {0}")]
+    DbusError(#[source] Context),
+
+    #[error(
+        "Power change failed for {}",
+        failed.iter().map(|(name, reason)| format!("{} ({})", name, reason)).collect::<Vec<_>>().join(", ")
+    )]
+    PartialFailure { failed: Vec<(String, String)> },
+
+    #[error("--dry-run is not supported by the {backend} backend")]
+    DryRunNotSupported { backend: String },
+
+    #[error("Compositor did not respond within {0:?}")]
+    CompositorTimeout(std::time::Duration),
+
+    #[error("Compositor IPC failed: {0}")]
+    CompositorIpc(#[source] Context),
+
+    #[error("Idle detection requires a Wayland compositor with ext_idle_notify_v1")]
+    IdleNotifyNotSupported,
+
+    #[error("--timeout needs the TTY daemon, which turns the displays back on by itself")]
+    OffTimeoutNotSupported,
+
+    #[error(
+        "DPMS timeouts are an X11 feature; the {backend} backend has none, as \
+         Wayland compositors and the TTY daemon own their idle timeouts"
+    )]
+    DpmsTimeoutsNotSupported { backend: String },
+
+    #[error(
+        "Power control for '{name}' is held by another client{}. \
+         Stop other power-management tools (e.g. swayidle, wlopm) and try again",
+        conflicts_hint(conflicts)
+    )]
+    PowerControlBusy {
+        name: String,
+        /// Likely culprits, e.g. `swayidle (pid 1234)`; best effort, may be empty
+        conflicts: Vec<String>,
+    },
+
+    #[error("Wayland socket '{path}' is not usable: {reason}")]
+    WaylandSocketInvalid { path: String, reason: String },
+
+    #[error(
+        "Multiple Wayland sockets found: {}. Choose one with --wayland-socket",
+        candidates.join(", ")
+    )]
+    AmbiguousWaylandSocket { candidates: Vec<String> },
+
+    #[error("Lost connection to compositor; gave up after {attempts} reconnect attempts")]
+    ReconnectFailed { attempts: u32 },
 
     #[error("I/O error: {0}")]
     Io(#[from] std::io::Error),
+
+    #[error(
+        "This dpms was built without the {backend} backend; rebuild it with `--features {backend}`"
+    )]
+    BackendNotBuilt { backend: String },
+
+    #[error(
+        "dpms is not installed on {host}, or not on the PATH of non-interactive \
+         SSH sessions there"
+    )]
+    RemoteNotInstalled { host: String },
+
+    #[error("SSH failed: {0}")]
+    SshFailed(String),
+
+    #[error("Permission denied on {resource}; {hint}")]
+    PermissionDenied {
+        resource: String,
+        /// What the user can do about it, e.g. join the 'video' group
+        hint: String,
+    },
+
+    #[error(
+        "No connected display found ({})",
+        connector_summary(device, connectors)
+    )]
+    NoConnectedDisplay {
+        /// DRM device node scanned, e.g. `/dev/dri/card0`
+        device: String,
+        /// The connectors looked at on it
+        connectors: Vec<ConnectorSeen>,
+    },
+
+    #[error("No connected display found; the compositor advertised no wl_output")]
+    NoOutputAdvertised,
+
+    #[error("The {backend} backend can only switch all displays at once; run without --display")]
+    PerDisplayNotSupported { backend: String },
+}
+
+/// Format display names one per line, indented under the message
+fn name_list(names: &[String]) -> String {
+    if names.is_empty() {
+        return " none".to_string();
+    }
+    names.iter().map(|name| format!("\n  {}", name)).collect()
+}
+
+/// Format the list of possibly conflicting clients for PowerControlBusy
+fn conflicts_hint(conflicts: &[String]) -> String {
+    if conflicts.is_empty() {
+        String::new()
+    } else {
+        format!(" (possible conflicting clients: {})", conflicts.join(", "))
+    }
 }
 
 impl Error {
     /// Get the appropriate exit code for this error
+    ///
+    /// Usage errors never get here; clap exits with `ExitCode::Usage` itself.
     pub fn exit_code(&self) -> ExitCode {
-        // All runtime errors use ExitCode::Error (1)
-        // Usage errors would be handled separately by clap
-        ExitCode::Error
+        match self {
+            Error::NoDisplayFound
+            | Error::NoConnectedDisplay { .. }
+            | Error::NoOutputAdvertised
+            | Error::DisplayNotFound { .. }
+            | Error::AmbiguousDisplay { .. } => ExitCode::NoDisplay,
+            Error::DrmPermissionDenied { .. }
+            | Error::DrmOpenFailed { .. }
+            | Error::DrmMasterHeld { .. }
+            | Error::DisplayServerActive { .. }
+            | Error::DrmDeviceBusy { .. }
+            | Error::NoSeatManager { .. }
+            | Error::FbconPermissionDenied { .. }
+            | Error::SeatError(_)
+            | Error::PermissionDenied { .. } => ExitCode::Permission,
+            Error::DaemonStartFailed(_)
+            | Error::DaemonStopTimeout
+            | Error::DaemonUnkillable { .. }
+            | Error::DaemonConflict { .. } => ExitCode::Daemon,
+            Error::UnsupportedEnvironment
+            | Error::ProtocolNotSupported
+            | Error::DryRunNotSupported { .. }
+            | Error::IdleNotifyNotSupported
+            | Error::OffTimeoutNotSupported
+            | Error::DpmsTimeoutsNotSupported { .. }
+            | Error::PerDisplayNotSupported { .. }
+            | Error::BackendNotBuilt { .. } => ExitCode::Unsupported,
+            Error::SignalError(_)
+            | Error::ControlError(_)
+            | Error::PidFileError(_)
+            | Error::DrmError(_)
+            | Error::DrmDeviceGone { .. }
+            | Error::DbusError(_)
+            | Error::PartialFailure { .. }
+            | Error::CompositorTimeout(_)
+            | Error::CompositorIpc(_)
+            | Error::PowerControlBusy { .. }
+            | Error::WaylandSocketInvalid { .. }
+            | Error::AmbiguousWaylandSocket { .. }
+            | Error::ReconnectFailed { .. }
+            | Error::Io(_)
+            | Error::RemoteNotInstalled { .. }
+            | Error::SshFailed(_) => ExitCode::Error,
+        }
+    }
+
+    /// Stable number of this variant, for programs that can't match on it
+    ///
+    /// The C interface and `dpms repl` report errors by these numbers. Every
+    /// variant has its own, so a new variant doesn't build until it gets the
+    /// next free one; numbers are never reused.
+    pub fn number(&self) -> i32 {
+        match self {
+            Error::UnsupportedEnvironment => 1,
+            Error::ProtocolNotSupported => 2,
+            Error::NoDisplayFound => 3,
+            Error::DisplayNotFound { .. } => 4,
+            Error::AmbiguousDisplay { .. } => 5,
+            Error::DaemonStartFailed(_) => 6,
+            Error::DaemonStopTimeout => 7,
+            Error::DaemonUnkillable { .. } => 8,
+            Error::DaemonConflict { .. } => 9,
+            Error::SignalError(_) => 10,
+            Error::ControlError(_) => 11,
+            Error::PidFileError(_) => 12,
+            Error::DrmError(_) => 13,
+            Error::DrmMasterHeld { .. } => 14,
+            Error::DrmPermissionDenied { .. } => 15,
+            Error::DrmOpenFailed { .. } => 16,
+            // 17 was RestoreRejected, for a restore test that couldn't fail
+            Error::NoSeatManager { .. } => 18,
+            Error::DisplayServerActive { .. } => 19,
+            Error::DrmDeviceBusy { .. } => 20,
+            Error::DrmDeviceGone { .. } => 21,
+            Error::FbconPermissionDenied { .. } => 22,
+            Error::SeatError(_) => 23,
+            Error::DbusError(_) => 24,
+            Error::PartialFailure { .. } => 25,
+            Error::DryRunNotSupported { .. } => 26,
+            Error::CompositorTimeout(_) => 27,
+            Error::CompositorIpc(_) => 28,
+            Error::IdleNotifyNotSupported => 29,
+            Error::OffTimeoutNotSupported => 30,
+            Error::DpmsTimeoutsNotSupported { .. } => 31,
+            Error::PowerControlBusy { .. } => 32,
+            Error::WaylandSocketInvalid { .. } => 33,
+            Error::AmbiguousWaylandSocket { .. } => 34,
+            Error::ReconnectFailed { .. } => 35,
+            Error::Io(_) => 36,
+            Error::BackendNotBuilt { .. } => 37,
+            Error::RemoteNotInstalled { .. } => 38,
+            Error::SshFailed(_) => 39,
+            Error::PermissionDenied { .. } => 40,
+            Error::NoConnectedDisplay { .. } => 41,
+            Error::NoOutputAdvertised => 42,
+            Error::PerDisplayNotSupported { .. } => 43,
+        }
+    }
+
+    /// Stable snake_case name of this variant, e.g. `no_display_found`
+    ///
+    /// `--json` error objects and `--error-codes` carry it, so scripts can
+    /// tell failures apart without matching messages. Like `number`, every
+    /// variant has its own and codes are never renamed; each one is listed
+    /// in the spec's error code table.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::UnsupportedEnvironment => "unsupported_environment",
+            Error::ProtocolNotSupported => "protocol_not_supported",
+            Error::NoDisplayFound => "no_display_found",
+            Error::DisplayNotFound { .. } => "display_not_found",
+            Error::AmbiguousDisplay { .. } => "ambiguous_display",
+            Error::DaemonStartFailed(_) => "daemon_start_failed",
+            Error::DaemonStopTimeout => "daemon_stop_timeout",
+            Error::DaemonUnkillable { .. } => "daemon_unkillable",
+            Error::DaemonConflict { .. } => "daemon_conflict",
+            Error::SignalError(_) => "signal",
+            Error::ControlError(_) => "control",
+            Error::PidFileError(_) => "pid_file",
+            Error::DrmError(_) => "drm",
+            Error::DrmMasterHeld { .. } => "drm_master_held",
+            Error::DrmPermissionDenied { .. } => "drm_permission",
+            Error::DrmOpenFailed { .. } => "drm_open_failed",
+            Error::NoSeatManager { .. } => "no_seat_manager",
+            Error::DisplayServerActive { .. } => "display_server_active",
+            Error::DrmDeviceBusy { .. } => "drm_device_busy",
+            Error::DrmDeviceGone { .. } => "drm_device_gone",
+            Error::FbconPermissionDenied { .. } => "fbcon_permission",
+            Error::SeatError(_) => "seat",
+            Error::DbusError(_) => "dbus",
+            Error::PartialFailure { .. } => "partial_failure",
+            Error::DryRunNotSupported { .. } => "dry_run_not_supported",
+            Error::CompositorTimeout(_) => "compositor_timeout",
+            Error::CompositorIpc(_) => "compositor_ipc",
+            Error::IdleNotifyNotSupported => "idle_notify_not_supported",
+            Error::OffTimeoutNotSupported => "off_timeout_not_supported",
+            Error::DpmsTimeoutsNotSupported { .. } => "dpms_timeouts_not_supported",
+            Error::PowerControlBusy { .. } => "power_control_busy",
+            Error::WaylandSocketInvalid { .. } => "wayland_socket_invalid",
+            Error::AmbiguousWaylandSocket { .. } => "ambiguous_wayland_socket",
+            Error::ReconnectFailed { .. } => "reconnect_failed",
+            Error::Io(_) => "io",
+            Error::BackendNotBuilt { .. } => "backend_not_built",
+            Error::RemoteNotInstalled { .. } => "remote_not_installed",
+            Error::SshFailed(_) => "ssh_failed",
+            Error::PermissionDenied { .. } => "permission_denied",
+            Error::NoConnectedDisplay { .. } => "no_connected_display",
+            Error::NoOutputAdvertised => "no_output_advertised",
+            Error::PerDisplayNotSupported { .. } => "per_display_not_supported",
+        }
     }
 }
 
@@ -86,6 +544,10 @@ mod tests {
         assert_eq!(ExitCode::Success as i32, 0);
         assert_eq!(ExitCode::Error as i32, 1);
         assert_eq!(ExitCode::Usage as i32, 2);
+        assert_eq!(ExitCode::NoDisplay as i32, 3);
+        assert_eq!(ExitCode::Permission as i32, 4);
+        assert_eq!(ExitCode::Daemon as i32, 5);
+        assert_eq!(ExitCode::Unsupported as i32, 6);
     }
 
     #[test]
@@ -93,10 +555,11 @@ mod tests {
         assert_eq!(i32::from(ExitCode::Success), 0);
         assert_eq!(i32::from(ExitCode::Error), 1);
         assert_eq!(i32::from(ExitCode::Usage), 2);
+        assert_eq!(i32::from(ExitCode::Unsupported), 6);
     }
 
     #[test]
-    fn all_errors_return_error_exit_code() {
+    fn errors_exit_with_their_category() {
         let errors = [
             Error::UnsupportedEnvironment,
             Error::ProtocolNotSupported,
@@ -111,21 +574,140 @@ mod tests {
             },
             Error::DaemonStartFailed("test".to_string()),
             Error::DaemonStopTimeout,
-            Error::SignalError("test".to_string()),
-            Error::PidFileError("test".to_string()),
-            Error::DrmError("test".to_string()),
-            Error::SeatError("test".to_string()),
+            Error::DaemonUnkillable { pid: 4242 },
+            Error::DaemonConflict {
+                holder: "all displays".to_string(),
+            },
+            Error::SignalError("test".into()),
+            Error::ControlError("test".into()),
+            Error::PidFileError("test".into()),
+            Error::DrmError("test".into()),
+            Error::DrmMasterHeld {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DrmPermissionDenied {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DrmOpenFailed {
+                libseat: "test".to_string(),
+                direct: "test".to_string(),
+            },
+            Error::NoSeatManager {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DisplayServerActive {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DrmDeviceBusy {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DrmDeviceGone {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::FbconPermissionDenied {
+                path: "/sys/class/graphics/fb0/blank".to_string(),
+            },
+            Error::SeatError("test".into()),
+            Error::ReconnectFailed { attempts: 5 },
+            Error::BackendNotBuilt {
+                backend: "wayland".to_string(),
+            },
+            Error::RemoteNotInstalled {
+                host: "htpc".to_string(),
+            },
+            Error::SshFailed("test".to_string()),
+            Error::PermissionDenied {
+                resource: "/run/user/1000".to_string(),
+                hint: "XDG_RUNTIME_DIR is not writable".to_string(),
+            },
+            Error::NoConnectedDisplay {
+                device: "/dev/dri/card0".to_string(),
+                connectors: Vec::new(),
+            },
+            Error::NoOutputAdvertised,
+            Error::DbusError("test".into()),
+            Error::CompositorIpc("test".into()),
+            Error::IdleNotifyNotSupported,
+            Error::OffTimeoutNotSupported,
+            Error::PartialFailure {
+                failed: vec![("DP-1".to_string(), "test".to_string())],
+            },
+            Error::DryRunNotSupported {
+                backend: "gnome".to_string(),
+            },
+            Error::DpmsTimeoutsNotSupported {
+                backend: "wayland".to_string(),
+            },
+            Error::PerDisplayNotSupported {
+                backend: "gnome".to_string(),
+            },
+            Error::CompositorTimeout(std::time::Duration::from_secs(5)),
+            Error::PowerControlBusy {
+                name: "DP-1".to_string(),
+                conflicts: Vec::new(),
+            },
+            Error::AmbiguousWaylandSocket {
+                candidates: vec!["wayland-0".to_string(), "wayland-1".to_string()],
+            },
+            Error::WaylandSocketInvalid {
+                path: "/run/user/1000/wayland-1".to_string(),
+                reason: "not a socket".to_string(),
+            },
             Error::Io(std::io::Error::other("test")),
         ];
 
-        for error in errors {
+        for error in &errors {
+            let expected = match error.code() {
+                "no_display_found"
+                | "no_connected_display"
+                | "no_output_advertised"
+                | "display_not_found"
+                | "ambiguous_display" => ExitCode::NoDisplay,
+                "drm_permission"
+                | "drm_open_failed"
+                | "drm_master_held"
+                | "display_server_active"
+                | "drm_device_busy"
+                | "no_seat_manager"
+                | "fbcon_permission"
+                | "seat"
+                | "permission_denied" => ExitCode::Permission,
+                code if code.starts_with("daemon_") => ExitCode::Daemon,
+                "unsupported_environment" | "backend_not_built" => ExitCode::Unsupported,
+                code if code.ends_with("_not_supported") => ExitCode::Unsupported,
+                _ => ExitCode::Error,
+            };
             assert_eq!(
                 error.exit_code(),
-                ExitCode::Error,
-                "Error variant {:?} should return ExitCode::Error",
-                error
+                expected,
+                "Error variant {:?} should return {:?}",
+                error,
+                expected
             );
         }
+
+        // Every variant keeps its own number
+        let mut numbers: Vec<i32> = errors.iter().map(Error::number).collect();
+        numbers.sort();
+        numbers.dedup();
+        assert_eq!(numbers.len(), errors.len());
+
+        // and its own code, documented in the spec
+        let spec = include_str!("../docs/projects/dpms/spec/dpms-spec.md");
+        let mut codes: Vec<&str> = errors.iter().map(Error::code).collect();
+        for error in &errors {
+            let code = error.code();
+            assert!(
+                code.bytes().all(|b| b.is_ascii_lowercase() || b == b'_'),
+                "{} is not snake_case",
+                code
+            );
+            let row = format!("| `{}` | {} |", code, error.number());
+            assert!(spec.contains(&row), "{} is missing from the spec", row);
+        }
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len());
     }
 
     #[test]
@@ -144,10 +726,85 @@ mod tests {
             },
             Error::DaemonStartFailed("test".to_string()),
             Error::DaemonStopTimeout,
-            Error::SignalError("test".to_string()),
-            Error::PidFileError("test".to_string()),
-            Error::DrmError("test".to_string()),
-            Error::SeatError("test".to_string()),
+            Error::DaemonUnkillable { pid: 4242 },
+            Error::DaemonConflict {
+                holder: "all displays".to_string(),
+            },
+            Error::SignalError("test".into()),
+            Error::ControlError("test".into()),
+            Error::PidFileError("test".into()),
+            Error::DrmError("test".into()),
+            Error::DrmMasterHeld {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DrmPermissionDenied {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DrmOpenFailed {
+                libseat: "test".to_string(),
+                direct: "test".to_string(),
+            },
+            Error::NoSeatManager {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DisplayServerActive {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DrmDeviceBusy {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::DrmDeviceGone {
+                path: "/dev/dri/card0".to_string(),
+            },
+            Error::FbconPermissionDenied {
+                path: "/sys/class/graphics/fb0/blank".to_string(),
+            },
+            Error::SeatError("test".into()),
+            Error::ReconnectFailed { attempts: 5 },
+            Error::BackendNotBuilt {
+                backend: "wayland".to_string(),
+            },
+            Error::RemoteNotInstalled {
+                host: "htpc".to_string(),
+            },
+            Error::SshFailed("test".to_string()),
+            Error::PermissionDenied {
+                resource: "/run/user/1000".to_string(),
+                hint: "XDG_RUNTIME_DIR is not writable".to_string(),
+            },
+            Error::NoConnectedDisplay {
+                device: "/dev/dri/card0".to_string(),
+                connectors: Vec::new(),
+            },
+            Error::NoOutputAdvertised,
+            Error::DbusError("test".into()),
+            Error::CompositorIpc("test".into()),
+            Error::IdleNotifyNotSupported,
+            Error::OffTimeoutNotSupported,
+            Error::PartialFailure {
+                failed: vec![("DP-1".to_string(), "test".to_string())],
+            },
+            Error::DryRunNotSupported {
+                backend: "gnome".to_string(),
+            },
+            Error::DpmsTimeoutsNotSupported {
+                backend: "wayland".to_string(),
+            },
+            Error::PerDisplayNotSupported {
+                backend: "gnome".to_string(),
+            },
+            Error::CompositorTimeout(std::time::Duration::from_secs(5)),
+            Error::PowerControlBusy {
+                name: "DP-1".to_string(),
+                conflicts: Vec::new(),
+            },
+            Error::AmbiguousWaylandSocket {
+                candidates: vec!["wayland-0".to_string(), "wayland-1".to_string()],
+            },
+            Error::WaylandSocketInvalid {
+                path: "/run/user/1000/wayland-1".to_string(),
+                reason: "not a socket".to_string(),
+            },
             Error::Io(std::io::Error::other("test")),
         ];
 
@@ -161,6 +818,40 @@ mod tests {
         }
     }
 
+    #[test]
+    fn connector_summary_lists_each_state() {
+        let seen = |name: &str, state: &str| ConnectorSeen {
+            name: name.to_string(),
+            state: state.to_string(),
+        };
+        let connectors = [
+            seen("eDP-1", "disconnected"),
+            seen("HDMI-A-1", "disconnected"),
+            seen("DP-1", "unknown"),
+        ];
+        assert_eq!(
+            connector_summary("/dev/dri/card0", &connectors),
+            "card0: eDP-1=disconnected, HDMI-A-1=disconnected, DP-1=unknown"
+        );
+        assert_eq!(
+            connector_summary("/dev/dri/card1", &[]),
+            "card1: no connectors"
+        );
+
+        let error = Error::NoConnectedDisplay {
+            device: "/dev/dri/card0".to_string(),
+            connectors: vec![seen("eDP-1", "connected (no CRTC)")],
+        };
+        assert_eq!(
+            error.to_string(),
+            "No connected display found (card0: eDP-1=connected (no CRTC))"
+        );
+        assert_eq!(
+            Error::NoOutputAdvertised.to_string(),
+            "No connected display found; the compositor advertised no wl_output"
+        );
+    }
+
     #[test]
     fn display_not_found_error_message() {
         let error = Error::DisplayNotFound {
@@ -172,6 +863,19 @@ mod tests {
         assert!(message.contains("not found"));
         assert!(message.contains("DP-1"));
         assert!(message.contains("eDP-1"));
+        assert_eq!(
+            message,
+            "Display 'HDMI-1' not found. Available:\n  DP-1\n  eDP-1"
+        );
+
+        let error = Error::DisplayNotFound {
+            name: "HDMI-1".to_string(),
+            available: Vec::new(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Display 'HDMI-1' not found. Available: none"
+        );
     }
 
     #[test]
@@ -185,5 +889,109 @@ mod tests {
         assert!(message.contains("ambiguous"));
         assert!(message.contains("DP-1"));
         assert!(message.contains("DP-2"));
+        assert_eq!(
+            message,
+            "Display 'DP' is ambiguous. Did you mean:\n  DP-1\n  DP-2"
+        );
+    }
+
+    #[test]
+    fn reconnect_failed_error_message() {
+        let error = Error::ReconnectFailed { attempts: 5 };
+        let message = error.to_string();
+        assert!(message.contains("reconnect"));
+        assert!(message.contains('5'));
+    }
+
+    #[test]
+    fn context_prints_the_chain_and_keeps_the_source() {
+        use std::error::Error as _;
+
+        let io = std::io::Error::from_raw_os_error(13);
+        let err = Error::DrmError(Context::wrap("Atomic commit failed", io));
+        assert_eq!(
+            err.to_string(),
+            "DRM operation failed: Atomic commit failed: Permission denied (os error 13)"
+        );
+
+        let context = err.source().unwrap();
+        let source = context.source().unwrap();
+        assert_eq!(
+            source
+                .downcast_ref::<std::io::Error>()
+                .and_then(std::io::Error::raw_os_error),
+            Some(13)
+        );
+
+        // Failures dpms finds itself have nothing underneath
+        let err = Error::DrmError("Invalid CRTC id 7".into());
+        assert_eq!(err.to_string(), "DRM operation failed: Invalid CRTC id 7");
+        assert!(err.source().unwrap().source().is_none());
+    }
+
+    #[test]
+    fn result_context_wraps_into_the_variant() {
+        let failed: Result<(), std::io::Error> =
+            Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+        let err = failed
+            .context(Error::PidFileError, "Failed to open PID file")
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::PidFileError(ref context) if context.message() == "Failed to open PID file"
+        ));
+        assert_eq!(
+            err.to_string(),
+            "PID file operation failed: Failed to open PID file: entity not found"
+        );
+
+        let mut built = false;
+        let ok: Result<u8, std::io::Error> = Ok(1);
+        let value = ok
+            .with_context(Error::SignalError, || {
+                built = true;
+                "unused"
+            })
+            .unwrap();
+        assert_eq!(value, 1);
+        assert!(!built, "the message is only built on failure");
+    }
+
+    #[test]
+    fn partial_failure_lists_every_failed_output() {
+        let error = Error::PartialFailure {
+            failed: vec![
+                (
+                    "DP-1".to_string(),
+                    "power control held by another client".to_string(),
+                ),
+                ("HDMI-A-1".to_string(), "DPMS not supported".to_string()),
+            ],
+        };
+        assert_eq!(
+            error.to_string(),
+            "Power change failed for DP-1 (power control held by another client), \
+             HDMI-A-1 (DPMS not supported)"
+        );
+        assert_eq!(error.exit_code(), ExitCode::Error);
+    }
+
+    #[test]
+    fn power_control_busy_lists_conflicts() {
+        let error = Error::PowerControlBusy {
+            name: "DP-1".to_string(),
+            conflicts: vec!["swayidle (pid 1234)".to_string()],
+        };
+        assert!(
+            error
+                .to_string()
+                .contains("(possible conflicting clients: swayidle (pid 1234))")
+        );
+
+        let error = Error::PowerControlBusy {
+            name: "DP-1".to_string(),
+            conflicts: Vec::new(),
+        };
+        assert!(!error.to_string().contains("possible conflicting clients"));
     }
 }
@@ -0,0 +1,451 @@
+/// Framebuffer console blanking
+///
+/// Fallback for the TTY backend on machines without a usable KMS device,
+/// such as some VMs. The console is blanked through the framebuffer's sysfs
+/// `blank` attribute, or with the `TIOCL_BLANKSCREEN` ioctl on `/dev/tty0`
+/// when there is no framebuffer device.
+///
+/// The kernel doesn't report the blank level back on every driver, so the
+/// last level written is also recorded in `$XDG_RUNTIME_DIR/dpms.fbcon`.
+///
+/// The TTY daemon also blanks the console while it holds DRM displays off,
+/// so the console doesn't redraw them (see `ConsoleHold`).
+use crate::backend::PowerBackend;
+use crate::daemon;
+use crate::display::{DisplayInfo, DisplayTarget};
+use crate::error::{Context, Error};
+use crate::output::PowerState;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// sysfs attribute of the first framebuffer device
+const FB_BLANK_PATH: &str = "/sys/class/graphics/fb0/blank";
+
+/// The foreground virtual console
+const CONSOLE_PATH: &str = "/dev/tty0";
+
+/// Kernel parameter holding the console blank timeout, in seconds
+const CONSOLEBLANK_PATH: &str = "/sys/module/kernel/parameters/consoleblank";
+
+/// `FB_BLANK_UNBLANK`: display on
+const FB_BLANK_UNBLANK: u8 = 0;
+
+/// `FB_BLANK_POWERDOWN`: display and backlight off
+const FB_BLANK_POWERDOWN: u8 = 4;
+
+/// `TIOCLINUX` subcode that blanks the console
+const TIOCL_BLANKSCREEN: u8 = 14;
+
+/// `TIOCLINUX` subcode that unblanks the console
+const TIOCL_UNBLANKSCREEN: u8 = 4;
+
+/// `TIOCLINUX` subcode that reports whether the console is blanked
+const TIOCL_BLANKEDSCREEN: u8 = 15;
+
+#[cfg(target_os = "linux")]
+nix::ioctl_write_ptr_bad!(tioclinux, nix::libc::TIOCLINUX, u8);
+
+/// How the console gets blanked
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Blanker {
+    /// Write a `FB_BLANK_*` level to this sysfs `blank` attribute
+    Sysfs(PathBuf),
+    /// `TIOCLINUX` ioctls on this console device
+    Console(PathBuf),
+}
+
+/// Console blanking backend, used when no DRM device is usable
+pub struct FbconBackend {
+    blanker: Blanker,
+    /// Where the last level written is recorded
+    record: PathBuf,
+}
+
+impl FbconBackend {
+    /// Find a way to blank the console on this system
+    ///
+    /// # Returns
+    /// `None` if there is neither a framebuffer device nor a console
+    pub fn detect() -> Option<Self> {
+        let blanker = if Path::new(FB_BLANK_PATH).exists() {
+            Blanker::Sysfs(PathBuf::from(FB_BLANK_PATH))
+        } else if Path::new(CONSOLE_PATH).exists() {
+            Blanker::Console(PathBuf::from(CONSOLE_PATH))
+        } else {
+            return None;
+        };
+        Some(FbconBackend {
+            blanker,
+            record: daemon::runtime_dir().join("dpms.fbcon"),
+        })
+    }
+
+    /// A backend writing the sysfs attribute `blank`, recording to `record`
+    #[cfg(test)]
+    pub fn sysfs(blank: PathBuf, record: PathBuf) -> Self {
+        FbconBackend {
+            blanker: Blanker::Sysfs(blank),
+            record,
+        }
+    }
+
+    /// Name the console is reported under, e.g. `fb0`
+    fn name(&self) -> String {
+        match &self.blanker {
+            // /sys/class/graphics/fb0/blank -> fb0
+            Blanker::Sysfs(path) => path
+                .parent()
+                .and_then(Path::file_name)
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "fb0".to_string()),
+            Blanker::Console(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "tty0".to_string()),
+        }
+    }
+
+    /// Path of the attribute or device that is written to
+    fn path(&self) -> &Path {
+        match &self.blanker {
+            Blanker::Sysfs(path) | Blanker::Console(path) => path,
+        }
+    }
+
+    /// Blank or unblank the console
+    fn blank(&self, state: PowerState) -> Result<(), Error> {
+        match &self.blanker {
+            Blanker::Sysfs(path) => {
+                fs::write(path, blank_level(state).to_string()).map_err(|e| write_error(path, e))
+            }
+            Blanker::Console(path) => {
+                let subcode = match state {
+                    PowerState::On => TIOCL_UNBLANKSCREEN,
+                    PowerState::Off => TIOCL_BLANKSCREEN,
+                };
+                console_ioctl(path, subcode)
+                    .map(|_| ())
+                    .map_err(|e| write_error(path, e))
+            }
+        }
+    }
+
+    /// Read the current blank state
+    ///
+    /// # Returns
+    /// The state the kernel reports, else the last one recorded, else `On`
+    fn read_state(&self) -> PowerState {
+        let reported = match &self.blanker {
+            Blanker::Sysfs(path) => fs::read_to_string(path)
+                .ok()
+                .and_then(|contents| parse_blank(&contents)),
+            Blanker::Console(_) => None,
+        };
+        reported
+            .or_else(|| {
+                fs::read_to_string(&self.record)
+                    .ok()
+                    .and_then(|contents| parse_blank(&contents))
+            })
+            .unwrap_or(PowerState::On)
+    }
+}
+
+/// Keeps the kernel console from lighting displays the daemon holds off
+///
+/// The console is blanked and its blank timer stopped, so neither the
+/// cursor, kernel messages nor the timer running out redraws it. Both are
+/// put back as they were when this is dropped.
+pub struct ConsoleHold {
+    console: PathBuf,
+    /// The blank timeout that was stopped, in seconds
+    blank_timeout: Option<u64>,
+    /// Whether the console was blanked here, rather than already
+    blanked: bool,
+}
+
+impl ConsoleHold {
+    /// Blank the foreground console and stop its blank timer
+    ///
+    /// Without permission, e.g. outside the `tty` group, the console is
+    /// left alone with a warning.
+    pub fn new() -> Self {
+        Self::at(Path::new(CONSOLE_PATH), Path::new(CONSOLEBLANK_PATH))
+    }
+
+    /// Hold the console at `console`, whose timeout is in `consoleblank`
+    fn at(console: &Path, consoleblank: &Path) -> Self {
+        let mut hold = ConsoleHold {
+            console: console.to_path_buf(),
+            blank_timeout: None,
+            blanked: false,
+        };
+        let timeout = fs::read_to_string(consoleblank)
+            .ok()
+            .and_then(|contents| contents.trim().parse::<u64>().ok());
+        if let Some(secs) = timeout.filter(|&secs| secs > 0) {
+            match set_blank_timeout(console, 0) {
+                Ok(()) => hold.blank_timeout = Some(secs),
+                Err(e) => eprintln!("Warning: console blank timer left running: {}", e),
+            }
+        }
+        match blank_console(console) {
+            Ok(blanked) => hold.blanked = blanked,
+            Err(e) => eprintln!("Warning: leaving the console unblanked: {}", e),
+        }
+        hold
+    }
+}
+
+impl Drop for ConsoleHold {
+    fn drop(&mut self) {
+        if self.blanked
+            && let Err(e) = console_ioctl(&self.console, TIOCL_UNBLANKSCREEN)
+        {
+            eprintln!("Failed to unblank the console: {}", e);
+        }
+        if let Some(secs) = self.blank_timeout
+            && let Err(e) = set_blank_timeout(&self.console, secs.div_ceil(60))
+        {
+            eprintln!("Failed to restore the console blank timer: {}", e);
+        }
+    }
+}
+
+/// Set the console blank timeout, in minutes, with the escape sequence
+/// `setterm --blank` uses; 0 stops the timer
+///
+/// `consoleblank` itself is read-only.
+fn set_blank_timeout(console: &Path, minutes: u64) -> io::Result<()> {
+    let mut file = File::options().write(true).open(console)?;
+    write!(file, "\x1b[9;{}]", minutes)
+}
+
+/// Blank the console unless it already is
+///
+/// # Returns
+/// Whether it was blanked here
+fn blank_console(console: &Path) -> io::Result<bool> {
+    if console_ioctl(console, TIOCL_BLANKEDSCREEN)? != 0 {
+        return Ok(false);
+    }
+    console_ioctl(console, TIOCL_BLANKSCREEN)?;
+    Ok(true)
+}
+
+/// Run the `TIOCLINUX` ioctl `subcode` on the console device at `path`
+///
+/// # Returns
+/// What the ioctl returned
+#[cfg(target_os = "linux")]
+fn console_ioctl(path: &Path, subcode: u8) -> io::Result<i32> {
+    use std::os::fd::AsRawFd;
+
+    let console = File::options().write(true).open(path)?;
+    // SAFETY: TIOCLINUX reads a single subcode byte from the pointer
+    unsafe { tioclinux(console.as_raw_fd(), &subcode) }.map_err(io::Error::from)
+}
+
+/// Only Linux has `TIOCLINUX`; FreeBSD's consoles have no `/dev/tty0`, so
+/// this is only reached for a console that was named explicitly
+#[cfg(not(target_os = "linux"))]
+fn console_ioctl(_path: &Path, _subcode: u8) -> io::Result<i32> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// The `FB_BLANK_*` level written for a power state
+fn blank_level(state: PowerState) -> u8 {
+    match state {
+        PowerState::On => FB_BLANK_UNBLANK,
+        PowerState::Off => FB_BLANK_POWERDOWN,
+    }
+}
+
+/// Parse a `FB_BLANK_*` level as read back from sysfs
+///
+/// # Returns
+/// `None` if the contents are empty or not a level
+fn parse_blank(contents: &str) -> Option<PowerState> {
+    match contents.trim().parse::<u8>().ok()? {
+        FB_BLANK_UNBLANK => Some(PowerState::On),
+        1..=FB_BLANK_POWERDOWN => Some(PowerState::Off),
+        _ => None,
+    }
+}
+
+/// Map a failure to write `path` to an error, with a hint for permissions
+fn write_error(path: &Path, err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => Error::FbconPermissionDenied {
+            path: path.display().to_string(),
+        },
+        _ => Error::Io(io::Error::new(
+            err.kind(),
+            Context::wrap(
+                format!("Failed to blank console via {}", path.display()),
+                err,
+            ),
+        )),
+    }
+}
+
+impl PowerBackend for FbconBackend {
+    fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
+        if let DisplayTarget::Named(name) = target
+            && *name != self.name()
+        {
+            return Err(Error::DisplayNotFound {
+                name: name.clone(),
+                available: vec![self.name()],
+            });
+        }
+
+        self.blank(state)?;
+        // Only a hint for reading the state back, so failing to record is fine
+        let _ = fs::write(&self.record, blank_level(state).to_string());
+        Ok(())
+    }
+
+    fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+        if let DisplayTarget::Named(name) = target
+            && *name != self.name()
+        {
+            return Err(Error::DisplayNotFound {
+                name: name.clone(),
+                available: vec![self.name()],
+            });
+        }
+
+        Ok(vec![DisplayInfo {
+            name: self.name(),
+            power: self.read_state(),
+            description: Some(format!("Console blanked via {}", self.path().display())),
+            make: None,
+            model: None,
+            drm: None,
+        }])
+    }
+
+    fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
+        self.get_power(&DisplayTarget::All)
+    }
+
+    fn name(&self) -> &'static str {
+        "fbcon"
+    }
+
+    fn describe(&self) -> String {
+        format!("fbcon (console blanking via {})", self.path().display())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A sysfs-style backend over files in a fresh directory under /tmp
+    fn sysfs_backend(dir: &str) -> FbconBackend {
+        let dir = Path::new("/tmp").join(dir);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("fb0")).unwrap();
+        fs::write(dir.join("fb0/blank"), "").unwrap();
+        FbconBackend::sysfs(dir.join("fb0/blank"), dir.join("dpms.fbcon"))
+    }
+
+    #[test]
+    fn parse_blank_levels() {
+        assert_eq!(parse_blank("0\n"), Some(PowerState::On));
+        assert_eq!(parse_blank("1"), Some(PowerState::Off));
+        assert_eq!(parse_blank("4\n"), Some(PowerState::Off));
+        assert_eq!(parse_blank(""), None);
+        assert_eq!(parse_blank("7"), None);
+    }
+
+    #[test]
+    fn set_power_writes_blank_levels() {
+        let mut backend = sysfs_backend("dpms-test-fbcon-write");
+
+        backend
+            .set_power(&DisplayTarget::Default, PowerState::Off)
+            .unwrap();
+        assert_eq!(fs::read_to_string(backend.path()).unwrap(), "4");
+        let displays = backend.get_power(&DisplayTarget::Default).unwrap();
+        assert_eq!(displays[0].name, "fb0");
+        assert_eq!(displays[0].power, PowerState::Off);
+
+        backend
+            .set_power(&DisplayTarget::Named("fb0".to_string()), PowerState::On)
+            .unwrap();
+        assert_eq!(fs::read_to_string(backend.path()).unwrap(), "0");
+        let displays = backend.get_power(&DisplayTarget::All).unwrap();
+        assert_eq!(displays[0].power, PowerState::On);
+    }
+
+    #[test]
+    fn get_power_falls_back_to_recorded_level() {
+        // Most drivers read the blank attribute back as empty
+        let backend = sysfs_backend("dpms-test-fbcon-record");
+        assert_eq!(backend.read_state(), PowerState::On);
+
+        fs::write(&backend.record, "4").unwrap();
+        assert_eq!(backend.read_state(), PowerState::Off);
+    }
+
+    #[test]
+    fn other_display_names_are_not_found() {
+        let mut backend = sysfs_backend("dpms-test-fbcon-name");
+        let target = DisplayTarget::Named("HDMI-A-1".to_string());
+
+        let err = backend.set_power(&target, PowerState::Off).unwrap_err();
+        assert!(
+            matches!(err, Error::DisplayNotFound { ref available, .. } if available == &["fb0"])
+        );
+        assert!(backend.get_power(&target).is_err());
+    }
+
+    #[test]
+    fn console_hold_stops_and_restores_the_blank_timer() {
+        let dir = Path::new("/tmp/dpms-test-console-hold");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let console = dir.join("tty0");
+        let consoleblank = dir.join("consoleblank");
+        fs::write(&console, "").unwrap();
+        fs::write(&consoleblank, "600\n").unwrap();
+
+        // A plain file takes the escape sequences but not the ioctls
+        let hold = ConsoleHold::at(&console, &consoleblank);
+        assert_eq!(fs::read_to_string(&console).unwrap(), "\x1b[9;0]");
+        assert!(!hold.blanked);
+        fs::write(&console, "").unwrap();
+        drop(hold);
+        assert_eq!(fs::read_to_string(&console).unwrap(), "\x1b[9;10]");
+
+        // A timer that's already off is left alone
+        fs::write(&console, "").unwrap();
+        fs::write(&consoleblank, "0\n").unwrap();
+        drop(ConsoleHold::at(&console, &consoleblank));
+        assert_eq!(fs::read_to_string(&console).unwrap(), "");
+    }
+
+    #[test]
+    fn console_hold_without_a_console_changes_nothing() {
+        let hold = ConsoleHold::at(
+            Path::new("/tmp/dpms-test-no-such-tty"),
+            Path::new("/tmp/dpms-test-no-such-consoleblank"),
+        );
+        assert_eq!((hold.blank_timeout, hold.blanked), (None, false));
+    }
+
+    #[test]
+    fn write_error_hints_at_permissions() {
+        let path = Path::new(FB_BLANK_PATH);
+        let err = write_error(path, io::Error::from(io::ErrorKind::PermissionDenied));
+        assert!(matches!(err, Error::FbconPermissionDenied { .. }));
+        assert!(err.to_string().contains(FB_BLANK_PATH));
+
+        let err = write_error(path, io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(err, Error::Io(_)));
+    }
+}
@@ -0,0 +1,450 @@
+/// C interface, built into the cdylib with the `ffi` feature
+///
+/// Each call picks a backend the way `dpms` does without options, going
+/// through a running `dpms serve` first, and drops it again before
+/// returning. Calls return `DPMS_OK` or a `DPMS_ERR_*` code, one per
+/// `Error` variant; `dpms_strerror` describes them. `include/dpms.h`
+/// declares all of this for C and is regenerated with
+/// `cbindgen --config cbindgen.toml --output include/dpms.h`.
+///
+/// Holding displays off on the console takes the daemon, which runs from
+/// the `dpms` binary found in `PATH`, as the calling program isn't dpms.
+use std::ffi::{CStr, c_char, c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+#[cfg(feature = "tty")]
+use std::sync::Once;
+
+use crate::backend::PowerBackend;
+use crate::cli::Options;
+#[cfg(feature = "dbus")]
+use crate::client::ServiceBackend;
+#[cfg(feature = "tty")]
+use crate::daemon;
+use crate::display::{DisplayInfo, DisplayTarget};
+use crate::error::Error;
+use crate::output::PowerState;
+use crate::select;
+
+/// The call succeeded
+pub const DPMS_OK: c_int = 0;
+/// A pointer argument was NULL, or a string not UTF-8
+pub const DPMS_ERR_INVALID_ARGUMENT: c_int = -1;
+/// dpms panicked; the displays were left as they were
+pub const DPMS_ERR_PANIC: c_int = -2;
+pub const DPMS_ERR_UNSUPPORTED_ENVIRONMENT: c_int = 1;
+pub const DPMS_ERR_PROTOCOL_NOT_SUPPORTED: c_int = 2;
+pub const DPMS_ERR_NO_DISPLAY_FOUND: c_int = 3;
+pub const DPMS_ERR_DISPLAY_NOT_FOUND: c_int = 4;
+pub const DPMS_ERR_AMBIGUOUS_DISPLAY: c_int = 5;
+pub const DPMS_ERR_DAEMON_START_FAILED: c_int = 6;
+pub const DPMS_ERR_DAEMON_STOP_TIMEOUT: c_int = 7;
+pub const DPMS_ERR_DAEMON_UNKILLABLE: c_int = 8;
+pub const DPMS_ERR_DAEMON_CONFLICT: c_int = 9;
+pub const DPMS_ERR_SIGNAL: c_int = 10;
+pub const DPMS_ERR_CONTROL: c_int = 11;
+pub const DPMS_ERR_PID_FILE: c_int = 12;
+pub const DPMS_ERR_DRM: c_int = 13;
+pub const DPMS_ERR_DRM_MASTER_HELD: c_int = 14;
+pub const DPMS_ERR_DRM_PERMISSION_DENIED: c_int = 15;
+pub const DPMS_ERR_DRM_OPEN_FAILED: c_int = 16;
+// 17 is retired; it was DPMS_ERR_RESTORE_REJECTED
+pub const DPMS_ERR_NO_SEAT_MANAGER: c_int = 18;
+pub const DPMS_ERR_DISPLAY_SERVER_ACTIVE: c_int = 19;
+pub const DPMS_ERR_DRM_DEVICE_BUSY: c_int = 20;
+pub const DPMS_ERR_DRM_DEVICE_GONE: c_int = 21;
+pub const DPMS_ERR_FBCON_PERMISSION_DENIED: c_int = 22;
+pub const DPMS_ERR_SEAT: c_int = 23;
+pub const DPMS_ERR_DBUS: c_int = 24;
+pub const DPMS_ERR_PARTIAL_FAILURE: c_int = 25;
+pub const DPMS_ERR_DRY_RUN_NOT_SUPPORTED: c_int = 26;
+pub const DPMS_ERR_COMPOSITOR_TIMEOUT: c_int = 27;
+pub const DPMS_ERR_COMPOSITOR_IPC: c_int = 28;
+pub const DPMS_ERR_IDLE_NOTIFY_NOT_SUPPORTED: c_int = 29;
+pub const DPMS_ERR_OFF_TIMEOUT_NOT_SUPPORTED: c_int = 30;
+pub const DPMS_ERR_DPMS_TIMEOUTS_NOT_SUPPORTED: c_int = 31;
+pub const DPMS_ERR_POWER_CONTROL_BUSY: c_int = 32;
+pub const DPMS_ERR_WAYLAND_SOCKET_INVALID: c_int = 33;
+pub const DPMS_ERR_AMBIGUOUS_WAYLAND_SOCKET: c_int = 34;
+pub const DPMS_ERR_RECONNECT_FAILED: c_int = 35;
+pub const DPMS_ERR_IO: c_int = 36;
+pub const DPMS_ERR_BACKEND_NOT_BUILT: c_int = 37;
+pub const DPMS_ERR_REMOTE_NOT_INSTALLED: c_int = 38;
+pub const DPMS_ERR_SSH_FAILED: c_int = 39;
+pub const DPMS_ERR_PERMISSION_DENIED: c_int = 40;
+pub const DPMS_ERR_NO_CONNECTED_DISPLAY: c_int = 41;
+pub const DPMS_ERR_NO_OUTPUT_ADVERTISED: c_int = 42;
+pub const DPMS_ERR_PER_DISPLAY_NOT_SUPPORTED: c_int = 43;
+
+/// What `dpms_strerror` answers for each code
+const MESSAGES: &[(c_int, &CStr)] = &[
+    (DPMS_OK, c"success"),
+    (DPMS_ERR_INVALID_ARGUMENT, c"invalid argument"),
+    (DPMS_ERR_PANIC, c"internal error"),
+    (
+        DPMS_ERR_UNSUPPORTED_ENVIRONMENT,
+        c"neither Wayland nor TTY environment available",
+    ),
+    (
+        DPMS_ERR_PROTOCOL_NOT_SUPPORTED,
+        c"compositor does not support power management protocol",
+    ),
+    (DPMS_ERR_NO_DISPLAY_FOUND, c"no connected display found"),
+    (DPMS_ERR_DISPLAY_NOT_FOUND, c"display not found"),
+    (DPMS_ERR_AMBIGUOUS_DISPLAY, c"display name is ambiguous"),
+    (DPMS_ERR_DAEMON_START_FAILED, c"daemon failed to start"),
+    (DPMS_ERR_DAEMON_STOP_TIMEOUT, c"daemon did not stop in time"),
+    (DPMS_ERR_DAEMON_UNKILLABLE, c"daemon survived SIGKILL"),
+    (
+        DPMS_ERR_DAEMON_CONFLICT,
+        c"another daemon holds the card; turn it on first",
+    ),
+    (DPMS_ERR_SIGNAL, c"signal operation failed"),
+    (DPMS_ERR_CONTROL, c"daemon control request failed"),
+    (DPMS_ERR_PID_FILE, c"PID file operation failed"),
+    (DPMS_ERR_DRM, c"DRM operation failed"),
+    (
+        DPMS_ERR_DRM_MASTER_HELD,
+        c"DRM master is held by another process",
+    ),
+    (
+        DPMS_ERR_DRM_PERMISSION_DENIED,
+        c"permission denied opening the DRM device",
+    ),
+    (DPMS_ERR_DRM_OPEN_FAILED, c"could not open a DRM device"),
+    (
+        DPMS_ERR_NO_SEAT_MANAGER,
+        c"permission denied and no seat manager found",
+    ),
+    (
+        DPMS_ERR_DISPLAY_SERVER_ACTIVE,
+        c"a compositor or X server is using the DRM device",
+    ),
+    (DPMS_ERR_DRM_DEVICE_BUSY, c"DRM device is busy"),
+    (DPMS_ERR_DRM_DEVICE_GONE, c"DRM device disappeared"),
+    (
+        DPMS_ERR_FBCON_PERMISSION_DENIED,
+        c"blanking the console requires root",
+    ),
+    (DPMS_ERR_SEAT, c"libseat operation failed"),
+    (DPMS_ERR_DBUS, c"D-Bus operation failed"),
+    (
+        DPMS_ERR_PARTIAL_FAILURE,
+        c"power change failed for some displays",
+    ),
+    (
+        DPMS_ERR_DRY_RUN_NOT_SUPPORTED,
+        c"dry runs are not supported by the backend",
+    ),
+    (
+        DPMS_ERR_COMPOSITOR_TIMEOUT,
+        c"compositor did not respond in time",
+    ),
+    (DPMS_ERR_COMPOSITOR_IPC, c"compositor IPC failed"),
+    (
+        DPMS_ERR_IDLE_NOTIFY_NOT_SUPPORTED,
+        c"idle detection is not supported by the compositor",
+    ),
+    (
+        DPMS_ERR_OFF_TIMEOUT_NOT_SUPPORTED,
+        c"timed power off needs the TTY daemon",
+    ),
+    (
+        DPMS_ERR_DPMS_TIMEOUTS_NOT_SUPPORTED,
+        c"DPMS timeouts are not supported by the backend",
+    ),
+    (
+        DPMS_ERR_POWER_CONTROL_BUSY,
+        c"power control is held by another client",
+    ),
+    (
+        DPMS_ERR_WAYLAND_SOCKET_INVALID,
+        c"Wayland socket is not usable",
+    ),
+    (
+        DPMS_ERR_AMBIGUOUS_WAYLAND_SOCKET,
+        c"multiple Wayland sockets found",
+    ),
+    (
+        DPMS_ERR_RECONNECT_FAILED,
+        c"lost connection to the compositor",
+    ),
+    (DPMS_ERR_IO, c"I/O error"),
+    (
+        DPMS_ERR_BACKEND_NOT_BUILT,
+        c"backend not built into this library",
+    ),
+    (
+        DPMS_ERR_REMOTE_NOT_INSTALLED,
+        c"dpms not installed on the remote host",
+    ),
+    (DPMS_ERR_SSH_FAILED, c"SSH failed"),
+    (DPMS_ERR_PERMISSION_DENIED, c"permission denied"),
+    (
+        DPMS_ERR_NO_CONNECTED_DISPLAY,
+        c"no connector on the DRM device is connected",
+    ),
+    (
+        DPMS_ERR_NO_OUTPUT_ADVERTISED,
+        c"the compositor advertised no outputs",
+    ),
+    (
+        DPMS_ERR_PER_DISPLAY_NOT_SUPPORTED,
+        c"the backend only switches all displays at once",
+    ),
+];
+
+/// The code reported for `error`
+///
+/// The same as `Error::number`; the `DPMS_ERR_*` constants name them for C.
+fn error_code(error: &Error) -> c_int {
+    error.number()
+}
+
+/// Called by `dpms_list_displays` for each display, with whether it is on
+pub type DpmsDisplayCallback =
+    Option<unsafe extern "C" fn(name: *const c_char, on: c_int, user_data: *mut c_void)>;
+
+/// Start the console daemon from the installed `dpms`, once per process
+#[cfg(feature = "tty")]
+fn use_installed_daemon() {
+    static FOUND: Once = Once::new();
+    FOUND.call_once(|| {
+        let installed = std::env::var_os("PATH").and_then(|path| {
+            std::env::split_paths(&path)
+                .map(|dir| dir.join("dpms"))
+                .find(|exe| exe.is_file())
+        });
+        if let Some(exe) = installed {
+            daemon::set_daemon_executable(exe);
+        }
+    });
+}
+
+/// Run `f` with the backend `dpms` would pick without options
+fn with_backend<R>(f: impl FnOnce(&mut dyn PowerBackend) -> Result<R, Error>) -> Result<R, Error> {
+    #[cfg(feature = "dbus")]
+    if let Some(mut backend) = ServiceBackend::connect() {
+        return f(&mut backend);
+    }
+
+    #[cfg(feature = "tty")]
+    use_installed_daemon();
+    f(select::backend(&Options::default())?.as_mut())
+}
+
+/// Run `f`, turning its outcome or a panic into a code
+fn guarded(f: impl FnOnce() -> Result<(), Error>) -> c_int {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(())) => DPMS_OK,
+        Ok(Err(e)) => error_code(&e),
+        Err(_) => DPMS_ERR_PANIC,
+    }
+}
+
+/// The display a C string names; NULL or empty means the default displays
+///
+/// # Safety
+/// `display` must be NULL or point to a NUL-terminated string.
+unsafe fn parse_target(display: *const c_char) -> Option<DisplayTarget> {
+    if display.is_null() {
+        return Some(DisplayTarget::Default);
+    }
+    // SAFETY: the caller passes a NUL-terminated string
+    match unsafe { CStr::from_ptr(display) }.to_str().ok()? {
+        "" => Some(DisplayTarget::Default),
+        name => Some(DisplayTarget::Named(name.to_string())),
+    }
+}
+
+/// Turn `display` on if `on` is non-zero, else off
+///
+/// # Safety
+/// `display` must be NULL, for the default displays, or point to a
+/// NUL-terminated display name such as `DP-1`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dpms_set_power(display: *const c_char, on: c_int) -> c_int {
+    // SAFETY: passed on from the caller
+    let Some(target) = (unsafe { parse_target(display) }) else {
+        return DPMS_ERR_INVALID_ARGUMENT;
+    };
+    let state = if on != 0 {
+        PowerState::On
+    } else {
+        PowerState::Off
+    };
+    guarded(|| with_backend(|backend| backend.set_power(&target, state)))
+}
+
+/// Store 1 in `*out` if any display `display` names is on, else 0
+///
+/// # Safety
+/// `display` must be NULL or point to a NUL-terminated string, and `out`
+/// must point to a writable `int`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dpms_get_power(display: *const c_char, out: *mut c_int) -> c_int {
+    // SAFETY: passed on from the caller
+    let Some(target) = (unsafe { parse_target(display) }) else {
+        return DPMS_ERR_INVALID_ARGUMENT;
+    };
+    if out.is_null() {
+        return DPMS_ERR_INVALID_ARGUMENT;
+    }
+    guarded(|| {
+        let displays = with_backend(|backend| backend.get_power(&target))?;
+        if displays.is_empty() {
+            return Err(Error::NoDisplayFound);
+        }
+        let on = displays.iter().any(|d| d.power == PowerState::On);
+        // SAFETY: checked for NULL above; the caller vouches for the rest
+        unsafe { *out = c_int::from(on) };
+        Ok(())
+    })
+}
+
+/// Call `callback` with each connected display's name and whether it is on
+///
+/// The name is only valid during the call.
+///
+/// # Safety
+/// `callback` must be safe to call with `user_data`, which is passed along
+/// untouched.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn dpms_list_displays(
+    callback: DpmsDisplayCallback,
+    user_data: *mut c_void,
+) -> c_int {
+    let Some(callback) = callback else {
+        return DPMS_ERR_INVALID_ARGUMENT;
+    };
+    guarded(|| {
+        let displays: Vec<DisplayInfo> = with_backend(|backend| backend.list_displays())?;
+        for display in displays {
+            // Names with a NUL in them can't be passed on
+            let Ok(name) = std::ffi::CString::new(display.name) else {
+                continue;
+            };
+            let on = c_int::from(display.power == PowerState::On);
+            // SAFETY: the caller vouches for the callback and its data
+            unsafe { callback(name.as_ptr(), on, user_data) };
+        }
+        Ok(())
+    })
+}
+
+/// Describe `code`, as returned by the other calls
+///
+/// The string is static and must not be freed.
+#[unsafe(no_mangle)]
+pub extern "C" fn dpms_strerror(code: c_int) -> *const c_char {
+    MESSAGES
+        .iter()
+        .find(|(known, _)| *known == code)
+        .map_or(c"unknown error", |(_, message)| message)
+        .as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_code_has_its_own_message() {
+        // Codes of removed errors, never reused
+        const RETIRED: &[c_int] = &[17];
+
+        let mut codes: Vec<c_int> = MESSAGES.iter().map(|(code, _)| *code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), MESSAGES.len());
+        assert_eq!(
+            codes,
+            (DPMS_ERR_PANIC..=DPMS_ERR_PER_DISPLAY_NOT_SUPPORTED)
+                .filter(|code| !RETIRED.contains(code))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn errors_map_to_their_codes() {
+        assert_eq!(
+            error_code(&Error::NoDisplayFound),
+            DPMS_ERR_NO_DISPLAY_FOUND
+        );
+        assert_eq!(
+            error_code(&Error::DisplayNotFound {
+                name: "HDMI-1".to_string(),
+                available: Vec::new(),
+            }),
+            DPMS_ERR_DISPLAY_NOT_FOUND
+        );
+        assert_eq!(
+            error_code(&Error::Io(std::io::Error::other("test"))),
+            DPMS_ERR_IO
+        );
+    }
+
+    #[test]
+    fn strerror_describes_codes() {
+        let message = |code| {
+            unsafe { CStr::from_ptr(dpms_strerror(code)) }
+                .to_str()
+                .unwrap()
+        };
+        assert_eq!(message(DPMS_OK), "success");
+        assert_eq!(
+            message(DPMS_ERR_NO_DISPLAY_FOUND),
+            "no connected display found"
+        );
+        assert_eq!(message(9999), "unknown error");
+    }
+
+    #[test]
+    fn bad_arguments_are_refused_before_touching_displays() {
+        let name = [0xffu8 as c_char, 0];
+        let mut on = 0;
+        unsafe {
+            assert_eq!(dpms_set_power(name.as_ptr(), 0), DPMS_ERR_INVALID_ARGUMENT);
+            assert_eq!(
+                dpms_get_power(std::ptr::null(), std::ptr::null_mut()),
+                DPMS_ERR_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                dpms_get_power(name.as_ptr(), &mut on),
+                DPMS_ERR_INVALID_ARGUMENT
+            );
+            assert_eq!(
+                dpms_list_displays(None, std::ptr::null_mut()),
+                DPMS_ERR_INVALID_ARGUMENT
+            );
+        }
+    }
+
+    #[test]
+    fn header_declares_every_code() {
+        let header =
+            std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/include/dpms.h"))
+                .unwrap();
+        let defines: Vec<&str> = header
+            .lines()
+            .filter(|line| line.starts_with("#define DPMS_") && line.split(' ').count() == 3)
+            .collect();
+        assert_eq!(defines.len(), MESSAGES.len());
+        for (code, _) in MESSAGES {
+            assert!(
+                defines
+                    .iter()
+                    .any(|line| line.ends_with(&format!(" {}", code))),
+                "include/dpms.h has no code {}",
+                code
+            );
+        }
+        for function in [
+            "dpms_set_power",
+            "dpms_get_power",
+            "dpms_list_displays",
+            "dpms_strerror",
+        ] {
+            assert!(header.contains(&format!("{}(", function)));
+        }
+    }
+}
@@ -0,0 +1,312 @@
+/// GNOME backend for monitor power control
+///
+/// Mutter implements neither the wlr nor the KWin power management protocol.
+/// This module implements the PowerBackend trait on top of the
+/// `org.gnome.Mutter.DisplayConfig` D-Bus interface instead, which exposes a
+/// single `PowerSaveMode` property covering all monitors. A `--display`
+/// naming one of several monitors is therefore refused rather than widened.
+///
+/// Selected from `run()` when the Wayland protocol probe fails and
+/// `XDG_CURRENT_DESKTOP` includes GNOME.
+use std::collections::HashMap;
+
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedValue;
+
+use crate::backend::PowerBackend;
+use crate::display::{DisplayInfo, DisplayTarget, find_display_by_name};
+use crate::error::{Error, ResultExt};
+use crate::output::PowerState;
+
+const DISPLAY_CONFIG_DEST: &str = "org.gnome.Mutter.DisplayConfig";
+const DISPLAY_CONFIG_PATH: &str = "/org/gnome/Mutter/DisplayConfig";
+const DISPLAY_CONFIG_IFACE: &str = "org.gnome.Mutter.DisplayConfig";
+
+/// `PowerSaveMode` values (DPMS levels; -1 means unknown)
+const POWER_SAVE_ON: i32 = 0;
+const POWER_SAVE_OFF: i32 = 3;
+
+/// Monitor identity: (connector, vendor, product, serial)
+type MonitorSpec = (String, String, String, String);
+
+/// Monitor mode: (id, width, height, refresh rate, preferred scale, supported scales, properties)
+type MonitorMode = (
+    String,
+    i32,
+    i32,
+    f64,
+    f64,
+    Vec<f64>,
+    HashMap<String, OwnedValue>,
+);
+
+/// Physical monitor: (spec, modes, properties)
+type Monitor = (MonitorSpec, Vec<MonitorMode>, HashMap<String, OwnedValue>);
+
+/// Logical monitor: (x, y, scale, transform, primary, monitors, properties)
+type LogicalMonitor = (
+    i32,
+    i32,
+    f64,
+    u32,
+    bool,
+    Vec<MonitorSpec>,
+    HashMap<String, OwnedValue>,
+);
+
+/// Reply of `GetCurrentState`: (serial, monitors, logical monitors, properties)
+type CurrentState = (
+    u32,
+    Vec<Monitor>,
+    Vec<LogicalMonitor>,
+    HashMap<String, OwnedValue>,
+);
+
+/// Convert a `PowerSaveMode` value to PowerState
+///
+/// Standby and suspend count as Off; unknown (-1) is reported as On.
+fn power_from_mode(mode: i32) -> PowerState {
+    if mode > POWER_SAVE_ON {
+        PowerState::Off
+    } else {
+        PowerState::On
+    }
+}
+
+/// Convert a PowerState to the `PowerSaveMode` value to request
+fn mode_from_power(state: PowerState) -> i32 {
+    match state {
+        PowerState::On => POWER_SAVE_ON,
+        PowerState::Off => POWER_SAVE_OFF,
+    }
+}
+
+/// Build the DisplayInfo for a monitor reported by `GetCurrentState`
+fn monitor_display_info(monitor: &Monitor, power: PowerState) -> DisplayInfo {
+    let ((connector, vendor, product, _serial), _modes, properties) = monitor;
+
+    let non_empty = |s: &String| (!s.is_empty()).then(|| s.clone());
+    let description = properties
+        .get("display-name")
+        .and_then(|v| String::try_from(v.clone()).ok());
+
+    DisplayInfo {
+        name: connector.clone(),
+        power,
+        description,
+        make: non_empty(vendor),
+        model: non_empty(product),
+        drm: None,
+    }
+}
+
+/// The monitors `target` selects, matched by connector name
+///
+/// # Returns
+/// - `Ok(monitors)` - The named monitor, or all of them
+/// - `Err(Error::DisplayNotFound)` - No monitor has the name
+/// - `Err(Error::AmbiguousDisplay)` - Several monitors start with the name
+fn target_monitors<'a>(
+    monitors: &'a [Monitor],
+    target: &DisplayTarget,
+) -> Result<Vec<&'a Monitor>, Error> {
+    match target {
+        DisplayTarget::Named(name) => Ok(vec![find_display_by_name(
+            monitors,
+            name,
+            |((connector, ..), ..)| Some(connector.as_str()),
+        )?]),
+        DisplayTarget::All | DisplayTarget::Default => Ok(monitors.iter().collect()),
+    }
+}
+
+/// Check that `target` covers every monitor, as `PowerSaveMode` switches them all
+///
+/// # Returns
+/// - `Ok(())` - Switching all monitors is what `target` asks for
+/// - `Err(Error::PerDisplayNotSupported)` - `target` names one of several monitors
+/// - `Err(Error)` - `target` names no monitor, see `target_monitors`
+fn check_whole_desktop(monitors: &[Monitor], target: &DisplayTarget) -> Result<(), Error> {
+    if target_monitors(monitors, target)?.len() < monitors.len() {
+        return Err(Error::PerDisplayNotSupported {
+            backend: "gnome".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// GNOME backend implementing PowerBackend trait
+pub struct GnomeBackend {
+    proxy: Proxy<'static>,
+}
+
+impl GnomeBackend {
+    /// Create a new GNOME backend by connecting to the session bus
+    ///
+    /// # Returns
+    /// - `Ok(GnomeBackend)` if Mutter's DisplayConfig interface is reachable
+    /// - `Err(Error::DbusError)` if the session bus or Mutter is unavailable
+    pub fn new() -> Result<Self, Error> {
+        let connection = Connection::session()
+            .context(Error::DbusError, "Failed to connect to the session bus")?;
+        let proxy = Proxy::new_owned(
+            connection,
+            DISPLAY_CONFIG_DEST,
+            DISPLAY_CONFIG_PATH,
+            DISPLAY_CONFIG_IFACE,
+        )
+        .context(Error::DbusError, "Failed to reach Mutter's DisplayConfig")?;
+
+        let backend = Self { proxy };
+
+        // Fail early if Mutter isn't actually there
+        backend.power_save_mode()?;
+
+        Ok(backend)
+    }
+
+    /// Read the current `PowerSaveMode` property
+    fn power_save_mode(&self) -> Result<i32, Error> {
+        self.proxy
+            .get_property::<i32>("PowerSaveMode")
+            .context(Error::DbusError, "Failed to read PowerSaveMode")
+    }
+
+    /// The physical monitors from `GetCurrentState`
+    fn monitors(&self) -> Result<Vec<Monitor>, Error> {
+        let (_serial, monitors, _logical, _props): CurrentState = self
+            .proxy
+            .call("GetCurrentState", &())
+            .context(Error::DbusError, "GetCurrentState failed")?;
+        Ok(monitors)
+    }
+}
+
+impl PowerBackend for GnomeBackend {
+    fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
+        check_whole_desktop(&self.monitors()?, target)?;
+
+        self.proxy
+            .set_property("PowerSaveMode", mode_from_power(state))
+            .context(Error::DbusError, "Failed to set PowerSaveMode")
+    }
+
+    fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+        let power = power_from_mode(self.power_save_mode()?);
+        let monitors = self.monitors()?;
+
+        Ok(target_monitors(&monitors, target)?
+            .into_iter()
+            .map(|monitor| monitor_display_info(monitor, power))
+            .collect())
+    }
+
+    fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
+        self.get_power(&DisplayTarget::All)
+    }
+
+    fn name(&self) -> &'static str {
+        "gnome"
+    }
+
+    fn describe(&self) -> String {
+        format!("gnome ({})", DISPLAY_CONFIG_IFACE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_monitor(connector: &str, vendor: &str, product: &str) -> Monitor {
+        (
+            (
+                connector.to_string(),
+                vendor.to_string(),
+                product.to_string(),
+                "0x0001".to_string(),
+            ),
+            Vec::new(),
+            HashMap::new(),
+        )
+    }
+
+    #[test]
+    fn named_target_must_be_a_monitor() {
+        let monitors = vec![
+            make_monitor("eDP-1", "BOE", "0x0747"),
+            make_monitor("DP-1", "DEL", "DELL U2720Q"),
+        ];
+
+        let named = DisplayTarget::Named("DP".to_string());
+        let found = target_monitors(&monitors, &named).unwrap();
+        assert_eq!(found, vec![&monitors[1]]);
+        assert_eq!(
+            target_monitors(&monitors, &DisplayTarget::All)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let missing = DisplayTarget::Named("DP-9".to_string());
+        assert!(matches!(
+            target_monitors(&monitors, &missing),
+            Err(Error::DisplayNotFound { ref name, .. }) if name == "DP-9"
+        ));
+        assert!(matches!(
+            check_whole_desktop(&monitors, &missing),
+            Err(Error::DisplayNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn set_power_refuses_one_of_several_monitors() {
+        let monitors = vec![
+            make_monitor("eDP-1", "BOE", "0x0747"),
+            make_monitor("DP-1", "DEL", "DELL U2720Q"),
+        ];
+
+        let named = DisplayTarget::Named("DP-1".to_string());
+        assert!(matches!(
+            check_whole_desktop(&monitors, &named),
+            Err(Error::PerDisplayNotSupported { ref backend }) if backend == "gnome"
+        ));
+        assert!(check_whole_desktop(&monitors, &DisplayTarget::Default).is_ok());
+        // With one monitor, naming it switches nothing else
+        assert!(check_whole_desktop(&monitors[1..], &named).is_ok());
+    }
+
+    #[test]
+    fn power_from_mode_values() {
+        assert_eq!(power_from_mode(-1), PowerState::On);
+        assert_eq!(power_from_mode(0), PowerState::On);
+        assert_eq!(power_from_mode(1), PowerState::Off);
+        assert_eq!(power_from_mode(2), PowerState::Off);
+        assert_eq!(power_from_mode(3), PowerState::Off);
+    }
+
+    #[test]
+    fn mode_from_power_values() {
+        assert_eq!(mode_from_power(PowerState::On), 0);
+        assert_eq!(mode_from_power(PowerState::Off), 3);
+    }
+
+    #[test]
+    fn monitor_display_info_fields() {
+        let monitor = make_monitor("DP-1", "DEL", "DELL U2720Q");
+        let info = monitor_display_info(&monitor, PowerState::Off);
+        assert_eq!(info.name, "DP-1");
+        assert_eq!(info.power, PowerState::Off);
+        assert_eq!(info.make, Some("DEL".to_string()));
+        assert_eq!(info.model, Some("DELL U2720Q".to_string()));
+        assert_eq!(info.description, None);
+    }
+
+    #[test]
+    fn monitor_display_info_empty_vendor() {
+        let monitor = make_monitor("eDP-1", "", "");
+        let info = monitor_display_info(&monitor, PowerState::On);
+        assert_eq!(info.make, None);
+        assert_eq!(info.model, None);
+    }
+}
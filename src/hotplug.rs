@@ -0,0 +1,186 @@
+/// DRM hotplug notifications
+///
+/// The kernel broadcasts a uevent on the `drm` subsystem with `HOTPLUG=1`
+/// whenever a connector's status may have changed. The daemon listens for
+/// these on a netlink socket so it can re-scan connectors while it holds the
+/// displays off. On FreeBSD, drm-kmod reports the same through devd as
+/// `!system=DRM subsystem=CONNECTOR type=HOTPLUG`, read from devd's pipe.
+use crate::error::{Error, ResultExt};
+#[cfg(target_os = "linux")]
+use nix::sys::socket::{
+    AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol, SockType, bind, recv, socket,
+};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd};
+
+/// Netlink multicast group the kernel sends uevents to
+#[cfg(target_os = "linux")]
+const KERNEL_UEVENT_GROUP: u32 = 1;
+
+/// devd's stream socket, which copies every event to each client
+#[cfg(target_os = "freebsd")]
+const DEVD_PIPE: &str = "/var/run/devd.pipe";
+
+/// Listener for DRM hotplug uevents
+pub struct HotplugMonitor {
+    socket: OwnedFd,
+}
+
+#[cfg(target_os = "linux")]
+impl HotplugMonitor {
+    /// Subscribe to kernel uevents
+    ///
+    /// # Returns
+    /// - `Ok(HotplugMonitor)` - Listening for uevents
+    /// - `Err(Error::DrmError)` - The netlink socket couldn't be set up
+    pub fn new() -> Result<Self, Error> {
+        let socket = socket(
+            AddressFamily::Netlink,
+            SockType::Datagram,
+            SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkKObjectUEvent,
+        )
+        .context(Error::DrmError, "Failed to open uevent socket")?;
+
+        bind(
+            socket.as_raw_fd(),
+            &NetlinkAddr::new(0, KERNEL_UEVENT_GROUP),
+        )
+        .context(Error::DrmError, "Failed to subscribe to uevents")?;
+
+        Ok(HotplugMonitor { socket })
+    }
+
+    /// Read every pending uevent without blocking
+    ///
+    /// # Returns
+    /// `true` if any of them was a DRM hotplug event
+    pub fn drain(&self) -> bool {
+        let mut buf = [0u8; 8192];
+        let mut hotplug = false;
+        while let Ok(len) = recv(self.socket.as_raw_fd(), &mut buf, MsgFlags::MSG_DONTWAIT) {
+            hotplug |= is_drm_hotplug(&buf[..len]);
+        }
+        hotplug
+    }
+}
+
+#[cfg(target_os = "freebsd")]
+impl HotplugMonitor {
+    /// Subscribe to devd's events
+    ///
+    /// # Returns
+    /// - `Ok(HotplugMonitor)` - Listening for events
+    /// - `Err(Error::DrmError)` - devd isn't running
+    pub fn new() -> Result<Self, Error> {
+        let stream = std::os::unix::net::UnixStream::connect(DEVD_PIPE)
+            .and_then(|stream| stream.set_nonblocking(true).map(|_| stream))
+            .context(Error::DrmError, "Failed to connect to devd")?;
+        Ok(HotplugMonitor {
+            socket: stream.into(),
+        })
+    }
+
+    /// Read every pending event without blocking
+    ///
+    /// devd writes each event as a whole line, so lines aren't carried over
+    /// between reads.
+    ///
+    /// # Returns
+    /// `true` if any of them was a DRM hotplug event
+    pub fn drain(&self) -> bool {
+        let mut buf = [0u8; 8192];
+        let mut hotplug = false;
+        while let Ok(len) = nix::unistd::read(self.socket.as_raw_fd(), &mut buf) {
+            if len == 0 {
+                break;
+            }
+            hotplug |= buf[..len].split(|&b| b == b'\n').any(is_devd_drm_hotplug);
+        }
+        hotplug
+    }
+}
+
+impl AsFd for HotplugMonitor {
+    /// The netlink or devd socket, readable when events are pending
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.socket.as_fd()
+    }
+}
+
+/// Whether a raw kernel uevent reports a DRM hotplug
+///
+/// A uevent is a `action@devpath` header followed by NUL-separated
+/// `KEY=value` pairs.
+fn is_drm_hotplug(message: &[u8]) -> bool {
+    let mut drm = false;
+    let mut hotplug = false;
+    for field in message.split(|&b| b == 0).skip(1) {
+        match field {
+            b"SUBSYSTEM=drm" => drm = true,
+            b"HOTPLUG=1" => hotplug = true,
+            _ => {}
+        }
+    }
+    drm && hotplug
+}
+
+/// Whether a devd event line reports a DRM hotplug
+///
+/// Notifications start with `!` and carry `key=value` pairs separated by
+/// spaces.
+#[cfg(any(target_os = "freebsd", test))]
+fn is_devd_drm_hotplug(line: &[u8]) -> bool {
+    let Some(event) = line.strip_prefix(b"!") else {
+        return false;
+    };
+    let mut drm = false;
+    let mut hotplug = false;
+    for field in event.split(|&b| b == b' ') {
+        match field {
+            b"system=DRM" => drm = true,
+            b"type=HOTPLUG" => hotplug = true,
+            _ => {}
+        }
+    }
+    drm && hotplug
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_drm_hotplug_uevent() {
+        let message = b"change@/devices/pci0000:00/0000:00:02.0/drm/card0\0\
+            ACTION=change\0DEVPATH=/devices/pci0000:00/0000:00:02.0/drm/card0\0\
+            SUBSYSTEM=drm\0HOTPLUG=1\0DEVNAME=dri/card0\0SEQNUM=4242\0";
+        assert!(is_drm_hotplug(message));
+    }
+
+    #[test]
+    fn ignores_other_uevents() {
+        // A DRM change without HOTPLUG, e.g. a content protection update
+        let message = b"change@/devices/pci0000:00/0000:00:02.0/drm/card0\0\
+            ACTION=change\0SUBSYSTEM=drm\0DEVNAME=dri/card0\0";
+        assert!(!is_drm_hotplug(message));
+
+        let message = b"add@/devices/virtual/input/input42\0\
+            ACTION=add\0SUBSYSTEM=input\0HOTPLUG=1\0";
+        assert!(!is_drm_hotplug(message));
+
+        // The header alone never counts, even if it looks like a field
+        assert!(!is_drm_hotplug(b"SUBSYSTEM=drm\0HOTPLUG=1\0"));
+    }
+
+    #[test]
+    fn recognizes_devd_drm_hotplug() {
+        assert!(is_devd_drm_hotplug(
+            b"!system=DRM subsystem=CONNECTOR type=HOTPLUG"
+        ));
+        assert!(!is_devd_drm_hotplug(
+            b"!system=USB subsystem=DEVICE type=ATTACH ugen=ugen0.2"
+        ));
+        // Device attach lines aren't notifications
+        assert!(!is_devd_drm_hotplug(b"+drmn0 at parent=vgapci0"));
+    }
+}
@@ -0,0 +1,321 @@
+/// Hyprland backend for monitor power control
+///
+/// Talks to Hyprland's request socket (`$XDG_RUNTIME_DIR/hypr/$HYPRLAND_INSTANCE_SIGNATURE/.socket.sock`)
+/// directly, the same way `hyprctl` does: one request per connection, the
+/// reply is read until the compositor closes the socket.
+///
+/// Power is changed with `dispatch dpms on|off NAME` and read back from the
+/// `dpmsStatus` field of `j/monitors`.
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::backend::PowerBackend;
+use crate::display::{DisplayInfo, DisplayTarget, find_display_by_name};
+use crate::error::{Context, Error};
+use crate::output::PowerState;
+
+/// A monitor as reported by `j/monitors`; other fields are ignored
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Monitor {
+    name: String,
+    #[serde(default, deserialize_with = "non_blank")]
+    description: Option<String>,
+    #[serde(default, deserialize_with = "non_blank")]
+    make: Option<String>,
+    #[serde(default, deserialize_with = "non_blank")]
+    model: Option<String>,
+    /// Older Hyprland versions don't report it; monitors are on by default
+    #[serde(rename = "dpmsStatus", default = "default_dpms")]
+    dpms: bool,
+}
+
+fn default_dpms() -> bool {
+    true
+}
+
+impl Monitor {
+    fn display_info(&self) -> DisplayInfo {
+        DisplayInfo {
+            name: self.name.clone(),
+            power: if self.dpms {
+                PowerState::On
+            } else {
+                PowerState::Off
+            },
+            description: self.description.clone(),
+            make: self.make.clone(),
+            model: self.model.clone(),
+            drm: None,
+        }
+    }
+}
+
+/// Hyprland backend implementing PowerBackend trait
+pub struct HyprlandBackend {
+    socket: PathBuf,
+}
+
+impl HyprlandBackend {
+    /// Create a new Hyprland backend for the given request socket
+    ///
+    /// # Returns
+    /// - `Ok(HyprlandBackend)` if the socket answers a monitor query
+    /// - `Err(Error::CompositorIpc)` if the socket is unreachable
+    pub fn new(socket: &Path) -> Result<Self, Error> {
+        let backend = Self {
+            socket: socket.to_path_buf(),
+        };
+
+        // Fail early if Hyprland isn't listening
+        backend.monitors()?;
+
+        Ok(backend)
+    }
+
+    /// Send a single request and return the reply
+    fn request(&self, request: &str) -> Result<String, Error> {
+        let ipc_error = |e: std::io::Error| {
+            Error::CompositorIpc(Context::wrap(self.socket.display().to_string(), e))
+        };
+
+        let mut stream = UnixStream::connect(&self.socket).map_err(ipc_error)?;
+        stream.write_all(request.as_bytes()).map_err(ipc_error)?;
+
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply).map_err(ipc_error)?;
+        Ok(reply)
+    }
+
+    /// Query all monitors, including ones that are currently powered off
+    fn monitors(&self) -> Result<Vec<Monitor>, Error> {
+        let reply = self.request("j/monitors all")?;
+        parse_monitors(&reply)
+    }
+
+    /// Switch one monitor with `dispatch dpms`
+    ///
+    /// # Returns
+    /// - `Ok(())` if Hyprland answered `ok`
+    /// - `Err(Error::CompositorIpc)` if the request failed or was rejected
+    fn dispatch_dpms(&self, action: &str, monitor: &str) -> Result<(), Error> {
+        let reply = self.request(&format!("dispatch dpms {} {}", action, monitor))?;
+        if reply.trim() != "ok" {
+            return Err(Error::CompositorIpc(
+                format!("dpms {} {} rejected: {}", action, monitor, reply.trim()).into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Resolve display target to the matching monitors
+    fn resolve_targets(&self, target: &DisplayTarget) -> Result<Vec<Monitor>, Error> {
+        let monitors = self.monitors()?;
+
+        let DisplayTarget::Named(name) = target else {
+            return Ok(monitors);
+        };
+
+        let monitor = find_display_by_name(&monitors, name, |m| Some(&m.name))?;
+        Ok(vec![monitor.clone()])
+    }
+}
+
+impl PowerBackend for HyprlandBackend {
+    fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
+        let action = match state {
+            PowerState::On => "on",
+            PowerState::Off => "off",
+        };
+
+        // Each monitor is its own request, so one failing doesn't stop the others
+        let monitors = self.resolve_targets(target)?;
+        let mut failed: Vec<(String, Error)> = monitors
+            .iter()
+            .filter_map(|monitor| {
+                self.dispatch_dpms(action, &monitor.name)
+                    .err()
+                    .map(|e| (monitor.name.clone(), e))
+            })
+            .collect();
+
+        match failed.len() {
+            0 => Ok(()),
+            1 if monitors.len() == 1 => Err(failed.remove(0).1),
+            _ => Err(Error::PartialFailure {
+                failed: failed
+                    .into_iter()
+                    .map(|(name, e)| match e {
+                        Error::CompositorIpc(context) => (name, context.to_string()),
+                        e => (name, e.to_string()),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+        Ok(self
+            .resolve_targets(target)?
+            .iter()
+            .map(Monitor::display_info)
+            .collect())
+    }
+
+    fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
+        self.get_power(&DisplayTarget::All)
+    }
+
+    fn name(&self) -> &'static str {
+        "hyprland"
+    }
+
+    fn describe(&self) -> String {
+        format!("hyprland (IPC at {})", self.socket.display())
+    }
+}
+
+/// Parse the reply of `j/monitors` into monitors
+fn parse_monitors(reply: &str) -> Result<Vec<Monitor>, Error> {
+    serde_json::from_str(reply)
+        .map_err(|e| Error::CompositorIpc(format!("invalid monitors reply: {}", e).into()))
+}
+
+/// A text field, with blank text read as missing
+fn non_blank<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let text = Option::<String>::deserialize(deserializer)?;
+    Ok(text.filter(|s| !s.trim().is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITORS_REPLY: &str = r#"[{
+        "id": 0,
+        "name": "DP-1",
+        "description": "Dell Inc. DELL U2720Q 1234",
+        "make": "Dell Inc.",
+        "model": "DELL U2720Q",
+        "serial": "1234",
+        "refreshRate": 59.99700,
+        "activeWorkspace": {"id": 1, "name": "1"},
+        "reserved": [0, 30, 0, 0],
+        "dpmsStatus": true,
+        "availableModes": ["3840x2160@60.00Hz"]
+    },{
+        "id": 1,
+        "name": "eDP-1",
+        "description": "",
+        "make": "",
+        "model": "",
+        "dpmsStatus": false
+    }]"#;
+
+    /// Answer `connections` requests on a socket at `path` like Hyprland
+    /// would, with `reply`
+    ///
+    /// # Returns
+    /// A thread that yields the requests received, once all were answered
+    fn fake_hyprland(
+        path: &Path,
+        connections: usize,
+        reply: fn(&str) -> String,
+    ) -> std::thread::JoinHandle<Vec<String>> {
+        let _ = std::fs::remove_file(path);
+        let listener = std::os::unix::net::UnixListener::bind(path).unwrap();
+        std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for stream in listener.incoming().take(connections) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0u8; 1024];
+                let len = stream.read(&mut buf).unwrap();
+                let request = String::from_utf8_lossy(&buf[..len]).to_string();
+                stream.write_all(reply(&request).as_bytes()).unwrap();
+                requests.push(request);
+            }
+            requests
+        })
+    }
+
+    #[test]
+    fn set_power_tries_every_monitor() {
+        let path = PathBuf::from(format!("/tmp/dpms-test-hyprland-{}", std::process::id()));
+        let server = fake_hyprland(&path, 4, |request| match request {
+            "j/monitors all" => r#"[{"name": "DP-1"}, {"name": "DP-2"}]"#.to_string(),
+            "dispatch dpms off DP-1" => "Monitor DP-1 is busy".to_string(),
+            _ => "ok".to_string(),
+        });
+
+        let mut backend = HyprlandBackend::new(&path).unwrap();
+        let result = backend.set_power(&DisplayTarget::All, PowerState::Off);
+        let requests = server.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        match result {
+            Err(Error::PartialFailure { failed }) => {
+                assert_eq!(failed.len(), 1);
+                assert_eq!(failed[0].0, "DP-1");
+                assert!(failed[0].1.contains("Monitor DP-1 is busy"));
+            }
+            other => panic!("Expected PartialFailure, got {:?}", other),
+        }
+        // DP-2 was still switched after DP-1 failed
+        assert_eq!(
+            requests[2..],
+            ["dispatch dpms off DP-1", "dispatch dpms off DP-2"]
+        );
+    }
+
+    #[test]
+    fn parse_monitors_reads_fields() {
+        let monitors = parse_monitors(MONITORS_REPLY).unwrap();
+        assert_eq!(monitors.len(), 2);
+        assert_eq!(monitors[0].name, "DP-1");
+        assert_eq!(monitors[0].make.as_deref(), Some("Dell Inc."));
+        assert_eq!(monitors[0].model.as_deref(), Some("DELL U2720Q"));
+        assert!(monitors[0].dpms);
+        assert_eq!(monitors[1].name, "eDP-1");
+        assert_eq!(monitors[1].description, None);
+        assert!(!monitors[1].dpms);
+    }
+
+    #[test]
+    fn parse_monitors_maps_power() {
+        let monitors = parse_monitors(MONITORS_REPLY).unwrap();
+        assert_eq!(monitors[0].display_info().power, PowerState::On);
+        assert_eq!(monitors[1].display_info().power, PowerState::Off);
+    }
+
+    #[test]
+    fn parse_monitors_rejects_garbage() {
+        assert!(matches!(
+            parse_monitors("unknown request"),
+            Err(Error::CompositorIpc(_))
+        ));
+        assert!(matches!(
+            parse_monitors(r#"{"name": "DP-1"}"#),
+            Err(Error::CompositorIpc(_))
+        ));
+        assert!(matches!(
+            parse_monitors(r#"[{"id": 0}]"#),
+            Err(Error::CompositorIpc(_))
+        ));
+    }
+
+    #[test]
+    fn parse_monitors_defaults_missing_fields() {
+        let monitors = parse_monitors(r#"[{"name": "HDMI-A-1", "make": null}]"#).unwrap();
+        assert_eq!(monitors[0].name, "HDMI-A-1");
+        assert_eq!(monitors[0].make, None);
+        assert!(monitors[0].dpms);
+    }
+
+    #[test]
+    fn new_fails_on_missing_socket() {
+        let result = HyprlandBackend::new(Path::new("/nonexistent/hypr/.socket.sock"));
+        assert!(matches!(result, Err(Error::CompositorIpc(_))));
+    }
+}
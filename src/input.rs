@@ -0,0 +1,324 @@
+/// Input activity monitoring for `--until-input`
+///
+/// The daemon opens every `/dev/input/event*` device through the seat, so no
+/// extra permissions are needed, and gives the displays back at the first
+/// key press or pointer movement. Keyboards and mice plugged in later are
+/// picked up through inotify on `/dev/input` on Linux; elsewhere they are
+/// picked up when the seat comes back. Reading input devices lets
+/// the daemon see every key typed on the seat, which is why this is opt-in;
+/// the events are only checked for being activity and never kept.
+use crate::drm_ops::{SeatDevice, SeatHolder};
+use nix::errno::Errno;
+#[cfg(target_os = "linux")]
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use nix::sys::time::TimeVal;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Where the kernel's input device nodes live
+const INPUT_DIR: &str = "/dev/input";
+
+/// How long input is ignored after the displays go off, so releasing the
+/// key that ran `dpms off` or a nudge of the mouse doesn't wake them
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+/// Size of a `struct input_event`: a timestamp, then type, code and value
+const EVENT_SIZE: usize = size_of::<TimeVal>() + 8;
+
+/// Event types that come from a person using the device
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+
+/// Input property of sensors that report the device's orientation, which
+/// change without anyone touching them
+const INPUT_PROP_ACCELEROMETER: usize = 0x06;
+
+nix::ioctl_read_buf!(eviocgprop, b'E', 0x09, u8);
+
+/// Watches the seat's input devices for activity
+pub struct InputMonitor {
+    /// Directory holding the device nodes
+    dir: PathBuf,
+    /// Notifies about device nodes appearing in `dir`
+    watch: NodeWatch,
+    /// The devices read, with their node
+    devices: Vec<(PathBuf, SeatDevice)>,
+    /// Activity before this is ignored
+    settled_at: Instant,
+}
+
+impl InputMonitor {
+    /// Open every input device on the seat and watch for new ones
+    ///
+    /// # Returns
+    /// - `Ok(InputMonitor)` - Watching, even if no device could be opened yet
+    /// - `Err(Errno)` - `/dev/input` couldn't be watched
+    pub fn new(seat: &mut SeatHolder) -> Result<Self, Errno> {
+        Self::watching(Path::new(INPUT_DIR), seat, SETTLE_TIME)
+    }
+
+    /// Open every input device in `dir` and watch it for new ones
+    fn watching(dir: &Path, seat: &mut SeatHolder, settle: Duration) -> Result<Self, Errno> {
+        let watch = NodeWatch::new(dir)?;
+        let mut monitor = InputMonitor {
+            dir: dir.to_path_buf(),
+            watch,
+            devices: Vec::new(),
+            settled_at: Instant::now() + settle,
+        };
+        monitor.rescan(seat);
+        Ok(monitor)
+    }
+
+    /// Open the devices in the directory that aren't open yet
+    ///
+    /// Needed after the seat comes back, as libseat revokes input devices
+    /// while the seat is away.
+    pub fn rescan(&mut self, seat: &mut SeatHolder) {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut names: Vec<String> = entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| is_event_node(name))
+            .collect();
+        names.sort();
+        for name in names {
+            self.open(seat, &name, true);
+        }
+    }
+
+    /// Open the device node `name`, unless it is open already or a sensor
+    fn open(&mut self, seat: &mut SeatHolder, name: &str, report: bool) {
+        let path = self.dir.join(name);
+        if self.devices.iter().any(|(open, _)| *open == path) {
+            return;
+        }
+        match seat.open_device(&path) {
+            Ok(device) if is_accelerometer(&device) => seat.close_device(device),
+            Ok(device) => self.devices.push((path, device)),
+            Err(e) if report => eprintln!("Not watching {}: {}", path.display(), e),
+            Err(_) => {}
+        }
+    }
+
+    /// Read everything pending, opening devices that appeared
+    ///
+    /// Devices that fail to read, e.g. because they were unplugged or
+    /// revoked, are closed.
+    ///
+    /// # Returns
+    /// `true` if anyone used a device since the displays settled
+    pub fn drain(&mut self, seat: &mut SeatHolder) -> bool {
+        for name in self.watch.appeared() {
+            // A node without permissions yet is reported again once it has them
+            self.open(seat, &name, false);
+        }
+
+        let settled = Instant::now() >= self.settled_at;
+        let mut active = false;
+        let mut gone = Vec::new();
+        for (index, (_, device)) in self.devices.iter().enumerate() {
+            match read_activity(device) {
+                Ok(activity) => active |= activity && settled,
+                Err(_) => gone.push(index),
+            }
+        }
+        for index in gone.into_iter().rev() {
+            let (_, device) = self.devices.remove(index);
+            seat.close_device(device);
+        }
+        active
+    }
+
+    /// The open devices, readable when they have events
+    pub fn fds(&self) -> impl Iterator<Item = BorrowedFd<'_>> {
+        self.devices.iter().map(|(_, device)| device.as_fd())
+    }
+
+    /// The descriptor that is readable when device nodes appeared, if
+    /// new nodes are noticed at all
+    pub fn watch_fd(&self) -> Option<BorrowedFd<'_>> {
+        self.watch.fd()
+    }
+}
+
+/// Notices evdev nodes appearing in a directory, through inotify
+#[cfg(target_os = "linux")]
+struct NodeWatch(Inotify);
+
+#[cfg(target_os = "linux")]
+impl NodeWatch {
+    fn new(dir: &Path) -> Result<Self, Errno> {
+        let watch = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        // Permissions are often only granted after the node appears
+        watch.add_watch(dir, AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ATTRIB)?;
+        Ok(NodeWatch(watch))
+    }
+
+    /// The evdev nodes that appeared or changed since the last call
+    fn appeared(&self) -> Vec<String> {
+        let Ok(events) = self.0.read_events() else {
+            return Vec::new();
+        };
+        events
+            .into_iter()
+            .filter_map(|event| event.name?.into_string().ok())
+            .filter(|name| is_event_node(name))
+            .collect()
+    }
+
+    fn fd(&self) -> Option<BorrowedFd<'_>> {
+        Some(self.0.as_fd())
+    }
+}
+
+/// Without inotify nothing is noticed; `InputMonitor::rescan` still opens
+/// new devices once the seat comes back
+#[cfg(not(target_os = "linux"))]
+struct NodeWatch;
+
+#[cfg(not(target_os = "linux"))]
+impl NodeWatch {
+    fn new(_dir: &Path) -> Result<Self, Errno> {
+        Ok(NodeWatch)
+    }
+
+    fn appeared(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    fn fd(&self) -> Option<BorrowedFd<'_>> {
+        None
+    }
+}
+
+/// Whether `name` is an evdev node, e.g. `event3`
+fn is_event_node(name: &str) -> bool {
+    name.strip_prefix("event")
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// Whether the device is an orientation sensor
+///
+/// Devices that don't answer are assumed not to be.
+fn is_accelerometer(device: &SeatDevice) -> bool {
+    let mut props = [0u8; 8];
+    // SAFETY: the buffer outlives the call and its length is passed along
+    unsafe { eviocgprop(device.as_fd().as_raw_fd(), &mut props) }.is_ok()
+        && props[INPUT_PROP_ACCELEROMETER / 8] & (1 << (INPUT_PROP_ACCELEROMETER % 8)) != 0
+}
+
+/// Read every pending event from `device`
+///
+/// # Returns
+/// - `Ok(bool)` - Whether any of them was activity
+/// - `Err(Errno)` - The device can't be read any more
+fn read_activity(device: &SeatDevice) -> Result<bool, Errno> {
+    let mut buf = [0u8; EVENT_SIZE * 64];
+    let mut active = false;
+    loop {
+        match nix::unistd::read(device.as_fd().as_raw_fd(), &mut buf) {
+            Ok(0) | Err(Errno::EAGAIN) => return Ok(active),
+            Ok(len) => active |= has_activity(&buf[..len]),
+            Err(Errno::EINTR) => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Whether raw `struct input_event`s contain a key or button press, or
+/// pointer movement
+///
+/// Key releases and autorepeat don't count, so a key held while the
+/// displays went off doesn't wake them.
+fn has_activity(events: &[u8]) -> bool {
+    events.chunks_exact(EVENT_SIZE).any(|event| {
+        let field = &event[size_of::<TimeVal>()..];
+        let kind = u16::from_ne_bytes([field[0], field[1]]);
+        let value = i32::from_ne_bytes([field[4], field[5], field[6], field[7]]);
+        match kind {
+            EV_KEY => value == 1,
+            EV_REL | EV_ABS => true,
+            _ => false,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A raw `struct input_event`
+    fn event(kind: u16, code: u16, value: i32) -> Vec<u8> {
+        let mut bytes = vec![0u8; size_of::<TimeVal>()];
+        bytes.extend(kind.to_ne_bytes());
+        bytes.extend(code.to_ne_bytes());
+        bytes.extend(value.to_ne_bytes());
+        bytes
+    }
+
+    /// A key press: EV_KEY, then the EV_SYN report
+    fn key_press() -> Vec<u8> {
+        [event(EV_KEY, 30, 1), event(0, 0, 0)].concat()
+    }
+
+    #[test]
+    fn presses_and_movement_are_activity() {
+        assert!(has_activity(&key_press()));
+        assert!(has_activity(&event(EV_REL, 0, -3)));
+        assert!(has_activity(&event(EV_ABS, 0, 512)));
+        // Releases, autorepeat, reports and LEDs are not
+        assert!(!has_activity(&event(EV_KEY, 30, 0)));
+        assert!(!has_activity(&event(EV_KEY, 30, 2)));
+        assert!(!has_activity(&event(0, 0, 0)));
+        assert!(!has_activity(&event(0x11, 1, 1)));
+        assert!(!has_activity(&[]));
+    }
+
+    #[test]
+    fn only_evdev_nodes_are_watched() {
+        assert!(is_event_node("event0"));
+        assert!(is_event_node("event12"));
+        assert!(!is_event_node("event"));
+        assert!(!is_event_node("mouse0"));
+        assert!(!is_event_node("by-id"));
+        assert!(!is_event_node("event1.tmp"));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn monitor_reads_devices_present_and_added() {
+        let dir = Path::new("/tmp/dpms-test-input");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("event0"), event(EV_KEY, 30, 0)).unwrap();
+        fs::write(dir.join("mouse0"), key_press()).unwrap();
+        let mut seat = SeatHolder::None;
+
+        let mut monitor = InputMonitor::watching(dir, &mut seat, Duration::ZERO).unwrap();
+        assert_eq!(monitor.fds().count(), 1);
+        assert!(!monitor.drain(&mut seat));
+
+        fs::write(dir.join("event1"), key_press()).unwrap();
+        assert!(monitor.drain(&mut seat));
+        assert_eq!(monitor.fds().count(), 2);
+        assert!(!monitor.drain(&mut seat));
+    }
+
+    #[test]
+    fn activity_before_settling_is_ignored() {
+        let dir = Path::new("/tmp/dpms-test-input-settle");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        fs::write(dir.join("event0"), key_press()).unwrap();
+        let mut seat = SeatHolder::None;
+
+        let mut monitor = InputMonitor::watching(dir, &mut seat, Duration::from_secs(60)).unwrap();
+        assert!(!monitor.drain(&mut seat));
+    }
+}
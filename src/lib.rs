@@ -0,0 +1,54 @@
+//! Monitor power control for Wayland compositors and the Linux console
+//!
+//! The `dpms` binary is a thin command line over this library. With the
+//! `ffi` feature, `ffi` exports a C interface for programs that would
+//! rather call in than spawn the binary.
+
+pub mod backend;
+#[cfg(feature = "tty")]
+mod backlight;
+pub mod cli;
+#[cfg(feature = "dbus")]
+pub mod client;
+#[cfg(feature = "tty")]
+mod control;
+pub mod crash;
+#[cfg(feature = "tty")]
+pub mod daemon;
+pub mod display;
+#[cfg(feature = "tty")]
+pub mod drm_ops;
+#[cfg(feature = "tty")]
+mod edid;
+pub mod env;
+pub mod error;
+#[cfg(feature = "tty")]
+mod fbcon;
+#[cfg(feature = "gnome")]
+pub mod gnome;
+#[cfg(feature = "tty")]
+mod hotplug;
+#[cfg(feature = "hyprland")]
+pub mod hyprland;
+#[cfg(feature = "tty")]
+mod input;
+#[cfg(feature = "tty")]
+mod logfile;
+pub mod output;
+pub mod remote;
+pub mod repl;
+pub mod select;
+#[cfg(feature = "dbus")]
+pub mod service;
+#[cfg(feature = "tty")]
+mod sleep;
+#[cfg(feature = "tty")]
+mod systemd;
+#[cfg(feature = "tty")]
+pub mod tty;
+pub mod ui;
+#[cfg(feature = "wayland")]
+pub mod wayland;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
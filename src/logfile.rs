@@ -0,0 +1,172 @@
+/// Daemon log file
+///
+/// A detached daemon has no terminal to report to. Its standard output and
+/// error are redirected into a pipe, and a thread appends every line to
+/// `$XDG_STATE_HOME/dpms/daemon.log`, prefixed with the time and the
+/// daemon's PID. Once the log grows past `MAX_LOG_SIZE` it is renamed to
+/// `daemon.log.old` and a new one is started.
+use crate::output::{format_timestamp, unix_now};
+use nix::unistd::dup2;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+/// Size after which the log is rotated
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// The thread writing the log, until `finish` waits for it
+static WRITER: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+
+/// Where the daemon logs to
+///
+/// # Returns
+/// `$XDG_STATE_HOME/dpms/daemon.log`, else `~/.local/state/dpms/daemon.log`,
+/// else `daemon.log` in the runtime directory
+pub fn log_path() -> PathBuf {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")));
+    match state_home {
+        Some(dir) => dir.join("dpms").join("daemon.log"),
+        None => crate::daemon::runtime_dir().join("daemon.log"),
+    }
+}
+
+/// Send standard output and error to the log at `path`
+///
+/// Must only be called once, after the daemon has forked for the last time.
+pub fn start(path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = open_log(path)?;
+    let (read, write) = nix::unistd::pipe()?;
+
+    let path = path.to_path_buf();
+    let pid = std::process::id();
+    let handle = thread::spawn(move || {
+        let mut lines = BufReader::new(File::from(read)).split(b'\n');
+        while let Some(Ok(line)) = lines.next() {
+            if file.metadata().is_ok_and(|m| m.len() >= MAX_LOG_SIZE)
+                && let Ok(rotated) = rotate(&path)
+            {
+                file = rotated;
+            }
+            let _ = writeln!(
+                file,
+                "{} [{}] {}",
+                format_timestamp(unix_now()),
+                pid,
+                String::from_utf8_lossy(&line)
+            );
+        }
+    });
+
+    for fd in [1, 2] {
+        dup2(write.as_raw_fd(), fd)?;
+    }
+    *WRITER.lock().unwrap_or_else(|e| e.into_inner()) = Some(handle);
+    Ok(())
+}
+
+/// Wait for everything written so far to reach the log
+///
+/// Call before exiting, as `process::exit` doesn't wait for the thread.
+/// Standard output and error point at `/dev/null` afterwards.
+pub fn finish() {
+    let Some(handle) = WRITER.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+    let _ = io::stdout().flush();
+    // Closing the last write ends lets the thread see the end of the pipe
+    if let Ok(null) = File::options().write(true).open("/dev/null") {
+        for fd in [1, 2] {
+            let _ = dup2(null.as_raw_fd(), fd);
+        }
+    }
+    let _ = handle.join();
+}
+
+/// Open the log for appending
+fn open_log(path: &Path) -> io::Result<File> {
+    File::options().create(true).append(true).open(path)
+}
+
+/// Move the log to `<path>.old`, replacing any older one, and start afresh
+fn rotate(path: &Path) -> io::Result<File> {
+    let mut old = path.as_os_str().to_owned();
+    old.push(".old");
+    fs::rename(path, old)?;
+    open_log(path)
+}
+
+/// The last `count` lines the daemon with `pid` logged to `path`
+///
+/// # Returns
+/// The lines without their prefix; empty if the log can't be read
+pub fn tail(path: &Path, pid: u32, count: usize) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let tag = format!(" [{}] ", pid);
+    let lines: Vec<String> = contents
+        .lines()
+        .filter_map(|line| Some(line.split_once(&tag)?.1.to_string()))
+        .collect();
+    lines[lines.len().saturating_sub(count)..].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_keeps_the_last_lines_of_one_daemon() {
+        let path = Path::new("/tmp/dpms-test-daemon.log");
+        fs::write(
+            path,
+            "2024-05-01T10:00:00Z [10] Using /dev/dri/card0\n\
+             2024-05-01T10:00:00Z [11] Another dpms daemon is already running\n\
+             2024-05-01T10:00:01Z [10] Failed to disable CRTC on card0: EBUSY\n\
+             2024-05-01T10:00:01Z [10] Exiting: no display found\n",
+        )
+        .unwrap();
+
+        let tail_10 = tail(path, 10, 2);
+        let tail_12 = tail(path, 12, 2);
+        let _ = fs::remove_file(path);
+
+        assert_eq!(
+            tail_10,
+            [
+                "Failed to disable CRTC on card0: EBUSY",
+                "Exiting: no display found"
+            ]
+        );
+        assert!(tail_12.is_empty());
+        assert!(tail(Path::new("/tmp/dpms-test-no-such.log"), 10, 2).is_empty());
+    }
+
+    #[test]
+    fn rotate_keeps_one_old_log() {
+        let dir = Path::new("/tmp/dpms-test-rotate");
+        let _ = fs::remove_dir_all(dir);
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join("daemon.log");
+        fs::write(&path, "first\n").unwrap();
+        fs::write(dir.join("daemon.log.old"), "older\n").unwrap();
+
+        let mut file = rotate(&path).unwrap();
+        writeln!(file, "second").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(dir.join("daemon.log.old")).unwrap(),
+            "first\n"
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+    }
+}
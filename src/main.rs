@@ -1,120 +1,673 @@
-mod backend;
-mod cli;
-mod daemon;
-mod display;
-mod drm_ops;
-mod env;
-mod error;
-mod output;
-mod tty;
-mod wayland;
-
+#[cfg(feature = "tty")]
+use dpms::daemon;
+use dpms::{backend, cli, crash, display, error, output, remote, repl, select, ui};
+#[cfg(feature = "dbus")]
+use dpms::{client, service};
 use std::process::ExitCode as StdExitCode;
 
 fn main() -> StdExitCode {
+    // A bug gets a report the user can act on, and exits with 1, not 101
+    crash::install();
+    std::panic::catch_unwind(run_main).unwrap_or_else(|_| error::ExitCode::Error.into())
+}
+
+/// Run the command line and print what it reports
+fn run_main() -> StdExitCode {
     // Parse CLI arguments - clap handles usage errors and exits with code 2 (default clap behavior)
-    let command = cli::parse();
+    let (command, options) = cli::parse();
+
+    // Errors go to stderr, as JSON for --json
+    let format = output_format(&command, &options);
+    ui::set_verbosity(match format {
+        output::Format::Json => ui::Verbosity::Collect,
+        _ if options.quiet => ui::Verbosity::Quiet,
+        _ => ui::Verbosity::Normal,
+    });
+    let codes = options.error_codes;
+    let quiet = options.quiet;
+    let report = move |e: error::Error| {
+        eprint!("{}", output::format_error(&e, format, codes));
+        StdExitCode::from(e.exit_code())
+    };
 
-    // Run the main logic
-    match run(command) {
+    // Hand the whole command line to dpms on the other host
+    if let Some(host) = &options.host {
+        let args = remote::forwarded_args(std::env::args_os().skip(1));
+        return match remote::run(host, &args) {
+            Ok(code) => StdExitCode::from(code),
+            Err(e) => report(e),
+        };
+    }
+
+    // Run the main logic and print what it reports
+    let result = run(command, options).and_then(|outcome| {
+        // With --quiet, the exit code alone says whether a dry run would pass
+        if !(quiet && matches!(outcome, output::Report::DryRun(_))) {
+            print!("{}", outcome.render(format));
+        }
+        eprint!("{}", outcome.notes(format));
+        outcome.into_result()
+    });
+    // Notices no report had room for are still worth a line
+    for notice in ui::take_notices() {
+        eprintln!("{}", notice);
+    }
+    match result {
         Ok(()) => error::ExitCode::Success.into(),
-        Err(e) => {
-            // All errors go to stderr
-            eprintln!("Error: {}", e);
-            // Map our error to exit code using proper From impl
-            e.exit_code().into()
+        Err(e) => report(e),
+    }
+}
+
+/// How the report of `command` is printed
+fn output_format(command: &cli::Command, options: &cli::Options) -> output::Format {
+    if command.json() {
+        output::Format::Json
+    } else if let cli::Command::Status { short: true, .. } = command {
+        output::Format::Short
+    } else {
+        output::Format::Text(output::Style::detect(options.color))
+    }
+}
+
+/// Set the power state of the targeted displays
+fn apply_power<B: backend::PowerBackend + ?Sized>(
+    backend: &mut B,
+    target: &display::DisplayTarget,
+    state: output::PowerState,
+    json: bool,
+) -> Result<output::Report, error::Error> {
+    let result = backend.set_power(target, state);
+    power_report(backend, target, result, json)
+}
+
+/// The report of a power change with outcome `result`
+///
+/// In JSON mode it has the per-display results, including when only some of
+/// the displays failed; any other error is returned as is.
+fn power_report<B: backend::PowerBackend + ?Sized>(
+    backend: &mut B,
+    target: &display::DisplayTarget,
+    result: Result<(), error::Error>,
+    json: bool,
+) -> Result<output::Report, error::Error> {
+    let off = backend.off_durations();
+    let failed = match result {
+        Ok(()) => Vec::new(),
+        Err(error::Error::PartialFailure { failed }) if json => failed,
+        Err(e) => return Err(e),
+    };
+
+    let mut results = Vec::new();
+    if json {
+        for display in backend.get_power(target)? {
+            let outcome = match failed.iter().find(|(name, _)| *name == display.name) {
+                Some((_, reason)) => Err(reason.clone()),
+                None => Ok(()),
+            };
+            results.push((display, outcome));
         }
     }
+
+    Ok(output::Report::Power {
+        results,
+        off,
+        notices: ui::take_notices(),
+        failure: (!failed.is_empty()).then_some(error::Error::PartialFailure { failed }),
+    })
 }
 
 /// Execute a command using the given backend
-fn execute_command<B: backend::PowerBackend>(
+///
+/// # Returns
+/// What the command has to report, for `main` to print
+fn execute_command<B: backend::PowerBackend + ?Sized>(
     backend: &mut B,
     command: cli::Command,
-) -> Result<(), error::Error> {
+) -> Result<output::Report, error::Error> {
     match command {
-        cli::Command::On { target } => {
-            backend.set_power(&target, output::PowerState::On)?;
-            Ok(())
+        cli::Command::On {
+            target,
+            dry_run: true,
+            ..
+        } => backend
+            .check_power(&target, output::PowerState::On)
+            .map(output::Report::DryRun),
+        cli::Command::Off {
+            target,
+            dry_run: true,
+            ..
+        } => backend
+            .check_power(&target, output::PowerState::Off)
+            .map(output::Report::DryRun),
+        cli::Command::On { target, json, .. } => {
+            apply_power(backend, &target, output::PowerState::On, json)
         }
-        cli::Command::Off { target } => {
-            backend.set_power(&target, output::PowerState::Off)?;
-            Ok(())
+        cli::Command::Off {
+            target,
+            json,
+            timeout: Some(timeout),
+            ..
+        } => {
+            let result = backend.power_off_for(&target, timeout);
+            power_report(backend, &target, result, json)
         }
-        cli::Command::Toggle { target } => {
+        cli::Command::Off { target, json, .. } => {
+            apply_power(backend, &target, output::PowerState::Off, json)
+        }
+        cli::Command::Toggle { target, json } => {
             let displays = backend.get_power(&target)?;
-            for display in displays {
+
+            // Toggle every display, even if an earlier one failed
+            let mut results = Vec::new();
+            let mut errors = Vec::new();
+            for mut display in displays {
                 let new_state = match display.power {
                     output::PowerState::On => output::PowerState::Off,
                     output::PowerState::Off => output::PowerState::On,
                 };
-                backend.set_power(&display::DisplayTarget::Named(display.name), new_state)?;
+                let target = display::DisplayTarget::Named(display.name.clone());
+                match backend.set_power(&target, new_state) {
+                    Ok(()) => {
+                        display.power = new_state;
+                        results.push((display, Ok(())));
+                    }
+                    Err(e) => {
+                        results.push((display, Err(e.to_string())));
+                        errors.push(e);
+                    }
+                }
             }
-            Ok(())
+
+            let failure = match errors.len() {
+                0 => None,
+                1 if results.len() == 1 => Some(errors.remove(0)),
+                _ => Some(error::Error::PartialFailure {
+                    failed: results
+                        .iter()
+                        .filter_map(|(display, result)| {
+                            result
+                                .as_ref()
+                                .err()
+                                .map(|reason| (display.name.clone(), reason.clone()))
+                        })
+                        .collect(),
+                }),
+            };
+
+            if !json {
+                return match failure {
+                    Some(e) => Err(e),
+                    None => Ok(output::Report::Empty),
+                };
+            }
+            Ok(output::Report::Power {
+                results,
+                off: backend.off_durations(),
+                notices: ui::take_notices(),
+                failure,
+            })
         }
-        cli::Command::Status { target, json } => {
-            let displays = backend.get_power(&target)?;
-            print!("{}", output::format_status(&displays, json));
-            Ok(())
+        cli::Command::Status {
+            target,
+            json,
+            short,
+            watch: true,
+            max_reconnects,
+            ..
+        } => {
+            // Events are streamed as they come, without styling
+            let format = match (json, short) {
+                (true, _) => output::Format::Json,
+                (_, true) => output::Format::Short,
+                _ => output::Format::Text(output::Style::PLAIN),
+            };
+            let mut sink = output::event_sink(format, std::io::stdout());
+            match backend.watch(&target, max_reconnects, &mut |event| sink.event(event)) {
+                // Whoever read the events stopped, e.g. `dpms status --watch | head -1`
+                Err(error::Error::Io(e)) if e.kind() == std::io::ErrorKind::BrokenPipe => {
+                    Ok(output::Report::Empty)
+                }
+                result => result.map(|()| output::Report::Empty),
+            }
+        }
+        cli::Command::Status {
+            target,
+            compat: true,
+            ..
+        } => Ok(output::Report::CompatStatus {
+            displays: backend.get_power(&target)?,
+            via: backend.via(),
+        }),
+        cli::Command::Status { target, json, .. } => {
+            let mut report =
+                output::StatusReport::new(backend.describe(), backend.get_power(&target)?);
+            report.via = backend.via().map(str::to_string);
+            // Asking the daemons takes a while; only JSON shows them
+            if json {
+                report.daemons = backend.daemons();
+            }
+            Ok(output::Report::Status(report))
         }
-        cli::Command::List { json, verbose } => {
+        cli::Command::List { verbose, .. } => {
             let displays = backend.list_displays()?;
             if displays.is_empty() {
                 return Err(error::Error::NoDisplayFound);
             }
-            print!("{}", output::format_list(&displays, json, verbose));
-            Ok(())
+            Ok(output::Report::List {
+                displays,
+                verbose,
+                backend: backend.describe(),
+            })
+        }
+        cli::Command::Idle { target, timeout } => backend
+            .idle(&target, timeout)
+            .map(|()| output::Report::Empty),
+        #[cfg(feature = "dbus")]
+        cli::Command::Serve { .. } => service::serve(backend).map(|()| output::Report::Empty),
+        #[cfg(not(feature = "dbus"))]
+        cli::Command::Serve { .. } => Err(error::Error::BackendNotBuilt {
+            backend: "dbus".to_string(),
+        }),
+        cli::Command::Repl { watch } => repl::run(backend, watch).map(|()| output::Report::Empty),
+        cli::Command::Timeouts { action } => {
+            let timeouts = backend.dpms_timeouts(action)?;
+            Ok(match action {
+                backend::TimeoutsAction::Show => output::Report::Timeouts(timeouts),
+                _ => output::Report::Empty,
+            })
         }
         cli::Command::Completion { shell } => {
             cli::generate_completions(shell);
-            Ok(())
+            Ok(output::Report::Empty)
         }
-        cli::Command::DaemonInternal => {
+        cli::Command::Recover
+        | cli::Command::DaemonStatus { .. }
+        | cli::Command::DaemonRestart
+        | cli::Command::DaemonInternal { .. } => {
             // This is handled in run() before reaching here
-            unreachable!("DaemonInternal should be handled before execute_command")
+            unreachable!("Recover and daemon commands should be handled before execute_command")
         }
     }
 }
 
-/// Main application logic - dispatches commands to appropriate backend
-fn run(command: cli::Command) -> Result<(), error::Error> {
-    // Handle daemon-internal command immediately (no backend needed)
-    if matches!(command, cli::Command::DaemonInternal) {
-        // This never returns - it runs the daemon main loop and exits
-        daemon::daemon_main();
-    }
-
-    // Detect which backend to use based on environment
-    let backend_type = env::detect_backend()?;
-
-    // Create appropriate backend and execute command
-    match backend_type {
-        env::Backend::Wayland => match wayland::WaylandBackend::new() {
-            Ok(mut backend) => execute_command(&mut backend, command),
-            Err(error::Error::Io(_) | error::Error::ProtocolNotSupported) => {
-                eprintln!("Warning: Wayland backend failed, falling back to TTY");
-                let mut backend = tty::TtyBackend::new()?;
-                execute_command(&mut backend, command)
-            }
-            Err(e) => Err(e),
-        },
-        env::Backend::Tty => {
-            let mut backend = tty::TtyBackend::new()?;
-            execute_command(&mut backend, command)
+/// Handle the commands that drive the TTY daemon rather than a backend
+///
+/// # Returns
+/// `None` if `command` is not one of them
+#[cfg(feature = "tty")]
+fn run_daemon_command(
+    command: &cli::Command,
+    options: &cli::Options,
+) -> Option<Result<output::Report, error::Error>> {
+    let open = select::drm_open_options(options);
+    let daemon = select::daemon_options(options);
+
+    if matches!(
+        command,
+        cli::Command::DaemonInternal { .. } | cli::Command::Recover | cli::Command::DaemonRestart
+    ) {
+        crash::set_backend("tty");
+    }
+    match command {
+        cli::Command::DaemonInternal {
+            connector,
+            foreground,
+            systemd,
+            restore_at,
+        } => {
+            let daemon = daemon::DaemonOptions {
+                restore_at: *restore_at,
+                ..daemon
+            };
+            // This never returns - it runs the daemon main loop and exits
+            daemon::daemon_main(connector.as_deref(), *foreground, *systemd, &open, &daemon);
+        }
+        // Recovery talks to DRM directly, whatever session is running
+        cli::Command::Recover => {
+            Some(daemon::recover(&open, options.kill_after).map(|()| output::Report::Empty))
+        }
+        cli::Command::DaemonStatus { .. } => {
+            Some(Ok(output::Report::DaemonStatus(daemon::daemon_statuses())))
         }
-        env::Backend::X11 => Err(error::Error::ProtocolNotSupported),
+        cli::Command::DaemonRestart => Some(
+            daemon::restart_daemons(&open, &daemon, options.kill_after)
+                .map(|()| output::Report::Empty),
+        ),
+        _ => None,
+    }
+}
+
+/// Without the TTY backend there is no daemon to drive
+#[cfg(not(feature = "tty"))]
+fn run_daemon_command(
+    command: &cli::Command,
+    _options: &cli::Options,
+) -> Option<Result<output::Report, error::Error>> {
+    matches!(
+        command,
+        cli::Command::Recover
+            | cli::Command::DaemonStatus { .. }
+            | cli::Command::DaemonRestart
+            | cli::Command::DaemonInternal { .. }
+    )
+    .then(|| {
+        Err(error::Error::BackendNotBuilt {
+            backend: "tty".to_string(),
+        })
+    })
+}
+
+fn run(command: cli::Command, options: cli::Options) -> Result<output::Report, error::Error> {
+    if options.debug_panic {
+        panic!("--debug-panic was given");
+    }
+
+    // Daemon commands need no backend
+    if let Some(result) = run_daemon_command(&command, &options) {
+        return result;
     }
+    #[cfg(feature = "dbus")]
+    if let cli::Command::Serve { install: true } = command {
+        let path = service::install()?;
+        ui::notice(format!("Installed {}", path.display()));
+        return Ok(output::Report::Empty);
+    }
+
+    // A running `dpms serve` owns the displays; ask it instead
+    #[cfg(feature = "dbus")]
+    if !options.no_service
+        && options.backend == cli::BackendChoice::Auto
+        && options.wayland_socket.is_none()
+        && client::routes(&command)
+        && let Some(mut backend) = client::ServiceBackend::connect()
+    {
+        crash::set_backend("service");
+        return execute_command(&mut backend, command);
+    }
+
+    let mut backend = select::backend(&options)?;
+    execute_command(backend.as_mut(), command)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use output::{PowerState, Report};
+
+    /// Backend whose displays switch in memory, except those named in `broken`
+    struct FakeBackend {
+        displays: Vec<display::DisplayInfo>,
+        broken: Vec<String>,
+    }
+
+    impl backend::PowerBackend for FakeBackend {
+        fn set_power(
+            &mut self,
+            target: &display::DisplayTarget,
+            state: PowerState,
+        ) -> Result<(), error::Error> {
+            let mut failed = Vec::new();
+            for display in &mut self.displays {
+                if matches!(target, display::DisplayTarget::Named(name) if *name != display.name) {
+                    continue;
+                }
+                if self.broken.contains(&display.name) {
+                    failed.push((display.name.clone(), "broken".to_string()));
+                } else if display.power == state {
+                    ui::notice(format_args!("{} already in that state", display.name));
+                } else {
+                    display.power = state;
+                }
+            }
+            match failed.len() {
+                0 => Ok(()),
+                _ => Err(error::Error::PartialFailure { failed }),
+            }
+        }
+
+        fn get_power(
+            &self,
+            target: &display::DisplayTarget,
+        ) -> Result<Vec<display::DisplayInfo>, error::Error> {
+            Ok(self
+                .displays
+                .iter()
+                .filter(
+                    |d| !matches!(target, display::DisplayTarget::Named(name) if *name != d.name),
+                )
+                .cloned()
+                .collect())
+        }
+
+        fn list_displays(&self) -> Result<Vec<display::DisplayInfo>, error::Error> {
+            Ok(self.displays.clone())
+        }
+
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn describe(&self) -> String {
+            "fake".to_string()
+        }
+    }
+
+    /// Held by tests that change the process' verbosity
+    static VERBOSITY: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    /// What `run` writes straight to the process' stderr, as notices do
+    fn stderr_of(run: impl FnOnce()) -> String {
+        use nix::unistd::{dup, dup2};
+        use std::io::Read;
+        use std::os::fd::AsRawFd;
+
+        let (read, write) = nix::unistd::pipe().unwrap();
+        let saved = dup(2).unwrap();
+        dup2(write.as_raw_fd(), 2).unwrap();
+        run();
+        dup2(saved, 2).unwrap();
+        nix::unistd::close(saved).unwrap();
+        drop(write);
+
+        let mut text = String::new();
+        std::fs::File::from(read).read_to_string(&mut text).unwrap();
+        text
+    }
+
+    fn fake_backend(broken: &[&str]) -> FakeBackend {
+        let display = |name: &str| display::DisplayInfo {
+            name: name.to_string(),
+            power: PowerState::On,
+            description: None,
+            make: None,
+            model: None,
+            drm: None,
+        };
+        FakeBackend {
+            displays: vec![display("DP-1"), display("HDMI-A-1")],
+            broken: broken.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn list_reports_the_displays_and_backend() {
+        let command = cli::Command::List {
+            json: false,
+            verbose: true,
+        };
+        let report = execute_command(&mut fake_backend(&[]), command).unwrap();
+        let Report::List {
+            displays, backend, ..
+        } = &report
+        else {
+            panic!("not a list: {:?}", report);
+        };
+        assert_eq!(displays.len(), 2);
+        assert_eq!(backend, "fake");
+
+        let text = output::Format::Text(output::Style::PLAIN);
+        assert_eq!(report.render(text), "DP-1: On\nHDMI-A-1: On\n");
+        assert_eq!(report.notes(text), "Backend: fake\n");
+        assert_eq!(report.notes(output::Format::Json), "");
+    }
 
     #[test]
-    fn error_converts_to_exit_code_1() {
-        // Test that a concrete error converts to exit code 1
+    fn off_reports_results_only_for_json() {
+        let off = |json| cli::Command::Off {
+            target: display::DisplayTarget::All,
+            json,
+            dry_run: false,
+            timeout: None,
+        };
+
+        let report = execute_command(&mut fake_backend(&[]), off(false)).unwrap();
+        assert!(
+            matches!(&report, Report::Power { results, failure: None, .. } if results.is_empty())
+        );
+
+        let report = execute_command(&mut fake_backend(&[]), off(true)).unwrap();
+        let Report::Power { results, .. } = &report else {
+            panic!("not a power change: {:?}", report);
+        };
+        assert!(
+            results
+                .iter()
+                .all(|(display, result)| display.power == PowerState::Off && result.is_ok())
+        );
+    }
+
+    #[test]
+    fn json_reports_collect_notices() {
+        let off = cli::Command::Off {
+            target: display::DisplayTarget::Named("DP-1".to_string()),
+            json: true,
+            dry_run: false,
+            timeout: None,
+        };
+        let mut backend = fake_backend(&[]);
+        execute_command(&mut backend, off.clone()).unwrap();
+
+        let _verbosity = VERBOSITY.lock().unwrap_or_else(|e| e.into_inner());
+        ui::set_verbosity(ui::Verbosity::Collect);
+        let report = execute_command(&mut backend, off);
+        ui::set_verbosity(ui::Verbosity::Normal);
+        let json = report.unwrap().render(output::Format::Json);
+        assert!(
+            json.contains(r#""notices":["DP-1 already in that state"]"#),
+            "{}",
+            json
+        );
+        assert!(ui::take_notices().is_empty());
+    }
+
+    #[test]
+    fn quiet_says_nothing_when_already_in_that_state() {
+        let power = |state| {
+            let target = display::DisplayTarget::All;
+            match state {
+                PowerState::Off => cli::Command::Off {
+                    target,
+                    json: false,
+                    dry_run: false,
+                    timeout: None,
+                },
+                PowerState::On => cli::Command::On {
+                    target,
+                    json: false,
+                    dry_run: false,
+                },
+            }
+        };
+        let format = output::Format::Text(output::Style::PLAIN);
+        let _verbosity = VERBOSITY.lock().unwrap_or_else(|e| e.into_inner());
+        let mut backend = fake_backend(&[]);
+
+        // Each state is asked for twice; the second time changes nothing
+        ui::set_verbosity(ui::Verbosity::Quiet);
+        let stderr = stderr_of(|| {
+            for state in [
+                PowerState::Off,
+                PowerState::Off,
+                PowerState::On,
+                PowerState::On,
+            ] {
+                let report = execute_command(&mut backend, power(state)).unwrap();
+                assert_eq!(report.render(format), "");
+                assert_eq!(report.notes(format), "");
+            }
+        });
+        ui::set_verbosity(ui::Verbosity::Normal);
+        assert_eq!(stderr, "");
+        assert!(ui::take_notices().is_empty());
+
+        // Without --quiet the same request says so
+        let stderr = stderr_of(|| {
+            execute_command(&mut backend, power(PowerState::On)).unwrap();
+        });
+        assert_eq!(
+            stderr,
+            "DP-1 already in that state\nHDMI-A-1 already in that state\n"
+        );
+    }
+
+    #[test]
+    fn partial_failure_is_reported_then_returned() {
+        let toggle = |json| cli::Command::Toggle {
+            target: display::DisplayTarget::All,
+            json,
+        };
+
+        let report = execute_command(&mut fake_backend(&["HDMI-A-1"]), toggle(true)).unwrap();
+        let json = report.render(output::Format::Json);
+        assert!(
+            json.contains(r#""name":"HDMI-A-1","power":"on""#),
+            "{}",
+            json
+        );
+        assert!(json.contains(r#""success":false"#), "{}", json);
+        assert!(matches!(
+            report.into_result(),
+            Err(error::Error::PartialFailure { failed }) if failed.len() == 1
+        ));
+
+        // Text has nothing to print for it, only the error
+        assert!(matches!(
+            execute_command(&mut fake_backend(&["HDMI-A-1"]), toggle(false)),
+            Err(error::Error::PartialFailure { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "tty")]
+    fn daemon_status_is_returned_as_a_report() {
+        let command = cli::Command::DaemonStatus { json: true };
+        let report = run_daemon_command(&command, &cli::Options::default())
+            .expect("daemon-status is a daemon command")
+            .unwrap();
+        let Report::DaemonStatus(statuses) = &report else {
+            panic!("expected the daemon statuses, got {:?}", report);
+        };
+
+        // It prints like any other report, in the format asked for
+        assert_eq!(
+            report.render(output::Format::Json),
+            output::format_daemon_status(statuses, true)
+        );
+        assert_eq!(
+            report.render(output::Format::Text(output::Style::PLAIN)),
+            output::format_daemon_status(statuses, false)
+        );
+    }
+
+    #[test]
+    fn error_converts_to_exit_code() {
+        // Test that a concrete error converts to its category's exit code
         let err = error::Error::UnsupportedEnvironment;
         let exit_code: i32 = err.exit_code().into();
+        assert_eq!(exit_code, 6);
+
+        let err = error::Error::SignalError("test".into());
+        let exit_code: i32 = err.exit_code().into();
         assert_eq!(exit_code, 1);
     }
 
@@ -184,22 +737,36 @@ mod tests {
     }
 
     #[test]
-    fn all_error_variants_map_to_exit_code_1() {
-        // Verify all error types return exit code 1 (Error)
+    fn error_variants_map_to_documented_exit_codes() {
+        // Verify error types return the codes listed in --help
         let errors = vec![
-            error::Error::UnsupportedEnvironment,
-            error::Error::ProtocolNotSupported,
-            error::Error::NoDisplayFound,
-            error::Error::DaemonStartFailed("test".to_string()),
-            error::Error::DaemonStopTimeout,
+            (error::Error::UnsupportedEnvironment, 6),
+            (error::Error::ProtocolNotSupported, 6),
+            (error::Error::NoDisplayFound, 3),
+            (
+                error::Error::DrmPermissionDenied {
+                    path: "/dev/dri/card0".to_string(),
+                },
+                4,
+            ),
+            (
+                error::Error::DrmMasterHeld {
+                    path: "/dev/dri/card0".to_string(),
+                },
+                4,
+            ),
+            (error::Error::DaemonStartFailed("test".to_string()), 5),
+            (error::Error::DaemonStopTimeout, 5),
+            (error::Error::ControlError("test".into()), 1),
         ];
 
-        for err in errors {
+        for (err, code) in errors {
             assert_eq!(
                 err.exit_code() as i32,
-                1,
-                "Error {:?} should exit with code 1",
-                err
+                code,
+                "Error {:?} should exit with code {}",
+                err,
+                code
             );
         }
     }
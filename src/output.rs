@@ -1,14 +1,411 @@
-use crate::display::DisplayInfo;
+use crate::backend::{DpmsTimeouts, WatchEvent};
+use crate::cli::ColorChoice;
+#[cfg(feature = "tty")]
+use crate::daemon::DaemonStatus;
+use crate::display::{self, DisplayInfo, DrmIds};
+use crate::error::{ConnectorSeen, Error};
+use serde::{Serialize, Serializer};
+use std::io::{self, IsTerminal, Write};
 
 /// Power state enum representing display power state
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PowerState {
     On,
     Off,
 }
 
+impl PowerState {
+    /// How `dpms serve` calls and `dpms repl` messages name the state
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerState::On => "on",
+            PowerState::Off => "off",
+        }
+    }
+
+    /// Parse a state as named by `name`
+    pub fn parse(state: &str) -> Result<Self, String> {
+        match state {
+            "on" => Ok(PowerState::On),
+            "off" => Ok(PowerState::Off),
+            other => Err(format!("invalid power state '{}' (use on or off)", other)),
+        }
+    }
+}
+
+/// ANSI escapes for the few colors text output uses
+mod ansi {
+    pub const BOLD: &str = "1";
+    pub const RED: &str = "31";
+    pub const GREEN: &str = "32";
+
+    /// `text` in the SGR `code`, followed by a reset
+    pub fn paint(text: &str, code: &str) -> String {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    }
+}
+
+/// How text output is rendered; JSON ignores it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Style {
+    /// Color power states with ANSI escapes
+    pub color: bool,
+    /// Render `list` as an aligned table instead of `name: state` lines
+    pub table: bool,
+}
+
+impl Style {
+    /// Lines without color, as for a pipe
+    pub const PLAIN: Style = Style {
+        color: false,
+        table: false,
+    };
+
+    /// The style for stdout under `--color`
+    pub fn detect(choice: ColorChoice) -> Self {
+        // https://no-color.org: set and not empty
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        Self::resolve(choice, io::stdout().is_terminal(), no_color)
+    }
+
+    /// Color and table on a terminal; `--color` overrides NO_COLOR
+    fn resolve(choice: ColorChoice, terminal: bool, no_color: bool) -> Self {
+        let color = match choice {
+            ColorChoice::Auto => terminal && !no_color,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        };
+        Style {
+            color,
+            table: terminal,
+        }
+    }
+
+    /// `text` in `code` when coloring
+    fn paint(self, text: &str, code: Option<&str>) -> String {
+        match code {
+            Some(code) if self.color && !text.is_empty() => ansi::paint(text, code),
+            _ => text.to_string(),
+        }
+    }
+}
+
+/// Version of the multi-display JSON of `status`, `list` and power changes
+///
+/// Bumped only when a field is removed or changes meaning; new fields don't.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A display in the schema 1 JSON
+///
+/// Every field is always present, `null` when the backend doesn't know it.
+#[derive(Serialize)]
+pub struct DisplayRecord<'a> {
+    name: &'a str,
+    power: PowerState,
+    /// Built-in panel, e.g. a laptop's `eDP-1`
+    internal: bool,
+    make: Option<&'a str>,
+    model: Option<&'a str>,
+    drm: Option<&'a DrmIds>,
+}
+
+impl<'a> From<&'a DisplayInfo> for DisplayRecord<'a> {
+    fn from(display: &'a DisplayInfo) -> Self {
+        Self {
+            name: &display.name,
+            power: display.power,
+            internal: display::is_internal_panel(&display.name),
+            make: display.make.as_deref(),
+            model: display.model.as_deref(),
+            drm: display.drm.as_ref(),
+        }
+    }
+}
+
+/// Serialize displays as schema 1 records
+fn serialize_displays<S: Serializer>(
+    displays: &[DisplayInfo],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(displays.iter().map(DisplayRecord::from))
+}
+
+/// What `dpms list --json` prints: `{"schema":1,"displays":[...]}`
+#[derive(Serialize)]
+struct DisplaySet<'a> {
+    schema: u32,
+    #[serde(serialize_with = "serialize_displays")]
+    displays: &'a [DisplayInfo],
+}
+
+/// What `dpms status --json` prints: the schema 1 displays, and where they
+/// were read
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StatusReport {
+    /// `SCHEMA_VERSION`
+    pub schema: u32,
+    /// The backend that answered, as `PowerBackend::describe` names it
+    pub backend: String,
+    /// When the status was read, in seconds since the epoch
+    pub timestamp: u64,
+    #[serde(serialize_with = "serialize_displays")]
+    pub displays: Vec<DisplayInfo>,
+    /// How the backend reached the displays, e.g. `service`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub via: Option<String>,
+    /// TTY daemons holding displays off
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub daemons: Vec<DaemonReport>,
+}
+
+impl StatusReport {
+    /// Report `displays` as read just now
+    pub fn new(backend: String, displays: Vec<DisplayInfo>) -> Self {
+        Self {
+            schema: SCHEMA_VERSION,
+            backend,
+            timestamp: unix_now(),
+            displays,
+            via: None,
+            daemons: Vec::new(),
+        }
+    }
+}
+
+/// A TTY daemon as status reports show it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DaemonReport {
+    pub pid: i32,
+    /// When it turned the displays off, in seconds since the epoch
+    pub started: u64,
+    /// Connectors it holds, e.g. `HDMI-A-1`
+    pub displays: Vec<String>,
+    /// When it restores the displays on its own, after `off --timeout`
+    pub restore_deadline: Option<u64>,
+}
+
+/// How a `Report` is printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Human-readable text in `Style`
+    Text(Style),
+    /// One line of JSON, for `--json`
+    Json,
+    /// One terse line for status bars, for `status --short`
+    Short,
+}
+
+/// What a command has to say, whatever the backend
+///
+/// Commands build a report; `render` turns it into stdout and `notes` into
+/// stderr, in either format.
+#[derive(Debug)]
+pub enum Report {
+    /// Nothing, e.g. after `idle` or a watch that ended
+    Empty,
+    /// `status` of one display or several
+    Status(StatusReport),
+    /// `status --json --compat`: the bare display array of earlier versions
+    CompatStatus {
+        displays: Vec<DisplayInfo>,
+        via: Option<&'static str>,
+    },
+    /// `list`, with the backend `--verbose` names
+    List {
+        displays: Vec<DisplayInfo>,
+        verbose: bool,
+        backend: String,
+    },
+    /// The acknowledgement of `on`, `off` or `toggle`
+    Power {
+        /// Each targeted display in the state it was left in, with the
+        /// reason its change failed; only gathered for `--json`
+        results: Vec<(DisplayInfo, Result<(), String>)>,
+        /// How long displays turned back on were off
+        off: Vec<(String, u64)>,
+        /// What the backend noted on the way, for `--json`; text printed
+        /// them as they came
+        notices: Vec<String>,
+        /// The error to exit with once the results are printed, when some
+        /// changes failed
+        failure: Option<Error>,
+    },
+    /// `timeouts --show`
+    Timeouts(DpmsTimeouts),
+    /// `daemon-status`: each running TTY daemon
+    #[cfg(feature = "tty")]
+    DaemonStatus(Vec<DaemonStatus>),
+    /// `on --dry-run` or `off --dry-run`: what the change would do, one
+    /// line each
+    DryRun(Vec<String>),
+}
+
+impl Report {
+    /// What goes to stdout
+    pub fn render(&self, format: Format) -> String {
+        let (json, style) = match format {
+            Format::Text(style) => (false, style),
+            Format::Json => (true, Style::PLAIN),
+            Format::Short => (false, Style::PLAIN),
+        };
+        match self {
+            Report::Empty => String::new(),
+            Report::Status(report) if format == Format::Short => format_short(&report.displays),
+            Report::Status(report) if json => format_status_report(report),
+            Report::Status(report) => format_status(&report.displays, false, None, style),
+            Report::CompatStatus { displays, via } => format_status(displays, json, *via, style),
+            Report::List {
+                displays, verbose, ..
+            } => format_list(displays, json, *verbose, style),
+            Report::Power {
+                results,
+                off,
+                notices,
+                ..
+            } if json => format_power_results(results, off, notices),
+            Report::Power { .. } => String::new(),
+            Report::Timeouts(timeouts) => format_timeouts(timeouts),
+            #[cfg(feature = "tty")]
+            Report::DaemonStatus(statuses) => format_daemon_status(statuses, json),
+            Report::DryRun(actions) => format_dry_run(actions, json),
+        }
+    }
+
+    /// What goes to stderr alongside the text: the backend for `list
+    /// --verbose` and how long displays turned back on were off
+    pub fn notes(&self, format: Format) -> String {
+        if format == Format::Json {
+            return String::new();
+        }
+        match self {
+            Report::List {
+                verbose: true,
+                backend,
+                ..
+            } => format!("Backend: {}\n", backend),
+            Report::Power {
+                off, failure: None, ..
+            } => format_off_durations(off),
+            _ => String::new(),
+        }
+    }
+
+    /// The error a partly failed power change still exits with
+    pub fn into_result(self) -> Result<(), Error> {
+        match self {
+            Report::Power {
+                failure: Some(e), ..
+            } => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A failure as `--json` prints it
+#[derive(Serialize)]
+struct ErrorReport<'a> {
+    error: ErrorObject<'a>,
+}
+
+#[derive(Serialize)]
+struct ErrorObject<'a> {
+    /// `Error::code`
+    code: &'a str,
+    /// `Error::number`
+    number: i32,
+    message: String,
+    /// The error's fields, for errors a script can act on
+    #[serde(flatten)]
+    details: Option<ErrorDetails<'a>>,
+}
+
+/// Fields of an error, next to its message
+#[derive(Serialize)]
+#[serde(untagged)]
+enum ErrorDetails<'a> {
+    /// `PermissionDenied`: what was denied, and what to do about it
+    Permission { resource: &'a str, hint: &'a str },
+    /// `DisplayNotFound`: the name asked for, and the displays there are
+    NotFound {
+        name: &'a str,
+        available: &'a [String],
+    },
+    /// `AmbiguousDisplay`: the name asked for, and the displays it could mean
+    Ambiguous {
+        name: &'a str,
+        candidates: &'a [String],
+    },
+    /// `NoConnectedDisplay`: the device scanned, and its connectors
+    NoConnected {
+        device: &'a str,
+        connectors: &'a [ConnectorSeen],
+    },
+}
+
+impl<'a> ErrorDetails<'a> {
+    fn of(error: &'a Error) -> Option<Self> {
+        match error {
+            Error::PermissionDenied { resource, hint } => {
+                Some(ErrorDetails::Permission { resource, hint })
+            }
+            Error::DisplayNotFound { name, available } => {
+                Some(ErrorDetails::NotFound { name, available })
+            }
+            Error::AmbiguousDisplay { name, candidates } => {
+                Some(ErrorDetails::Ambiguous { name, candidates })
+            }
+            Error::NoConnectedDisplay { device, connectors } => {
+                Some(ErrorDetails::NoConnected { device, connectors })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Format `error` for stderr
+///
+/// `--json` gets `{"error":{"code","number","message"}}`, plus the fields of
+/// `PermissionDenied`, the display lookup errors and the ones saying what was
+/// scanned; text gets
+/// `Error: message`, or `Error[code]: message` with `codes`.
+pub fn format_error(error: &Error, format: Format, codes: bool) -> String {
+    match format {
+        Format::Json => {
+            let report = ErrorReport {
+                error: ErrorObject {
+                    code: error.code(),
+                    number: error.number(),
+                    message: error.to_string(),
+                    details: ErrorDetails::of(error),
+                },
+            };
+            format!("{}\n", to_json(&report))
+        }
+        Format::Text(_) | Format::Short if codes => {
+            format!("Error[{}]: {}\n", error.code(), error)
+        }
+        Format::Text(_) | Format::Short => format!("Error: {}\n", error),
+    }
+}
+
+/// Format a status report as one line of JSON
+pub fn format_status_report(report: &StatusReport) -> String {
+    format!("{}\n", to_json(report))
+}
+
 /// Format status output for one or more displays
-pub fn format_status(displays: &[DisplayInfo], json: bool) -> String {
+///
+/// The JSON is the bare display array that `status --json` printed before
+/// `StatusReport`, kept for `--compat`. `via` is noted on each display when
+/// the status came from somewhere other than the backends, e.g.
+/// `"via":"service"`. Only the text is styled.
+pub fn format_status(
+    displays: &[DisplayInfo],
+    json: bool,
+    via: Option<&str>,
+    style: Style,
+) -> String {
     if displays.is_empty() {
         return if json {
             "[]".to_string()
@@ -18,44 +415,371 @@ pub fn format_status(displays: &[DisplayInfo], json: bool) -> String {
     }
 
     if json {
-        format_displays_json(displays)
+        format_displays_json(displays, via)
     } else {
-        format_displays_text(displays, false)
+        format_displays_text(displays, false, style)
     }
 }
 
 /// Format list output for all displays
-pub fn format_list(displays: &[DisplayInfo], json: bool, verbose: bool) -> String {
-    if displays.is_empty() {
-        return if json {
-            "[]".to_string()
-        } else {
-            String::new()
+///
+/// The JSON is schema 1, one line. The text is a table when `style` asks
+/// for one.
+pub fn format_list(displays: &[DisplayInfo], json: bool, verbose: bool, style: Style) -> String {
+    if json {
+        let set = DisplaySet {
+            schema: SCHEMA_VERSION,
+            displays,
+        };
+        format!("{}\n", to_json(&set))
+    } else if style.table && !displays.is_empty() {
+        format_displays_table(displays, verbose, style)
+    } else {
+        format_displays_text(displays, verbose, style)
+    }
+}
+
+/// Where streamed events go, e.g. from `status --watch`
+pub trait EventSink {
+    /// Write `event`, flushed so pipes and `tail -f` see it at once
+    fn event(&mut self, event: &WatchEvent) -> io::Result<()>;
+}
+
+/// The sink for events in `format` on `out`
+pub fn event_sink<'a>(format: Format, out: impl Write + 'a) -> Box<dyn EventSink + 'a> {
+    match format {
+        Format::Text(_) => Box::new(TextSink(out)),
+        Format::Json => Box::new(NdjsonSink::new(out)),
+        Format::Short => Box::new(ShortSink {
+            out,
+            displays: Vec::new(),
+        }),
+    }
+}
+
+/// Format displays as one line for status bars: `on` or `off` for a single
+/// display, `DP-1:on HDMI-A-1:off` for several
+///
+/// The line is never empty of its newline, so a bar always gets one.
+pub fn format_short(displays: &[DisplayInfo]) -> String {
+    let state = |power| match power {
+        PowerState::On => "on",
+        PowerState::Off => "off",
+    };
+    let line = match displays {
+        [display] => state(display.power).to_string(),
+        _ => displays
+            .iter()
+            .map(|display| format!("{}:{}", display.name, state(display.power)))
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    format!("{}\n", line)
+}
+
+/// Events as a `status --short` line per change, covering every display
+/// seen so far
+pub struct ShortSink<W: Write> {
+    out: W,
+    displays: Vec<DisplayInfo>,
+}
+
+impl<W: Write> EventSink for ShortSink<W> {
+    fn event(&mut self, event: &WatchEvent) -> io::Result<()> {
+        // A reconnect changes no state; the events after it will
+        let WatchEvent::Power(display) = event else {
+            return Ok(());
         };
+        match self.displays.iter_mut().find(|d| d.name == display.name) {
+            Some(known) => known.power = display.power,
+            None => self.displays.push(display.clone()),
+        }
+        self.out
+            .write_all(format_short(&self.displays).as_bytes())?;
+        self.out.flush()
     }
+}
 
+/// Events as text lines, e.g. `DP-1: Off`
+pub struct TextSink<W: Write>(pub W);
+
+impl<W: Write> EventSink for TextSink<W> {
+    fn event(&mut self, event: &WatchEvent) -> io::Result<()> {
+        match event {
+            WatchEvent::Power(display) => self
+                .0
+                .write_all(format_display_line(display, false, Style::PLAIN).as_bytes())?,
+            WatchEvent::Reconnected => self.0.write_all(b"reconnected\n")?,
+        }
+        self.0.flush()
+    }
+}
+
+/// Events as NDJSON: one object per line, stamped with the time, e.g.
+/// `{"event":"power","display":"DP-1","state":"off","ts":"2024-05-01T10:00:00Z"}`
+pub struct NdjsonSink<W: Write> {
+    out: W,
+    /// Seconds since the epoch, `unix_now` but for tests
+    clock: fn() -> u64,
+}
+
+impl<W: Write> NdjsonSink<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            clock: unix_now,
+        }
+    }
+}
+
+/// An event as an NDJSON line has it, `event` first
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum EventRecord<'a> {
+    Power {
+        display: &'a str,
+        state: PowerState,
+        ts: String,
+    },
+    Reconnected {
+        ts: String,
+    },
+}
+
+impl<W: Write> EventSink for NdjsonSink<W> {
+    fn event(&mut self, event: &WatchEvent) -> io::Result<()> {
+        let ts = format_timestamp((self.clock)());
+        let record = match event {
+            WatchEvent::Power(display) => EventRecord::Power {
+                display: &display.name,
+                state: display.power,
+                ts,
+            },
+            WatchEvent::Reconnected => EventRecord::Reconnected { ts },
+        };
+        writeln!(self.out, "{}", to_json(&record))?;
+        self.out.flush()
+    }
+}
+
+/// Outcome of a power change on one display, as `--json` reports it
+#[derive(Serialize)]
+struct PowerResult<'a> {
+    #[serde(flatten)]
+    display: DisplayRecord<'a>,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    off_secs: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+/// Power change results in the schema 1 JSON
+#[derive(Serialize)]
+struct PowerResults<'a> {
+    schema: u32,
+    displays: Vec<PowerResult<'a>>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    notices: &'a [String],
+}
+
+/// Format the outcome of a power change for each display as schema 1 JSON
+///
+/// Each display, in the state it was left in, gets `success` and, if the
+/// change failed, `error`. Displays turned back on get an `off_secs` field
+/// with how long they were off, where `off` knows it. `notices`, e.g. that
+/// a display was already off, are listed after the displays.
+pub fn format_power_results(
+    results: &[(DisplayInfo, Result<(), String>)],
+    off: &[(String, u64)],
+    notices: &[String],
+) -> String {
+    let displays = results
+        .iter()
+        .map(|(display, result)| PowerResult {
+            display: DisplayRecord::from(display),
+            success: result.is_ok(),
+            off_secs: result
+                .is_ok()
+                .then(|| off.iter().find(|(name, _)| *name == display.name))
+                .flatten()
+                .map(|(_, secs)| *secs),
+            error: result.as_ref().err().map(String::as_str),
+        })
+        .collect();
+    let results = PowerResults {
+        schema: SCHEMA_VERSION,
+        displays,
+        notices,
+    };
+    format!("{}\n", to_json(&results))
+}
+
+/// What a dry run would do in the schema 1 JSON
+#[derive(Serialize)]
+struct DryRunReport<'a> {
+    schema: u32,
+    actions: &'a [String],
+}
+
+/// Format what a dry run would do, one line each, or as schema 1 JSON with
+/// the lines in `actions`
+pub fn format_dry_run(actions: &[String], json: bool) -> String {
     if json {
-        format_displays_json(displays)
+        let report = DryRunReport {
+            schema: SCHEMA_VERSION,
+            actions,
+        };
+        return format!("{}\n", to_json(&report));
+    }
+    actions
+        .iter()
+        .map(|action| format!("{}\n", action))
+        .collect()
+}
+
+/// Format the X server's DPMS timeouts, as `dpms timeouts --show` prints
+/// them
+pub fn format_timeouts(timeouts: &DpmsTimeouts) -> String {
+    let stage = |secs: u16| match secs {
+        0 => "disabled".to_string(),
+        secs => format!("{}s", secs),
+    };
+    format!(
+        "Standby: {}\nSuspend: {}\nOff: {}\n",
+        stage(timeouts.standby),
+        stage(timeouts.suspend),
+        stage(timeouts.off)
+    )
+}
+
+/// The current time in seconds since the epoch
+///
+/// A clock set before 1970 reads as 0 rather than failing.
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Format seconds since the epoch as an RFC 3339 UTC time, e.g.
+/// `2024-05-01T10:00:00Z`
+pub fn format_timestamp(secs: u64) -> String {
+    let (days, time) = (secs / 86_400, secs % 86_400);
+
+    // Civil date from days since 1970-01-01, after Howard Hinnant's
+    // `civil_from_days`
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
     } else {
-        format_displays_text(displays, verbose)
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Format a number of seconds as e.g. `45s`, `12m` or `3h05m`
+pub fn format_elapsed(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        _ => format!("{}h{:02}m", secs / 3600, secs / 60 % 60),
+    }
+}
+
+/// Format how long each display turned back on was off, one line each
+pub fn format_off_durations(off: &[(String, u64)]) -> String {
+    off.iter()
+        .map(|(name, secs)| format!("{} was off for {}\n", name, format_elapsed(*secs)))
+        .collect()
+}
+
+/// Format the status of the running TTY daemons
+///
+/// The JSON is an array with one object per daemon, whose `source` says
+/// whether the daemon answered itself or its state file was read.
+#[cfg(feature = "tty")]
+pub fn format_daemon_status(statuses: &[DaemonStatus], json: bool) -> String {
+    if json {
+        return format!("{}\n", serde_json::to_string(statuses).unwrap_or_default());
+    }
+    if statuses.is_empty() {
+        return "No daemon running\n".to_string();
+    }
+
+    let mut text = String::new();
+    for status in statuses {
+        let info = &status.info;
+        text.push_str(&format!(
+            "{}: PID {}, up {}, seat {} ({})\n",
+            status.instance,
+            info.pid,
+            format_elapsed(status.uptime),
+            info.seat.as_deref().unwrap_or("none"),
+            status.source
+        ));
+        for display in &info.displays {
+            text.push_str(&format!(
+                "  {} (CRTC {}) on {}: {}\n",
+                display.connector, display.crtc, display.device, display.asserted
+            ));
+        }
+        if let Some(at) = info.restore_deadline {
+            text.push_str(&format!("  Restoring at {}\n", format_timestamp(at)));
+        }
+        if info.sleep_inhibited {
+            text.push_str("  Holding a sleep inhibitor\n");
+        }
+        if info.seat_cycles > 0 {
+            text.push_str(&format!(
+                "  Seat taken away and given back {} time(s)\n",
+                info.seat_cycles
+            ));
+        }
+        if let Some(error) = &info.last_error {
+            text.push_str(&format!("  Last error: {}\n", error));
+        }
     }
+    text
+}
+
+/// Serialize `value` as compact JSON
+///
+/// Everything printed here serializes infallibly: plain structs, strings and
+/// numbers, with string keys.
+fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).expect("Output types serialize to JSON")
 }
 
 /// Format multiple displays as text
-fn format_displays_text(displays: &[DisplayInfo], verbose: bool) -> String {
+fn format_displays_text(displays: &[DisplayInfo], verbose: bool, style: Style) -> String {
     displays
         .iter()
-        .map(|d| format_display_line(d, verbose))
+        .map(|d| format_display_line(d, verbose, style))
         .collect::<Vec<_>>()
         .join("")
 }
 
 /// Format a single display line for text output
-fn format_display_line(display: &DisplayInfo, verbose: bool) -> String {
-    let power_str = match display.power {
-        PowerState::On => "On",
-        PowerState::Off => "Off",
-    };
+fn format_display_line(display: &DisplayInfo, verbose: bool, style: Style) -> String {
+    let power_str = style.paint(power_label(display.power), Some(power_color(display.power)));
 
     if verbose {
         // Include make/model if available
@@ -74,25 +798,162 @@ fn format_display_line(display: &DisplayInfo, verbose: bool) -> String {
     }
 }
 
-/// Format multiple displays as JSON array
-fn format_displays_json(displays: &[DisplayInfo]) -> String {
-    let parts: Vec<String> = displays
-        .iter()
-        .map(|d| {
-            let power_str = match d.power {
-                PowerState::On => "on",
-                PowerState::Off => "off",
-            };
-            format!(r#"{{"name":"{}","power":"{}"}}"#, d.name, power_str)
+/// `On` or `Off`, as text output shows a power state
+fn power_label(power: PowerState) -> &'static str {
+    match power {
+        PowerState::On => "On",
+        PowerState::Off => "Off",
+    }
+}
+
+/// The color of a power state: green for on, red for off
+fn power_color(power: PowerState) -> &'static str {
+    match power {
+        PowerState::On => ansi::GREEN,
+        PowerState::Off => ansi::RED,
+    }
+}
+
+/// Format displays as a table with a power indicator column, e.g.
+///
+/// ```text
+///    NAME   POWER
+/// ●  DP-1   On
+/// ○  eDP-1  Off
+/// ```
+///
+/// `verbose` adds the make and model. Columns line up on the text before
+/// coloring.
+fn format_displays_table(displays: &[DisplayInfo], verbose: bool, style: Style) -> String {
+    let mut header = vec!["", "NAME", "POWER"];
+    if verbose {
+        header.push("MONITOR");
+    }
+    let mut rows: Vec<Vec<(String, Option<&str>)>> = vec![
+        header
+            .into_iter()
+            .map(|title| (title.to_string(), Some(ansi::BOLD)))
+            .collect(),
+    ];
+    for display in displays {
+        let color = Some(power_color(display.power));
+        let indicator = match display.power {
+            PowerState::On => "●",
+            PowerState::Off => "○",
+        };
+        let mut row = vec![
+            (indicator.to_string(), color),
+            (display.name.clone(), None),
+            (power_label(display.power).to_string(), color),
+        ];
+        if verbose {
+            let monitor = [&display.make, &display.model]
+                .into_iter()
+                .flatten()
+                .map(|part| part.trim())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            row.push((monitor, None));
+        }
+        rows.push(row);
+    }
+
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].0.chars().count())
+                .max()
+                .unwrap_or(0)
         })
         .collect();
+    let mut table = String::new();
+    for row in &rows {
+        let mut line = String::new();
+        for (column, (text, code)) in row.iter().enumerate() {
+            line.push_str(&style.paint(text, *code));
+            if column + 1 < row.len() {
+                let padding = widths[column] - text.chars().count() + 2;
+                line.push_str(&" ".repeat(padding));
+            }
+        }
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
 
-    format!("[{}]", parts.join(","))
+/// A display in the bare JSON array, with where its status came from
+#[derive(Serialize)]
+struct ListedDisplay<'a> {
+    #[serde(flatten)]
+    display: &'a DisplayInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    via: Option<&'a str>,
+}
+
+/// Format multiple displays as JSON array
+fn format_displays_json(displays: &[DisplayInfo], via: Option<&str>) -> String {
+    let displays: Vec<ListedDisplay> = displays
+        .iter()
+        .map(|display| ListedDisplay { display, via })
+        .collect();
+    to_json(&displays)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::display::DrmIds;
+
+    #[test]
+    fn power_states_parse_their_names() {
+        for state in [PowerState::On, PowerState::Off] {
+            assert_eq!(PowerState::parse(state.name()), Ok(state));
+        }
+        assert!(PowerState::parse("Off").is_err());
+    }
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(format_timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp(1_714_557_600), "2024-05-01T10:00:00Z");
+        // Leap day
+        assert_eq!(format_timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_timestamp(4_102_444_799), "2099-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn formats_elapsed_time() {
+        assert_eq!(format_elapsed(45), "45s");
+        assert_eq!(format_elapsed(12 * 60 + 5), "12m");
+        assert_eq!(format_elapsed(3 * 3600 + 5 * 60), "3h05m");
+    }
+
+    /// The displays behind the golden files: an external monitor the TTY
+    /// backend knows everything about, and a bare internal panel
+    fn golden_displays() -> Vec<DisplayInfo> {
+        let mut external = make_display_verbose("DP-1", PowerState::Off, "Dell", "U2720Q");
+        external.drm = Some(DrmIds {
+            card: "/dev/dri/card0".to_string(),
+            connector_id: 77,
+            crtc_id: None,
+        });
+        vec![external, make_display("eDP-1", PowerState::On)]
+    }
+
+    /// Check that `output` is one line of JSON equal to the golden file
+    ///
+    /// The golden files under tests/golden lock the schema 1 JSON that
+    /// downstream parsers rely on; change them only with `SCHEMA_VERSION`,
+    /// or when adding a field.
+    fn assert_golden(output: &str, golden: &str) {
+        assert!(output.ends_with('\n'));
+        assert_eq!(output.lines().count(), 1);
+        let output: serde_json::Value = serde_json::from_str(output).unwrap();
+        let golden: serde_json::Value = serde_json::from_str(golden).unwrap();
+        assert_eq!(output, golden);
+    }
 
     // Helper to create DisplayInfo for tests
     fn make_display(name: &str, power: PowerState) -> DisplayInfo {
@@ -102,6 +963,7 @@ mod tests {
             description: None,
             make: None,
             model: None,
+            drm: None,
         }
     }
 
@@ -112,6 +974,7 @@ mod tests {
             description: None,
             make: Some(make.to_string()),
             model: Some(model.to_string()),
+            drm: None,
         }
     }
 
@@ -120,14 +983,17 @@ mod tests {
     #[test]
     fn format_status_single_text() {
         let displays = vec![make_display("DP-1", PowerState::On)];
-        assert_eq!(format_status(&displays, false), "DP-1: On\n");
+        assert_eq!(
+            format_status(&displays, false, None, Style::PLAIN),
+            "DP-1: On\n"
+        );
     }
 
     #[test]
     fn format_status_single_json() {
         let displays = vec![make_display("DP-1", PowerState::On)];
         assert_eq!(
-            format_status(&displays, true),
+            format_status(&displays, true, None, Style::PLAIN),
             r#"[{"name":"DP-1","power":"on"}]"#
         );
     }
@@ -138,7 +1004,10 @@ mod tests {
             make_display("DP-1", PowerState::On),
             make_display("eDP-1", PowerState::Off),
         ];
-        assert_eq!(format_status(&displays, false), "DP-1: On\neDP-1: Off\n");
+        assert_eq!(
+            format_status(&displays, false, None, Style::PLAIN),
+            "DP-1: On\neDP-1: Off\n"
+        );
     }
 
     #[test]
@@ -148,11 +1017,210 @@ mod tests {
             make_display("eDP-1", PowerState::Off),
         ];
         assert_eq!(
-            format_status(&displays, true),
+            format_status(&displays, true, None, Style::PLAIN),
             r#"[{"name":"DP-1","power":"on"},{"name":"eDP-1","power":"off"}]"#
         );
     }
 
+    #[test]
+    fn format_status_notes_the_service_in_json() {
+        let displays = vec![make_display("DP-1", PowerState::Off)];
+        assert_eq!(
+            format_status(&displays, true, Some("service"), Style::PLAIN),
+            r#"[{"name":"DP-1","power":"off","via":"service"}]"#
+        );
+        assert_eq!(
+            format_status(&displays, false, Some("service"), Style::PLAIN),
+            "DP-1: Off\n"
+        );
+    }
+
+    #[test]
+    fn format_status_colors_the_state() {
+        let displays = vec![
+            make_display("DP-1", PowerState::On),
+            make_display("eDP-1", PowerState::Off),
+        ];
+        let style = Style {
+            color: true,
+            table: true,
+        };
+        assert_eq!(
+            format_status(&displays, false, None, style),
+            "DP-1: \x1b[32mOn\x1b[0m\neDP-1: \x1b[31mOff\x1b[0m\n"
+        );
+    }
+
+    // ===== Color and tables =====
+
+    #[test]
+    fn style_follows_terminal_no_color_and_flag() {
+        let resolve = Style::resolve;
+        let table = |color| Style { color, table: true };
+        assert_eq!(resolve(ColorChoice::Auto, true, false), table(true));
+        assert_eq!(resolve(ColorChoice::Auto, true, true), table(false));
+        assert_eq!(resolve(ColorChoice::Auto, false, false), Style::PLAIN);
+        assert_eq!(resolve(ColorChoice::Never, true, false), table(false));
+        assert_eq!(resolve(ColorChoice::Always, true, true), table(true));
+        assert_eq!(
+            resolve(ColorChoice::Always, false, false),
+            Style {
+                color: true,
+                table: false
+            }
+        );
+    }
+
+    #[test]
+    fn format_list_table_matches_snapshots() {
+        let plain = Style {
+            color: false,
+            table: true,
+        };
+        let color = Style {
+            color: true,
+            table: true,
+        };
+        assert_eq!(
+            format_list(&golden_displays(), false, false, plain),
+            include_str!("../tests/golden/list_table.txt")
+        );
+        assert_eq!(
+            format_list(&golden_displays(), false, true, plain),
+            include_str!("../tests/golden/list_table_verbose.txt")
+        );
+        assert_eq!(
+            format_list(&golden_displays(), false, false, color),
+            include_str!("../tests/golden/list_table_color.txt")
+        );
+    }
+
+    #[test]
+    fn short_status_matches_snapshots() {
+        let displays = golden_displays();
+        let report = |displays: &[DisplayInfo]| {
+            Report::Status(StatusReport::new("test".to_string(), displays.to_vec()))
+                .render(Format::Short)
+        };
+        assert_eq!(
+            report(&displays[1..]),
+            include_str!("../tests/golden/status_short.txt")
+        );
+        assert_eq!(
+            report(&displays),
+            include_str!("../tests/golden/status_short_multi.txt")
+        );
+        assert_eq!(report(&[]), "\n");
+    }
+
+    #[test]
+    fn errors_format_with_their_code() {
+        let error = Error::NoDisplayFound;
+        let text = Format::Text(Style::PLAIN);
+        assert_eq!(
+            format_error(&error, text, false),
+            "Error: No connected display found\n"
+        );
+        assert_eq!(
+            format_error(&error, text, true),
+            "Error[no_display_found]: No connected display found\n"
+        );
+        assert_eq!(
+            format_error(&error, Format::Json, false),
+            "{\"error\":{\"code\":\"no_display_found\",\"number\":3,\
+             \"message\":\"No connected display found\"}}\n"
+        );
+    }
+
+    #[test]
+    fn permission_errors_carry_resource_and_hint() {
+        let error = Error::PermissionDenied {
+            resource: "/run/user/1000".to_string(),
+            hint: "XDG_RUNTIME_DIR is not writable".to_string(),
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&format_error(&error, Format::Json, false)).unwrap();
+        assert_eq!(json["error"]["code"], "permission_denied");
+        assert_eq!(json["error"]["resource"], "/run/user/1000");
+        assert_eq!(json["error"]["hint"], "XDG_RUNTIME_DIR is not writable");
+    }
+
+    #[test]
+    fn display_errors_carry_their_names() {
+        let error = Error::DisplayNotFound {
+            name: "HDMI-1".to_string(),
+            available: vec!["DP-1".to_string(), "eDP-1".to_string()],
+        };
+        assert_eq!(
+            format_error(&error, Format::Json, false),
+            "{\"error\":{\"code\":\"display_not_found\",\"number\":4,\
+             \"message\":\"Display 'HDMI-1' not found. Available:\\n  DP-1\\n  eDP-1\",\
+             \"name\":\"HDMI-1\",\"available\":[\"DP-1\",\"eDP-1\"]}}\n"
+        );
+
+        let error = Error::AmbiguousDisplay {
+            name: "DP".to_string(),
+            candidates: vec!["DP-1".to_string(), "DP-2".to_string()],
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&format_error(&error, Format::Json, false)).unwrap();
+        assert_eq!(json["error"]["code"], "ambiguous_display");
+        assert_eq!(json["error"]["name"], "DP");
+        assert_eq!(
+            json["error"]["candidates"],
+            serde_json::json!(["DP-1", "DP-2"])
+        );
+    }
+
+    #[test]
+    fn scan_errors_carry_what_was_seen() {
+        let error = Error::NoConnectedDisplay {
+            device: "/dev/dri/card0".to_string(),
+            connectors: vec![ConnectorSeen {
+                name: "eDP-1".to_string(),
+                state: "disconnected".to_string(),
+            }],
+        };
+        assert_eq!(
+            format_error(&error, Format::Text(Style::PLAIN), false),
+            "Error: No connected display found (card0: eDP-1=disconnected)\n"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&format_error(&error, Format::Json, false)).unwrap();
+        assert_eq!(json["error"]["code"], "no_connected_display");
+        assert_eq!(json["error"]["device"], "/dev/dri/card0");
+        assert_eq!(
+            json["error"]["connectors"],
+            serde_json::json!([{"name": "eDP-1", "state": "disconnected"}])
+        );
+
+        // With no output advertised there is nothing more to tell
+        let error = Error::NoOutputAdvertised;
+        let json: serde_json::Value =
+            serde_json::from_str(&format_error(&error, Format::Json, false)).unwrap();
+        assert_eq!(json["error"]["code"], "no_output_advertised");
+        assert!(json["error"].get("outputs").is_none(), "{}", json);
+    }
+
+    #[test]
+    fn json_is_never_colored() {
+        let style = Style {
+            color: true,
+            table: true,
+        };
+        let displays = golden_displays();
+        for output in [
+            format_list(&displays, true, true, style),
+            format_status(&displays, true, None, style),
+        ] {
+            assert!(!output.contains('\x1b'), "{}", output);
+        }
+        assert_eq!(
+            format_list(&displays, true, false, style),
+            format_list(&displays, true, false, Style::PLAIN)
+        );
+    }
+
     // ===== List command tests =====
 
     #[test]
@@ -162,7 +1230,7 @@ mod tests {
             make_display("eDP-1", PowerState::Off),
         ];
         assert_eq!(
-            format_list(&displays, false, false),
+            format_list(&displays, false, false, Style::PLAIN),
             "DP-1: On\neDP-1: Off\n"
         );
     }
@@ -171,8 +1239,17 @@ mod tests {
     fn format_list_json() {
         let displays = vec![make_display("DP-1", PowerState::On)];
         assert_eq!(
-            format_list(&displays, true, false),
-            r#"[{"name":"DP-1","power":"on"}]"#
+            format_list(&displays, true, false, Style::PLAIN),
+            "{\"schema\":1,\"displays\":[{\"name\":\"DP-1\",\"power\":\"on\",\
+             \"internal\":false,\"make\":null,\"model\":null,\"drm\":null}]}\n"
+        );
+    }
+
+    #[test]
+    fn format_list_json_matches_golden() {
+        assert_golden(
+            &format_list(&golden_displays(), true, false, Style::PLAIN),
+            include_str!("../tests/golden/list.json"),
         );
     }
 
@@ -185,11 +1262,68 @@ mod tests {
             "U2720Q",
         )];
         assert_eq!(
-            format_list(&displays, false, true),
+            format_list(&displays, false, true, Style::PLAIN),
             "DP-1: On (Dell U2720Q)\n"
         );
     }
 
+    #[test]
+    fn format_list_json_includes_make_model() {
+        let displays = vec![make_display_verbose(
+            "DP-1",
+            PowerState::On,
+            "Dell",
+            "U2720Q",
+        )];
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_list(&displays, true, false, Style::PLAIN)).unwrap();
+        assert_eq!(parsed["displays"][0]["make"], "Dell");
+        assert_eq!(parsed["displays"][0]["model"], "U2720Q");
+    }
+
+    #[test]
+    fn format_status_json_omits_missing_make_model() {
+        let displays = vec![DisplayInfo {
+            name: "DP-1".to_string(),
+            power: PowerState::Off,
+            description: None,
+            make: Some("Dell".to_string()),
+            model: None,
+            drm: None,
+        }];
+        assert_eq!(
+            format_status(&displays, true, None, Style::PLAIN),
+            r#"[{"name":"DP-1","power":"off","make":"Dell"}]"#
+        );
+    }
+
+    #[test]
+    fn format_status_json_includes_drm_ids() {
+        let mut display = make_display("HDMI-A-1", PowerState::Off);
+        display.drm = Some(DrmIds {
+            card: "/dev/dri/card0".to_string(),
+            connector_id: 77,
+            crtc_id: Some(51),
+        });
+        let mut dark = make_display("DP-1", PowerState::Off);
+        dark.drm = Some(DrmIds {
+            card: "/dev/dri/card0".to_string(),
+            connector_id: 80,
+            crtc_id: None,
+        });
+        let displays = vec![display, dark];
+
+        assert_eq!(
+            format_status(&displays, true, None, Style::PLAIN),
+            r#"[{"name":"HDMI-A-1","power":"off","drm":{"card":"/dev/dri/card0","connector_id":77,"crtc_id":51}},{"name":"DP-1","power":"off","drm":{"card":"/dev/dri/card0","connector_id":80,"crtc_id":null}}]"#
+        );
+        // Text output is unchanged
+        assert_eq!(
+            format_status(&displays, false, None, Style::PLAIN),
+            "HDMI-A-1: Off\nDP-1: Off\n"
+        );
+    }
+
     #[test]
     fn format_list_verbose_partial_info() {
         let displays = vec![DisplayInfo {
@@ -198,8 +1332,9 @@ mod tests {
             description: None,
             make: Some("Dell".to_string()),
             model: None,
+            drm: None,
         }];
-        let output = format_list(&displays, false, true);
+        let output = format_list(&displays, false, true, Style::PLAIN);
         assert!(output.contains("DP-1: On"));
         assert!(output.contains("Dell"));
     }
@@ -207,8 +1342,86 @@ mod tests {
     #[test]
     fn format_list_empty() {
         let displays: Vec<DisplayInfo> = vec![];
-        assert_eq!(format_list(&displays, false, false), "");
-        assert_eq!(format_list(&displays, true, false), "[]");
+        assert_eq!(format_list(&displays, false, false, Style::PLAIN), "");
+        assert_eq!(
+            format_list(&displays, true, false, Style::PLAIN),
+            "{\"schema\":1,\"displays\":[]}\n"
+        );
+    }
+
+    // ===== Watch event tests =====
+
+    /// An NDJSON sink into a buffer whose clock stands at 2024-05-01T10:00:00Z
+    fn ndjson_sink(out: &mut Vec<u8>) -> NdjsonSink<&mut Vec<u8>> {
+        NdjsonSink {
+            out,
+            clock: || 1_714_557_600,
+        }
+    }
+
+    #[test]
+    fn text_sink_writes_lines() {
+        let mut out = Vec::new();
+        let mut sink = TextSink(&mut out);
+        sink.event(&WatchEvent::Power(make_display("DP-1", PowerState::Off)))
+            .unwrap();
+        sink.event(&WatchEvent::Reconnected).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "DP-1: Off\nreconnected\n");
+    }
+
+    #[test]
+    fn ndjson_sink_stamps_each_event() {
+        let mut out = Vec::new();
+        let mut sink = ndjson_sink(&mut out);
+        sink.event(&WatchEvent::Power(make_display("DP-1", PowerState::Off)))
+            .unwrap();
+        sink.event(&WatchEvent::Reconnected).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                r#"{"event":"power","display":"DP-1","state":"off","ts":"2024-05-01T10:00:00Z"}"#,
+                "\n",
+                r#"{"event":"reconnected","ts":"2024-05-01T10:00:00Z"}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn short_sink_writes_a_line_per_change() {
+        let mut out = Vec::new();
+        let mut sink = event_sink(Format::Short, &mut out);
+        for (name, power) in [
+            ("DP-1", PowerState::On),
+            ("HDMI-A-1", PowerState::On),
+            ("DP-1", PowerState::Off),
+        ] {
+            sink.event(&WatchEvent::Power(make_display(name, power)))
+                .unwrap();
+        }
+        sink.event(&WatchEvent::Reconnected).unwrap();
+        drop(sink);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "on\nDP-1:on HDMI-A-1:on\nDP-1:off HDMI-A-1:on\n"
+        );
+    }
+
+    #[test]
+    fn event_sink_follows_the_format() {
+        let mut out = Vec::new();
+        event_sink(Format::Text(Style::PLAIN), &mut out)
+            .event(&WatchEvent::Reconnected)
+            .unwrap();
+        event_sink(Format::Json, &mut out)
+            .event(&WatchEvent::Reconnected)
+            .unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("reconnected"));
+        let parsed: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(parsed["event"], "reconnected");
+        assert!(parsed["ts"].as_str().unwrap().ends_with('Z'));
     }
 
     // ===== Empty input tests =====
@@ -216,8 +1429,8 @@ mod tests {
     #[test]
     fn format_status_empty() {
         let displays: Vec<DisplayInfo> = vec![];
-        assert_eq!(format_status(&displays, false), "");
-        assert_eq!(format_status(&displays, true), "[]");
+        assert_eq!(format_status(&displays, false, None, Style::PLAIN), "");
+        assert_eq!(format_status(&displays, true, None, Style::PLAIN), "[]");
     }
 
     // ===== PowerState tests =====
@@ -242,7 +1455,7 @@ mod tests {
     #[test]
     fn json_output_is_valid_json() {
         let displays = vec![make_display("DP-1", PowerState::On)];
-        let json = format_status(&displays, true);
+        let json = format_status(&displays, true, None, Style::PLAIN);
         assert!(json.starts_with('['));
         assert!(json.ends_with(']'));
         assert!(json.contains("\"power\""));
@@ -255,11 +1468,234 @@ mod tests {
             make_display("DP-1", PowerState::On),
             make_display("eDP-1", PowerState::Off),
         ];
-        let json = format_status(&displays, true);
+        let json = format_status(&displays, true, None, Style::PLAIN);
         assert!(json.starts_with('['));
         assert!(json.ends_with(']'));
         assert!(json.contains("\"name\""));
         assert!(json.contains("\"DP-1\""));
         assert!(json.contains("\"eDP-1\""));
     }
+
+    // ===== Power result tests =====
+
+    #[test]
+    fn format_power_results_all_ok() {
+        let results = vec![
+            (make_display("DP-1", PowerState::Off), Ok(())),
+            (make_display("DP-2", PowerState::Off), Ok(())),
+        ];
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_power_results(&results, &[], &[])).unwrap();
+        assert_eq!(parsed["schema"], SCHEMA_VERSION);
+        for (display, name) in parsed["displays"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .zip(["DP-1", "DP-2"])
+        {
+            assert_eq!(display["name"], name);
+            assert_eq!(display["power"], "off");
+            assert_eq!(display["success"], true);
+            assert!(display.get("error").is_none());
+        }
+    }
+
+    #[test]
+    fn format_dry_run_prints_a_line_per_action() {
+        let actions = [
+            "Would turn off eDP-1 on /dev/dri/card0".to_string(),
+            "Would turn off HDMI-A-1 on /dev/dri/card0".to_string(),
+        ];
+        assert_eq!(
+            format_dry_run(&actions, false),
+            "Would turn off eDP-1 on /dev/dri/card0\nWould turn off HDMI-A-1 on /dev/dri/card0\n"
+        );
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_dry_run(&actions, true)).unwrap();
+        assert_eq!(parsed["schema"], SCHEMA_VERSION);
+        assert_eq!(parsed["actions"], serde_json::json!(actions));
+    }
+
+    #[test]
+    fn format_timeouts_names_disabled_stages() {
+        let timeouts = DpmsTimeouts {
+            standby: 600,
+            suspend: 0,
+            off: 1200,
+        };
+        assert_eq!(
+            format_timeouts(&timeouts),
+            "Standby: 600s\nSuspend: disabled\nOff: 1200s\n"
+        );
+    }
+
+    #[test]
+    fn format_power_results_with_off_duration() {
+        let results = vec![
+            (make_display("DP-1", PowerState::On), Ok(())),
+            (make_display("DP-2", PowerState::On), Ok(())),
+        ];
+        let parsed: serde_json::Value = serde_json::from_str(&format_power_results(
+            &results,
+            &[("DP-2".to_string(), 754)],
+            &[],
+        ))
+        .unwrap();
+        assert!(parsed["displays"][0].get("off_secs").is_none());
+        assert_eq!(parsed["displays"][1]["off_secs"], 754);
+        assert_eq!(
+            format_off_durations(&[("DP-2".to_string(), 754)]),
+            "DP-2 was off for 12m\n"
+        );
+        assert_eq!(format_off_durations(&[]), "");
+    }
+
+    #[test]
+    fn format_power_results_lists_notices() {
+        let results = vec![(make_display("DP-1", PowerState::Off), Ok(()))];
+        let notices = ["Display already off".to_string()];
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_power_results(&results, &[], &notices)).unwrap();
+        assert_eq!(
+            parsed["notices"],
+            serde_json::json!(["Display already off"])
+        );
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_power_results(&results, &[], &[])).unwrap();
+        assert!(parsed.get("notices").is_none());
+    }
+
+    #[test]
+    fn format_power_results_with_failure() {
+        let results = vec![
+            (make_display("DP-1", PowerState::On), Ok(())),
+            (
+                make_display("eDP-1", PowerState::Off),
+                Err("power control held by another client".to_string()),
+            ),
+        ];
+        assert_golden(
+            &format_power_results(&results, &[("DP-1".to_string(), 754)], &[]),
+            include_str!("../tests/golden/power_results.json"),
+        );
+    }
+
+    #[test]
+    fn awkward_names_survive_json() {
+        let name = "Unknown-1 \"flaky\" \\ line\nbreak\t";
+        let displays = vec![make_display(name, PowerState::Off)];
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_status(&displays, true, None, Style::PLAIN)).unwrap();
+        assert_eq!(parsed[0]["name"], name);
+
+        let mut out = Vec::new();
+        ndjson_sink(&mut out)
+            .event(&WatchEvent::Power(displays[0].clone()))
+            .unwrap();
+        let parsed: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(parsed["display"], name);
+
+        let results = vec![(displays[0].clone(), Err("say \"no\"".to_string()))];
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_power_results(&results, &[], &[])).unwrap();
+        assert_eq!(parsed["displays"][0]["name"], name);
+        assert_eq!(parsed["displays"][0]["error"], "say \"no\"");
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_list(&displays, true, false, Style::PLAIN)).unwrap();
+        assert_eq!(parsed["displays"][0]["name"], name);
+    }
+
+    #[test]
+    fn status_report_matches_golden() {
+        let report = StatusReport {
+            schema: SCHEMA_VERSION,
+            backend: "tty (DRM)".to_string(),
+            timestamp: 1_700_000_000,
+            displays: golden_displays(),
+            via: None,
+            daemons: vec![DaemonReport {
+                pid: 4242,
+                started: 1_699_999_000,
+                displays: vec!["DP-1".to_string()],
+                restore_deadline: None,
+            }],
+        };
+
+        assert_golden(
+            &format_status_report(&report),
+            include_str!("../tests/golden/status.json"),
+        );
+    }
+
+    #[test]
+    fn status_report_notes_the_service() {
+        let mut report = StatusReport::new(
+            "service".to_string(),
+            vec![make_display("DP-1", PowerState::On)],
+        );
+        report.via = Some("service".to_string());
+        assert!(report.timestamp > 0);
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&format_status_report(&report)).unwrap();
+        assert_eq!(parsed["via"], "service");
+        assert!(parsed.get("daemons").is_none());
+    }
+
+    // ===== Daemon status tests =====
+
+    /// A daemon's status, from state JSON written before `asserted` was
+    #[cfg(feature = "tty")]
+    fn daemon_status(source: &'static str) -> DaemonStatus {
+        let info = serde_json::from_str(
+            r#"{"version":1,"pid":4242,"started":1700000000,"devices":["/dev/dri/card0"],
+                "displays":[{"device":"/dev/dri/card0","connector":"HDMI-A-1","crtc":51,"was_active":true}],
+                "restore_deadline":1714557600,"seat":"seat0","seat_cycles":2,"last_error":"EBUSY",
+                "sleep_inhibited":true}"#,
+        )
+        .unwrap();
+        DaemonStatus {
+            source,
+            instance: "all displays".to_string(),
+            uptime: 750,
+            info,
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tty")]
+    fn format_daemon_status_json_marks_the_source() {
+        let json = format_daemon_status(&[daemon_status("live"), daemon_status("statefile")], true);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed[0]["source"], "live");
+        assert_eq!(parsed[1]["source"], "statefile");
+        assert_eq!(parsed[0]["uptime"], 750);
+        assert_eq!(parsed[0]["seat"], "seat0");
+        assert_eq!(parsed[0]["seat_cycles"], 2);
+        assert_eq!(parsed[0]["sleep_inhibited"], true);
+        assert_eq!(parsed[0]["last_error"], "EBUSY");
+        assert_eq!(parsed[0]["restore_deadline"], 1_714_557_600);
+        assert_eq!(parsed[0]["displays"][0]["asserted"], "off");
+        assert_eq!(format_daemon_status(&[], true), "[]\n");
+    }
+
+    #[test]
+    #[cfg(feature = "tty")]
+    fn format_daemon_status_text() {
+        assert_eq!(
+            format_daemon_status(&[daemon_status("live")], false),
+            "all displays: PID 4242, up 12m, seat seat0 (live)\n\
+             \x20 HDMI-A-1 (CRTC 51) on /dev/dri/card0: off\n\
+             \x20 Restoring at 2024-05-01T10:00:00Z\n\
+             \x20 Holding a sleep inhibitor\n\
+             \x20 Seat taken away and given back 2 time(s)\n\
+             \x20 Last error: EBUSY\n"
+        );
+        assert_eq!(format_daemon_status(&[], false), "No daemon running\n");
+    }
 }
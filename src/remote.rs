@@ -0,0 +1,242 @@
+/// Running dpms on another host over SSH (`--host`)
+///
+/// The system `ssh` runs the remote `dpms` with the same subcommand and
+/// flags. Its stdin, stdout and stderr are passed through untouched, so JSON
+/// output and exit codes look the same as from a local run.
+use crate::error::Error;
+use std::ffi::{OsStr, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+use std::str::FromStr;
+
+/// Name of the binary run on the remote host
+const REMOTE_BINARY: &str = "dpms";
+
+/// Exit code of a POSIX shell that could not find the command
+const COMMAND_NOT_FOUND: i32 = 127;
+
+/// Host to run dpms on, as given to `--host user@host:port`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Destination {
+    /// `host` or `user@host`, passed to ssh as is
+    pub target: String,
+    /// SSH port, when not the one from the ssh config
+    pub port: Option<u16>,
+}
+
+impl FromStr for Destination {
+    type Err = String;
+
+    /// Parse `host`, `user@host`, `host:port` or `user@[v6addr]:port`
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (user, host) = match value.rsplit_once('@') {
+            Some((user, host)) => (Some(user), host),
+            None => (None, value),
+        };
+
+        let (host, port) = if let Some(bracketed) = host.strip_prefix('[') {
+            let (address, rest) = bracketed
+                .split_once(']')
+                .ok_or_else(|| format!("missing ']' in host '{}'", value))?;
+            match rest {
+                "" => (address, None),
+                _ => match rest.strip_prefix(':') {
+                    Some(port) => (address, Some(port)),
+                    None => return Err(format!("unexpected '{}' after ']'", rest)),
+                },
+            }
+        } else {
+            match host.split_once(':') {
+                // A bare IPv6 address has several colons and no port
+                Some((name, port)) if !port.contains(':') => (name, Some(port)),
+                _ => (host, None),
+            }
+        };
+
+        if host.is_empty() || user.is_some_and(str::is_empty) {
+            return Err(format!("invalid host '{}'", value));
+        }
+        let port = port
+            .map(|port| {
+                port.parse::<u16>()
+                    .ok()
+                    .filter(|&port| port != 0)
+                    .ok_or_else(|| format!("invalid port '{}'", port))
+            })
+            .transpose()?;
+
+        Ok(Destination {
+            target: match user {
+                Some(user) => format!("{}@{}", user, host),
+                None => host.to_string(),
+            },
+            port,
+        })
+    }
+}
+
+/// The command line to forward: `args` without `--host` and its value
+///
+/// `args` excludes the program name.
+pub fn forwarded_args(args: impl IntoIterator<Item = OsString>) -> Vec<OsString> {
+    let mut forwarded = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            forwarded.push(arg);
+            forwarded.extend(args);
+            break;
+        }
+        if arg == "--host" {
+            args.next();
+        } else if !arg.as_bytes().starts_with(b"--host=") {
+            forwarded.push(arg);
+        }
+    }
+    forwarded
+}
+
+/// Quote `arg` for the POSIX shell ssh hands the remote command to
+fn shell_quote(arg: &OsStr) -> OsString {
+    let bytes = arg.as_bytes();
+    let plain = !bytes.is_empty()
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_alphanumeric() || b"-_./=:,@%+".contains(b));
+    if plain {
+        return arg.to_os_string();
+    }
+
+    let mut quoted = vec![b'\''];
+    for &b in bytes {
+        if b == b'\'' {
+            quoted.extend_from_slice(b"'\\''");
+        } else {
+            quoted.push(b);
+        }
+    }
+    quoted.push(b'\'');
+    OsString::from_vec(quoted)
+}
+
+/// The remote command line, quoted so the remote shell splits it back into `args`
+fn remote_command(args: &[OsString]) -> OsString {
+    let mut command = OsString::from(REMOTE_BINARY);
+    for arg in args {
+        command.push(" ");
+        command.push(shell_quote(arg));
+    }
+    command
+}
+
+/// Run `dpms args` on `destination` and wait for it
+///
+/// # Returns
+/// The remote exit code, or ssh's own (255) if it could not connect
+///
+/// # Errors
+/// `RemoteNotInstalled` if the remote shell can't find dpms, `SshFailed` if
+/// ssh can't be started or is killed
+pub fn run(destination: &Destination, args: &[OsString]) -> Result<u8, Error> {
+    let mut ssh = Command::new("ssh");
+    if let Some(port) = destination.port {
+        ssh.arg("-p").arg(port.to_string());
+    }
+    ssh.arg("--")
+        .arg(&destination.target)
+        .arg(remote_command(args));
+
+    let status = ssh
+        .status()
+        .map_err(|e| Error::SshFailed(format!("could not run ssh: {}", e)))?;
+    match status.code() {
+        Some(COMMAND_NOT_FOUND) => Err(Error::RemoteNotInstalled {
+            host: destination.target.clone(),
+        }),
+        Some(code) => Ok(code as u8),
+        None => Err(Error::SshFailed(format!(
+            "ssh was killed by signal {}",
+            status.signal().unwrap_or_default()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os_args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parse_destinations() {
+        let parse = |value: &str| value.parse::<Destination>();
+        assert_eq!(
+            parse("htpc"),
+            Ok(Destination {
+                target: "htpc".to_string(),
+                port: None
+            })
+        );
+        assert_eq!(
+            parse("me@htpc:2222"),
+            Ok(Destination {
+                target: "me@htpc".to_string(),
+                port: Some(2222)
+            })
+        );
+        assert_eq!(
+            parse("me@[fe80::1]:22"),
+            Ok(Destination {
+                target: "me@fe80::1".to_string(),
+                port: Some(22)
+            })
+        );
+        assert_eq!(
+            parse("fe80::1"),
+            Ok(Destination {
+                target: "fe80::1".to_string(),
+                port: None
+            })
+        );
+        assert!(parse("htpc:ssh").is_err());
+        assert!(parse("htpc:0").is_err());
+        assert!(parse("@htpc").is_err());
+        assert!(parse("me@").is_err());
+        assert!(parse("[fe80::1").is_err());
+    }
+
+    #[test]
+    fn forwarded_args_drop_host() {
+        assert_eq!(
+            forwarded_args(os_args(&["--host", "htpc", "status", "--json"])),
+            os_args(&["status", "--json"])
+        );
+        assert_eq!(
+            forwarded_args(os_args(&["off", "--host=me@htpc", "DP-1"])),
+            os_args(&["off", "DP-1"])
+        );
+        assert_eq!(
+            forwarded_args(os_args(&["on", "--", "--host"])),
+            os_args(&["on", "--", "--host"])
+        );
+    }
+
+    #[test]
+    fn remote_command_quotes_arguments() {
+        assert_eq!(
+            remote_command(&os_args(&["status", "--json", "DP-1"])),
+            "dpms status --json DP-1"
+        );
+        assert_eq!(
+            remote_command(&os_args(&["on", "Unknown-1 \"flaky\"", "it's", ""])),
+            "dpms on 'Unknown-1 \"flaky\"' 'it'\\''s' ''"
+        );
+        assert_eq!(
+            remote_command(&os_args(&["on", "$(reboot)"])),
+            "dpms on '$(reboot)'"
+        );
+    }
+}
@@ -0,0 +1,504 @@
+/// `dpms repl`: JSON requests on standard input, answers on standard output
+///
+/// Each input line is one request, e.g.
+/// `{"id":1,"method":"set_power","params":{"state":"off","display":"DP-1"}}`,
+/// answered by one line carrying the same `id` and either `result`, the
+/// schema 1 records of the displays concerned, or `error`. A request
+/// without an `id` is a notification: it is carried out but not
+/// answered. Error objects have the `Error::number` of the failure as
+/// `code` and its `Error::code` as `data.code`; requests that can't be
+/// carried out at all get JSON-RPC's own negative codes. The backend is
+/// connected once and reused for every request. With `--watch`, power
+/// changes are also sent unprompted, as
+/// `{"method":"power_changed","params":{"display":"DP-1","state":"off","ts":"2024-05-01T10:00:00Z"}}`.
+/// End of input exits without touching the displays.
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+
+use serde_json::{Map, Value, json};
+
+use crate::backend::{self, PowerBackend};
+use crate::display::{DisplayInfo, DisplayTarget};
+use crate::error::Error;
+use crate::output::{self, DisplayRecord, PowerState};
+
+/// The request isn't JSON
+const PARSE_ERROR: i32 = -32700;
+/// The request isn't an object with a method
+const INVALID_REQUEST: i32 = -32600;
+/// No such method
+const METHOD_NOT_FOUND: i32 = -32601;
+/// The method's parameters are missing or of the wrong type
+const INVALID_PARAMS: i32 = -32602;
+
+/// What a request asks of the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Method {
+    SetPower(DisplayTarget, PowerState),
+    GetPower(DisplayTarget),
+    ListDisplays,
+}
+
+/// Why a request was refused before reaching the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Refusal {
+    code: i32,
+    message: String,
+    /// `Error::code`, when the backend failed
+    name: Option<&'static str>,
+}
+
+impl Refusal {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Refusal {
+            code,
+            message: message.into(),
+            name: None,
+        }
+    }
+}
+
+impl From<Error> for Refusal {
+    fn from(e: Error) -> Self {
+        Refusal {
+            code: e.number(),
+            message: e.to_string(),
+            name: Some(e.code()),
+        }
+    }
+}
+
+/// Parse one request line
+///
+/// # Returns
+/// The request's `id`, `None` for an object without one, `null` if it
+/// can't be read, and the method or why it was refused
+fn parse_request(line: &str) -> (Option<Value>, Result<Method, Refusal>) {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return (
+                Some(Value::Null),
+                Err(Refusal::new(PARSE_ERROR, e.to_string())),
+            );
+        }
+    };
+    let id = match &request {
+        Value::Object(fields) => fields.get("id").cloned(),
+        _ => Some(Value::Null),
+    };
+    let Some(method) = request.get("method").and_then(Value::as_str) else {
+        return (
+            id,
+            Err(Refusal::new(
+                INVALID_REQUEST,
+                "expected an object with a method",
+            )),
+        );
+    };
+    let empty = Map::new();
+    let params = match request.get("params") {
+        None | Some(Value::Null) => &empty,
+        Some(Value::Object(params)) => params,
+        Some(_) => {
+            return (
+                id,
+                Err(Refusal::new(INVALID_PARAMS, "params must be an object")),
+            );
+        }
+    };
+    (id, parse_method(method, params))
+}
+
+/// Parse a method and its parameters
+fn parse_method(method: &str, params: &Map<String, Value>) -> Result<Method, Refusal> {
+    let target = || -> Result<DisplayTarget, Refusal> {
+        if params.get("all").and_then(Value::as_bool) == Some(true) {
+            return Ok(DisplayTarget::All);
+        }
+        match params.get("display") {
+            None | Some(Value::Null) => Ok(DisplayTarget::Default),
+            Some(Value::String(display)) => Ok(DisplayTarget::from_name(display)),
+            Some(_) => Err(Refusal::new(INVALID_PARAMS, "display must be a string")),
+        }
+    };
+    match method {
+        "set_power" => {
+            let state = params
+                .get("state")
+                .and_then(Value::as_str)
+                .ok_or_else(|| Refusal::new(INVALID_PARAMS, "state is required"))?;
+            let state =
+                PowerState::parse(state).map_err(|reason| Refusal::new(INVALID_PARAMS, reason))?;
+            Ok(Method::SetPower(target()?, state))
+        }
+        "get_power" => Ok(Method::GetPower(target()?)),
+        "list_displays" => Ok(Method::ListDisplays),
+        other => Err(Refusal::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method '{}'", other),
+        )),
+    }
+}
+
+/// Carry out `method` with `backend`
+///
+/// # Returns
+/// The displays concerned, after a change for `set_power`
+fn answer<B: PowerBackend + ?Sized>(backend: &mut B, method: &Method) -> Result<Value, Error> {
+    let displays = match method {
+        Method::SetPower(target, state) => {
+            backend.set_power(target, *state)?;
+            backend.get_power(target)?
+        }
+        Method::GetPower(target) => backend.get_power(target)?,
+        Method::ListDisplays => backend.list_displays()?,
+    };
+    let records: Vec<DisplayRecord> = displays.iter().map(DisplayRecord::from).collect();
+    Ok(serde_json::to_value(records).expect("Output types serialize to JSON"))
+}
+
+/// The response line to a request with `id`
+fn response(id: Value, outcome: Result<Value, Refusal>) -> Value {
+    match outcome {
+        Ok(result) => json!({ "id": id, "result": result }),
+        Err(Refusal {
+            code,
+            message,
+            name: None,
+        }) => json!({ "id": id, "error": { "code": code, "message": message } }),
+        Err(Refusal {
+            code,
+            message,
+            name: Some(name),
+        }) => json!({
+            "id": id,
+            "error": { "code": code, "message": message, "data": { "code": name } },
+        }),
+    }
+}
+
+/// Carry out one request line
+///
+/// # Returns
+/// The response line, or `None` for a notification
+fn handle<B: PowerBackend + ?Sized>(backend: &mut B, line: &str) -> Option<Value> {
+    let (id, method) = parse_request(line);
+    let outcome = method.and_then(|method| answer(backend, &method).map_err(Refusal::from));
+    id.map(|id| response(id, outcome))
+}
+
+/// The notification of `display` changing state, stamped with the time
+fn power_changed(display: &DisplayInfo) -> Value {
+    json!({
+        "method": "power_changed",
+        "params": {
+            "display": display.name,
+            "state": display.power.name(),
+            "ts": output::format_timestamp(output::unix_now()),
+        },
+    })
+}
+
+/// Write `message` as one line and flush it, so pipes see it at once
+fn send(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    writeln!(output, "{}", message)?;
+    output.flush()
+}
+
+/// Answer requests from `input` on `output` until end of input
+///
+/// Lines are read on their own thread, so changes can be reported while
+/// waiting for the next request. Blank lines are skipped.
+fn serve_lines<B: PowerBackend + ?Sized>(
+    backend: &mut B,
+    input: impl BufRead + Send + 'static,
+    output: &mut impl Write,
+    watch: bool,
+) -> io::Result<()> {
+    let (lines, requests) = mpsc::channel();
+    thread::spawn(move || {
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut last = match watch {
+        true => backend.get_power(&DisplayTarget::All).unwrap_or_default(),
+        false => Vec::new(),
+    };
+    loop {
+        let line = if watch {
+            requests.recv_timeout(backend::WATCH_POLL_INTERVAL)
+        } else {
+            requests.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        };
+        match line {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => {
+                if let Some(response) = handle(backend, &line) {
+                    send(output, &response)?;
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        if watch && let Ok(displays) = backend.get_power(&DisplayTarget::All) {
+            for display in backend::power_changes(&last, &displays) {
+                send(output, &power_changed(display))?;
+            }
+            last = displays;
+        }
+    }
+}
+
+/// Answer requests on standard input with `backend` until end of input
+///
+/// # Returns
+/// - `Ok(())` - Standard input ended; the displays are left as they are
+/// - `Err(Error::Io)` - Standard output was closed
+pub fn run<B: PowerBackend + ?Sized>(backend: &mut B, watch: bool) -> Result<(), Error> {
+    let stdin = io::BufReader::new(io::stdin());
+    serve_lines(backend, stdin, &mut io::stdout(), watch)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(name: &str, power: PowerState) -> DisplayInfo {
+        DisplayInfo {
+            name: name.to_string(),
+            power,
+            description: None,
+            make: None,
+            model: None,
+            drm: None,
+        }
+    }
+
+    /// Backend whose displays are switched in memory
+    struct MemoryBackend(Vec<DisplayInfo>);
+
+    impl PowerBackend for MemoryBackend {
+        fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
+            let mut found = false;
+            for display in &mut self.0 {
+                if matches!(target, DisplayTarget::Named(name) if *name != display.name) {
+                    continue;
+                }
+                display.power = state;
+                found = true;
+            }
+            match found {
+                true => Ok(()),
+                false => Err(Error::NoDisplayFound),
+            }
+        }
+
+        fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|d| !matches!(target, DisplayTarget::Named(name) if *name != d.name))
+                .cloned()
+                .collect())
+        }
+
+        fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
+            Ok(self.0.clone())
+        }
+
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn describe(&self) -> String {
+            "memory".to_string()
+        }
+    }
+
+    fn backend() -> MemoryBackend {
+        MemoryBackend(vec![
+            display("DP-1", PowerState::On),
+            display("HDMI-A-1", PowerState::On),
+        ])
+    }
+
+    /// The schema 1 record of a display with only a name and power state
+    fn record(name: &str, power: &str) -> Value {
+        json!({
+            "name": name,
+            "power": power,
+            "internal": false,
+            "make": null,
+            "model": null,
+            "drm": null,
+        })
+    }
+
+    /// Run the lines of `input` through a repl and collect the output lines
+    fn session(backend: &mut MemoryBackend, input: &str) -> Vec<Value> {
+        let mut output = Vec::new();
+        serve_lines(
+            backend,
+            io::Cursor::new(input.to_string()),
+            &mut output,
+            false,
+        )
+        .unwrap();
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn parses_methods_and_targets() {
+        let (id, method) = parse_request(
+            r#"{"id":1,"method":"set_power","params":{"state":"off","display":"DP-1"}}"#,
+        );
+        assert_eq!(id, Some(json!(1)));
+        assert_eq!(
+            method,
+            Ok(Method::SetPower(
+                DisplayTarget::Named("DP-1".to_string()),
+                PowerState::Off
+            ))
+        );
+        assert_eq!(
+            parse_request(r#"{"id":"a","method":"get_power"}"#),
+            (
+                Some(json!("a")),
+                Ok(Method::GetPower(DisplayTarget::Default))
+            )
+        );
+        assert_eq!(
+            parse_request(r#"{"method":"get_power","params":{"all":true}}"#),
+            (None, Ok(Method::GetPower(DisplayTarget::All)))
+        );
+        assert_eq!(
+            parse_request(r#"{"id":2,"method":"list_displays","params":null}"#).1,
+            Ok(Method::ListDisplays)
+        );
+    }
+
+    #[test]
+    fn malformed_requests_get_json_rpc_codes() {
+        let code = |line| parse_request(line).1.unwrap_err().code;
+        assert_eq!(code("{not json"), PARSE_ERROR);
+        assert_eq!(parse_request("{not json").0, Some(Value::Null));
+        assert_eq!(parse_request("[]").0, Some(Value::Null));
+        assert_eq!(code(r#"{"id":1}"#), INVALID_REQUEST);
+        assert_eq!(code(r#"{"id":1,"method":"reboot"}"#), METHOD_NOT_FOUND);
+        assert_eq!(code(r#"{"id":1,"method":"set_power"}"#), INVALID_PARAMS);
+        assert_eq!(
+            code(r#"{"id":1,"method":"set_power","params":{"state":"dim"}}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(r#"{"id":1,"method":"get_power","params":{"display":1}}"#),
+            INVALID_PARAMS
+        );
+        assert_eq!(
+            code(r#"{"id":1,"method":"get_power","params":[]}"#),
+            INVALID_PARAMS
+        );
+    }
+
+    #[test]
+    fn requests_are_answered_in_order_with_their_ids() {
+        let mut backend = backend();
+        let replies = session(
+            &mut backend,
+            "{\"id\":1,\"method\":\"set_power\",\"params\":{\"state\":\"off\",\"display\":\"DP-1\"}}\n\
+             \n\
+             {\"id\":2,\"method\":\"list_displays\"}\n",
+        );
+
+        assert_eq!(
+            replies,
+            [
+                json!({"id":1,"result":[record("DP-1", "off")]}),
+                json!({"id":2,"result":[record("DP-1", "off"), record("HDMI-A-1", "on")]}),
+            ]
+        );
+        // End of input leaves the displays as they are
+        assert_eq!(backend.0[0].power, PowerState::Off);
+    }
+
+    #[test]
+    fn notifications_are_carried_out_without_an_answer() {
+        let mut backend = backend();
+        let replies = session(
+            &mut backend,
+            "{\"method\":\"set_power\",\"params\":{\"state\":\"off\",\"display\":\"DP-1\"}}\n\
+             {\"method\":\"toggle\"}\n\
+             {\"id\":3,\"method\":\"get_power\",\"params\":{\"display\":\"DP-1\"}}\n",
+        );
+
+        assert_eq!(replies, [json!({"id":3,"result":[record("DP-1", "off")]})]);
+    }
+
+    #[test]
+    fn failures_carry_the_error_number() {
+        let mut backend = backend();
+        let replies = session(
+            &mut backend,
+            "{\"id\":7,\"method\":\"set_power\",\"params\":{\"state\":\"off\",\"display\":\"DP-9\"}}\n\
+             {\"id\":8,\"method\":\"toggle\"}\n",
+        );
+
+        assert_eq!(replies[0]["id"], 7);
+        assert_eq!(replies[0]["error"]["code"], Error::NoDisplayFound.number());
+        assert_eq!(replies[0]["error"]["message"], "No connected display found");
+        assert_eq!(replies[0]["error"]["data"]["code"], "no_display_found");
+        assert_eq!(replies[1]["error"]["code"], METHOD_NOT_FOUND);
+        assert!(replies[1]["error"].get("data").is_none());
+    }
+
+    #[test]
+    fn watching_reports_changes_as_notifications() {
+        let (reader, writer) = std::os::unix::net::UnixStream::pair().unwrap();
+        let input = io::BufReader::new(reader);
+        let mut backend = backend();
+        let mut output = Vec::new();
+
+        let request = thread::spawn(move || {
+            writeln!(
+                &writer,
+                r#"{{"id":1,"method":"set_power","params":{{"state":"off","display":"HDMI-A-1"}}}}"#
+            )
+            .unwrap();
+            // Give the change time to be noticed before input ends
+            thread::sleep(backend::WATCH_POLL_INTERVAL * 2);
+        });
+        serve_lines(&mut backend, input, &mut output, true).unwrap();
+        request.join().unwrap();
+
+        let mut lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines[0]["id"], 1);
+        let ts = lines[1]["params"]
+            .as_object_mut()
+            .unwrap()
+            .remove("ts")
+            .unwrap();
+        assert!(ts.as_str().unwrap().ends_with('Z'));
+        assert_eq!(
+            lines[1],
+            json!({"method":"power_changed","params":{"display":"HDMI-A-1","state":"off"}})
+        );
+        assert_eq!(lines.len(), 2);
+    }
+}
@@ -0,0 +1,166 @@
+/// Picking and connecting the backend a command runs on
+///
+/// `dpms` and the C interface share this: a backend chosen with `--backend`
+/// or `--wayland-socket` is used as is, otherwise the environment decides.
+/// A Wayland compositor without the power protocols is handed to the GNOME
+/// or Hyprland backend, and failing those to the TTY backend with a warning.
+///
+/// A running `dpms serve` is not considered here; callers ask it first.
+use crate::backend::PowerBackend;
+use crate::cli::{BackendChoice, Options};
+#[cfg(any(
+    feature = "wayland",
+    feature = "gnome",
+    feature = "hyprland",
+    feature = "tty"
+))]
+use crate::crash;
+#[cfg(feature = "tty")]
+use crate::daemon;
+#[cfg(feature = "tty")]
+use crate::drm_ops;
+use crate::env;
+use crate::error::Error;
+#[cfg(feature = "gnome")]
+use crate::gnome;
+#[cfg(feature = "hyprland")]
+use crate::hyprland;
+#[cfg(feature = "tty")]
+use crate::tty;
+#[cfg(feature = "wayland")]
+use crate::{ui, wayland};
+
+/// The backend `options` ask for, connected
+///
+/// # Returns
+/// - `Ok(Box<dyn PowerBackend>)` - The backend, ready for commands
+/// - `Err(Error::BackendNotBuilt)` - The backend was left out of this build
+/// - `Err(Error::ProtocolNotSupported)` - No backend can drive the session,
+///   e.g. under X11
+/// - `Err(Error)` - Connecting to the backend failed
+pub fn backend(options: &Options) -> Result<Box<dyn PowerBackend>, Error> {
+    match options.backend {
+        BackendChoice::Auto => {}
+        BackendChoice::Wayland => return wayland_backend(options, false),
+        BackendChoice::Gnome => return gnome_backend(),
+        BackendChoice::Hyprland => return hyprland_backend(),
+        BackendChoice::Tty => return tty_backend(options),
+    }
+
+    // An explicit socket forces the Wayland backend, without TTY fallback
+    if options.wayland_socket.is_some() {
+        return wayland_backend(options, false);
+    }
+
+    match env::detect_backend()? {
+        env::Backend::Wayland => wayland_backend(options, true),
+        env::Backend::Tty => tty_backend(options),
+        env::Backend::X11 => Err(Error::ProtocolNotSupported),
+    }
+}
+
+/// How the TTY backend and daemon open DRM devices
+#[cfg(feature = "tty")]
+pub fn drm_open_options(options: &Options) -> drm_ops::OpenOptions {
+    drm_ops::OpenOptions {
+        no_libseat: options.no_libseat,
+        card: options.card.clone(),
+        seat: options.seat.clone(),
+    }
+}
+
+/// How the TTY daemon treats displays while it holds them off
+#[cfg(feature = "tty")]
+pub fn daemon_options(options: &Options) -> daemon::DaemonOptions {
+    daemon::DaemonOptions {
+        allow_new_displays: options.allow_new_displays,
+        with_backlight: options.with_backlight,
+        restore_on_suspend: options.restore_on_suspend,
+        until_input: options.until_input,
+        inhibit_sleep: options.inhibit_sleep,
+        restore_at: None,
+    }
+}
+
+/// Connect to Mutter's display configuration over D-Bus
+#[cfg(feature = "gnome")]
+fn gnome_backend() -> Result<Box<dyn PowerBackend>, Error> {
+    crash::set_backend("gnome");
+    Ok(Box::new(gnome::GnomeBackend::new()?))
+}
+
+#[cfg(not(feature = "gnome"))]
+fn gnome_backend() -> Result<Box<dyn PowerBackend>, Error> {
+    Err(Error::BackendNotBuilt {
+        backend: "gnome".to_string(),
+    })
+}
+
+/// Connect to the running Hyprland instance's IPC socket
+#[cfg(feature = "hyprland")]
+fn hyprland_backend() -> Result<Box<dyn PowerBackend>, Error> {
+    crash::set_backend("hyprland");
+    let socket = env::hyprland_socket()
+        .ok_or_else(|| Error::CompositorIpc("HYPRLAND_INSTANCE_SIGNATURE is not set".into()))?;
+    Ok(Box::new(hyprland::HyprlandBackend::new(&socket)?))
+}
+
+#[cfg(not(feature = "hyprland"))]
+fn hyprland_backend() -> Result<Box<dyn PowerBackend>, Error> {
+    Err(Error::BackendNotBuilt {
+        backend: "hyprland".to_string(),
+    })
+}
+
+/// The Wayland backend, at `--wayland-socket` if given
+///
+/// With `fallback`, a compositor without the power protocols is handed to
+/// the GNOME or Hyprland backend, and failing those to the TTY backend. A
+/// GNOME or Hyprland session whose backend this build left out is
+/// `Error::BackendNotBuilt`, like a compiled-out Wayland or TTY backend.
+#[cfg(feature = "wayland")]
+fn wayland_backend(options: &Options, fallback: bool) -> Result<Box<dyn PowerBackend>, Error> {
+    crash::set_backend("wayland");
+    let backend = match &options.wayland_socket {
+        Some(socket) => wayland::WaylandBackend::with_socket(socket, options.compositor_timeout),
+        None => wayland::WaylandBackend::new(options.compositor_timeout),
+    };
+    match backend {
+        Ok(backend) => Ok(Box::new(backend)),
+        Err(e) if !fallback => Err(e),
+        // Mutter has no Wayland power protocol, use its D-Bus API instead
+        Err(Error::ProtocolNotSupported) if env::is_gnome_desktop() => gnome_backend(),
+        Err(Error::ProtocolNotSupported) if env::hyprland_socket().is_some() => hyprland_backend(),
+        Err(Error::Io(_) | Error::ProtocolNotSupported) if env::is_built(env::Backend::Tty) => {
+            ui::warning("Wayland backend failed, falling back to TTY");
+            tty_backend(options)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(not(feature = "wayland"))]
+fn wayland_backend(_options: &Options, _fallback: bool) -> Result<Box<dyn PowerBackend>, Error> {
+    Err(Error::BackendNotBuilt {
+        backend: "wayland".to_string(),
+    })
+}
+
+/// The TTY backend
+#[cfg(feature = "tty")]
+fn tty_backend(options: &Options) -> Result<Box<dyn PowerBackend>, Error> {
+    crash::set_backend("tty");
+    Ok(Box::new(tty::TtyBackend::new(
+        drm_open_options(options),
+        daemon_options(options),
+        options.force_tty,
+        options.kill_after,
+    )?))
+}
+
+#[cfg(not(feature = "tty"))]
+fn tty_backend(_options: &Options) -> Result<Box<dyn PowerBackend>, Error> {
+    Err(Error::BackendNotBuilt {
+        backend: "tty".to_string(),
+    })
+}
@@ -0,0 +1,356 @@
+/// `dpms serve`: power control for other applications over D-Bus
+///
+/// Claims `org.dpms.PowerControl` on the session bus and exports the
+/// `org.dpms.PowerControl` interface at `/org/dpms/PowerControl`, so status
+/// bars and automation can switch displays without running `dpms`. Method
+/// calls arrive on zbus's thread and are handed to the thread that owns the
+/// backend, which also polls the displays and emits `PowerChanged` for
+/// every change, as the default `watch` does.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use signal_hook::flag;
+use zbus::blocking::Connection;
+use zbus::blocking::connection::Builder;
+use zbus::fdo;
+use zbus::object_server::SignalEmitter;
+
+use crate::backend::{self, PowerBackend};
+use crate::display::{DisplayInfo, DisplayTarget};
+use crate::error::{Context, Error, ResultExt};
+use crate::output::PowerState;
+use crate::ui;
+
+/// Well-known bus name of the service
+pub const SERVICE_NAME: &str = "org.dpms.PowerControl";
+/// Object path the interface is exported at
+pub const SERVICE_PATH: &str = "/org/dpms/PowerControl";
+/// Name of the exported interface
+pub const SERVICE_IFACE: &str = "org.dpms.PowerControl";
+
+/// How long a method call waits for the backend before failing
+const CALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a D-Bus method call asks of the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Request {
+    SetPower(DisplayTarget, PowerState),
+    GetPower(DisplayTarget),
+    ListDisplays,
+}
+
+/// A method call waiting for the backend
+struct Call {
+    request: Request,
+    /// Where the displays afterwards, or why it failed, are sent
+    reply: mpsc::Sender<Result<Vec<DisplayInfo>, String>>,
+}
+
+/// The exported `org.dpms.PowerControl` interface
+struct PowerControl {
+    calls: mpsc::Sender<Call>,
+}
+
+impl PowerControl {
+    /// Hand `request` to the backend thread and wait for its answer
+    fn call(&self, request: Request) -> fdo::Result<Vec<DisplayInfo>> {
+        let (reply, answer) = mpsc::channel();
+        self.calls
+            .send(Call { request, reply })
+            .map_err(|_| fdo::Error::Failed("dpms serve is shutting down".to_string()))?;
+        answer
+            .recv_timeout(CALL_TIMEOUT)
+            .map_err(|_| fdo::Error::Failed("the backend did not answer".to_string()))?
+            .map_err(fdo::Error::Failed)
+    }
+}
+
+#[zbus::interface(name = "org.dpms.PowerControl")]
+impl PowerControl {
+    /// Turn `display` on or off; an empty name means the default displays
+    fn set_power(&self, display: &str, state: &str) -> fdo::Result<()> {
+        let state = PowerState::parse(state).map_err(fdo::Error::InvalidArgs)?;
+        self.call(Request::SetPower(DisplayTarget::from_name(display), state))
+            .map(|_| ())
+    }
+
+    /// `off` if every display `display` names is off, else `on`
+    fn get_power(&self, display: &str) -> fdo::Result<String> {
+        let displays = self.call(Request::GetPower(DisplayTarget::from_name(display)))?;
+        Ok(summarize(&displays).name().to_string())
+    }
+
+    /// Every connected display as (name, description, on)
+    fn list_displays(&self) -> fdo::Result<Vec<(String, String, bool)>> {
+        Ok(self
+            .call(Request::ListDisplays)?
+            .into_iter()
+            .map(|display| {
+                (
+                    display.name,
+                    display.description.unwrap_or_default(),
+                    display.power == PowerState::On,
+                )
+            })
+            .collect())
+    }
+
+    /// A display was turned on or off, by dpms or anything else
+    #[zbus(signal)]
+    async fn power_changed(
+        emitter: &SignalEmitter<'_>,
+        display: &str,
+        state: &str,
+    ) -> zbus::Result<()>;
+}
+
+/// `Off` if every display is off, else `On`
+fn summarize(displays: &[DisplayInfo]) -> PowerState {
+    if !displays.is_empty() && displays.iter().all(|d| d.power == PowerState::Off) {
+        PowerState::Off
+    } else {
+        PowerState::On
+    }
+}
+
+/// Carry out `request` with `backend`
+fn answer<B: PowerBackend + ?Sized>(
+    backend: &mut B,
+    request: &Request,
+) -> Result<Vec<DisplayInfo>, Error> {
+    match request {
+        Request::SetPower(target, state) => {
+            backend.set_power(target, *state)?;
+            Ok(Vec::new())
+        }
+        Request::GetPower(target) => backend.get_power(target),
+        Request::ListDisplays => backend.list_displays(),
+    }
+}
+
+/// Serve `backend` on the session bus until SIGTERM or SIGINT
+///
+/// # Returns
+/// - `Ok(())` - Stopped by a signal; the bus name is released
+/// - `Err(Error::DbusError)` - No session bus, or another `dpms serve` owns
+///   the name
+pub fn serve<B: PowerBackend + ?Sized>(backend: &mut B) -> Result<(), Error> {
+    let shutdown = Arc::new(AtomicBool::new(false));
+    for signal in [SIGINT, SIGTERM] {
+        flag::register(signal, Arc::clone(&shutdown))
+            .context(Error::SignalError, "Failed to register signal handler")?;
+    }
+
+    let (calls, requests) = mpsc::channel();
+    let connection = Builder::session()
+        .and_then(|builder| builder.name(SERVICE_NAME))
+        .and_then(|builder| builder.serve_at(SERVICE_PATH, PowerControl { calls }))
+        .and_then(Builder::build)
+        .map_err(|e| match e {
+            zbus::Error::NameTaken => Error::DbusError(
+                format!("{} is taken; dpms serve is already running", SERVICE_NAME).into(),
+            ),
+            e => Error::DbusError(Context::wrap("Failed to serve on the session bus", e)),
+        })?;
+    ui::notice(format_args!("Serving {} on the session bus", SERVICE_NAME));
+
+    let mut last = backend.get_power(&DisplayTarget::All).unwrap_or_default();
+    while !shutdown.load(Ordering::Relaxed) {
+        match requests.recv_timeout(backend::WATCH_POLL_INTERVAL) {
+            Ok(Call { request, reply }) => {
+                let _ = reply.send(answer(backend, &request).map_err(|e| e.to_string()));
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        // Report what changed since the last look, whoever changed it
+        if let Ok(displays) = backend.get_power(&DisplayTarget::All) {
+            for display in backend::power_changes(&last, &displays) {
+                emit_power_changed(&connection, display);
+            }
+            last = displays;
+        }
+    }
+
+    ui::notice("Stopping");
+    Ok(())
+}
+
+/// Emit `PowerChanged` for `display`
+fn emit_power_changed(connection: &Connection, display: &DisplayInfo) {
+    if let Err(e) = connection.emit_signal(
+        None::<()>,
+        SERVICE_PATH,
+        SERVICE_IFACE,
+        "PowerChanged",
+        &(display.name.as_str(), display.power.name()),
+    ) {
+        ui::warning(format_args!(
+            "Failed to emit PowerChanged for {}: {}",
+            display.name, e
+        ));
+    }
+}
+
+/// Where the session bus looks for activatable services
+///
+/// # Returns
+/// `$XDG_DATA_HOME/dbus-1/services`, else `~/.local/share/dbus-1/services`
+fn services_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .map(|dir| dir.join("dbus-1").join("services"))
+}
+
+/// The D-Bus service file that starts `exe serve` on demand
+fn service_file(exe: &Path) -> String {
+    format!(
+        "[D-BUS Service]\nName={}\nExec={} serve\n",
+        SERVICE_NAME,
+        exe.display()
+    )
+}
+
+/// Install a service file, so the session bus starts `dpms serve` the first
+/// time anyone calls it
+///
+/// # Returns
+/// - `Ok(PathBuf)` - Where the file was written
+/// - `Err(Error::Io)` - It couldn't be written
+pub fn install() -> Result<PathBuf, Error> {
+    let dir = services_dir().ok_or_else(|| {
+        Error::Io(std::io::Error::other(
+            "neither XDG_DATA_HOME nor HOME is set",
+        ))
+    })?;
+    let exe = std::env::current_exe()?;
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.service", SERVICE_NAME));
+    fs::write(&path, service_file(&exe))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(name: &str, power: PowerState) -> DisplayInfo {
+        DisplayInfo {
+            name: name.to_string(),
+            power,
+            description: None,
+            make: None,
+            model: None,
+            drm: None,
+        }
+    }
+
+    /// Backend whose displays are switched in memory
+    struct MemoryBackend(Vec<DisplayInfo>);
+
+    impl PowerBackend for MemoryBackend {
+        fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
+            for display in &mut self.0 {
+                if matches!(target, DisplayTarget::Named(name) if *name != display.name) {
+                    continue;
+                }
+                display.power = state;
+            }
+            Ok(())
+        }
+
+        fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|d| !matches!(target, DisplayTarget::Named(name) if *name != d.name))
+                .cloned()
+                .collect())
+        }
+
+        fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
+            Ok(self.0.clone())
+        }
+
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn describe(&self) -> String {
+            "memory".to_string()
+        }
+    }
+
+    #[test]
+    fn power_is_off_only_if_every_display_is() {
+        assert_eq!(
+            summarize(&[
+                display("DP-1", PowerState::Off),
+                display("DP-2", PowerState::On)
+            ]),
+            PowerState::On
+        );
+        assert_eq!(
+            summarize(&[display("DP-1", PowerState::Off)]),
+            PowerState::Off
+        );
+        assert_eq!(summarize(&[]), PowerState::On);
+    }
+
+    #[test]
+    fn requests_reach_the_backend() {
+        let mut backend = MemoryBackend(vec![
+            display("DP-1", PowerState::On),
+            display("DP-2", PowerState::On),
+        ]);
+
+        let target = DisplayTarget::Named("DP-2".to_string());
+        answer(
+            &mut backend,
+            &Request::SetPower(target.clone(), PowerState::Off),
+        )
+        .unwrap();
+        assert_eq!(
+            answer(&mut backend, &Request::GetPower(target)).unwrap(),
+            [display("DP-2", PowerState::Off)]
+        );
+        assert_eq!(
+            answer(&mut backend, &Request::ListDisplays).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn calls_wait_for_the_backend_thread() {
+        let (calls, requests) = mpsc::channel::<Call>();
+        let control = PowerControl { calls };
+        let worker = std::thread::spawn(move || {
+            let mut backend = MemoryBackend(vec![display("DP-1", PowerState::Off)]);
+            let Call { request, reply } = requests.recv().unwrap();
+            reply
+                .send(answer(&mut backend, &request).map_err(|e| e.to_string()))
+                .unwrap();
+        });
+
+        assert_eq!(control.get_power("DP-1").unwrap(), "off");
+        worker.join().unwrap();
+        // With the backend thread gone, calls fail instead of hanging
+        assert!(control.list_displays().is_err());
+    }
+
+    #[test]
+    fn service_file_runs_serve() {
+        assert_eq!(
+            service_file(Path::new("/usr/bin/dpms")),
+            "[D-BUS Service]\nName=org.dpms.PowerControl\nExec=/usr/bin/dpms serve\n"
+        );
+    }
+}
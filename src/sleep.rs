@@ -0,0 +1,342 @@
+/// System suspend and resume
+///
+/// Drivers often light the panels back up on resume, so the daemon has to
+/// know when the system slept. It listens for logind's `PrepareForSleep`
+/// signal on the system bus. Where logind isn't reachable, a resume is still
+/// noticed afterwards: `CLOCK_BOOTTIME` keeps counting while suspended and
+/// `CLOCK_MONOTONIC` doesn't, so the gap between them jumps. Only Linux has
+/// such a clock, so elsewhere resumes are only learned from logind.
+///
+/// To restore the displays before sleeping, the monitor can hold a logind
+/// delay inhibitor lock, which is released once the daemon has reacted.
+/// Separately, `SleepInhibitor` blocks sleep and idle actions altogether
+/// while the displays are held off.
+///
+/// Without the `dbus` feature there is no logind: resumes are only noticed
+/// by the clocks, and neither lock can be taken.
+use crate::error::Error;
+#[cfg(feature = "dbus")]
+use crate::error::ResultExt;
+#[cfg(target_os = "linux")]
+use nix::time::{ClockId, clock_gettime};
+use std::io::Read;
+#[cfg(feature = "dbus")]
+use std::io::Write;
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc;
+#[cfg(feature = "dbus")]
+use std::thread;
+use std::time::Duration;
+#[cfg(feature = "dbus")]
+use zbus::blocking::{Connection, Proxy};
+
+#[cfg(feature = "dbus")]
+const LOGIND_DEST: &str = "org.freedesktop.login1";
+#[cfg(feature = "dbus")]
+const LOGIND_PATH: &str = "/org/freedesktop/login1";
+#[cfg(feature = "dbus")]
+const LOGIND_IFACE: &str = "org.freedesktop.login1.Manager";
+
+/// How far `CLOCK_BOOTTIME` must get ahead of `CLOCK_MONOTONIC` between
+/// two polls to count as a resume
+const SUSPEND_GAP: Duration = Duration::from_secs(1);
+
+/// How often the clocks are compared when logind isn't reachable
+const CLOCK_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A sleep transition the daemon reacts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SleepEvent {
+    /// The system is about to suspend
+    Suspending,
+    /// The system has resumed
+    Resumed,
+}
+
+/// Connection to logind's sleep notifications
+#[cfg_attr(not(feature = "dbus"), allow(dead_code))]
+struct Logind {
+    #[cfg(feature = "dbus")]
+    proxy: Proxy<'static>,
+    /// `PrepareForSleep` arguments: true before sleep, false after resume
+    signals: mpsc::Receiver<bool>,
+    /// Readable whenever `signals` may have something new
+    wake: UnixStream,
+    /// Delay lock, held while the system is awake if requested
+    inhibitor: Option<OwnedFd>,
+}
+
+#[cfg(feature = "dbus")]
+impl Logind {
+    /// Subscribe to `PrepareForSleep` on the system bus
+    fn connect() -> Result<Self, Error> {
+        let proxy = logind_proxy()?;
+        let messages = proxy
+            .receive_signal("PrepareForSleep")
+            .context(Error::DbusError, "Failed to subscribe to PrepareForSleep")?;
+
+        // The iterator blocks, so it's drained on its own thread, which
+        // pokes `wake` so the daemon needn't poll the channel
+        let (sender, signals) = mpsc::channel();
+        let (wake, mut waker) = UnixStream::pair().map_err(Error::Io)?;
+        wake.set_nonblocking(true).map_err(Error::Io)?;
+        thread::spawn(move || {
+            for message in messages {
+                let Ok(start) = message.body().deserialize::<bool>() else {
+                    continue;
+                };
+                if sender.send(start).is_err() || waker.write_all(&[1]).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Logind {
+            proxy,
+            signals,
+            wake,
+            inhibitor: None,
+        })
+    }
+
+    /// Take a delay lock so logind waits for us before suspending
+    fn inhibit(&mut self) -> Result<(), Error> {
+        self.inhibitor = Some(inhibit(
+            &self.proxy,
+            "sleep",
+            "Restore displays before suspend",
+            "delay",
+        )?);
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "dbus"))]
+impl Logind {
+    fn connect() -> Result<Self, Error> {
+        Err(dbus_not_built())
+    }
+
+    fn inhibit(&mut self) -> Result<(), Error> {
+        Err(dbus_not_built())
+    }
+}
+
+/// Why logind can't be reached without the `dbus` feature
+#[cfg(not(feature = "dbus"))]
+fn dbus_not_built() -> Error {
+    Error::BackendNotBuilt {
+        backend: "dbus".to_string(),
+    }
+}
+
+/// logind's manager object on the system bus
+#[cfg(feature = "dbus")]
+fn logind_proxy() -> Result<Proxy<'static>, Error> {
+    let connection =
+        Connection::system().context(Error::DbusError, "Failed to connect to the system bus")?;
+    Proxy::new_owned(connection, LOGIND_DEST, LOGIND_PATH, LOGIND_IFACE)
+        .context(Error::DbusError, "Failed to reach logind")
+}
+
+/// Take a logind inhibitor lock, held until the returned descriptor is closed
+#[cfg(feature = "dbus")]
+fn inhibit(proxy: &Proxy<'_>, what: &str, why: &str, mode: &str) -> Result<OwnedFd, Error> {
+    let fd: zbus::zvariant::OwnedFd = proxy
+        .call("Inhibit", &(what, "dpms", why, mode))
+        .context(Error::DbusError, "logind refused an inhibitor lock")?;
+    Ok(fd.into())
+}
+
+/// Block lock that keeps idle actions and autosuspend from putting the
+/// system to sleep while the displays are held off
+///
+/// A machine with its displays off looks unused to idle daemons, which
+/// would otherwise suspend it mid-download. The lock is released when this
+/// is dropped.
+pub struct SleepInhibitor {
+    _fd: OwnedFd,
+}
+
+impl SleepInhibitor {
+    /// Take the lock from logind on the system bus
+    ///
+    /// # Returns
+    /// - `Ok(SleepInhibitor)` - Sleep and idle actions are blocked
+    /// - `Err(Error::DbusError)` - logind is unreachable or refused
+    #[cfg(feature = "dbus")]
+    pub fn take() -> Result<Self, Error> {
+        let proxy = logind_proxy()?;
+        let fd = inhibit(&proxy, "sleep:idle", "Displays are held off", "block")?;
+        Ok(SleepInhibitor { _fd: fd })
+    }
+
+    #[cfg(not(feature = "dbus"))]
+    pub fn take() -> Result<Self, Error> {
+        Err(dbus_not_built())
+    }
+}
+
+/// Watches for the system going to sleep and waking up
+pub struct SleepMonitor {
+    logind: Option<Logind>,
+    /// Whether to hold a delay lock while awake
+    delay_sleep: bool,
+    /// Release the delay lock on the next poll, after `Suspending` was handled
+    release_pending: bool,
+    /// `(CLOCK_BOOTTIME, CLOCK_MONOTONIC)` at the last poll
+    clocks: Option<(Duration, Duration)>,
+}
+
+impl SleepMonitor {
+    /// Start watching for sleep transitions
+    ///
+    /// With `delay_sleep`, a logind delay lock is held while the system is
+    /// awake, so that `Suspending` is handled before the system sleeps.
+    /// Without logind, only resumes are noticed.
+    pub fn new(delay_sleep: bool) -> Self {
+        let logind = match Logind::connect() {
+            Ok(mut logind) => {
+                if delay_sleep && let Err(e) = logind.inhibit() {
+                    eprintln!("Not delaying suspend: {}", e);
+                }
+                Some(logind)
+            }
+            Err(e) => {
+                eprintln!("logind unavailable, detecting resume by clock: {}", e);
+                None
+            }
+        };
+        SleepMonitor {
+            logind,
+            delay_sleep,
+            release_pending: false,
+            clocks: read_clocks(),
+        }
+    }
+
+    /// Descriptor that becomes readable when `poll` may have events
+    ///
+    /// # Returns
+    /// `None` without logind; call `poll` every `poll_interval` instead
+    pub fn fd(&self) -> Option<BorrowedFd<'_>> {
+        self.logind.as_ref().map(|logind| logind.wake.as_fd())
+    }
+
+    /// How long to wait at most before calling `poll` again
+    ///
+    /// # Returns
+    /// `None` if waiting for `fd` suffices
+    pub fn poll_interval(&self) -> Option<Duration> {
+        match self.logind {
+            Some(_) => None,
+            None => Some(CLOCK_POLL_INTERVAL),
+        }
+    }
+
+    /// Collect sleep transitions without blocking
+    ///
+    /// After `Suspending` is returned, the delay lock is released on the
+    /// next call, letting the system sleep.
+    pub fn poll(&mut self) -> Vec<SleepEvent> {
+        let Some(logind) = &mut self.logind else {
+            let previous = self.clocks;
+            self.clocks = read_clocks();
+            return if resumed(previous, self.clocks) {
+                vec![SleepEvent::Resumed]
+            } else {
+                Vec::new()
+            };
+        };
+
+        if self.release_pending {
+            logind.inhibitor = None;
+            self.release_pending = false;
+        }
+
+        // Drain the wakeups before the channel, so none is lost for a
+        // signal that arrives in between
+        let mut buf = [0u8; 64];
+        while matches!(logind.wake.read(&mut buf), Ok(len) if len > 0) {}
+
+        let signals: Vec<bool> = logind.signals.try_iter().collect();
+        let mut events = Vec::new();
+        for start in signals {
+            if start {
+                self.release_pending = true;
+                events.push(SleepEvent::Suspending);
+            } else {
+                // Be ready for the next suspend
+                if self.delay_sleep
+                    && let Err(e) = logind.inhibit()
+                {
+                    eprintln!("Not delaying suspend: {}", e);
+                }
+                events.push(SleepEvent::Resumed);
+            }
+        }
+        events
+    }
+
+    /// A monitor that only compares clocks, as without logind
+    #[cfg(test)]
+    pub fn clocks_only() -> Self {
+        SleepMonitor {
+            logind: None,
+            delay_sleep: false,
+            release_pending: false,
+            clocks: read_clocks(),
+        }
+    }
+}
+
+/// Read `(CLOCK_BOOTTIME, CLOCK_MONOTONIC)`
+#[cfg(target_os = "linux")]
+fn read_clocks() -> Option<(Duration, Duration)> {
+    let boottime = clock_gettime(ClockId::CLOCK_BOOTTIME).ok()?;
+    let monotonic = clock_gettime(ClockId::CLOCK_MONOTONIC).ok()?;
+    Some((boottime.into(), monotonic.into()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn read_clocks() -> Option<(Duration, Duration)> {
+    None
+}
+
+/// Whether the system was suspended between two `read_clocks` readings
+///
+/// The clocks needn't start out equal, only their progress is compared.
+fn resumed(previous: Option<(Duration, Duration)>, current: Option<(Duration, Duration)>) -> bool {
+    let (Some((boot_before, mono_before)), Some((boot_after, mono_after))) = (previous, current)
+    else {
+        return false;
+    };
+    let awake = mono_after.saturating_sub(mono_before);
+    let elapsed = boot_after.saturating_sub(boot_before);
+    elapsed.saturating_sub(awake) >= SUSPEND_GAP
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clock_gap_means_resume() {
+        let clocks = |boot, mono| Some((Duration::from_millis(boot), Duration::from_millis(mono)));
+        // 10 minutes asleep between two polls
+        assert!(resumed(clocks(5_000, 4_000), clocks(605_100, 4_100)));
+        // Both clocks advanced together
+        assert!(!resumed(clocks(5_000, 4_000), clocks(5_100, 4_100)));
+        assert!(!resumed(clocks(5_000, 4_000), clocks(5_600, 4_100)));
+        assert!(!resumed(None, clocks(605_100, 4_100)));
+        assert!(!resumed(clocks(5_000, 4_000), None));
+    }
+
+    #[test]
+    fn clocks_are_readable() {
+        let before = read_clocks();
+        assert!(before.is_some());
+        assert!(!resumed(before, read_clocks()));
+    }
+}
@@ -0,0 +1,92 @@
+/// systemd service integration
+///
+/// Run as `dpms daemon-internal --systemd` from a `Type=notify` service, the
+/// daemon stays in the foreground, logs to the journal through standard
+/// error, and tells the service manager when the displays are off and when
+/// it starts restoring them. Notifications go to the datagram socket named
+/// in `$NOTIFY_SOCKET`; without one they are dropped.
+use std::ffi::OsStr;
+use std::io;
+#[cfg(target_os = "linux")]
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::path::Path;
+
+/// Environment variable naming the service manager's notification socket
+const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+
+/// The displays are off and held
+pub const READY: &str = "READY=1";
+/// The daemon is restoring the displays and about to exit
+pub const STOPPING: &str = "STOPPING=1";
+
+/// Send `state` to the service manager, if there is one
+pub fn notify(state: &str) {
+    if let Some(socket) = std::env::var_os(NOTIFY_SOCKET_ENV)
+        && let Err(e) = notify_to(&socket, state)
+    {
+        eprintln!("Failed to notify systemd of {}: {}", state, e);
+    }
+}
+
+/// Send `state` to the notification socket at `socket`
+///
+/// A leading `@` names a socket in the abstract namespace.
+fn notify_to(socket: &OsStr, state: &str) -> io::Result<()> {
+    let address = match socket.as_bytes().strip_prefix(b"@") {
+        Some(name) => abstract_address(name)?,
+        None => SocketAddr::from_pathname(Path::new(socket))?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+fn abstract_address(name: &[u8]) -> io::Result<SocketAddr> {
+    SocketAddr::from_abstract_name(name)
+}
+
+/// Only Linux has an abstract socket namespace
+#[cfg(not(target_os = "linux"))]
+fn abstract_address(_name: &[u8]) -> io::Result<SocketAddr> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn notifies_a_socket_by_path() {
+        let path = Path::new("/tmp/dpms-test-notify.sock");
+        let _ = std::fs::remove_file(path);
+        let manager = UnixDatagram::bind(path).unwrap();
+
+        notify_to(path.as_os_str(), READY).unwrap();
+        let mut buf = [0; 64];
+        let len = manager.recv(&mut buf).unwrap();
+        let _ = std::fs::remove_file(path);
+
+        assert_eq!(&buf[..len], b"READY=1");
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn notifies_an_abstract_socket() {
+        let name = format!("dpms-test-notify-{}", std::process::id());
+        let address = SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+        let manager = UnixDatagram::bind_addr(&address).unwrap();
+
+        notify_to(OsStr::new(&format!("@{}", name)), STOPPING).unwrap();
+        let mut buf = [0; 64];
+        let len = manager.recv(&mut buf).unwrap();
+
+        assert_eq!(&buf[..len], b"STOPPING=1");
+    }
+
+    #[test]
+    fn missing_socket_is_an_error() {
+        assert!(notify_to(OsStr::new("/tmp/dpms-test-no-such-notify.sock"), READY).is_err());
+    }
+}
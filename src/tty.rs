@@ -6,26 +6,58 @@
 ///
 /// The backend coordinates with the daemon lifecycle:
 /// - When turning display off: spawns daemon if not running
-/// - When turning display on: signals daemon to restore and exit
-/// - When querying status: checks if daemon is running
+/// - When turning display on: signals daemon to restore and exit, or
+///   re-enables inactive CRTCs directly when no daemon holds them
+/// - When querying status: reads the CRTC state from the hardware, using
+///   the daemon's state only as a fallback and to warn about mismatches
+///
+/// On machines with no DRM device at all, the console is blanked through
+/// the framebuffer instead (see `fbcon`).
 use crate::backend::PowerBackend;
 use crate::daemon;
-use crate::display::{DisplayInfo, DisplayTarget};
+use crate::display::{self, DisplayInfo, DisplayTarget, DrmIds};
+use crate::drm_ops::{self, DrmControl};
+use crate::edid::EdidInfo;
 use crate::error::Error;
-use crate::output::PowerState;
+use crate::fbcon::FbconBackend;
+use crate::output::{self, PowerState};
+use crate::ui;
+use drm::control::connector;
+use std::time::Duration;
 
 /// TTY backend implementing PowerBackend trait
 ///
 /// This backend uses a daemon process to manage display power state in TTY
 /// environments. It delegates actual power control to daemon functions (F8).
 ///
-/// Note: TTY backend currently operates on all displays as a single unit.
-/// Multi-display selection (F20) is a future enhancement.
-pub struct TtyBackend;
+/// A named target only turns off the CRTC driving that connector (e.g.
+/// `HDMI-A-1`); otherwise each device's active display is turned off.
+pub struct TtyBackend {
+    /// How the daemon and direct recovery open DRM devices
+    open: drm_ops::OpenOptions,
+    /// How the daemon treats displays while it holds them off
+    daemon: daemon::DaemonOptions,
+    /// Console blanking to fall back to when there is no DRM device
+    fbcon: Option<FbconBackend>,
+    /// Start the daemon even if a display server holds the devices
+    force: bool,
+    /// How long the daemon gets to exit before it's killed, if ever
+    kill_after: Option<Duration>,
+    /// The daemons stopped to turn displays on
+    stopped: Vec<daemon::StoppedDaemon>,
+}
 
 impl TtyBackend {
     /// Create a new TTY backend
     ///
+    /// # Parameters
+    /// - `open`: How DRM devices are opened
+    /// - `daemon`: How the daemon treats displays while it holds them off
+    /// - `force`: Don't refuse to turn displays off while a display server
+    ///   is running
+    /// - `kill_after`: When turning displays on, SIGKILL a daemon that hasn't
+    ///   exited after this long and restore its displays directly
+    ///
     /// # Returns
     /// - `Ok(TtyBackend)` - Backend ready to use
     ///
@@ -37,68 +69,152 @@ impl TtyBackend {
     /// # Example
     /// ```no_run
     /// # use dpms::tty::TtyBackend;
-    /// let backend = TtyBackend::new()?;
+    /// # use dpms::drm_ops::OpenOptions;
+    /// # use dpms::daemon::DaemonOptions;
+    /// let backend = TtyBackend::new(OpenOptions::default(), DaemonOptions::default(), false, None)?;
     /// # Ok::<(), dpms::error::Error>(())
     /// ```
-    pub fn new() -> Result<Self, Error> {
-        Ok(TtyBackend)
+    pub fn new(
+        open: drm_ops::OpenOptions,
+        daemon: daemon::DaemonOptions,
+        force: bool,
+        kill_after: Option<Duration>,
+    ) -> Result<Self, Error> {
+        Ok(TtyBackend {
+            open,
+            daemon,
+            fbcon: FbconBackend::detect(),
+            force,
+            kill_after,
+            stopped: Vec::new(),
+        })
     }
 
-    /// Get the current power state (internal helper)
-    fn current_power_state(&self) -> PowerState {
-        match daemon::is_daemon_running() {
-            Some(_pid) => PowerState::Off,
-            None => PowerState::On,
+    /// Why DRM can't be used at all, if the console blanking fallback applies
+    ///
+    /// A card chosen with `--card` is never replaced by the fallback.
+    ///
+    /// # Returns
+    /// `None` if DRM devices exist or the console can't be blanked either
+    fn fbcon_reason(&self) -> Option<Error> {
+        if self.open.card.is_some() || self.fbcon.is_none() {
+            return None;
         }
+        drm_ops::candidate_devices(&self.open).err()
     }
-}
 
-impl PowerBackend for TtyBackend {
-    fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
-        // TTY backend currently doesn't support per-display control
-        // Warn if a specific display is targeted
-        if let DisplayTarget::Named(name) = target {
-            eprintln!(
-                "Warning: TTY backend does not support per-display control. \
-                 Ignoring display name '{}', operating on all displays.",
-                name
-            );
+    /// The connector `target` names, if it names one
+    ///
+    /// The name may be the start of a single connected connector's name, as
+    /// with the other backends. Without DRM access it is taken as given.
+    ///
+    /// # Returns
+    /// - `Ok(Option<String>)` - The full connector name, or `None` for every display
+    /// - `Err(Error::DisplayNotFound)` - No connected connector matches
+    /// - `Err(Error::AmbiguousDisplay)` - Several connected connectors start with the name
+    fn target_connector(&self, target: &DisplayTarget) -> Result<Option<String>, Error> {
+        let DisplayTarget::Named(name) = target else {
+            return Ok(None);
+        };
+        match hardware_states(&self.open) {
+            Some(states) => named_state(&states, name).map(|s| Some(s.connector.clone())),
+            None => Ok(Some(name.clone())),
         }
+    }
 
-        match state {
-            PowerState::Off => {
-                // Check if daemon is already running
-                if daemon::is_daemon_running().is_some() {
-                    // Already off, idempotent operation
-                    eprintln!("Display already off");
-                    return Ok(());
-                }
-
-                // Start daemon - it will turn off the display
-                daemon::start_daemon()
+    /// Have a daemon hold the display on `connector`, or every display, off
+    ///
+    /// # Parameters
+    /// - `connector`: Only turn off the display on this connector
+    /// - `restore_at`: When the daemon turns them back on, in seconds since
+    ///   the epoch; a daemon already holding them gets the new time
+    fn turn_off(&self, connector: Option<&str>, restore_at: Option<u64>) -> Result<(), Error> {
+        // Check if a daemon already holds the target off
+        if daemon::is_daemon_running(connector).is_some() {
+            daemon::warn_outdated_daemons(connector);
+            if let Some(at) = restore_at {
+                daemon::reschedule_daemons(connector, at)?;
+            }
+            // A daemon toggled on with SIGUSR1 just turns it off again
+            if daemon::reassert_daemons(connector)? {
+                ui::notice("Asked the daemon to turn the display off again");
+                return Ok(());
             }
-            PowerState::On => {
-                // Check if daemon is running
-                if daemon::is_daemon_running().is_none() {
-                    // Already on, idempotent operation
-                    eprintln!("Display already on");
-                    return Ok(());
-                }
 
-                // Signal daemon to restore display and exit
-                daemon::stop_daemon()
+            // Already off, idempotent operation
+            match restore_at {
+                Some(_) => ui::notice("Display already off; moved the time it turns back on"),
+                None => ui::notice("Display already off"),
             }
+            return Ok(());
+        }
+
+        // Fail fast if the daemon can't possibly open the devices
+        drm_ops::probe_access(&self.open)?;
+        // Our own daemons are DRM master too; they aren't a compositor
+        daemon::check_daemon_conflict(connector, &self.open)?;
+        // A compositor would light the displays again straight away
+        if !self.force {
+            drm_ops::check_no_display_server(&self.open)?;
         }
+
+        // Start daemon - it will turn off the display, unless another
+        // daemon is master of its card
+        let options = daemon::DaemonOptions {
+            restore_at,
+            ..self.daemon.clone()
+        };
+        daemon::start_daemon(connector, &self.open, &options)
     }
 
-    fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
-        // TTY backend currently doesn't support per-display queries
+    /// The displays as the hardware reports them, described with what the
+    /// daemon holds; without DRM access, as the daemon reports them
+    fn drm_displays(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+        let mut owned = daemon::owned_crtcs();
+        let seat = daemon::daemon_seat();
+
+        if let Some(mut states) = hardware_states(&self.open) {
+            if let DisplayTarget::Named(name) = target {
+                let name = named_state(&states, name)?.connector.clone();
+                states.retain(|s| s.connector == name);
+                owned.retain(|o| o.connector == name);
+            }
+
+            let (displays, warnings) = reconcile(&states, &owned, seat.as_deref());
+            for warning in warnings {
+                ui::warning(warning);
+            }
+            return Ok(displays);
+        }
+
+        // Without DRM access, fall back to what the daemon reports; only the
+        // connectors it holds are known, and they are off
         if let DisplayTarget::Named(name) = target {
-            eprintln!(
-                "Warning: TTY backend does not support per-display queries. \
-                 Ignoring display name '{}', showing all displays.",
-                name
-            );
+            let entry =
+                display::find_display_by_name(&owned, name, |o| Some(o.connector.as_str()))?;
+            return Ok(vec![DisplayInfo {
+                name: entry.connector.clone(),
+                power: PowerState::Off,
+                description: Some(owned_description(entry, seat.as_deref())),
+                make: None,
+                model: None,
+                drm: None,
+            }]);
+        }
+
+        // Report the displays the daemon holds off
+        if !owned.is_empty() {
+            return Ok(owned
+                .iter()
+                .map(|entry| DisplayInfo {
+                    name: entry.connector.clone(),
+                    power: PowerState::Off,
+                    description: Some(owned_description(entry, seat.as_deref())),
+                    make: None,
+                    model: None,
+                    drm: None,
+                })
+                .collect());
         }
 
         // Return a single "display" representing the TTY state
@@ -110,12 +226,409 @@ impl PowerBackend for TtyBackend {
             description: Some("TTY/Console display".to_string()),
             make: None,
             model: None,
+            drm: None,
         }])
     }
 
+    /// Get the current power state (internal helper)
+    fn current_power_state(&self) -> PowerState {
+        // A daemon toggled on with SIGUSR1 holds its displays lit
+        if daemon::daemons_holding_off().is_empty() {
+            PowerState::On
+        } else {
+            PowerState::Off
+        }
+    }
+}
+
+/// Describe which CRTC the daemon turned off for a display, how, and on
+/// which seat
+fn owned_description(entry: &daemon::OwnedCrtc, seat: Option<&str>) -> String {
+    let mut description = format!("CRTC {} on {}", entry.crtc, entry.device);
+    if let Some(mechanism) = entry.mechanism {
+        description.push_str(&format!(" via {}", mechanism));
+    }
+    description.push_str(", held off by daemon");
+    if let Some(seat) = seat {
+        description.push_str(&format!(" on {}", seat));
+    }
+    description
+}
+
+/// Add how long the daemon has held each display off to its description
+fn describe_held_for(displays: &mut [DisplayInfo], infos: &[daemon::DaemonInfo], now: u64) {
+    for display in displays {
+        if display.power != PowerState::Off {
+            continue;
+        }
+        let Some(info) = infos
+            .iter()
+            .find(|info| info.displays.iter().any(|d| d.connector == display.name))
+        else {
+            continue;
+        };
+        if let Some(description) = &mut display.description {
+            let held = output::format_elapsed(now.saturating_sub(info.started));
+            description.push_str(&format!(" for {}", held));
+            if let Some(at) = info.restore_deadline {
+                let left = output::format_elapsed(at.saturating_sub(now));
+                description.push_str(&format!(", restoring in {}", left));
+            }
+        }
+    }
+}
+
+/// The power state of a connected connector as read from the hardware
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HardwareState {
+    /// DRM device node, e.g. `/dev/dri/card0`
+    device: String,
+    /// Connector name, e.g. `HDMI-A-1`
+    connector: String,
+    /// DRM object id of the connector
+    connector_id: u32,
+    /// DRM object id of the CRTC driving the connector, if any
+    crtc: Option<u32>,
+    /// Whether that CRTC is active
+    active: bool,
+    /// Make and model from the display's EDID
+    edid: EdidInfo,
+}
+
+/// Read the real power state of every connected connector
+///
+/// # Returns
+/// `None` if no DRM device could be opened or none has a connected display
+fn hardware_states(open: &drm_ops::OpenOptions) -> Option<Vec<HardwareState>> {
+    let states = read_hardware_states(&drm_ops::open_drm_readonly(open));
+    Some(states).filter(|states| !states.is_empty())
+}
+
+/// Read the power state of every connected connector of `devices`
+///
+/// Devices whose connectors can't be read are skipped.
+fn read_hardware_states<D: DrmControl>(devices: &[D]) -> Vec<HardwareState> {
+    let mut states = Vec::new();
+
+    for drm in devices {
+        let Ok(connectors) = drm.list_connectors() else {
+            continue;
+        };
+        for c in connectors
+            .into_iter()
+            .filter(|c| c.state == connector::State::Connected)
+        {
+            let active = c
+                .crtc
+                .is_some_and(|h| drm.get_crtc_active(h).unwrap_or(false));
+            states.push(HardwareState {
+                device: drm.path().display().to_string(),
+                connector: c.name,
+                connector_id: c.handle.into(),
+                crtc: c.crtc.map(u32::from),
+                active,
+                edid: c.edid,
+            });
+        }
+    }
+
+    states
+}
+
+/// The state of the connector called `name`, or the only one whose name
+/// starts with it
+fn named_state<'a>(states: &'a [HardwareState], name: &str) -> Result<&'a HardwareState, Error> {
+    display::find_display_by_name(states, name, |s| Some(s.connector.as_str()))
+}
+
+/// The connected connectors that are dark, limited to `connector` if given
+///
+/// This includes connectors with no CRTC at all, e.g. after a crash that
+/// left nothing mapped.
+fn inactive_targets<'a>(
+    states: &'a [HardwareState],
+    connector: Option<&str>,
+) -> Vec<&'a HardwareState> {
+    states
+        .iter()
+        .filter(|s| !s.active)
+        .filter(|s| connector.is_none_or(|name| name == s.connector))
+        .collect()
+}
+
+/// Build the status of each connector from its hardware state
+///
+/// The daemon's owned CRTCs and its `seat` only add descriptions; the power
+/// state always comes from the hardware.
+///
+/// # Returns
+/// The displays, and a warning for each place the daemon's state disagrees
+/// with the hardware
+fn reconcile(
+    states: &[HardwareState],
+    owned: &[daemon::OwnedCrtc],
+    seat: Option<&str>,
+) -> (Vec<DisplayInfo>, Vec<String>) {
+    let mut warnings = Vec::new();
+
+    let displays = states
+        .iter()
+        .map(|state| {
+            let entry = owned
+                .iter()
+                .find(|o| o.connector == state.connector && o.device == state.device);
+            match (entry, state.active) {
+                (Some(_), true) => warnings.push(format!(
+                    "the daemon holds {} off but its CRTC is active",
+                    state.connector
+                )),
+                (None, false) if state.crtc.is_some() => warnings.push(format!(
+                    "{} is off but not held by the daemon",
+                    state.connector
+                )),
+                _ => {}
+            }
+
+            DisplayInfo {
+                name: state.connector.clone(),
+                power: if state.active {
+                    PowerState::On
+                } else {
+                    PowerState::Off
+                },
+                description: entry.map(|e| owned_description(e, seat)).or_else(|| {
+                    state
+                        .crtc
+                        .map(|crtc| format!("CRTC {} on {}", crtc, state.device))
+                }),
+                make: state.edid.make.clone(),
+                model: state.edid.model.clone(),
+                drm: Some(DrmIds {
+                    card: state.device.clone(),
+                    connector_id: state.connector_id,
+                    crtc_id: state.crtc,
+                }),
+            }
+        })
+        .collect();
+
+    for entry in owned {
+        if !states
+            .iter()
+            .any(|s| s.connector == entry.connector && s.device == entry.device)
+        {
+            warnings.push(format!(
+                "the daemon holds {} off but it is no longer connected",
+                entry.connector
+            ));
+        }
+    }
+
+    (displays, warnings)
+}
+
+impl PowerBackend for TtyBackend {
+    fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
+        if let Some(reason) = self.fbcon_reason()
+            && let Some(fbcon) = &mut self.fbcon
+        {
+            ui::notice(format_args!("{}; blanking the console instead", reason));
+            return fbcon.set_power(target, state);
+        }
+
+        let connector = self.target_connector(target)?;
+        let connector = connector.as_deref();
+
+        match state {
+            PowerState::Off => self.turn_off(connector, None),
+            PowerState::On => {
+                // Check if any daemon is running
+                if daemon::running_daemons().is_empty() {
+                    // A daemon that died left its displays off
+                    if daemon::has_saved_state() {
+                        return daemon::recover(&self.open, self.kill_after);
+                    }
+
+                    // Something else may have turned the display off
+                    let states = hardware_states(&self.open).unwrap_or_default();
+                    if !inactive_targets(&states, connector).is_empty() {
+                        return daemon::reenable_inactive(connector, &self.open);
+                    }
+
+                    // Already on, idempotent operation
+                    ui::notice("Display already on");
+                    return Ok(());
+                }
+
+                // A named display no daemon holds is already on
+                if connector.is_some() && daemon::is_daemon_running(connector).is_none() {
+                    ui::notice("Display already on");
+                    return Ok(());
+                }
+
+                // Signal the daemons holding it to restore and exit; the
+                // other instances keep their displays off
+                let stopped = daemon::stop_daemons(connector, self.kill_after, &self.open)?;
+                self.stopped.extend(stopped);
+                Ok(())
+            }
+        }
+    }
+
+    /// The daemon turns the displays back on and exits at the deadline, as if
+    /// `dpms on` had been run; running this again moves the deadline.
+    fn power_off_for(&mut self, target: &DisplayTarget, timeout: Duration) -> Result<(), Error> {
+        // Nothing would unblank the console again
+        if self.fbcon_reason().is_some() {
+            return Err(Error::OffTimeoutNotSupported);
+        }
+
+        let connector = self.target_connector(target)?;
+        // Round up, so the displays never come back early
+        let secs = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+        self.turn_off(connector.as_deref(), Some(output::unix_now() + secs))
+    }
+
+    /// Known from the state JSON of the daemons `set_power` stopped
+    fn off_durations(&self) -> Vec<(String, u64)> {
+        self.stopped
+            .iter()
+            .filter_map(|daemon| Some((daemon.connectors.clone(), daemon.duration?)))
+            .flat_map(|(connectors, secs)| connectors.into_iter().map(move |name| (name, secs)))
+            .collect()
+    }
+
+    /// The running daemons, live or from their state JSON
+    fn daemons(&self) -> Vec<output::DaemonReport> {
+        daemon::daemon_statuses()
+            .into_iter()
+            .map(|status| output::DaemonReport {
+                pid: status.info.pid,
+                started: status.info.started,
+                displays: status
+                    .info
+                    .displays
+                    .into_iter()
+                    .map(|display| display.connector)
+                    .collect(),
+                restore_deadline: status.info.restore_deadline,
+            })
+            .collect()
+    }
+
+    /// Nothing is turned off, but the DRM commits are tested with the
+    /// driver. Turning displays on is only described: the CRTCs the daemon
+    /// holds can't be tested while it is DRM master.
+    fn check_power(&self, target: &DisplayTarget, state: PowerState) -> Result<Vec<String>, Error> {
+        let action = match state {
+            PowerState::On => "unblank",
+            PowerState::Off => "blank",
+        };
+        if let Some(reason) = self.fbcon_reason()
+            && self.fbcon.is_some()
+        {
+            return Ok(vec![format!(
+                "{}; would {} the console instead",
+                reason, action
+            )]);
+        }
+
+        let connector = self.target_connector(target)?;
+        let connector = connector.as_deref();
+
+        let running = match (state, connector) {
+            (PowerState::On, None) => daemon::running_daemons().first().map(|(_, pid)| *pid),
+            _ => daemon::is_daemon_running(connector),
+        };
+        match (state, running) {
+            (PowerState::Off, Some(_)) => {
+                let held: Vec<String> = daemon::owned_crtcs()
+                    .into_iter()
+                    .map(|o| o.connector)
+                    .collect();
+                Ok(vec![format!(
+                    "The daemon already holds {} off",
+                    held.join(", ")
+                )])
+            }
+            (PowerState::Off, None) => {
+                drm_ops::probe_access(&self.open)?;
+                daemon::check_off(connector, &self.open)
+            }
+            (PowerState::On, Some(pid)) => Ok(vec![format!(
+                "Would stop the daemon (pid {}) to turn displays on",
+                pid
+            )]),
+            (PowerState::On, None) if daemon::has_saved_state() => Ok(vec![
+                "Would recover the displays left off by a daemon that died".to_string(),
+            ]),
+            (PowerState::On, None) => {
+                let states = hardware_states(&self.open).unwrap_or_default();
+                let inactive = inactive_targets(&states, connector);
+                if inactive.is_empty() {
+                    return Ok(vec!["Display already on".to_string()]);
+                }
+                Ok(inactive
+                    .into_iter()
+                    .map(|state| format!("Would turn on {} on {}", state.connector, state.device))
+                    .collect())
+            }
+        }
+    }
+
+    fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+        if self.fbcon_reason().is_some()
+            && let Some(fbcon) = &self.fbcon
+        {
+            return fbcon.get_power(target);
+        }
+
+        let mut displays = self.drm_displays(target)?;
+        describe_held_for(&mut displays, &daemon::daemon_infos(), output::unix_now());
+        Ok(displays)
+    }
+
     fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
         self.get_power(&DisplayTarget::All)
     }
+
+    fn name(&self) -> &'static str {
+        "tty"
+    }
+
+    fn describe(&self) -> String {
+        // DRM is always preferred; say so when it had to be skipped
+        if let Some(reason) = self.fbcon_reason()
+            && let Some(fbcon) = &self.fbcon
+        {
+            return format!(
+                "tty (DRM unavailable: {}; fell back to {})",
+                reason,
+                fbcon.describe()
+            );
+        }
+
+        // Show which devices would be used and why, if they can be opened
+        let choices = drm_ops::device_choices(&self.open);
+        if !choices.is_empty() {
+            let devices: Vec<String> = choices
+                .iter()
+                .map(|(path, driver, choice)| {
+                    format!("{} [{}]: {}", path.display(), driver, choice)
+                })
+                .collect();
+            return format!("tty (DRM atomic commit via daemon, {})", devices.join("; "));
+        }
+
+        match drm_ops::preferred_drm_device(&self.open) {
+            Some((path, driver)) => format!(
+                "tty (DRM atomic commit via daemon, {} [{}])",
+                path.display(),
+                driver
+            ),
+            None => "tty (DRM atomic commit via daemon)".to_string(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -129,9 +642,45 @@ mod tests {
         assert_power_backend::<TtyBackend>();
     }
 
+    #[test]
+    fn read_hardware_states_reports_crtc_activity() {
+        use crate::drm_ops::fake::FakeDrm;
+
+        let devices = [FakeDrm::new("/dev/dri/card0")
+            .with_connector("eDP-1", 70, Some(40))
+            .with_connector("HDMI-A-1", 77, Some(51))
+            .with_inactive_crtc(51)
+            .with_connector("DP-1", 80, None)];
+
+        let states = read_hardware_states(&devices);
+
+        let summary: Vec<_> = states
+            .iter()
+            .map(|s| (s.connector.as_str(), s.crtc, s.active))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("eDP-1", Some(40), true),
+                ("HDMI-A-1", Some(51), false),
+                ("DP-1", None, false)
+            ]
+        );
+        assert!(states.iter().all(|s| s.device == "/dev/dri/card0"));
+        let ids: Vec<u32> = states.iter().map(|s| s.connector_id).collect();
+        assert_eq!(ids, [70, 77, 80]);
+    }
+
     #[test]
     fn get_power_returns_display_info() {
-        let backend = TtyBackend;
+        let mut backend = TtyBackend::new(
+            drm_ops::OpenOptions::default(),
+            daemon::DaemonOptions::default(),
+            false,
+            None,
+        )
+        .unwrap();
+        backend.fbcon = None;
         let result = backend.get_power(&DisplayTarget::Default);
 
         assert!(result.is_ok());
@@ -142,7 +691,14 @@ mod tests {
 
     #[test]
     fn list_displays_returns_tty_display() {
-        let backend = TtyBackend;
+        let mut backend = TtyBackend::new(
+            drm_ops::OpenOptions::default(),
+            daemon::DaemonOptions::default(),
+            false,
+            None,
+        )
+        .unwrap();
+        backend.fbcon = None;
         let result = backend.list_displays();
 
         assert!(result.is_ok());
@@ -151,6 +707,291 @@ mod tests {
         assert_eq!(displays[0].name, "tty");
     }
 
+    #[test]
+    fn falls_back_to_fbcon_without_drm_devices() {
+        let open = drm_ops::OpenOptions::default();
+        if drm_ops::candidate_devices(&open).is_ok() {
+            // Only testable on machines without DRM devices
+            return;
+        }
+        let dir = std::path::Path::new("/tmp/dpms-test-tty-fbcon");
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir.join("fb0")).unwrap();
+        let mut backend =
+            TtyBackend::new(open, daemon::DaemonOptions::default(), false, None).unwrap();
+        backend.fbcon = Some(FbconBackend::sysfs(
+            dir.join("fb0/blank"),
+            dir.join("dpms.fbcon"),
+        ));
+
+        backend
+            .set_power(&DisplayTarget::Default, PowerState::Off)
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("fb0/blank")).unwrap(), "4");
+        let displays = backend.get_power(&DisplayTarget::Default).unwrap();
+        assert_eq!(displays[0].name, "fb0");
+        assert_eq!(displays[0].power, PowerState::Off);
+        assert!(backend.describe().contains("fell back to fbcon"));
+    }
+
+    #[test]
+    fn unknown_display_is_not_found_without_drm_access() {
+        let open = drm_ops::OpenOptions::default();
+        if hardware_states(&open).is_some() {
+            eprintln!("skipping: DRM devices are readable");
+            return;
+        }
+        let mut backend =
+            TtyBackend::new(open, daemon::DaemonOptions::default(), false, None).unwrap();
+        backend.fbcon = None;
+
+        // Only the connectors a daemon holds are known
+        let result = backend.get_power(&DisplayTarget::Named("NOPE-9".to_string()));
+        assert!(
+            matches!(&result, Err(Error::DisplayNotFound { name, .. }) if name == "NOPE-9"),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn chosen_card_never_falls_back_to_fbcon() {
+        let open = drm_ops::OpenOptions {
+            card: Some(std::path::PathBuf::from("/tmp/dpms-test-no-such-card")),
+            ..Default::default()
+        };
+        let mut backend =
+            TtyBackend::new(open, daemon::DaemonOptions::default(), false, None).unwrap();
+        backend.fbcon = Some(FbconBackend::sysfs(
+            std::path::PathBuf::from("/tmp/dpms-test-no-such-dir/blank"),
+            std::path::PathBuf::from("/tmp/dpms-test-no-such-dir/dpms.fbcon"),
+        ));
+        assert!(backend.fbcon_reason().is_none());
+    }
+
+    #[test]
+    fn held_displays_say_for_how_long() {
+        let display = |name: &str, power| DisplayInfo {
+            name: name.to_string(),
+            power,
+            description: Some("CRTC 51 on /dev/dri/card0, held off by daemon".to_string()),
+            make: None,
+            model: None,
+            drm: None,
+        };
+        let mut displays = [
+            display("HDMI-A-1", PowerState::Off),
+            display("eDP-1", PowerState::Off),
+            display("DP-1", PowerState::On),
+        ];
+        let info = daemon::DaemonInfo {
+            version: 1,
+            pid: 4242,
+            started: 1_000,
+            devices: vec!["/dev/dri/card0".to_string()],
+            displays: ["HDMI-A-1", "DP-1"]
+                .iter()
+                .map(|connector| daemon::HeldDisplay {
+                    device: "/dev/dri/card0".to_string(),
+                    connector: connector.to_string(),
+                    crtc: 51,
+                    was_active: true,
+                    asserted: "off".to_string(),
+                })
+                .collect(),
+            restore_deadline: None,
+            seat: None,
+            seat_cycles: 0,
+            last_error: None,
+            sleep_inhibited: false,
+        };
+
+        describe_held_for(&mut displays, std::slice::from_ref(&info), 1_000 + 12 * 60);
+        assert_eq!(
+            displays[0].description.as_deref(),
+            Some("CRTC 51 on /dev/dri/card0, held off by daemon for 12m")
+        );
+        // Not held, or lit despite being held
+        assert!(!displays[1].description.as_ref().unwrap().contains(" for "));
+        assert!(!displays[2].description.as_ref().unwrap().contains(" for "));
+
+        // A daemon started with --timeout says when it lets go
+        let info = daemon::DaemonInfo {
+            restore_deadline: Some(1_000 + 30 * 60),
+            ..info
+        };
+        let mut timed = [display("HDMI-A-1", PowerState::Off)];
+        describe_held_for(&mut timed, &[info], 1_000 + 12 * 60);
+        assert_eq!(
+            timed[0].description.as_deref(),
+            Some("CRTC 51 on /dev/dri/card0, held off by daemon for 12m, restoring in 18m")
+        );
+    }
+
+    #[test]
+    fn owned_description_names_crtc_and_device() {
+        let mut entry = daemon::OwnedCrtc {
+            device: "/dev/dri/card0".to_string(),
+            connector: "HDMI-A-1".to_string(),
+            crtc: 51,
+            mechanism: None,
+        };
+        assert_eq!(
+            owned_description(&entry, None),
+            "CRTC 51 on /dev/dri/card0, held off by daemon"
+        );
+        assert_eq!(
+            owned_description(&entry, Some("seat1")),
+            "CRTC 51 on /dev/dri/card0, held off by daemon on seat1"
+        );
+
+        entry.mechanism = Some(drm_ops::PowerMechanism::LegacyDpms);
+        assert_eq!(
+            owned_description(&entry, None),
+            "CRTC 51 on /dev/dri/card0 via legacy DPMS, held off by daemon"
+        );
+    }
+
+    fn hardware_state(connector: &str, crtc: Option<u32>, active: bool) -> HardwareState {
+        HardwareState {
+            device: "/dev/dri/card0".to_string(),
+            connector: connector.to_string(),
+            connector_id: 70,
+            crtc,
+            active,
+            edid: EdidInfo::default(),
+        }
+    }
+
+    fn owned_crtc(connector: &str, crtc: u32) -> daemon::OwnedCrtc {
+        daemon::OwnedCrtc {
+            device: "/dev/dri/card0".to_string(),
+            connector: connector.to_string(),
+            crtc,
+            mechanism: None,
+        }
+    }
+
+    #[test]
+    fn reconcile_reports_hardware_state() {
+        let states = [
+            hardware_state("eDP-1", Some(40), true),
+            hardware_state("HDMI-A-1", Some(51), false),
+        ];
+        let owned = [owned_crtc("HDMI-A-1", 51)];
+
+        let (displays, warnings) = reconcile(&states, &owned, None);
+
+        assert!(warnings.is_empty());
+        assert_eq!(displays.len(), 2);
+        assert_eq!(displays[0].power, PowerState::On);
+        assert_eq!(
+            displays[0].description.as_deref(),
+            Some("CRTC 40 on /dev/dri/card0")
+        );
+        assert_eq!(displays[1].power, PowerState::Off);
+        assert_eq!(
+            displays[1].description.as_deref(),
+            Some("CRTC 51 on /dev/dri/card0, held off by daemon")
+        );
+        assert_eq!(
+            displays[1].drm,
+            Some(DrmIds {
+                card: "/dev/dri/card0".to_string(),
+                connector_id: 70,
+                crtc_id: Some(51),
+            })
+        );
+    }
+
+    #[test]
+    fn reconcile_reports_edid_make_and_model() {
+        let mut state = hardware_state("DP-1", Some(40), true);
+        state.edid = EdidInfo {
+            make: Some("DEL".to_string()),
+            model: Some("DELL U2415".to_string()),
+        };
+
+        let (displays, _) = reconcile(&[state], &[], None);
+
+        assert_eq!(displays[0].make.as_deref(), Some("DEL"));
+        assert_eq!(displays[0].model.as_deref(), Some("DELL U2415"));
+    }
+
+    #[test]
+    fn reconcile_warns_when_daemon_disagrees() {
+        // The daemon thinks HDMI-A-1 is off, but a compositor re-enabled it;
+        // eDP-1 was blanked by another tool; DP-1 was unplugged
+        let states = [
+            hardware_state("HDMI-A-1", Some(51), true),
+            hardware_state("eDP-1", Some(40), false),
+        ];
+        let owned = [owned_crtc("HDMI-A-1", 51), owned_crtc("DP-1", 60)];
+
+        let (displays, warnings) = reconcile(&states, &owned, None);
+
+        assert_eq!(displays[0].power, PowerState::On);
+        assert_eq!(displays[1].power, PowerState::Off);
+        assert_eq!(
+            warnings,
+            [
+                "the daemon holds HDMI-A-1 off but its CRTC is active",
+                "eDP-1 is off but not held by the daemon",
+                "the daemon holds DP-1 off but it is no longer connected",
+            ]
+        );
+    }
+
+    #[test]
+    fn inactive_targets_filters_by_connector() {
+        let states = [
+            hardware_state("eDP-1", Some(40), true),
+            hardware_state("HDMI-A-1", Some(51), false),
+            hardware_state("DP-2", None, false),
+        ];
+
+        let all: Vec<&str> = inactive_targets(&states, None)
+            .iter()
+            .map(|s| s.connector.as_str())
+            .collect();
+        assert_eq!(all, ["HDMI-A-1", "DP-2"]);
+
+        assert!(inactive_targets(&states, Some("eDP-1")).is_empty());
+        assert_eq!(inactive_targets(&states, Some("HDMI-A-1")).len(), 1);
+    }
+
+    #[test]
+    fn named_state_matches_a_unique_prefix() {
+        let states = [
+            hardware_state("eDP-1", Some(40), true),
+            hardware_state("HDMI-A-1", Some(51), true),
+            hardware_state("DP-1", Some(60), true),
+            hardware_state("DP-2", None, false),
+        ];
+
+        assert_eq!(named_state(&states, "HDMI").unwrap().connector, "HDMI-A-1");
+        assert_eq!(named_state(&states, "DP-2").unwrap().connector, "DP-2");
+        assert!(matches!(
+            named_state(&states, "DP"),
+            Err(Error::AmbiguousDisplay { candidates, .. }) if candidates == ["DP-1", "DP-2"]
+        ));
+        assert!(matches!(
+            named_state(&states, "VGA"),
+            Err(Error::DisplayNotFound { available, .. }) if available.len() == 4
+        ));
+    }
+
+    #[test]
+    fn reconcile_connector_without_crtc_is_off_without_warning() {
+        let states = [hardware_state("DP-2", None, false)];
+
+        let (displays, warnings) = reconcile(&states, &[], None);
+
+        assert!(warnings.is_empty());
+        assert_eq!(displays[0].power, PowerState::Off);
+        assert_eq!(displays[0].description, None);
+    }
+
     // Note: More comprehensive tests require F8 implementation or mocking
     // Integration tests will verify the full daemon coordination logic
 }
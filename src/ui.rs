@@ -0,0 +1,113 @@
+/// Notices and warnings for the user, on stderr
+///
+/// Backends say what is worth knowing but isn't an error, e.g. that a
+/// display was already off, with `notice`. At normal verbosity that is a line
+/// on stderr; `--quiet` drops it, and `--json` commands collect it for the
+/// `notices` array of their report, so scripts get no prose. Warnings say
+/// something went wrong and are always printed.
+use std::fmt::Display;
+use std::io::{self, Write};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// What becomes of notices
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Print them on stderr
+    #[default]
+    Normal,
+    /// Drop them, for `--quiet`
+    Quiet,
+    /// Keep them for `take_notices`, for `--json`
+    Collect,
+}
+
+/// Notices not taken yet, and what to do with new ones
+struct Notices {
+    verbosity: Verbosity,
+    collected: Vec<String>,
+}
+
+impl Notices {
+    /// Print, drop or keep `message`, as the verbosity says
+    fn notice(&mut self, message: String, out: &mut impl Write) {
+        match self.verbosity {
+            Verbosity::Normal => {
+                // Nothing to be done about a closed stderr
+                let _ = writeln!(out, "{}", message);
+            }
+            Verbosity::Quiet => {}
+            Verbosity::Collect => self.collected.push(message),
+        }
+    }
+}
+
+static NOTICES: Mutex<Notices> = Mutex::new(Notices {
+    verbosity: Verbosity::Normal,
+    collected: Vec::new(),
+});
+
+/// The process' notices; a panic while holding them leaves them usable
+fn notices() -> MutexGuard<'static, Notices> {
+    NOTICES.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Set what becomes of notices from now on
+pub fn set_verbosity(verbosity: Verbosity) {
+    notices().verbosity = verbosity;
+}
+
+/// Tell the user `message`, unless they asked for quiet
+pub fn notice(message: impl Display) {
+    notices().notice(message.to_string(), &mut io::stderr());
+}
+
+/// Warn the user, at any verbosity
+pub fn warning(message: impl Display) {
+    eprintln!("Warning: {}", message);
+}
+
+/// The notices collected so far, oldest first; later calls only get newer ones
+pub fn take_notices() -> Vec<String> {
+    std::mem::take(&mut notices().collected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notices(verbosity: Verbosity) -> Notices {
+        Notices {
+            verbosity,
+            collected: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn normal_notices_are_printed() {
+        let mut out = Vec::new();
+        let mut notices = notices(Verbosity::Normal);
+        notices.notice("Display already off".to_string(), &mut out);
+        assert_eq!(out, b"Display already off\n");
+        assert!(notices.collected.is_empty());
+    }
+
+    #[test]
+    fn quiet_prints_nothing() {
+        let mut out = Vec::new();
+        let mut notices = notices(Verbosity::Quiet);
+        notices.notice("Display already off".to_string(), &mut out);
+        notices.notice("Display already on".to_string(), &mut out);
+        assert!(out.is_empty());
+        assert!(notices.collected.is_empty());
+    }
+
+    #[test]
+    fn collected_notices_are_kept_in_order() {
+        let mut out = Vec::new();
+        let mut notices = notices(Verbosity::Collect);
+        notices.notice("first".to_string(), &mut out);
+        notices.notice("second".to_string(), &mut out);
+        assert!(out.is_empty());
+        assert_eq!(notices.collected, ["first", "second"]);
+    }
+}
@@ -2,55 +2,367 @@
 ///
 /// This module implements the PowerBackend trait using the Wayland compositor's
 /// `zwlr_output_power_management_v1` protocol to control display power state.
+/// KWin does not implement that protocol, so `org_kde_kwin_dpms` is used as a
+/// fallback when the wlr power manager is not advertised.
 ///
 /// The backend connects to the Wayland display socket, binds to the necessary
 /// global objects, and uses the power management protocol to send power state
 /// commands to the compositor.
+///
+/// `dpms idle` additionally uses `ext_idle_notify_v1` to turn outputs off
+/// after a period of user inactivity.
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use nix::errno::Errno;
+use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+use signal_hook::consts::signal::{SIGINT, SIGTERM};
+use signal_hook::flag;
 
-use crate::backend::PowerBackend;
-use crate::display::{DisplayInfo, DisplayTarget};
-use crate::error::Error;
+use crate::backend::{PowerBackend, WatchEvent};
+use crate::display::{DisplayInfo, DisplayTarget, find_display_by_name, natural_cmp};
+use crate::env;
+use crate::error::{Error, ResultExt};
 use crate::output::PowerState;
 
 use wayland_client::{
-    Connection, Dispatch, QueueHandle, WEnum,
+    Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum,
+    backend::{Backend, ObjectData, ObjectId, protocol::Message},
     globals::{GlobalListContents, registry_queue_init},
-    protocol::{wl_output, wl_registry},
+    protocol::{wl_display, wl_output, wl_registry, wl_seat},
 };
+use wayland_protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1, ext_idle_notifier_v1,
+};
+use wayland_protocols::xdg::xdg_output::zv1::client::{zxdg_output_manager_v1, zxdg_output_v1};
+use wayland_protocols_plasma::dpms::client::{org_kde_kwin_dpms, org_kde_kwin_dpms_manager};
 use wayland_protocols_wlr::output_power_management::v1::client::{
     zwlr_output_power_manager_v1, zwlr_output_power_v1,
 };
 
+/// Default delay before the first reconnect attempt; doubled after each failure
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// Upper bound for the reconnect backoff delay
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(8);
+
+/// How long `dpms idle` waits for events before checking for a shutdown signal
+const IDLE_SIGNAL_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Resolve a socket path, treating relative paths as relative to XDG_RUNTIME_DIR
+fn resolve_socket_path(path: &Path) -> PathBuf {
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(runtime_dir) => PathBuf::from(runtime_dir).join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Open a Wayland connection on the socket at `path`
+///
+/// Validates the path up front so a typo produces a clear error instead of
+/// a bare "connection refused".
+fn connect_to_socket(path: &Path) -> Result<Connection, Error> {
+    let invalid = |reason: String| Error::WaylandSocketInvalid {
+        path: path.display().to_string(),
+        reason,
+    };
+
+    let metadata = std::fs::metadata(path).map_err(|e| invalid(e.to_string()))?;
+    if !metadata.file_type().is_socket() {
+        return Err(invalid("not a socket".to_string()));
+    }
+
+    let stream = UnixStream::connect(path)?;
+    Connection::from_socket(stream)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotConnected, e).into())
+}
+
+/// Treat empty strings sent by the compositor as missing values
+fn non_empty(value: String) -> Option<String> {
+    if value.trim().is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
 /// Information about a single output
 struct OutputInfo {
     proxy: wl_output::WlOutput,
+    /// Name from wl_output (version 4+ only)
     name: Option<String>,
+    /// Description from wl_output (version 4+ only)
     description: Option<String>,
+    /// Name from xdg-output, preferred over the wl_output name
+    xdg_name: Option<String>,
+    /// Description from xdg-output, preferred over the wl_output description
+    xdg_description: Option<String>,
     make: Option<String>,
     model: Option<String>,
 }
 
+impl OutputInfo {
+    /// Name used for display and matching (xdg-output first, then wl_output)
+    fn display_name(&self) -> Option<&str> {
+        self.xdg_name.as_deref().or(self.name.as_deref())
+    }
+
+    /// Description used for display (xdg-output first, then wl_output)
+    fn display_description(&self) -> Option<&str> {
+        self.xdg_description
+            .as_deref()
+            .or(self.description.as_deref())
+    }
+}
+
+/// Power management protocol offered by the compositor
+enum PowerManager {
+    /// wlroots `zwlr_output_power_management_v1`
+    Wlr(zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1),
+    /// KWin `org_kde_kwin_dpms`
+    Kde(org_kde_kwin_dpms_manager::OrgKdeKwinDpmsManager),
+}
+
+/// Per-output power control object created by a PowerManager
+enum PowerControl {
+    Wlr(zwlr_output_power_v1::ZwlrOutputPowerV1),
+    Kde(org_kde_kwin_dpms::OrgKdeKwinDpms),
+}
+
+/// KWin DPMS mode values (from the `mode` enum in dpms.xml)
+const KDE_DPMS_MODE_ON: u32 = 0;
+const KDE_DPMS_MODE_OFF: u32 = 3;
+
+/// Newest `zwlr_output_power_manager_v1` version known to wayland-protocols-wlr
+///
+/// Only version 1 exists so far, so nothing is gated on the negotiated
+/// version yet. Behavior added in later revisions should check
+/// `WaylandState::power_manager_version` before relying on it.
+fn wlr_power_manager_max_version() -> u32 {
+    zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1::interface().version
+}
+
+impl PowerManager {
+    /// Protocol name, for verbose output
+    fn protocol_name(&self) -> &'static str {
+        match self {
+            PowerManager::Wlr(_) => "zwlr_output_power_management_v1",
+            PowerManager::Kde(_) => "org_kde_kwin_dpms",
+        }
+    }
+
+    /// Version of the bound manager global
+    fn version(&self) -> u32 {
+        match self {
+            PowerManager::Wlr(manager) => manager.version(),
+            PowerManager::Kde(manager) => manager.version(),
+        }
+    }
+
+    /// Create a power control object for `output` on the queue of `qh`
+    fn get_control<D, U>(
+        &self,
+        output: &wl_output::WlOutput,
+        qh: &QueueHandle<D>,
+        data: U,
+    ) -> PowerControl
+    where
+        D: Dispatch<zwlr_output_power_v1::ZwlrOutputPowerV1, U>
+            + Dispatch<org_kde_kwin_dpms::OrgKdeKwinDpms, U>
+            + 'static,
+        U: Send + Sync + 'static,
+    {
+        match self {
+            PowerManager::Wlr(manager) => {
+                PowerControl::Wlr(manager.get_output_power(output, qh, data))
+            }
+            PowerManager::Kde(manager) => PowerControl::Kde(manager.get(output, qh, data)),
+        }
+    }
+}
+
+impl PowerControl {
+    /// Request a new power state for the output
+    fn set_power(&self, state: PowerState) {
+        match self {
+            PowerControl::Wlr(control) => control.set_mode(match state {
+                PowerState::On => zwlr_output_power_v1::Mode::On,
+                PowerState::Off => zwlr_output_power_v1::Mode::Off,
+            }),
+            PowerControl::Kde(control) => control.set(match state {
+                PowerState::On => KDE_DPMS_MODE_ON,
+                PowerState::Off => KDE_DPMS_MODE_OFF,
+            }),
+        }
+    }
+
+    /// Destroy the control object
+    fn destroy(&self) {
+        match self {
+            PowerControl::Wlr(control) => control.destroy(),
+            PowerControl::Kde(control) => control.release(),
+        }
+    }
+}
+
+/// Convert a wlr power mode to PowerState
+fn wlr_power(mode: zwlr_output_power_v1::Mode) -> PowerState {
+    match mode {
+        zwlr_output_power_v1::Mode::Off => PowerState::Off,
+        _ => PowerState::On,
+    }
+}
+
+/// Convert a KWin DPMS mode to PowerState (Standby and Suspend count as Off)
+fn kde_power(mode: u32) -> PowerState {
+    if mode == KDE_DPMS_MODE_ON {
+        PowerState::On
+    } else {
+        PowerState::Off
+    }
+}
+
 /// Wayland backend implementing PowerBackend trait
 pub struct WaylandBackend {
     connection: Connection,
     state: WaylandState,
+    /// Explicit socket path, if not connecting via WAYLAND_DISPLAY
+    socket: Option<PathBuf>,
+    /// Upper bound for each roundtrip with the compositor
+    timeout: Duration,
+    /// Long-lived power controls and their last reported modes
+    cache: RefCell<PowerCache>,
+    /// Delay before the first reconnect attempt of `watch`
+    reconnect_delay: Duration,
 }
 
 /// Internal state for Wayland event handling
 struct WaylandState {
-    power_manager: Option<zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1>,
+    power_manager: Option<PowerManager>,
+    /// Negotiated version of the bound power manager global
+    power_manager_version: u32,
+    /// Optional xdg-output manager for logical output names and descriptions
+    xdg_output_manager: Option<zxdg_output_manager_v1::ZxdgOutputManagerV1>,
     /// All discovered outputs, keyed by wl_output id
     outputs: HashMap<u32, OutputInfo>,
-    /// Current mode received from power query
-    current_mode: Option<zwlr_output_power_v1::Mode>,
-    failed: bool,
 }
 
-/// Minimal state for querying power mode (avoids cloning full WaylandState)
+/// Why the compositor rejected a power control
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlFailure {
+    /// wlr `failed`: another client holds the output, or it went away
+    Busy,
+    /// KWin reported DPMS as unsupported for the output
+    Unsupported,
+}
+
+impl ControlFailure {
+    /// Short reason for per-output error listings
+    fn reason(self) -> &'static str {
+        match self {
+            ControlFailure::Busy => "power control held by another client",
+            ControlFailure::Unsupported => "DPMS not supported by this output",
+        }
+    }
+
+    /// Error for a single failed output
+    fn into_error(self, name: String) -> Error {
+        match self {
+            ControlFailure::Busy => Error::PowerControlBusy {
+                name,
+                conflicts: env::find_conflicting_clients(Path::new("/proc")),
+            },
+            ControlFailure::Unsupported => Error::ProtocolNotSupported,
+        }
+    }
+}
+
+/// Power controls kept alive for the lifetime of the backend
+///
+/// The compositor pushes a Mode event whenever an output's power changes, so
+/// while a control exists the cached mode is current and `get_power` is a pure
+/// read. Controls are created on first use and shared by `get_power`,
+/// `set_power` and `watch`: wlroots allows only one control per output, so
+/// creating a second one would fail.
+struct PowerCache {
+    event_queue: EventQueue<CacheState>,
+    state: CacheState,
+    /// Power control per output id
+    controls: HashMap<u32, PowerControl>,
+    /// Roundtrips done so far, so tests can check requests are batched
+    #[cfg(test)]
+    roundtrips: usize,
+}
+
+/// Events received on the power controls of a PowerCache
 #[derive(Default)]
-struct QueryState {
-    current_mode: Option<zwlr_output_power_v1::Mode>,
+struct CacheState {
+    /// Last mode reported per output id
+    modes: HashMap<u32, PowerState>,
+    /// Controls rejected by the compositor since the last check
+    failed: Vec<(u32, ControlFailure)>,
+}
+
+impl PowerCache {
+    fn new(connection: &Connection) -> Self {
+        Self {
+            event_queue: connection.new_event_queue(),
+            state: CacheState::default(),
+            controls: HashMap::new(),
+            #[cfg(test)]
+            roundtrips: 0,
+        }
+    }
+
+    /// Read and dispatch events that already arrived, without blocking
+    fn refresh(&mut self) -> Result<(), Error> {
+        dispatch_timeout(&mut self.event_queue, &mut self.state, Duration::ZERO)?;
+        Ok(())
+    }
+
+    /// Wait for the compositor to process all requests sent so far
+    fn roundtrip(&mut self, connection: &Connection, timeout: Duration) -> Result<(), Error> {
+        #[cfg(test)]
+        {
+            self.roundtrips += 1;
+        }
+        roundtrip_timeout(connection, &mut self.event_queue, &mut self.state, timeout)?;
+        Ok(())
+    }
+
+    /// Take the failures since the last call, dropping the dead controls
+    ///
+    /// A failed control never recovers, so it is removed to let a later call
+    /// try again with a fresh one.
+    fn take_failures(&mut self) -> Vec<(u32, ControlFailure)> {
+        let failed = std::mem::take(&mut self.state.failed);
+        for (id, _) in &failed {
+            if let Some(control) = self.controls.remove(id) {
+                control.destroy();
+            }
+            self.state.modes.remove(id);
+        }
+        failed
+    }
+}
+
+/// State for `dpms idle`: idle notifications plus held power controls
+#[derive(Default)]
+struct IdleState {
+    /// Latest idle transition not yet acted upon (true = idled, false = resumed)
+    idle: Option<bool>,
+    /// Output id whose power control was taken over by another client
+    failed: Option<u32>,
 }
 
 impl WaylandBackend {
@@ -59,15 +371,58 @@ impl WaylandBackend {
     /// This connects to the Wayland display using the WAYLAND_DISPLAY environment
     /// variable and binds to the necessary global objects.
     ///
+    /// # Parameters
+    /// - `timeout`: Upper bound for each roundtrip with the compositor
+    ///
     /// # Returns
     /// - `Ok(WaylandBackend)` if connection succeeds
     /// - `Err(Error::Io)` if connection fails
+    /// - `Err(Error::CompositorTimeout)` if the compositor doesn't answer in time
     /// - `Err(Error::ProtocolNotSupported)` if compositor doesn't support power management
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(timeout: Duration) -> Result<Self, Error> {
         // Connect to Wayland display
         let connection = Connection::connect_to_env()
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::NotConnected, e))?;
 
+        Self::from_connection(connection, None, timeout)
+    }
+
+    /// Create a new Wayland backend connected to an explicit socket path
+    ///
+    /// Relative paths are resolved against `XDG_RUNTIME_DIR`, so both
+    /// `/run/user/1001/wayland-1` and `wayland-1` are accepted.
+    ///
+    /// # Returns
+    /// - `Ok(WaylandBackend)` if connection succeeds
+    /// - `Err(Error::WaylandSocketInvalid)` if the path is missing or not a socket
+    /// - `Err(Error::Io)` if connecting to the socket fails
+    /// - `Err(Error::CompositorTimeout)` if the compositor doesn't answer in time
+    /// - `Err(Error::ProtocolNotSupported)` if compositor doesn't support power management
+    pub fn with_socket(path: &Path, timeout: Duration) -> Result<Self, Error> {
+        let path = resolve_socket_path(path);
+        let connection = connect_to_socket(&path)?;
+        Self::from_connection(connection, Some(path), timeout)
+    }
+
+    /// Connect the same way this backend was originally connected
+    fn reopen(&self) -> Result<Self, Error> {
+        match &self.socket {
+            Some(path) => Self::with_socket(path, self.timeout),
+            None => Self::new(self.timeout),
+        }
+    }
+
+    /// Bind globals and discover outputs on an established connection
+    fn from_connection(
+        connection: Connection,
+        socket: Option<PathBuf>,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        // registry_queue_init() does an unbounded roundtrip, so make sure the
+        // compositor is answering at all before handing control to it
+        let mut probe_queue = connection.new_event_queue();
+        roundtrip_timeout(&connection, &mut probe_queue, &mut (), timeout)?;
+
         // Initialize registry and get globals
         let (globals, mut event_queue) =
             registry_queue_init(&connection).map_err(std::io::Error::other)?;
@@ -77,24 +432,39 @@ impl WaylandBackend {
         // Create initial state
         let mut state = WaylandState {
             power_manager: None,
+            power_manager_version: 0,
+            xdg_output_manager: None,
             outputs: HashMap::new(),
-            current_mode: None,
-            failed: false,
         };
 
-        // Bind to power manager (required)
+        // Bind to power manager (required), preferring wlr over KWin's protocol.
+        // Accept any wlr version up to the newest one we were built against.
         state.power_manager = globals
-            .bind::<zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1, _, _>(&qh, 1..=1, ())
+            .bind::<zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1, _, _>(
+                &qh,
+                1..=wlr_power_manager_max_version(),
+                (),
+            )
+            .map(PowerManager::Wlr)
+            .or_else(|_| {
+                globals
+                    .bind::<org_kde_kwin_dpms_manager::OrgKdeKwinDpmsManager, _, _>(&qh, 1..=1, ())
+                    .map(PowerManager::Kde)
+            })
             .ok();
-        if state.power_manager.is_none() {
-            return Err(Error::ProtocolNotSupported);
+        match &state.power_manager {
+            Some(manager) => state.power_manager_version = manager.version(),
+            None => return Err(Error::ProtocolNotSupported),
         }
 
+        // Bind to xdg-output manager (optional). Version 2 added name/description.
+        state.xdg_output_manager = globals
+            .bind::<zxdg_output_manager_v1::ZxdgOutputManagerV1, _, _>(&qh, 2..=3, ())
+            .ok();
+
         // Bind to all outputs - iterate through globals to find all wl_output
         // We need to do a roundtrip first to ensure we have all globals
-        event_queue
-            .roundtrip(&mut state)
-            .map_err(std::io::Error::other)?;
+        roundtrip_timeout(&connection, &mut event_queue, &mut state, timeout)?;
 
         // Now bind to each wl_output global
         for global in globals.contents().clone_list() {
@@ -102,196 +472,582 @@ impl WaylandBackend {
                 // Use version 4 for wl_output (supports name and description events)
                 // Clamp to what the compositor advertises
                 let version = global.version.min(4);
-                if let Ok(output) =
-                    globals.bind::<wl_output::WlOutput, _, _>(&qh, version..=version, global.name)
-                {
-                    state.outputs.insert(
-                        global.name,
-                        OutputInfo {
-                            proxy: output,
-                            name: None,
-                            description: None,
-                            make: None,
-                            model: None,
-                        },
-                    );
-                }
+                // GlobalList::bind() always picks the first global of an
+                // interface, so bind each output by its registry name
+                let output = globals.registry().bind::<wl_output::WlOutput, _, _>(
+                    global.name,
+                    version,
+                    &qh,
+                    global.name,
+                );
+                state.outputs.insert(
+                    global.name,
+                    OutputInfo {
+                        proxy: output,
+                        name: None,
+                        description: None,
+                        xdg_name: None,
+                        xdg_description: None,
+                        make: None,
+                        model: None,
+                    },
+                );
             }
         }
 
         if state.outputs.is_empty() {
-            return Err(Error::NoDisplayFound);
+            return Err(Error::NoOutputAdvertised);
         }
 
+        // Request xdg-output objects so their events arrive in the same roundtrip
+        let xdg_outputs: Vec<zxdg_output_v1::ZxdgOutputV1> = match &state.xdg_output_manager {
+            Some(manager) => state
+                .outputs
+                .iter()
+                .map(|(id, output_info)| manager.get_xdg_output(&output_info.proxy, &qh, *id))
+                .collect(),
+            None => Vec::new(),
+        };
+
         // Roundtrip to receive output info events (name, description, etc.)
-        event_queue
-            .roundtrip(&mut state)
-            .map_err(std::io::Error::other)?;
+        roundtrip_timeout(&connection, &mut event_queue, &mut state, timeout)?;
+
+        // xdg-output objects are only needed for the initial info events
+        for xdg_output in xdg_outputs {
+            xdg_output.destroy();
+        }
 
-        Ok(Self { connection, state })
+        let cache = RefCell::new(PowerCache::new(&connection));
+
+        Ok(Self {
+            connection,
+            state,
+            socket,
+            timeout,
+            cache,
+            reconnect_delay: RECONNECT_INITIAL_DELAY,
+        })
     }
 
-    /// Resolve display target to list of output IDs
+    /// All output IDs in display order
+    ///
+    /// Outputs are sorted by name using natural ordering (`DP-2` before
+    /// `DP-10`). Unnamed outputs come last. Ties are broken by the registry
+    /// global name, which compositors hand out in increasing order, so the
+    /// result does not depend on HashMap iteration order.
+    fn sorted_output_ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.state.outputs.keys().copied().collect();
+        ids.sort_by(|a, b| {
+            let a_name = self.state.outputs[a].display_name();
+            let b_name = self.state.outputs[b].display_name();
+            match (a_name, b_name) {
+                (Some(a_name), Some(b_name)) => natural_cmp(a_name, b_name),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            }
+            .then(a.cmp(b))
+        });
+        ids
+    }
+
+    /// Resolve display target to list of output IDs, in display order
     fn resolve_targets(&self, target: &DisplayTarget) -> Result<Vec<u32>, Error> {
+        let ids = self.sorted_output_ids();
+
         match target {
             DisplayTarget::All | DisplayTarget::Default => {
                 // Return all output IDs
-                Ok(self.state.outputs.keys().copied().collect())
+                Ok(ids)
             }
             DisplayTarget::Named(name) => {
-                // Exact match first
-                for (id, info) in &self.state.outputs {
-                    if info.name.as_deref() == Some(name.as_str()) {
-                        return Ok(vec![*id]);
-                    }
-                }
-
-                // Partial match (prefix)
-                let matches: Vec<u32> = self
-                    .state
-                    .outputs
-                    .iter()
-                    .filter(|(_, info)| {
-                        info.name
-                            .as_ref()
-                            .map(|n| n.starts_with(name))
-                            .unwrap_or(false)
-                    })
-                    .map(|(id, _)| *id)
-                    .collect();
-
-                if matches.len() == 1 {
-                    Ok(matches)
-                } else if matches.len() > 1 {
-                    let candidates: Vec<String> = matches
-                        .iter()
-                        .filter_map(|id| self.state.outputs.get(id).and_then(|o| o.name.clone()))
-                        .collect();
-                    Err(Error::AmbiguousDisplay {
-                        name: name.clone(),
-                        candidates,
-                    })
-                } else {
-                    let available: Vec<String> = self
-                        .state
-                        .outputs
-                        .values()
-                        .filter_map(|o| o.name.clone())
-                        .collect();
-                    Err(Error::DisplayNotFound {
-                        name: name.clone(),
-                        available,
-                    })
-                }
+                let id =
+                    find_display_by_name(&ids, name, |id| self.state.outputs[id].display_name())?;
+                Ok(vec![*id])
             }
         }
     }
 }
 
-impl PowerBackend for WaylandBackend {
-    fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
-        let target_ids = self.resolve_targets(target)?;
-
-        let mut event_queue = self.connection.new_event_queue();
-        let qh = event_queue.handle();
+impl WaylandBackend {
+    /// Build the DisplayInfo for a tracked output
+    fn display_info(&self, id: u32, output_info: &OutputInfo, power: PowerState) -> DisplayInfo {
+        DisplayInfo {
+            name: output_info
+                .display_name()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("output-{}", id)),
+            power,
+            description: output_info.display_description().map(str::to_string),
+            make: output_info.make.clone(),
+            model: output_info.model.clone(),
+            drm: None,
+        }
+    }
 
-        // Get power manager
+    /// Create cached power controls for those of `ids` that don't have one
+    fn ensure_controls(&self, cache: &mut PowerCache, ids: &[u32]) -> Result<(), Error> {
         let power_manager = self
             .state
             .power_manager
             .as_ref()
             .ok_or(Error::ProtocolNotSupported)?;
+        let qh = cache.event_queue.handle();
 
-        // Convert PowerState to Mode
-        let mode = match state {
-            PowerState::On => zwlr_output_power_v1::Mode::On,
-            PowerState::Off => zwlr_output_power_v1::Mode::Off,
-        };
+        for id in ids {
+            if cache.controls.contains_key(id) {
+                continue;
+            }
+            if let Some(output_info) = self.state.outputs.get(id) {
+                let control = power_manager.get_control(&output_info.proxy, &qh, *id);
+                cache.controls.insert(*id, control);
+            }
+        }
 
-        // Set power for each target output
-        for id in target_ids {
-            if let Some(output_info) = self.state.outputs.get(&id) {
-                // Create power control object for this output
-                let power_control = power_manager.get_output_power(&output_info.proxy, &qh, ());
+        Ok(())
+    }
 
-                // Send set_mode request
-                power_control.set_mode(mode);
+    /// Watch the targeted outputs on the current connection
+    ///
+    /// Uses the cached power controls, for which the compositor pushes a
+    /// Mode event on every change.
+    ///
+    /// # Returns
+    /// Only returns on error. `Err(Error::Io)` means the connection broke,
+    /// or `on_event` failed.
+    fn watch_connection(
+        &self,
+        target: &DisplayTarget,
+        on_event: &mut dyn FnMut(&WatchEvent) -> std::io::Result<()>,
+    ) -> Result<(), Error> {
+        let target_ids = self.resolve_targets(target)?;
+
+        let mut cache = self.cache.borrow_mut();
+        let cache = &mut *cache;
+        self.ensure_controls(cache, &target_ids)?;
+
+        // The initial mode events must arrive promptly; afterwards waiting
+        // indefinitely for changes is expected
+        cache.roundtrip(&self.connection, self.timeout)?;
+
+        let mut last: HashMap<u32, PowerState> = HashMap::new();
+
+        loop {
+            if let Some((id, failure)) = cache.take_failures().first() {
+                return Err(failure.into_error(self.output_name(*id)));
+            }
 
-                // Destroy the power control object (single-use per protocol spec)
-                power_control.destroy();
+            // Report changes in display order
+            for id in &target_ids {
+                let Some(&power) = cache.state.modes.get(id) else {
+                    continue;
+                };
+                if last.insert(*id, power) == Some(power) {
+                    continue;
+                }
+                if let Some(output_info) = self.state.outputs.get(id) {
+                    on_event(&WatchEvent::Power(self.display_info(
+                        *id,
+                        output_info,
+                        power,
+                    )))?;
+                }
             }
+
+            cache
+                .event_queue
+                .blocking_dispatch(&mut cache.state)
+                .map_err(std::io::Error::other)?;
         }
+    }
 
-        // Flush and wait for compositor to process
-        event_queue
-            .roundtrip(&mut self.state)
-            .map_err(std::io::Error::other)?;
+    /// Tear down the current connection and connect again with backoff
+    ///
+    /// # Parameters
+    /// - `max_attempts`: Number of connection attempts before giving up
+    ///
+    /// # Returns
+    /// - `Ok(())` once a fresh connection with re-bound globals is in place
+    /// - `Err(Error::ReconnectFailed)` if every attempt failed
+    fn reconnect(&mut self, max_attempts: u32) -> Result<(), Error> {
+        let mut delay = self.reconnect_delay;
 
-        // Check if operation failed
-        if self.state.failed {
-            self.state.failed = false; // Reset flag
-            return Err(Error::ProtocolNotSupported);
+        for _ in 0..max_attempts {
+            thread::sleep(delay);
+
+            // A restarting compositor may briefly refuse connections or
+            // not yet advertise its globals, so any error is retried
+            if let Ok(backend) = self.reopen() {
+                *self = Self {
+                    reconnect_delay: self.reconnect_delay,
+                    ..backend
+                };
+                return Ok(());
+            }
+
+            delay = (delay * 2).min(RECONNECT_MAX_DELAY);
         }
 
-        Ok(())
+        Err(Error::ReconnectFailed {
+            attempts: max_attempts,
+        })
     }
 
-    fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+    /// Turn the controls the compositor rejected into an error
+    ///
+    /// # Parameters
+    /// - `failed`: Rejected controls, from `PowerCache::take_failures`
+    /// - `targets`: Number of outputs the request was for
+    ///
+    /// # Returns
+    /// - `Ok(())` if nothing failed
+    /// - `Err(Error::PowerControlBusy)` / `Err(Error::ProtocolNotSupported)`
+    ///   if the only targeted output failed
+    /// - `Err(Error::PartialFailure)` listing the failed outputs otherwise
+    fn check_failures(
+        &self,
+        failed: &[(u32, ControlFailure)],
+        targets: usize,
+    ) -> Result<(), Error> {
+        match failed {
+            [] => Ok(()),
+            [(id, failure)] if targets == 1 => Err(failure.into_error(self.output_name(*id))),
+            failed => Err(Error::PartialFailure {
+                failed: failed
+                    .iter()
+                    .map(|(id, failure)| (self.output_name(*id), failure.reason().to_string()))
+                    .collect(),
+            }),
+        }
+    }
+
+    /// Name of a tracked output, for error messages
+    fn output_name(&self, id: u32) -> String {
+        self.state
+            .outputs
+            .get(&id)
+            .and_then(|info| info.display_name().map(str::to_string))
+            .unwrap_or_else(|| format!("output-{}", id))
+    }
+
+    /// Turn the targeted outputs off while the seat is idle
+    ///
+    /// Holds a power control for each output for the whole run so another
+    /// power-management client can't silently fight over the outputs. Runs
+    /// until SIGINT/SIGTERM and always leaves the outputs On when it returns.
+    fn run_idle(
+        &self,
+        target: &DisplayTarget,
+        timeout: Duration,
+        shutdown: &AtomicBool,
+    ) -> Result<(), Error> {
         let target_ids = self.resolve_targets(target)?;
 
-        let mut event_queue = self.connection.new_event_queue();
+        let (globals, mut event_queue) =
+            registry_queue_init::<IdleState>(&self.connection).map_err(std::io::Error::other)?;
         let qh = event_queue.handle();
 
-        // Get power manager
+        let notifier = globals
+            .bind::<ext_idle_notifier_v1::ExtIdleNotifierV1, _, _>(&qh, 1..=1, ())
+            .map_err(|_| Error::IdleNotifyNotSupported)?;
+        let seat = globals
+            .bind::<wl_seat::WlSeat, _, _>(&qh, 1..=1, ())
+            .map_err(|_| Error::IdleNotifyNotSupported)?;
+
         let power_manager = self
             .state
             .power_manager
             .as_ref()
             .ok_or(Error::ProtocolNotSupported)?;
 
-        let mut results = Vec::new();
+        let controls: Vec<PowerControl> = target_ids
+            .iter()
+            .filter_map(|id| {
+                self.state
+                    .outputs
+                    .get(id)
+                    .map(|info| power_manager.get_control(&info.proxy, &qh, *id))
+            })
+            .collect();
 
-        for id in target_ids {
-            if let Some(output_info) = self.state.outputs.get(&id) {
-                // Create power control object for this output
-                let power_control = power_manager.get_output_power(&output_info.proxy, &qh, ());
+        let timeout_ms = u32::try_from(timeout.as_millis()).unwrap_or(u32::MAX);
+        let notification = notifier.get_idle_notification(timeout_ms, &seat, &qh, ());
 
-                // Create minimal query state
-                let mut query_state = QueryState::default();
+        let mut idle_state = IdleState::default();
+        let result = roundtrip_timeout(
+            &self.connection,
+            &mut event_queue,
+            &mut idle_state,
+            self.timeout,
+        )
+        .and_then(|_| idle_loop(&mut event_queue, &mut idle_state, &controls, shutdown))
+        .map_err(|e| match idle_state.failed {
+            Some(id) => ControlFailure::Busy.into_error(self.output_name(id)),
+            None => e,
+        });
 
-                // Roundtrip to receive mode event
-                event_queue
-                    .roundtrip(&mut query_state)
-                    .map_err(std::io::Error::other)?;
+        // Always leave the outputs on, even if the loop failed
+        for control in &controls {
+            control.set_power(PowerState::On);
+            control.destroy();
+        }
+        notification.destroy();
+        notifier.destroy();
+        let _ = roundtrip_timeout(
+            &self.connection,
+            &mut event_queue,
+            &mut idle_state,
+            self.timeout,
+        );
 
-                // Destroy the power control object
-                power_control.destroy();
+        result
+    }
+}
 
-                // Convert mode to PowerState
-                let power = match query_state.current_mode {
-                    Some(zwlr_output_power_v1::Mode::Off) => PowerState::Off,
-                    _ => PowerState::On, // Default to On if unknown
-                };
+/// Dispatch idle transitions until `shutdown` is set
+///
+/// # Returns
+/// - `Ok(())` once a shutdown was requested
+/// - `Err(Error::ProtocolNotSupported)` if a power control failed
+/// - `Err(Error::Io)` if the connection broke
+fn idle_loop(
+    event_queue: &mut EventQueue<IdleState>,
+    idle_state: &mut IdleState,
+    controls: &[PowerControl],
+    shutdown: &AtomicBool,
+) -> Result<(), Error> {
+    while !shutdown.load(Ordering::SeqCst) {
+        if idle_state.failed.is_some() {
+            return Err(Error::ProtocolNotSupported);
+        }
 
-                results.push(DisplayInfo {
-                    name: output_info
-                        .name
-                        .clone()
-                        .unwrap_or_else(|| format!("output-{}", id)),
-                    power,
-                    description: output_info.description.clone(),
-                    make: output_info.make.clone(),
-                    model: output_info.model.clone(),
-                });
+        if let Some(idle) = idle_state.idle.take() {
+            let state = if idle {
+                PowerState::Off
+            } else {
+                PowerState::On
+            };
+            for control in controls {
+                control.set_power(state);
             }
         }
 
-        Ok(results)
+        dispatch_timeout(event_queue, idle_state, IDLE_SIGNAL_CHECK_INTERVAL)?;
+    }
+
+    Ok(())
+}
+
+/// Object data for a `wl_display.sync` callback, set once the compositor answers
+#[derive(Default)]
+struct SyncDone(AtomicBool);
+
+impl ObjectData for SyncDone {
+    fn event(
+        self: Arc<Self>,
+        _backend: &Backend,
+        _msg: Message<ObjectId, std::os::fd::OwnedFd>,
+    ) -> Option<Arc<dyn ObjectData>> {
+        self.0.store(true, Ordering::SeqCst);
+        None
+    }
+
+    fn destroyed(&self, _object_id: ObjectId) {}
+}
+
+/// Like `EventQueue::roundtrip`, but gives up after `timeout`
+///
+/// # Returns
+/// - `Ok(n)` with the number of dispatched events once the compositor answered
+/// - `Err(Error::CompositorTimeout)` if it did not answer in time
+/// - `Err(Error::Io)` if the connection broke
+fn roundtrip_timeout<D>(
+    connection: &Connection,
+    event_queue: &mut EventQueue<D>,
+    state: &mut D,
+    timeout: Duration,
+) -> Result<usize, Error> {
+    let done = Arc::new(SyncDone::default());
+    connection
+        .send_request(
+            &connection.display(),
+            wl_display::Request::Sync {},
+            Some(done.clone()),
+        )
+        .map_err(std::io::Error::other)?;
+
+    let deadline = Instant::now() + timeout;
+    let mut dispatched = 0;
+
+    while !done.0.load(Ordering::SeqCst) {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(Error::CompositorTimeout(timeout));
+        }
+        dispatched += dispatch_timeout(event_queue, state, remaining)?;
+    }
+
+    // Events read together with the callback are queued but not yet dispatched
+    dispatched += event_queue
+        .dispatch_pending(state)
+        .map_err(std::io::Error::other)?;
+
+    Ok(dispatched)
+}
+
+/// Wait up to `timeout` for events on `event_queue` and dispatch them
+///
+/// Unlike `blocking_dispatch`, this returns when the timeout expires or a
+/// signal interrupts the wait, so callers can check for shutdown.
+///
+/// # Returns
+/// Number of dispatched events (0 if the timeout expired)
+fn dispatch_timeout<D>(
+    event_queue: &mut EventQueue<D>,
+    state: &mut D,
+    timeout: Duration,
+) -> Result<usize, Error> {
+    let dispatched = event_queue
+        .dispatch_pending(state)
+        .map_err(std::io::Error::other)?;
+    if dispatched > 0 {
+        return Ok(dispatched);
+    }
+
+    event_queue.flush().map_err(std::io::Error::other)?;
+
+    if let Some(guard) = event_queue.prepare_read() {
+        let mut fds = [PollFd::new(guard.connection_fd(), PollFlags::POLLIN)];
+        let poll_timeout = PollTimeout::try_from(timeout).unwrap_or(PollTimeout::MAX);
+        match poll(&mut fds, poll_timeout) {
+            Ok(0) | Err(Errno::EINTR) => return Ok(0),
+            Ok(_) => {
+                guard.read().map_err(std::io::Error::other)?;
+            }
+            Err(e) => return Err(Error::Io(e.into())),
+        }
+    }
+
+    Ok(event_queue
+        .dispatch_pending(state)
+        .map_err(std::io::Error::other)?)
+}
+
+impl PowerBackend for WaylandBackend {
+    fn set_power(&mut self, target: &DisplayTarget, state: PowerState) -> Result<(), Error> {
+        let target_ids = self.resolve_targets(target)?;
+
+        let mut cache = self.cache.borrow_mut();
+        let cache = &mut *cache;
+        self.ensure_controls(cache, &target_ids)?;
+
+        // Request the new state on every target output before waiting, so
+        // the compositor gets all changes in one batch and can apply them in
+        // the same frame. Each output has its own control, so one failing
+        // doesn't stop the others.
+        for id in &target_ids {
+            if let Some(control) = cache.controls.get(id) {
+                control.set_power(state);
+            }
+        }
+
+        // Flush everything at once and wait for the compositor to process it
+        cache.roundtrip(&self.connection, self.timeout)?;
+
+        self.check_failures(&cache.take_failures(), target_ids.len())
+    }
+
+    fn get_power(&self, target: &DisplayTarget) -> Result<Vec<DisplayInfo>, Error> {
+        let target_ids = self.resolve_targets(target)?;
+
+        let mut cache = self.cache.borrow_mut();
+        let cache = &mut *cache;
+
+        // Pick up changes the compositor pushed since the last call
+        cache.refresh()?;
+
+        // Only outputs without a cached mode need a control and a roundtrip
+        let missing: Vec<u32> = target_ids
+            .iter()
+            .copied()
+            .filter(|id| !cache.state.modes.contains_key(id))
+            .collect();
+        if !missing.is_empty() {
+            self.ensure_controls(cache, &missing)?;
+            cache.roundtrip(&self.connection, self.timeout)?;
+            self.check_failures(&cache.take_failures(), target_ids.len())?;
+        }
+
+        target_ids
+            .iter()
+            .filter_map(|id| {
+                self.state.outputs.get(id).map(|output_info| {
+                    // Every accepted control reports its mode right away, so
+                    // a missing one means the compositor broke the protocol
+                    let power = *cache
+                        .state
+                        .modes
+                        .get(id)
+                        .ok_or(Error::ProtocolNotSupported)?;
+                    Ok(self.display_info(*id, output_info, power))
+                })
+            })
+            .collect()
     }
 
     fn list_displays(&self) -> Result<Vec<DisplayInfo>, Error> {
         self.get_power(&DisplayTarget::All)
     }
+
+    fn name(&self) -> &'static str {
+        "wayland"
+    }
+
+    fn describe(&self) -> String {
+        match &self.state.power_manager {
+            Some(manager) => format!(
+                "wayland ({} v{})",
+                manager.protocol_name(),
+                self.state.power_manager_version
+            ),
+            None => "wayland".to_string(),
+        }
+    }
+
+    fn watch(
+        &mut self,
+        target: &DisplayTarget,
+        max_reconnects: u32,
+        on_event: &mut dyn FnMut(&WatchEvent) -> std::io::Result<()>,
+    ) -> Result<(), Error> {
+        loop {
+            // An error passing an event on is the caller's, not the
+            // connection's, and isn't reconnected from
+            let mut stopped = false;
+            let result = self.watch_connection(target, &mut |event| {
+                on_event(event).inspect_err(|_| stopped = true)
+            });
+            match result {
+                // Dispatch errors or timeouts mean the compositor went away
+                // or stopped responding
+                Err(Error::Io(_) | Error::CompositorTimeout(_)) if !stopped => {
+                    self.reconnect(max_reconnects)?;
+                    on_event(&WatchEvent::Reconnected)?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn idle(&mut self, target: &DisplayTarget, timeout: Duration) -> Result<(), Error> {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        for signal in [SIGINT, SIGTERM] {
+            flag::register(signal, Arc::clone(&shutdown))
+                .context(Error::SignalError, "Failed to register signal handler")?;
+        }
+
+        self.run_idle(target, timeout, &shutdown)
+    }
 }
 
 // Implement Dispatch for registry events (needed for bind operations)
@@ -328,8 +1084,46 @@ impl Dispatch<wl_output::WlOutput, u32> for WaylandState {
                     output_info.description = Some(description);
                 }
                 wl_output::Event::Geometry { make, model, .. } => {
-                    output_info.make = Some(make);
-                    output_info.model = Some(model);
+                    output_info.make = non_empty(make);
+                    output_info.model = non_empty(model);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+// Implement Dispatch for xdg-output manager events (none defined in protocol)
+impl Dispatch<zxdg_output_manager_v1::ZxdgOutputManagerV1, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &zxdg_output_manager_v1::ZxdgOutputManagerV1,
+        _event: zxdg_output_manager_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // No events defined for xdg-output manager
+    }
+}
+
+// Implement Dispatch for xdg-output events to capture logical name and description
+impl Dispatch<zxdg_output_v1::ZxdgOutputV1, u32> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _proxy: &zxdg_output_v1::ZxdgOutputV1,
+        event: zxdg_output_v1::Event,
+        data: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let Some(output_info) = state.outputs.get_mut(data) {
+            match event {
+                zxdg_output_v1::Event::Name { name } => {
+                    output_info.xdg_name = Some(name);
+                }
+                zxdg_output_v1::Event::Description { description } => {
+                    output_info.xdg_description = Some(description);
                 }
                 _ => {}
             }
@@ -351,13 +1145,27 @@ impl Dispatch<zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1, ()> for Wa
     }
 }
 
-// Implement Dispatch for power control events (mode and failed)
-impl Dispatch<zwlr_output_power_v1::ZwlrOutputPowerV1, ()> for WaylandState {
+// Implement Dispatch for KWin DPMS manager events (none defined in protocol)
+impl Dispatch<org_kde_kwin_dpms_manager::OrgKdeKwinDpmsManager, ()> for WaylandState {
+    fn event(
+        _state: &mut Self,
+        _proxy: &org_kde_kwin_dpms_manager::OrgKdeKwinDpmsManager,
+        _event: org_kde_kwin_dpms_manager::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // No events defined for KWin DPMS manager
+    }
+}
+
+// Implement Dispatch for CacheState (power controls tagged with their output id)
+impl Dispatch<zwlr_output_power_v1::ZwlrOutputPowerV1, u32> for CacheState {
     fn event(
         state: &mut Self,
         _proxy: &zwlr_output_power_v1::ZwlrOutputPowerV1,
         event: zwlr_output_power_v1::Event,
-        _data: &(),
+        data: &u32,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
@@ -365,20 +1173,39 @@ impl Dispatch<zwlr_output_power_v1::ZwlrOutputPowerV1, ()> for WaylandState {
             zwlr_output_power_v1::Event::Mode {
                 mode: WEnum::Value(m),
             } => {
-                // Store the current mode, extracting from WEnum wrapper
-                state.current_mode = Some(m);
+                state.modes.insert(*data, wlr_power(m));
             }
             zwlr_output_power_v1::Event::Failed => {
-                // Operation failed
-                state.failed = true;
+                state.failed.push((*data, ControlFailure::Busy));
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<org_kde_kwin_dpms::OrgKdeKwinDpms, u32> for CacheState {
+    fn event(
+        state: &mut Self,
+        _proxy: &org_kde_kwin_dpms::OrgKdeKwinDpms,
+        event: org_kde_kwin_dpms::Event,
+        data: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            org_kde_kwin_dpms::Event::Mode { mode } => {
+                state.modes.insert(*data, kde_power(mode));
+            }
+            org_kde_kwin_dpms::Event::Supported { supported: 0 } => {
+                state.failed.push((*data, ControlFailure::Unsupported));
             }
             _ => {}
         }
     }
 }
 
-// Implement Dispatch for QueryState (minimal state for get_power queries)
-impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for QueryState {
+// Implement Dispatch for IdleState (idle notifications and held power controls)
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for IdleState {
     fn event(
         _state: &mut Self,
         _proxy: &wl_registry::WlRegistry,
@@ -387,50 +1214,596 @@ impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for QueryState {
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
-        // Not used for queries
+        // Registry events are handled by registry_queue_init
     }
 }
 
-impl Dispatch<wl_output::WlOutput, u32> for QueryState {
+impl Dispatch<wl_seat::WlSeat, ()> for IdleState {
     fn event(
         _state: &mut Self,
-        _proxy: &wl_output::WlOutput,
-        _event: wl_output::Event,
-        _data: &u32,
+        _proxy: &wl_seat::WlSeat,
+        _event: wl_seat::Event,
+        _data: &(),
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
-        // Not used for queries
+        // Seat capabilities are not needed for idle notifications
     }
 }
 
-impl Dispatch<zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1, ()> for QueryState {
+impl Dispatch<ext_idle_notifier_v1::ExtIdleNotifierV1, ()> for IdleState {
     fn event(
         _state: &mut Self,
-        _proxy: &zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1,
-        _event: zwlr_output_power_manager_v1::Event,
+        _proxy: &ext_idle_notifier_v1::ExtIdleNotifierV1,
+        _event: ext_idle_notifier_v1::Event,
         _data: &(),
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
-        // Not used for queries
+        // No events defined for idle notifier
     }
 }
 
-impl Dispatch<zwlr_output_power_v1::ZwlrOutputPowerV1, ()> for QueryState {
+impl Dispatch<ext_idle_notification_v1::ExtIdleNotificationV1, ()> for IdleState {
+    fn event(
+        state: &mut Self,
+        _proxy: &ext_idle_notification_v1::ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_idle_notification_v1::Event::Idled => state.idle = Some(true),
+            ext_idle_notification_v1::Event::Resumed => state.idle = Some(false),
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<zwlr_output_power_v1::ZwlrOutputPowerV1, u32> for IdleState {
     fn event(
         state: &mut Self,
         _proxy: &zwlr_output_power_v1::ZwlrOutputPowerV1,
         event: zwlr_output_power_v1::Event,
-        _data: &(),
+        data: &u32,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
     ) {
-        if let zwlr_output_power_v1::Event::Mode {
-            mode: WEnum::Value(m),
-        } = event
-        {
-            state.current_mode = Some(m);
+        if let zwlr_output_power_v1::Event::Failed = event {
+            state.failed = Some(*data);
         }
     }
 }
+
+impl Dispatch<org_kde_kwin_dpms::OrgKdeKwinDpms, u32> for IdleState {
+    fn event(
+        state: &mut Self,
+        _proxy: &org_kde_kwin_dpms::OrgKdeKwinDpms,
+        event: org_kde_kwin_dpms::Event,
+        data: &u32,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let org_kde_kwin_dpms::Event::Supported { supported: 0 } = event {
+            state.failed = Some(*data);
+        }
+    }
+}
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{MockCompositor, MockConfig, MockOutput};
+    use super::*;
+
+    const MOCK_TIMEOUT: Duration = Duration::from_secs(2);
+
+    fn mock_backend(compositor: &mut MockCompositor) -> Result<WaylandBackend, Error> {
+        WaylandBackend::from_connection(compositor.connection(), None, MOCK_TIMEOUT)
+    }
+
+    fn names(displays: &[DisplayInfo]) -> Vec<&str> {
+        displays.iter().map(|d| d.name.as_str()).collect()
+    }
+
+    #[test]
+    fn mock_set_power_off_and_on() {
+        let mut compositor = MockCompositor::start(MockConfig::new(vec![MockOutput::new("DP-1")]));
+        let mut backend = mock_backend(&mut compositor).unwrap();
+        let target = DisplayTarget::Named("DP-1".to_string());
+
+        backend.set_power(&target, PowerState::Off).unwrap();
+        assert_eq!(
+            backend.get_power(&target).unwrap()[0].power,
+            PowerState::Off
+        );
+
+        backend.set_power(&target, PowerState::On).unwrap();
+        assert_eq!(backend.get_power(&target).unwrap()[0].power, PowerState::On);
+
+        assert_eq!(
+            compositor.requests(),
+            vec![
+                ("DP-1".to_string(), PowerState::Off),
+                ("DP-1".to_string(), PowerState::On),
+            ]
+        );
+    }
+
+    #[test]
+    fn mock_reports_initial_mode_and_output_info() {
+        let mut compositor = MockCompositor::start(MockConfig::new(vec![
+            MockOutput::new("HDMI-A-1").with_mode(PowerState::Off),
+        ]));
+        let backend = mock_backend(&mut compositor).unwrap();
+
+        let displays = backend.list_displays().unwrap();
+        assert_eq!(displays.len(), 1);
+        assert_eq!(displays[0].name, "HDMI-A-1");
+        assert_eq!(displays[0].power, PowerState::Off);
+        assert_eq!(displays[0].make.as_deref(), Some("Mock"));
+        assert_eq!(displays[0].model.as_deref(), Some("HDMI-A-1 Panel"));
+        assert!(compositor.requests().is_empty());
+    }
+
+    #[test]
+    fn mock_without_power_manager_is_unsupported() {
+        let config = MockConfig::new(vec![MockOutput::new("DP-1")]).without_power_manager();
+        let mut compositor = MockCompositor::start(config);
+
+        assert!(matches!(
+            mock_backend(&mut compositor),
+            Err(Error::ProtocolNotSupported)
+        ));
+    }
+
+    #[test]
+    fn mock_binds_power_manager_v1() {
+        let config = MockConfig::new(vec![MockOutput::new("DP-1")]).with_power_manager_version(1);
+        let mut compositor = MockCompositor::start(config);
+        let mut backend = mock_backend(&mut compositor).unwrap();
+
+        assert_eq!(backend.state.power_manager_version, 1);
+        assert_eq!(
+            backend.describe(),
+            "wayland (zwlr_output_power_management_v1 v1)"
+        );
+        backend
+            .set_power(&DisplayTarget::All, PowerState::Off)
+            .unwrap();
+        assert_eq!(
+            compositor.requests(),
+            vec![("DP-1".to_string(), PowerState::Off)]
+        );
+    }
+
+    #[test]
+    fn mock_binds_newer_power_manager_at_known_version() {
+        let newer = wlr_power_manager_max_version() + 2;
+        let config =
+            MockConfig::new(vec![MockOutput::new("DP-1")]).with_power_manager_version(newer);
+        let mut compositor = MockCompositor::start(config);
+        let mut backend = mock_backend(&mut compositor).unwrap();
+
+        assert_eq!(
+            backend.state.power_manager_version,
+            wlr_power_manager_max_version()
+        );
+        backend
+            .set_power(&DisplayTarget::All, PowerState::Off)
+            .unwrap();
+        assert_eq!(
+            compositor.requests(),
+            vec![("DP-1".to_string(), PowerState::Off)]
+        );
+    }
+
+    #[test]
+    fn mock_set_power_batches_outputs_into_one_roundtrip() {
+        let outputs = ["DP-1", "DP-2", "HDMI-A-1", "eDP-1"];
+        let mut compositor = MockCompositor::start(MockConfig::new(
+            outputs.iter().map(|name| MockOutput::new(name)).collect(),
+        ));
+        let mut backend = mock_backend(&mut compositor).unwrap();
+
+        backend
+            .set_power(&DisplayTarget::All, PowerState::Off)
+            .unwrap();
+
+        assert_eq!(backend.cache.borrow().roundtrips, 1);
+        assert_eq!(
+            compositor.requests(),
+            outputs
+                .iter()
+                .map(|name| (name.to_string(), PowerState::Off))
+                .collect::<Vec<_>>()
+        );
+
+        // Cached controls are reused, so switching back is one roundtrip too
+        backend
+            .set_power(&DisplayTarget::All, PowerState::On)
+            .unwrap();
+        assert_eq!(backend.cache.borrow().roundtrips, 2);
+    }
+
+    #[test]
+    fn mock_failed_control_is_busy() {
+        let mut compositor =
+            MockCompositor::start(MockConfig::new(vec![MockOutput::new("DP-1").busy()]));
+        let mut backend = mock_backend(&mut compositor).unwrap();
+
+        let result = backend.set_power(&DisplayTarget::All, PowerState::Off);
+
+        assert!(matches!(
+            result,
+            Err(Error::PowerControlBusy { name, .. }) if name == "DP-1"
+        ));
+    }
+
+    #[test]
+    fn mock_failed_control_among_several_is_partial() {
+        let mut compositor = MockCompositor::start(MockConfig::new(vec![
+            MockOutput::new("DP-1"),
+            MockOutput::new("DP-2").busy(),
+        ]));
+        let mut backend = mock_backend(&mut compositor).unwrap();
+
+        let result = backend.set_power(&DisplayTarget::All, PowerState::Off);
+
+        match result {
+            Err(Error::PartialFailure { failed }) => {
+                assert_eq!(failed.len(), 1);
+                assert_eq!(failed[0].0, "DP-2");
+            }
+            other => panic!("Expected PartialFailure, got {:?}", other),
+        }
+        // The healthy output was still switched
+        assert_eq!(
+            compositor.requests(),
+            vec![("DP-1".to_string(), PowerState::Off)]
+        );
+    }
+
+    #[test]
+    fn mock_get_power_reports_busy_output() {
+        let mut compositor =
+            MockCompositor::start(MockConfig::new(vec![MockOutput::new("DP-1").busy()]));
+        let backend = mock_backend(&mut compositor).unwrap();
+
+        assert!(matches!(
+            backend.get_power(&DisplayTarget::All),
+            Err(Error::PowerControlBusy { name, .. }) if name == "DP-1"
+        ));
+    }
+
+    #[test]
+    fn mock_get_power_with_busy_output_among_several_is_partial() {
+        let mut compositor = MockCompositor::start(MockConfig::new(vec![
+            MockOutput::new("DP-1").with_mode(PowerState::Off),
+            MockOutput::new("DP-2").busy(),
+        ]));
+        let backend = mock_backend(&mut compositor).unwrap();
+
+        match backend.get_power(&DisplayTarget::All) {
+            Err(Error::PartialFailure { failed }) => {
+                assert_eq!(failed.len(), 1);
+                assert_eq!(failed[0].0, "DP-2");
+            }
+            other => panic!("Expected PartialFailure, got {:?}", other),
+        }
+
+        // The healthy output alone still reads back
+        let target = DisplayTarget::Named("DP-1".to_string());
+        assert_eq!(
+            backend.get_power(&target).unwrap()[0].power,
+            PowerState::Off
+        );
+    }
+
+    #[test]
+    fn mock_prefers_xdg_output_name_and_description() {
+        let outputs = vec![MockOutput::new("DP-1").with_xdg("DP-1-xdg", "Dell Inc. DELL U2720Q")];
+
+        let mut compositor = MockCompositor::start(MockConfig::new(outputs.clone()));
+        let displays = mock_backend(&mut compositor)
+            .unwrap()
+            .list_displays()
+            .unwrap();
+        assert_eq!(displays[0].name, "DP-1");
+        assert_eq!(
+            displays[0].description.as_deref(),
+            Some("Mock DP-1 Panel (DP-1)")
+        );
+
+        let mut compositor = MockCompositor::start(MockConfig::new(outputs).with_xdg_output());
+        let displays = mock_backend(&mut compositor)
+            .unwrap()
+            .list_displays()
+            .unwrap();
+        assert_eq!(displays[0].name, "DP-1-xdg");
+        assert_eq!(
+            displays[0].description.as_deref(),
+            Some("Dell Inc. DELL U2720Q")
+        );
+    }
+
+    #[test]
+    fn mock_matches_xdg_output_names_without_wl_output_v4() {
+        let outputs = vec![MockOutput::new("DP-1"), MockOutput::new("HDMI-A-1")];
+
+        // wl_output v3 has no name event, so the outputs are nameless
+        let config = MockConfig::new(outputs.clone()).with_output_version(3);
+        let mut compositor = MockCompositor::start(config);
+        let mut backend = mock_backend(&mut compositor).unwrap();
+        assert!(matches!(
+            backend.set_power(&DisplayTarget::Named("HDMI".to_string()), PowerState::Off),
+            Err(Error::DisplayNotFound { .. })
+        ));
+
+        let config = MockConfig::new(outputs)
+            .with_output_version(3)
+            .with_xdg_output();
+        let mut compositor = MockCompositor::start(config);
+        let mut backend = mock_backend(&mut compositor).unwrap();
+
+        let displays = backend.list_displays().unwrap();
+        assert_eq!(names(&displays), vec!["DP-1", "HDMI-A-1"]);
+
+        backend
+            .set_power(&DisplayTarget::Named("HDMI".to_string()), PowerState::Off)
+            .unwrap();
+        assert_eq!(
+            compositor.requests(),
+            vec![("HDMI-A-1".to_string(), PowerState::Off)]
+        );
+    }
+
+    /// Backend on a mock compositor listening at `path`, reconnecting quickly
+    fn listening_backend(path: &Path) -> WaylandBackend {
+        let mut backend = WaylandBackend::with_socket(path, MOCK_TIMEOUT).unwrap();
+        backend.reconnect_delay = Duration::from_millis(10);
+        backend
+    }
+
+    /// Watch events as (name, power), with `None` for `Reconnected`
+    fn watch_summary(events: &[WatchEvent]) -> Vec<Option<(&str, PowerState)>> {
+        events
+            .iter()
+            .map(|event| match event {
+                WatchEvent::Power(display) => Some((display.name.as_str(), display.power)),
+                WatchEvent::Reconnected => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn mock_watch_reconnects_after_compositor_restart() {
+        let path = PathBuf::from(format!("/tmp/dpms-test-reconnect-{}", std::process::id()));
+        let mut compositor = Some(MockCompositor::listen(
+            MockConfig::new(vec![MockOutput::new("DP-1")]),
+            &path,
+        ));
+        let mut backend = listening_backend(&path);
+
+        let mut events = Vec::new();
+        let result = backend.watch(&DisplayTarget::All, 3, &mut |event| {
+            events.push(event.clone());
+            match events.len() {
+                // Crash the compositor and start it again with the display off
+                1 => {
+                    drop(compositor.take());
+                    compositor = Some(MockCompositor::listen(
+                        MockConfig::new(vec![MockOutput::new("DP-1").with_mode(PowerState::Off)]),
+                        &path,
+                    ));
+                }
+                // Crash it for good once the new one was heard from
+                3 => drop(compositor.take()),
+                _ => {}
+            }
+            Ok(())
+        });
+
+        assert!(matches!(
+            result,
+            Err(Error::ReconnectFailed { attempts: 3 })
+        ));
+        assert_eq!(
+            watch_summary(&events),
+            vec![
+                Some(("DP-1", PowerState::On)),
+                None,
+                Some(("DP-1", PowerState::Off)),
+            ]
+        );
+    }
+
+    #[test]
+    fn mock_watch_gives_up_when_compositor_stays_gone() {
+        let path = PathBuf::from(format!(
+            "/tmp/dpms-test-no-reconnect-{}",
+            std::process::id()
+        ));
+        let mut compositor = Some(MockCompositor::listen(
+            MockConfig::new(vec![MockOutput::new("DP-1")]),
+            &path,
+        ));
+        let mut backend = listening_backend(&path);
+
+        let mut events = Vec::new();
+        let result = backend.watch(&DisplayTarget::All, 2, &mut |event| {
+            events.push(event.clone());
+            drop(compositor.take());
+            Ok(())
+        });
+
+        assert!(matches!(
+            result,
+            Err(Error::ReconnectFailed { attempts: 2 })
+        ));
+        assert_eq!(watch_summary(&events), vec![Some(("DP-1", PowerState::On))]);
+    }
+
+    #[test]
+    fn mock_watch_ends_when_events_cannot_be_passed_on() {
+        let mut compositor = MockCompositor::start(MockConfig::new(vec![MockOutput::new("DP-1")]));
+        let mut backend = mock_backend(&mut compositor).unwrap();
+
+        let mut events = 0;
+        let result = backend.watch(&DisplayTarget::All, 3, &mut |_| {
+            events += 1;
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        });
+
+        // Not taken for a broken connection and reconnected from
+        assert!(matches!(
+            result,
+            Err(Error::Io(ref e)) if e.kind() == std::io::ErrorKind::BrokenPipe
+        ));
+        assert_eq!(events, 1);
+    }
+
+    #[test]
+    fn mock_tracks_multiple_outputs_by_name() {
+        let mut compositor = MockCompositor::start(MockConfig::new(vec![
+            MockOutput::new("DP-10"),
+            MockOutput::new("eDP-1"),
+            MockOutput::new("DP-2"),
+        ]));
+        let mut backend = mock_backend(&mut compositor).unwrap();
+
+        let displays = backend.list_displays().unwrap();
+        assert_eq!(names(&displays), vec!["DP-2", "DP-10", "eDP-1"]);
+
+        backend
+            .set_power(&DisplayTarget::Named("eDP".to_string()), PowerState::Off)
+            .unwrap();
+        assert_eq!(
+            compositor.requests(),
+            vec![("eDP-1".to_string(), PowerState::Off)]
+        );
+
+        let displays = backend.list_displays().unwrap();
+        let powers: Vec<PowerState> = displays.iter().map(|d| d.power).collect();
+        assert_eq!(
+            powers,
+            vec![PowerState::On, PowerState::On, PowerState::Off]
+        );
+
+        assert!(matches!(
+            backend.set_power(&DisplayTarget::Named("DP".to_string()), PowerState::Off),
+            Err(Error::AmbiguousDisplay { candidates, .. }) if candidates == ["DP-2", "DP-10"]
+        ));
+    }
+
+    #[test]
+    fn resolve_socket_path_keeps_absolute() {
+        let path = resolve_socket_path(Path::new("/run/user/1001/wayland-1"));
+        assert_eq!(path, PathBuf::from("/run/user/1001/wayland-1"));
+    }
+
+    #[test]
+    fn connect_to_socket_missing_path() {
+        let result = connect_to_socket(Path::new("/tmp/dpms-test-no-such-socket"));
+        assert!(matches!(result, Err(Error::WaylandSocketInvalid { .. })));
+    }
+
+    #[test]
+    fn connect_to_socket_not_a_socket() {
+        let test_path = "/tmp/dpms-test-not-a-socket";
+        let _ = std::fs::File::create(test_path);
+
+        let result = connect_to_socket(Path::new(test_path));
+        let _ = std::fs::remove_file(test_path);
+
+        match result {
+            Err(Error::WaylandSocketInvalid { reason, .. }) => {
+                assert_eq!(reason, "not a socket");
+            }
+            _ => panic!("Expected WaylandSocketInvalid"),
+        }
+    }
+
+    #[test]
+    fn roundtrip_timeout_gives_up_on_silent_compositor() {
+        // A peer that accepts the connection but never answers
+        let (client, _server) = UnixStream::pair().unwrap();
+        let connection = Connection::from_socket(client).unwrap();
+        let mut event_queue = connection.new_event_queue();
+
+        let timeout = Duration::from_millis(100);
+        let started = Instant::now();
+        let result = roundtrip_timeout(&connection, &mut event_queue, &mut (), timeout);
+
+        assert!(matches!(result, Err(Error::CompositorTimeout(t)) if t == timeout));
+        assert!(started.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn new_with_socket_times_out_on_silent_compositor() {
+        let test_path = "/tmp/dpms-test-silent-compositor";
+        let _ = std::fs::remove_file(test_path);
+        let _listener = std::os::unix::net::UnixListener::bind(test_path).unwrap();
+
+        let result = WaylandBackend::with_socket(Path::new(test_path), Duration::from_millis(100));
+        let _ = std::fs::remove_file(test_path);
+
+        assert!(matches!(result, Err(Error::CompositorTimeout(_))));
+    }
+
+    #[test]
+    fn power_cache_drops_failed_outputs() {
+        let (client, _server) = UnixStream::pair().unwrap();
+        let connection = Connection::from_socket(client).unwrap();
+        let mut cache = PowerCache::new(&connection);
+
+        cache.state.modes.insert(1, PowerState::Off);
+        cache.state.modes.insert(2, PowerState::On);
+        cache.state.failed.push((1, ControlFailure::Busy));
+
+        assert_eq!(cache.take_failures(), vec![(1, ControlFailure::Busy)]);
+        assert!(!cache.state.modes.contains_key(&1));
+        assert_eq!(cache.state.modes.get(&2), Some(&PowerState::On));
+        assert!(cache.take_failures().is_empty());
+    }
+
+    #[test]
+    fn control_failure_single_output_errors() {
+        assert!(matches!(
+            ControlFailure::Busy.into_error("DP-1".to_string()),
+            Error::PowerControlBusy { name, .. } if name == "DP-1"
+        ));
+        assert!(matches!(
+            ControlFailure::Unsupported.into_error("DP-1".to_string()),
+            Error::ProtocolNotSupported
+        ));
+    }
+
+    #[test]
+    fn kde_power_folds_standby_and_suspend_into_off() {
+        assert_eq!(kde_power(KDE_DPMS_MODE_ON), PowerState::On);
+        assert_eq!(kde_power(1), PowerState::Off); // Standby
+        assert_eq!(kde_power(2), PowerState::Off); // Suspend
+        assert_eq!(kde_power(KDE_DPMS_MODE_OFF), PowerState::Off);
+    }
+
+    #[test]
+    fn wlr_power_maps_modes() {
+        assert_eq!(wlr_power(zwlr_output_power_v1::Mode::On), PowerState::On);
+        assert_eq!(wlr_power(zwlr_output_power_v1::Mode::Off), PowerState::Off);
+    }
+
+    #[test]
+    fn non_empty_keeps_values() {
+        assert_eq!(non_empty("Dell".to_string()), Some("Dell".to_string()));
+    }
+
+    #[test]
+    fn non_empty_drops_empty_and_blank() {
+        assert_eq!(non_empty(String::new()), None);
+        assert_eq!(non_empty("  ".to_string()), None);
+    }
+}
@@ -0,0 +1,572 @@
+//! In-process mock compositor for testing the Wayland backend
+//!
+//! Runs a `wayland-server` display on a background thread, connected to the
+//! client over a socket pair, or listening on a socket path so a backend can
+//! reconnect after the compositor is dropped and started again. It advertises
+//! one `wl_output` per configured output and, optionally,
+//! `zwlr_output_power_manager_v1` and `zxdg_output_manager_v1`. Every
+//! `set_mode` request is recorded so tests can check what the backend asked
+//! for.
+
+use std::os::fd::OwnedFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use wayland_client::Connection;
+use wayland_protocols::xdg::xdg_output::zv1::server::{zxdg_output_manager_v1, zxdg_output_v1};
+use wayland_protocols_wlr::output_power_management::v1::server::{
+    zwlr_output_power_manager_v1, zwlr_output_power_v1,
+};
+use wayland_server::backend::protocol::{Argument, Interface, Message};
+use wayland_server::backend::{
+    ClientData, ClientId, DisconnectReason, GlobalHandler, GlobalId, Handle, ObjectData, ObjectId,
+};
+use wayland_server::protocol::wl_output;
+use wayland_server::{Client, DataInit, Dispatch, Display, DisplayHandle, GlobalDispatch, New};
+use wayland_server::{Resource, WEnum};
+
+use crate::output::PowerState;
+
+/// How long the server thread sleeps between dispatching client requests
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A scripted output advertised by the mock compositor
+#[derive(Debug, Clone)]
+pub struct MockOutput {
+    pub name: String,
+    /// Sent by `wl_output` v4 along with the name
+    pub description: String,
+    /// Name sent over xdg-output; `name` if not set
+    pub xdg_name: Option<String>,
+    /// Description sent over xdg-output; `description` if not set
+    pub xdg_description: Option<String>,
+    pub make: String,
+    pub model: String,
+    /// Mode reported when a power control is created
+    pub mode: PowerState,
+    /// Answer every power control for this output with `Failed`
+    pub busy: bool,
+}
+
+impl MockOutput {
+    /// An output that is on and accepts power controls
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            description: format!("Mock {} Panel ({})", name, name),
+            xdg_name: None,
+            xdg_description: None,
+            make: "Mock".to_string(),
+            model: format!("{} Panel", name),
+            mode: PowerState::On,
+            busy: false,
+        }
+    }
+
+    /// Report `mode` as the initial power state
+    pub fn with_mode(mut self, mode: PowerState) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Reject power controls, as if another client already held one
+    pub fn busy(mut self) -> Self {
+        self.busy = true;
+        self
+    }
+
+    /// Send `name` and `description` over xdg-output instead of the `wl_output` ones
+    pub fn with_xdg(mut self, name: &str, description: &str) -> Self {
+        self.xdg_name = Some(name.to_string());
+        self.xdg_description = Some(description.to_string());
+        self
+    }
+}
+
+/// What the mock compositor advertises
+#[derive(Debug, Clone)]
+pub struct MockConfig {
+    pub outputs: Vec<MockOutput>,
+    /// Advertise `zwlr_output_power_manager_v1`
+    pub power_manager: bool,
+    /// Version the power manager global is advertised with
+    pub power_manager_version: u32,
+    /// Version the `wl_output` globals are advertised with; `name` and
+    /// `description` need 4
+    pub output_version: u32,
+    /// Advertise `zxdg_output_manager_v1`
+    pub xdg_output: bool,
+}
+
+impl MockConfig {
+    /// A compositor with power management and the given outputs
+    pub fn new(outputs: Vec<MockOutput>) -> Self {
+        Self {
+            outputs,
+            power_manager: true,
+            power_manager_version: 1,
+            output_version: 4,
+            xdg_output: false,
+        }
+    }
+
+    /// Don't advertise any power management protocol
+    pub fn without_power_manager(mut self) -> Self {
+        self.power_manager = false;
+        self
+    }
+
+    /// Advertise the power manager with `version`
+    pub fn with_power_manager_version(mut self, version: u32) -> Self {
+        self.power_manager_version = version;
+        self
+    }
+
+    /// Advertise the outputs with `wl_output` `version`
+    pub fn with_output_version(mut self, version: u32) -> Self {
+        self.output_version = version;
+        self
+    }
+
+    /// Advertise `zxdg_output_manager_v1` at version 3
+    pub fn with_xdg_output(mut self) -> Self {
+        self.xdg_output = true;
+        self
+    }
+}
+
+/// Server-side state shared by all dispatch impls
+struct MockState {
+    outputs: Vec<MockOutput>,
+    requests: Arc<Mutex<Vec<(String, PowerState)>>>,
+}
+
+/// Client data for connected clients
+struct MockClient;
+
+impl ClientData for MockClient {
+    fn initialized(&self, _client_id: ClientId) {}
+    fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+}
+
+/// A running mock compositor and its client sockets
+pub struct MockCompositor {
+    client: Option<UnixStream>,
+    /// Socket path clients connect to, removed when the compositor stops
+    path: Option<PathBuf>,
+    requests: Arc<Mutex<Vec<(String, PowerState)>>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockCompositor {
+    /// Start the compositor thread
+    pub fn start(config: MockConfig) -> Self {
+        let (client, server) = UnixStream::pair().unwrap();
+        let mut compositor = Self::spawn(config, Some(server), None);
+        compositor.client = Some(client);
+        compositor
+    }
+
+    /// Start the compositor thread, accepting clients on a socket at `path`
+    ///
+    /// Dropping the compositor disconnects its clients and removes the
+    /// socket, like a compositor that crashed.
+    pub fn listen(config: MockConfig, path: &Path) -> Self {
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let mut compositor = Self::spawn(config, None, Some(listener));
+        compositor.path = Some(path.to_path_buf());
+        compositor
+    }
+
+    /// Advertise the globals of `config` and dispatch clients on a thread
+    fn spawn(
+        config: MockConfig,
+        server: Option<UnixStream>,
+        listener: Option<UnixListener>,
+    ) -> Self {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let mut display: Display<MockState> = Display::new().unwrap();
+        let mut handle = display.handle();
+
+        for index in 0..config.outputs.len() {
+            handle.create_global::<MockState, wl_output::WlOutput, usize>(
+                config.output_version,
+                index,
+            );
+        }
+        if config.xdg_output {
+            handle
+                .create_global::<MockState, zxdg_output_manager_v1::ZxdgOutputManagerV1, ()>(3, ());
+        }
+        let known_version =
+            zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1::interface().version;
+        if config.power_manager && config.power_manager_version > known_version {
+            create_newer_power_manager(&handle, config.power_manager_version);
+        } else if config.power_manager {
+            handle.create_global::<
+                MockState,
+                zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1,
+                (),
+            >(config.power_manager_version, ());
+        }
+        if let Some(server) = server {
+            handle.insert_client(server, Arc::new(MockClient)).unwrap();
+        }
+
+        let mut state = MockState {
+            outputs: config.outputs,
+            requests: Arc::clone(&requests),
+        };
+        let thread_stop = Arc::clone(&stop);
+        let thread = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                if let Some(Ok((stream, _))) = listener.as_ref().map(UnixListener::accept) {
+                    let _ = handle.insert_client(stream, Arc::new(MockClient));
+                }
+                let _ = display.dispatch_clients(&mut state);
+                let _ = display.flush_clients();
+                thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        Self {
+            client: None,
+            path: None,
+            requests,
+            stop,
+            thread: Some(thread),
+        }
+    }
+
+    /// Client connection to the mock compositor
+    ///
+    /// Can only be taken once, as there is a single client socket.
+    pub fn connection(&mut self) -> Connection {
+        let client = self.client.take().expect("connection already taken");
+        Connection::from_socket(client).unwrap()
+    }
+
+    /// All `set_mode` requests received so far, in order
+    pub fn requests(&self) -> Vec<(String, PowerState)> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockCompositor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+fn wlr_mode(state: PowerState) -> zwlr_output_power_v1::Mode {
+    match state {
+        PowerState::On => zwlr_output_power_v1::Mode::On,
+        PowerState::Off => zwlr_output_power_v1::Mode::Off,
+    }
+}
+
+impl GlobalDispatch<wl_output::WlOutput, usize> for MockState {
+    fn bind(
+        state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<wl_output::WlOutput>,
+        index: &usize,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        let output = data_init.init(resource, *index);
+        let info = &state.outputs[*index];
+
+        output.geometry(
+            0,
+            0,
+            600,
+            340,
+            wl_output::Subpixel::Unknown,
+            info.make.clone(),
+            info.model.clone(),
+            wl_output::Transform::Normal,
+        );
+        if output.version() >= 4 {
+            output.name(info.name.clone());
+            output.description(info.description.clone());
+        }
+        if output.version() >= 2 {
+            output.done();
+        }
+    }
+}
+
+impl Dispatch<wl_output::WlOutput, usize> for MockState {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &wl_output::WlOutput,
+        _request: wl_output::Request,
+        _index: &usize,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        // Only `release`, which needs no handling
+    }
+}
+
+impl GlobalDispatch<zxdg_output_manager_v1::ZxdgOutputManagerV1, ()> for MockState {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<zxdg_output_manager_v1::ZxdgOutputManagerV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<zxdg_output_manager_v1::ZxdgOutputManagerV1, ()> for MockState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &zxdg_output_manager_v1::ZxdgOutputManagerV1,
+        request: zxdg_output_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zxdg_output_manager_v1::Request::GetXdgOutput { id, output } = request {
+            let index = *output.data::<usize>().unwrap();
+            let xdg_output = data_init.init(id, index);
+            let info = &state.outputs[index];
+
+            xdg_output.logical_position(0, 0);
+            xdg_output.logical_size(1920, 1080);
+            xdg_output.name(info.xdg_name.clone().unwrap_or_else(|| info.name.clone()));
+            xdg_output.description(
+                info.xdg_description
+                    .clone()
+                    .unwrap_or_else(|| info.description.clone()),
+            );
+        }
+    }
+}
+
+impl Dispatch<zxdg_output_v1::ZxdgOutputV1, usize> for MockState {
+    fn request(
+        _state: &mut Self,
+        _client: &Client,
+        _resource: &zxdg_output_v1::ZxdgOutputV1,
+        _request: zxdg_output_v1::Request,
+        _index: &usize,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        // Only `destroy`, which needs no handling
+    }
+}
+
+impl GlobalDispatch<zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1, ()> for MockState {
+    fn bind(
+        _state: &mut Self,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1>,
+        _data: &(),
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl Dispatch<zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1, ()> for MockState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        _resource: &zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1,
+        request: zwlr_output_power_manager_v1::Request,
+        _data: &(),
+        _handle: &DisplayHandle,
+        data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_output_power_manager_v1::Request::GetOutputPower { id, output } = request {
+            let index = *output.data::<usize>().unwrap();
+            let control = data_init.init(id, index);
+            state.control_created(&control, index);
+        }
+    }
+}
+
+impl Dispatch<zwlr_output_power_v1::ZwlrOutputPowerV1, usize> for MockState {
+    fn request(
+        state: &mut Self,
+        _client: &Client,
+        resource: &zwlr_output_power_v1::ZwlrOutputPowerV1,
+        request: zwlr_output_power_v1::Request,
+        index: &usize,
+        _handle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, Self>,
+    ) {
+        if let zwlr_output_power_v1::Request::SetMode { mode } = request {
+            state.set_mode(resource, *index, mode);
+        }
+    }
+}
+
+impl MockState {
+    /// Report the initial state on a newly created power control
+    fn control_created(&self, control: &zwlr_output_power_v1::ZwlrOutputPowerV1, index: usize) {
+        let info = &self.outputs[index];
+        if info.busy {
+            control.failed();
+        } else {
+            control.mode(wlr_mode(info.mode));
+        }
+    }
+
+    /// Record and apply a `set_mode` request
+    fn set_mode(
+        &mut self,
+        control: &zwlr_output_power_v1::ZwlrOutputPowerV1,
+        index: usize,
+        mode: WEnum<zwlr_output_power_v1::Mode>,
+    ) {
+        let WEnum::Value(mode) = mode else {
+            return;
+        };
+        let power = match mode {
+            zwlr_output_power_v1::Mode::Off => PowerState::Off,
+            _ => PowerState::On,
+        };
+        let info = &mut self.outputs[index];
+        // A control that was sent `failed` is inert
+        if info.busy {
+            return;
+        }
+        info.mode = power;
+        self.requests
+            .lock()
+            .unwrap()
+            .push((info.name.clone(), power));
+        control.mode(wlr_mode(power));
+    }
+}
+
+// wayland-server refuses to advertise a global above the version its
+// generated interface knows. To mimic a compositor implementing a newer
+// protocol revision, the power manager is advertised through a copy of the
+// interface with a bumped version, and requests are parsed by hand.
+
+/// Advertise the power manager with a version newer than the generated interface
+fn create_newer_power_manager(handle: &DisplayHandle, version: u32) {
+    let interface: &'static Interface = Box::leak(Box::new(Interface {
+        version,
+        ..*zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1::interface()
+    }));
+    handle.backend_handle().create_global::<MockState>(
+        interface,
+        version,
+        Arc::new(NewerPowerManager),
+    );
+}
+
+/// Global handler and object data for the bumped power manager
+struct NewerPowerManager;
+
+impl GlobalHandler<MockState> for NewerPowerManager {
+    fn bind(
+        self: Arc<Self>,
+        _handle: &Handle,
+        _state: &mut MockState,
+        _client_id: ClientId,
+        _global_id: GlobalId,
+        _object_id: ObjectId,
+    ) -> Arc<dyn ObjectData<MockState>> {
+        self
+    }
+}
+
+impl ObjectData<MockState> for NewerPowerManager {
+    fn request(
+        self: Arc<Self>,
+        handle: &Handle,
+        state: &mut MockState,
+        _client_id: ClientId,
+        msg: Message<ObjectId, OwnedFd>,
+    ) -> Option<Arc<dyn ObjectData<MockState>>> {
+        let handle = DisplayHandle::from(handle.clone());
+        // The new control's id isn't reachable through the parsed `New<_>`
+        let control_id = match msg.args.first() {
+            Some(Argument::NewId(id)) => id.clone(),
+            _ => return None,
+        };
+        let (_, request) =
+            zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1::parse_request(&handle, msg)
+                .ok()?;
+
+        if let zwlr_output_power_manager_v1::Request::GetOutputPower { output, .. } = request {
+            let index = *output.data::<usize>()?;
+            let control =
+                zwlr_output_power_v1::ZwlrOutputPowerV1::from_id(&handle, control_id).ok()?;
+            state.control_created(&control, index);
+            return Some(Arc::new(RawPowerControl { index }));
+        }
+        None
+    }
+
+    fn destroyed(
+        self: Arc<Self>,
+        _handle: &Handle,
+        _state: &mut MockState,
+        _client_id: ClientId,
+        _object_id: ObjectId,
+    ) {
+    }
+}
+
+/// Object data for power controls created by the bumped power manager
+struct RawPowerControl {
+    index: usize,
+}
+
+impl ObjectData<MockState> for RawPowerControl {
+    fn request(
+        self: Arc<Self>,
+        handle: &Handle,
+        state: &mut MockState,
+        _client_id: ClientId,
+        msg: Message<ObjectId, OwnedFd>,
+    ) -> Option<Arc<dyn ObjectData<MockState>>> {
+        let handle = DisplayHandle::from(handle.clone());
+        let (control, request) =
+            zwlr_output_power_v1::ZwlrOutputPowerV1::parse_request(&handle, msg).ok()?;
+
+        if let zwlr_output_power_v1::Request::SetMode { mode } = request {
+            state.set_mode(&control, self.index, mode);
+        }
+        None
+    }
+
+    fn destroyed(
+        self: Arc<Self>,
+        _handle: &Handle,
+        _state: &mut MockState,
+        _client_id: ClientId,
+        _object_id: ObjectId,
+    ) {
+    }
+}
@@ -0,0 +1,59 @@
+//! Smoke test of the C interface
+//!
+//! Compiles tests/ffi/smoke.c against include/dpms.h and the cdylib with
+//! the system C compiler, then runs it. Only built with the `ffi` feature.
+#![cfg(feature = "ffi")]
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Directory cargo built the cdylib for this test in, e.g. `target/debug/deps`
+///
+/// It only gets copied up to `target/debug` by a plain `cargo build`, so
+/// the one next to the test binary is the one with the `ffi` feature.
+fn library_dir() -> PathBuf {
+    let exe = std::env::current_exe().expect("Failed to find the test binary");
+    exe.parent()
+        .expect("Test binary is not in a target directory")
+        .to_path_buf()
+}
+
+#[test]
+fn c_program_links_and_runs() {
+    let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let lib_dir = library_dir();
+    assert!(
+        lib_dir.join("libdpms.so").exists(),
+        "libdpms.so not built in {}",
+        lib_dir.display()
+    );
+    let program = lib_dir.join("dpms-ffi-smoke");
+
+    let compiled = Command::new(std::env::var_os("CC").unwrap_or_else(|| "cc".into()))
+        .arg(manifest_dir.join("tests/ffi/smoke.c"))
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(&lib_dir)
+        // An RPATH rather than RUNPATH wins over cargo's LD_LIBRARY_PATH,
+        // which also lists `target/debug` with a library built without ffi
+        .arg("-Wl,--disable-new-dtags")
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-ldpms")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .expect("Failed to run the C compiler");
+    assert!(compiled.success(), "smoke.c failed to compile");
+
+    let output = Command::new(&program)
+        .output()
+        .expect("Failed to run the smoke test");
+    assert!(
+        output.status.success(),
+        "smoke test failed:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
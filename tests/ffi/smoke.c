@@ -0,0 +1,32 @@
+/* Links against libdpms and checks the calls that don't touch displays */
+#include <stdio.h>
+#include <string.h>
+
+#include "dpms.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                  \
+    do {                                                             \
+        if (!(cond)) {                                               \
+            fprintf(stderr, "%s:%d: %s\n", __FILE__, __LINE__, #cond); \
+            failures++;                                              \
+        }                                                            \
+    } while (0)
+
+int main(void)
+{
+    int on = -1;
+
+    CHECK(strcmp(dpms_strerror(DPMS_OK), "success") == 0);
+    CHECK(strcmp(dpms_strerror(DPMS_ERR_NO_DISPLAY_FOUND), "no connected display found") == 0);
+    CHECK(strcmp(dpms_strerror(9999), "unknown error") == 0);
+
+    CHECK(dpms_get_power(NULL, NULL) == DPMS_ERR_INVALID_ARGUMENT);
+    CHECK(dpms_get_power("\xff", &on) == DPMS_ERR_INVALID_ARGUMENT);
+    CHECK(dpms_set_power("\xff", 0) == DPMS_ERR_INVALID_ARGUMENT);
+    CHECK(dpms_list_displays(NULL, NULL) == DPMS_ERR_INVALID_ARGUMENT);
+    CHECK(on == -1);
+
+    return failures == 0 ? 0 : 1;
+}
@@ -101,3 +101,255 @@ fn test_invalid_command_fails() {
 
     assert!(!output.status.success(), "Invalid command should fail");
 }
+
+/// Write an executable shell script to `dir/name`
+fn write_script(dir: &std::path::Path, name: &str, body: &str) {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join(name);
+    std::fs::write(&path, format!("#!/bin/sh\n{}", body)).expect("Failed to write script");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .expect("Failed to make script executable");
+}
+
+/// A directory with a fake `ssh` that logs its arguments and runs the
+/// remote command in a local shell, optionally next to a fake remote `dpms`
+///
+/// The fake `dpms` logs its arguments, prints a status and exits with 3.
+fn fake_remote(name: &str, with_dpms: bool) -> std::path::PathBuf {
+    let dir = std::path::PathBuf::from(format!("/tmp/dpms-test-remote-{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("Failed to create test dir");
+
+    write_script(
+        &dir,
+        "ssh",
+        &format!(
+            "printf '%s\\n' \"$@\" > {dir}/ssh-args\n\
+             for command; do :; done\n\
+             exec sh -c \"$command\"\n",
+            dir = dir.display()
+        ),
+    );
+    if with_dpms {
+        write_script(
+            &dir,
+            "dpms",
+            &format!(
+                "printf '%s\\n' \"$@\" > {dir}/dpms-args\n\
+                 echo '{{\"power\":\"off\"}}'\n\
+                 exit 3\n",
+                dir = dir.display()
+            ),
+        );
+    }
+    dir
+}
+
+/// Run the local dpms with only `dir` and the system directories on PATH
+fn dpms_with_path(dir: &std::path::Path, args: &[&str]) -> std::process::Output {
+    Command::new(dpms_bin())
+        .args(args)
+        .env("PATH", format!("{}:/usr/bin:/bin", dir.display()))
+        .output()
+        .expect("Failed to execute dpms")
+}
+
+#[test]
+fn test_host_relays_output_and_exit_code() {
+    let dir = fake_remote("relay", true);
+    let output = dpms_with_path(
+        &dir,
+        &[
+            "status",
+            "--host",
+            "me@htpc:2222",
+            "--json",
+            "Unknown-1 \"it's\"",
+        ],
+    );
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "{\"power\":\"off\"}\n"
+    );
+
+    let ssh_args = std::fs::read_to_string(dir.join("ssh-args")).unwrap();
+    let ssh_args: Vec<&str> = ssh_args.lines().collect();
+    assert_eq!(&ssh_args[..4], ["-p", "2222", "--", "me@htpc"]);
+    let dpms_args = std::fs::read_to_string(dir.join("dpms-args")).unwrap();
+    assert_eq!(dpms_args, "status\n--json\nUnknown-1 \"it's\"\n");
+}
+
+#[test]
+fn test_host_without_remote_dpms() {
+    let dir = fake_remote("missing", false);
+    let output = dpms_with_path(&dir, &["--host", "htpc", "off"]);
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("dpms is not installed on htpc"),
+        "Unexpected stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+fn test_errors_carry_their_code() {
+    let dir = fake_remote("codes", false);
+
+    let output = dpms_with_path(&dir, &["--host", "htpc", "off", "--json"]);
+    assert_eq!(output.status.code(), Some(1));
+    // The remote shell's own complaint comes first
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(error["error"]["code"], "remote_not_installed");
+    assert_eq!(error["error"]["number"], 38);
+
+    let output = dpms_with_path(&dir, &["--host", "htpc", "--error-codes", "off"]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Error[remote_not_installed]: dpms is not installed on htpc"),
+        "Unexpected stderr: {}",
+        stderr
+    );
+}
+
+#[test]
+#[cfg(feature = "tty")]
+fn test_permission_denied_exits_with_4() {
+    // Needs a card node this user may not open; anyone who can would have
+    // their displays turned off
+    let denied = std::fs::read_dir("/dev/dri")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("card"))
+        })
+        .find(|card| {
+            std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(card)
+                .is_err_and(|e| e.kind() == std::io::ErrorKind::PermissionDenied)
+        });
+    let Some(card) = denied else {
+        eprintln!("skipping: no DRM card node this user is denied");
+        return;
+    };
+
+    let output = Command::new(dpms_bin())
+        .args(["--backend", "tty", "--no-libseat", "--card"])
+        .arg(&card)
+        .args(["off", "--json"])
+        .output()
+        .expect("Failed to execute dpms");
+
+    assert_eq!(output.status.code(), Some(4));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let error: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(error["error"]["code"], "drm_permission");
+}
+
+#[test]
+fn test_panic_prints_a_report_and_exits_with_1() {
+    let output = Command::new(dpms_bin())
+        .args(["--debug-panic", "status"])
+        .env_remove("DPMS_BACKTRACE")
+        .env_remove("RUST_BACKTRACE")
+        .output()
+        .expect("Failed to execute dpms");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.lines().count(), 1, "Unexpected stderr: {}", stderr);
+    assert!(
+        stderr.starts_with(&format!(
+            "dpms {} crashed running `{} --debug-panic status` (no backend chosen yet): \
+             --debug-panic was given at src/main.rs:",
+            env!("CARGO_PKG_VERSION"),
+            dpms_bin().display()
+        )),
+        "Unexpected stderr: {}",
+        stderr
+    );
+    assert!(stderr.contains("again with DPMS_BACKTRACE=1"));
+    assert!(stderr.contains("/issues"));
+
+    let output = Command::new(dpms_bin())
+        .args(["--debug-panic", "status"])
+        .env("DPMS_BACKTRACE", "1")
+        .output()
+        .expect("Failed to execute dpms");
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("with the backtrace below"));
+    assert!(stderr.lines().count() > 1, "No backtrace: {}", stderr);
+}
+
+#[test]
+#[cfg(feature = "hyprland")]
+fn test_status_watch_exits_when_stdout_is_closed() {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::time::{Duration, Instant};
+
+    // A fake Hyprland whose display turns on and off with every request
+    let runtime =
+        std::path::PathBuf::from(format!("/tmp/dpms-test-watch-pipe-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&runtime);
+    let socket_dir = runtime.join("hypr").join("test");
+    std::fs::create_dir_all(&socket_dir).unwrap();
+    let listener = std::os::unix::net::UnixListener::bind(socket_dir.join(".socket.sock")).unwrap();
+    std::thread::spawn(move || {
+        for (requests, stream) in listener.incoming().enumerate() {
+            let Ok(mut stream) = stream else {
+                return;
+            };
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf);
+            let reply = format!(
+                r#"[{{"name": "DP-1", "dpmsStatus": {}}}]"#,
+                requests % 2 == 0
+            );
+            let _ = stream.write_all(reply.as_bytes());
+        }
+    });
+
+    let mut child = Command::new(dpms_bin())
+        .args(["--backend", "hyprland", "status", "--watch"])
+        .env("XDG_RUNTIME_DIR", &runtime)
+        .env("HYPRLAND_INSTANCE_SIGNATURE", "test")
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("Failed to execute dpms");
+
+    // Read one event and close the pipe, like `| head -1`
+    let mut line = String::new();
+    BufReader::new(child.stdout.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+
+    // The next change can't be written, which ends the watch
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break Some(status);
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    let _ = std::fs::remove_dir_all(&runtime);
+
+    assert!(line.starts_with("DP-1: "), "Unexpected event: {:?}", line);
+    assert_eq!(status.and_then(|s| s.code()), Some(0), "dpms kept running");
+}
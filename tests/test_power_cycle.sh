@@ -36,7 +36,7 @@ fi
 cleanup() {
     info "Cleaning up..."
     pkill -9 dpms 2>/dev/null || true
-    rm -f "/run/user/$(id -u)/dpms.pid" 2>/dev/null || true
+    rm -f "/run/user/$(id -u)"/dpms.pid "/run/user/$(id -u)"/dpms-*.pid 2>/dev/null || true
     sleep 0.2
 }
 
@@ -97,6 +97,14 @@ EXIT_CODE=$?
 echo "$OUTPUT" | grep -qi "already off" || fail "Should indicate 'already off', got: $OUTPUT"
 pass "Idempotent off works"
 
+# Test 6b: Quiet and JSON idempotent off
+info "Testing idempotent off with --quiet and --json..."
+STDERR=$("$dpms" off --quiet 2>&1 >/dev/null)
+[[ -z "$STDERR" ]] || fail "--quiet should print nothing on stderr, got: $STDERR"
+OUTPUT=$("$dpms" off --json 2>/dev/null)
+echo "$OUTPUT" | grep -q '"notices":\["Display already off"\]' || fail "JSON should list the notice, got: $OUTPUT"
+pass "Idempotent off is silent with --quiet and noted in JSON"
+
 # Test 7: Turn display on
 info "Testing 'on' command..."
 "$dpms" on
@@ -126,6 +134,21 @@ EXIT_CODE=$?
 echo "$OUTPUT" | grep -qi "already on" || fail "Should indicate 'already on', got: $OUTPUT"
 pass "Idempotent on works"
 
+# Test 10b: Quiet idempotent on
+info "Testing idempotent on with --quiet..."
+STDERR=$("$dpms" on --quiet 2>&1 >/dev/null)
+[[ -z "$STDERR" ]] || fail "--quiet should print nothing on stderr, got: $STDERR"
+pass "Idempotent on is silent with --quiet"
+
+# Test 11: Off outlives the terminal it was started from
+info "Testing off from a terminal that closes..."
+script -qc "$dpms off" /dev/null >/dev/null || fail "off under a pseudo-terminal failed"
+sleep 0.5
+OUTPUT=$("$dpms" status 2>&1)
+echo "$OUTPUT" | grep -q "Off" || fail "Display came back on when the terminal closed: $OUTPUT"
+"$dpms" on
+pass "Display stays off after its terminal closes"
+
 echo ""
 echo "=========================================="
 echo -e "  ${GREEN}All tests passed!${NC}"